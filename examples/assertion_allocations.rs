@@ -0,0 +1,71 @@
+//! Measures the cost of storing a node's assertions.
+//!
+//! Prints the size of `EnvelopeCase`, which every envelope element occupies,
+//! and the sizes of a `Vec` of assertions and of a `SmallVec` that keeps up to
+//! four inline. Then, for each number of assertions, prints the allocations
+//! made to build the node, including computing its digest, and those made to
+//! store its assertions in each of the two.
+//!
+//! Nodes store their assertions in a `Vec`: storing four inline would save
+//! one allocation per small node, but would make every element, including
+//! leaves, larger.
+//!
+//! ```text
+//! cargo run --release --example assertion_allocations
+//! ```
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::mem::size_of;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bc_envelope::base::envelope::EnvelopeCase;
+use bc_envelope::prelude::full::*;
+use smallvec::SmallVec;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const ROUNDS: usize = 10_000;
+
+/// Returns the average number of allocations made by `f`.
+fn allocations_per_call<T>(f: impl Fn() -> T) -> f64 {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..ROUNDS {
+        std::hint::black_box(f());
+    }
+    (ALLOCATIONS.load(Ordering::Relaxed) - before) as f64 / ROUNDS as f64
+}
+
+fn main() {
+    println!("EnvelopeCase: {} bytes", size_of::<EnvelopeCase>());
+    println!("Vec of assertions: {} bytes", size_of::<Vec<Envelope>>());
+    println!("SmallVec of four assertions: {} bytes", size_of::<SmallVec<[Envelope; 4]>>());
+    println!();
+
+    let subject = Envelope::new("subject");
+    println!("assertions  node  Vec storage  SmallVec storage");
+    for count in 1..=6 {
+        let assertions: Vec<Envelope> = (0..count)
+            .map(|i| Envelope::new_assertion(format!("predicate {}", i), i))
+            .collect();
+        let node = allocations_per_call(|| subject.add_assertion_envelopes(&assertions).unwrap());
+        let vec_storage = allocations_per_call(|| assertions.to_vec());
+        let small_vec_storage = allocations_per_call(|| assertions.iter().cloned().collect::<SmallVec<[Envelope; 4]>>());
+        println!("{:>10}  {:>4}  {:>11}  {:>16}", count, node, vec_storage, small_vec_storage);
+    }
+}
@@ -0,0 +1,36 @@
+//! Writes the canonical test corpus to a directory.
+//!
+//! ```text
+//! cargo run --example generate_corpus --features test-support -- --out corpus
+//! ```
+
+use std::{fs, path::PathBuf, process::exit};
+
+use bc_envelope::corpus::{corpus_files, corpus_manifest, MANIFEST_FILE_NAME};
+
+fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let mut out = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => out = args.next().map(PathBuf::from),
+            _ => {
+                eprintln!("unexpected argument: {}", arg);
+                exit(2);
+            },
+        }
+    }
+    let Some(out) = out else {
+        eprintln!("usage: generate_corpus --out <dir>");
+        exit(2);
+    };
+
+    fs::create_dir_all(&out)?;
+    let files = corpus_files();
+    for file in &files {
+        fs::write(out.join(&file.name), &file.contents)?;
+    }
+    fs::write(out.join(MANIFEST_FILE_NAME), corpus_manifest(&files))?;
+    println!("Wrote {} files to {}", files.len() + 1, out.display());
+    Ok(())
+}
@@ -0,0 +1,55 @@
+//! Compares the time taken by `walk` and `par_walk` to re-derive the digest
+//! of every element of a node with 50,000 assertions.
+//!
+//! ```text
+//! cargo run --release --example par_walk --features rayon
+//! ```
+
+use std::{cell::RefCell, time::Instant};
+
+use bc_envelope::prelude::full::*;
+use bc_envelope::base::walk::EdgeType;
+
+const ASSERTIONS: usize = 50_000;
+
+/// The digest of an element, computed from its encoding rather than read
+/// from the element.
+fn rederive(envelope: &Envelope) -> Digest {
+    Digest::from_image(envelope.tagged_cbor_data())
+}
+
+/// Returns a node with distinct assertions, decoded from CBOR built directly
+/// rather than by adding the assertions one at a time.
+fn wide_envelope() -> Envelope {
+    let leaf = |text: String| CBOR::to_tagged_value(201, text);
+    let mut elements = vec![leaf("subject".to_string())];
+    for i in 0..ASSERTIONS {
+        let mut assertion = Map::new();
+        assertion.insert(leaf(format!("predicate {}", i)), leaf(format!("object {}", i)));
+        elements.push(assertion.into());
+    }
+    Envelope::from_tagged_cbor(CBOR::to_tagged_value(200, elements)).unwrap()
+}
+
+fn main() {
+    let envelope = wide_envelope();
+    println!("{} elements", envelope.elements_count());
+
+    let start = Instant::now();
+    let digests = RefCell::new(Vec::new());
+    envelope.walk(false, &|element, _, _, _: Option<()>| {
+        digests.borrow_mut().push(rederive(&element));
+        None
+    });
+    let serial = start.elapsed();
+
+    let start = Instant::now();
+    let parallel_digests = envelope.par_walk(&|element: Envelope, _, _: EdgeType, _: Option<()>| {
+        (None, rederive(&element))
+    });
+    let parallel = start.elapsed();
+
+    assert_eq!(digests.into_inner(), parallel_digests);
+    println!("walk:     {:?}", serial);
+    println!("par_walk: {:?} ({:.1}x)", parallel, serial.as_secs_f64() / parallel.as_secs_f64());
+}
@@ -0,0 +1,108 @@
+//! Counts the heap allocations made by a workload that signs, compresses,
+//! and elides an envelope with 1000 assertions, and serializes the results.
+//!
+//! The workload is run twice: on envelopes that must be encoded from
+//! scratch, and on an envelope created by `Envelope::with_cached_encoding`,
+//! whose encoding is copied wherever it appears. The allocations are reported
+//! for each step, and the outputs are checked to be byte-identical.
+//!
+//! ```text
+//! cargo run --release --example serialization_cache
+//! ```
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bc_components::PrivateKeyBase;
+use bc_envelope::prelude::full::*;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const ROUNDS: usize = 20;
+
+fn document() -> Envelope {
+    (0..1000).fold(Envelope::new("Document"), |envelope, i| {
+        envelope.add_assertion(format!("field {}", i), format!("value {}", i))
+    })
+}
+
+const STEPS: [&str; 5] = ["sign", "compress", "elide", "serialize", "total"];
+
+/// Returns the allocations and bytes allocated by `f` so far, added to
+/// `counts`.
+fn count<T>(counts: &mut (usize, usize), f: impl FnOnce() -> T) -> T {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let result = std::hint::black_box(f());
+    counts.0 += ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    counts.1 += ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes;
+    result
+}
+
+/// Signs, compresses, and elides the envelope, and returns the encodings of
+/// the results, adding the allocations made by each step to `counts`.
+fn workload(envelope: &Envelope, key: &PrivateKeyBase, counts: &mut [(usize, usize); 5]) -> Vec<Vec<u8>> {
+    let signed = count(&mut counts[0], || envelope.sign(&key.ed25519_signing_private_key()));
+    let compressed = count(&mut counts[1], || signed.compress().unwrap());
+    let elided = count(&mut counts[2], || signed.elide_removing_target(&signed.assertions()[0]));
+    count(&mut counts[3], || vec![compressed.tagged_cbor_data(), elided.tagged_cbor_data()])
+}
+
+/// Returns the average allocations and bytes allocated by each step of
+/// `workload` on the envelopes returned by `setup`, which are not counted.
+fn measure(setup: impl Fn() -> Envelope, key: &PrivateKeyBase) -> ([(f64, f64); 5], Vec<Vec<u8>>) {
+    let mut counts = [(0, 0); 5];
+    let mut outputs = Vec::new();
+    for _ in 0..ROUNDS {
+        let envelope = setup();
+        outputs = workload(&envelope, key, &mut counts);
+    }
+    counts[4] = counts[..4].iter().fold((0, 0), |total, step| (total.0 + step.0, total.1 + step.1));
+    let averages = counts.map(|(allocations, bytes)| (allocations as f64 / ROUNDS as f64, bytes as f64 / ROUNDS as f64));
+    (averages, outputs)
+}
+
+fn main() {
+    let key = PrivateKeyBase::from_data(b"serialization cache example key!".to_vec());
+    let serialized = document().with_cached_encoding();
+    let encoding = serialized.tagged_cbor_data();
+
+    let (cold, cold_outputs) = measure(document, &key);
+    let (warm, warm_outputs) = measure(|| serialized.clone(), &key);
+
+    // The encodings are the same however they were produced.
+    assert_eq!(cold_outputs, warm_outputs);
+    let signed = serialized.sign(&key.ed25519_signing_private_key());
+    assert_eq!(warm_outputs[0], signed.compress().unwrap().tagged_cbor().to_cbor_data());
+    assert_eq!(encoding, serialized.tagged_cbor().to_cbor_data());
+
+    println!("             not cached              cached");
+    println!("step       allocations    bytes  allocations    bytes");
+    for (step, (cold, warm)) in STEPS.iter().zip(cold.iter().zip(warm.iter())) {
+        println!("{:<9}  {:>11}  {:>7}  {:>11}  {:>7}", step, cold.0, cold.1, warm.0, warm.1);
+    }
+}
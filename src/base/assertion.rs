@@ -1,9 +1,13 @@
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
 use std::borrow::Cow;
 use anyhow::{bail, Error, Result};
 use bc_components::{Digest, DigestProvider};
 use dcbor::prelude::*;
 
-use crate::{Envelope, EnvelopeEncodable};
+use crate::{Envelope, EnvelopeEncodable, EnvelopeError, EnvelopeLimits};
+
+use super::limits::DecodeBudget;
 
 /// Represents an assertion.
 ///
@@ -19,9 +23,17 @@ pub struct Assertion {
 
 impl Assertion {
     /// Creates an assertion and calculates its digest.
+    ///
+    /// The predicate and object may be any type that can be encoded as an
+    /// envelope, including `Envelope`, `&Envelope`, and `Box<Envelope>`, which
+    /// are used as-is.
     pub fn new(predicate: impl EnvelopeEncodable, object: impl EnvelopeEncodable) -> Self {
-        let predicate = predicate.into_envelope();
-        let object = object.into_envelope();
+        Self::new_with_envelopes(predicate.into_envelope(), object.into_envelope())
+    }
+
+    /// Creates an assertion from a predicate and object that are already
+    /// envelopes, and calculates its digest.
+    pub fn new_with_envelopes(predicate: Envelope, object: Envelope) -> Self {
         let digest = Digest::from_digests(&[
             predicate.digest().into_owned(),
             object.digest().into_owned(),
@@ -78,7 +90,7 @@ impl TryFrom<CBOR> for Assertion {
         if let CBORCase::Map(map) = value.as_case() {
             return map.clone().try_into();
         }
-        bail!("assertion must be a map")
+        bail!(EnvelopeError::InvalidFormat { message: "assertion must be a map".to_string() })
     }
 }
 
@@ -86,12 +98,18 @@ impl TryFrom<Map> for Assertion {
     type Error = Error;
 
     fn try_from(map: Map) -> Result<Self> {
-        if map.len() != 1 {
-            bail!("assertion map must have exactly one element")
-        }
-        let elem = map.iter().next().unwrap();
-        let predicate = Envelope::from_untagged_cbor(elem.0.clone())?;
-        let object = Envelope::from_untagged_cbor(elem.1.clone())?;
-        Ok(Self::new(predicate, object))
+        Self::from_map_with_budget(&map, &mut DecodeBudget::new(&EnvelopeLimits::default()))
+    }
+}
+
+impl Assertion {
+    pub(crate) fn from_map_with_budget(map: &Map, budget: &mut DecodeBudget<'_>) -> Result<Self> {
+        let elem = match map.iter().next() {
+            Some(elem) if map.len() == 1 => elem,
+            _ => bail!(EnvelopeError::InvalidFormat { message: "assertion map must have exactly one element".to_string() }),
+        };
+        let predicate = Envelope::decode_untagged(elem.0, budget)?;
+        let object = Envelope::decode_untagged(elem.1, budget)?;
+        Ok(Self::new_with_envelopes(predicate, object))
     }
 }
@@ -0,0 +1,44 @@
+use anyhow::Result;
+
+use crate::Envelope;
+
+/// A type that contributes a set of assertions to an envelope.
+///
+/// Domain types often map to several assertions rather than to a single
+/// subject. For example, an address might contribute `"street"`, `"city"`, and
+/// `"postalCode"` assertions to the envelope describing a person.
+///
+/// The assertions are added with [`Envelope::add_assertions_from`] or
+/// [`EnvelopePipeline::add_assertions_from`](crate::EnvelopePipeline::add_assertions_from).
+pub trait AssertionProvider {
+    /// Returns the assertion envelopes this value contributes.
+    fn assertions(&self) -> Vec<Envelope>;
+}
+
+/// A type that can be reconstructed from a subset of an envelope's assertions.
+///
+/// This is the inverse of [`AssertionProvider`]. Implementations should ignore
+/// assertions they don't recognize, so that the value can be recovered from an
+/// envelope that also carries unrelated assertions, and should return an
+/// error such as [`EnvelopeError::NonexistentPredicate`](crate::EnvelopeError::NonexistentPredicate)
+/// if a required assertion is missing.
+pub trait FromAssertions: Sized {
+    /// Reconstructs a value from the given assertion envelopes.
+    fn from_assertions(assertions: &[Envelope]) -> Result<Self>;
+}
+
+impl Envelope {
+    /// Returns a new envelope with the assertions contributed by the given
+    /// provider added.
+    ///
+    /// Returns an error if any of the provided envelopes is not a valid
+    /// assertion envelope, or an obscured variant of one.
+    pub fn add_assertions_from(&self, provider: &dyn AssertionProvider) -> Result<Self> {
+        self.add_assertion_envelopes(&provider.assertions())
+    }
+
+    /// Reconstructs a value of the given type from this envelope's assertions.
+    pub fn extract_from_assertions<T: FromAssertions>(&self) -> Result<T> {
+        T::from_assertions(self.assertions())
+    }
+}
@@ -1,16 +1,29 @@
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
 use anyhow::{bail, Result};
 use bc_components::DigestProvider;
 
-use crate::{Envelope, EnvelopeEncodable, EnvelopeError};
+use crate::{Envelope, EnvelopeEncodable, EnvelopeError, EnvelopeLimits};
 
 use super::envelope::EnvelopeCase;
 
 /// Support for adding assertions.
 impl Envelope {
     /// Returns the result of adding the given assertion to the envelope.
+    ///
+    /// The node is not held to [`EnvelopeLimits::DEFAULT_MAX_NODE_ASSERTIONS`];
+    /// use [`Envelope::try_add_assertion`] to enforce it.
     pub fn add_assertion(&self, predicate: impl EnvelopeEncodable, object: impl EnvelopeEncodable) -> Self {
-        let assertion = Self::new_assertion(predicate, object);
-        self.add_optional_assertion_envelope(Some(assertion)).unwrap()
+        self.add_valid_assertion_envelope(Self::new_assertion(predicate, object))
+    }
+
+    /// Returns the result of adding the given assertion to the envelope.
+    ///
+    /// Returns `EnvelopeError::TooManyAssertions` if the node would have more
+    /// assertions than [`EnvelopeLimits::DEFAULT_MAX_NODE_ASSERTIONS`], as it
+    /// could not be decoded with the default limits.
+    pub fn try_add_assertion(&self, predicate: impl EnvelopeEncodable, object: impl EnvelopeEncodable) -> Result<Self> {
+        self.try_add_valid_assertion_envelope(Self::new_assertion(predicate, object))
     }
 
     /// Returns the result of adding the given assertion to the envelope.
@@ -25,48 +38,87 @@ impl Envelope {
     ///
     /// Each assertion envelope must be a valid assertion envelope, or an
     /// obscured variant (elided, encrypted, compressed) of one.
+    ///
+    /// The resulting node is constructed (and its digest calculated) once,
+    /// rather than once per added assertion.
+    ///
+    /// Returns `EnvelopeError::TooManyAssertions` if the node would have more
+    /// assertions than [`EnvelopeLimits::DEFAULT_MAX_NODE_ASSERTIONS`].
     pub fn add_assertion_envelopes(&self, assertions: &[Self]) -> Result<Self> {
-        let mut e = self.clone();
+        if assertions.iter().any(|a| !a.is_subject_assertion() && !a.is_subject_obscured()) {
+            bail!(EnvelopeError::InvalidFormat { message: "not an assertion envelope".to_string() })
+        }
+        if assertions.is_empty() {
+            return Ok(self.clone());
+        }
+        let (subject, mut all_assertions) = match self.case() {
+            EnvelopeCase::Node { subject, assertions, .. } => (subject.clone(), assertions.clone()),
+            _ => (self.subject(), Vec::new()),
+        };
         for assertion in assertions {
-            e = e.add_assertion_envelope(assertion.clone())?;
+            if !all_assertions.iter().any(|a| a.digest() == assertion.digest()) {
+                all_assertions.push(assertion.clone());
+            }
         }
-        Ok(e)
+        EnvelopeLimits::default().check_node_length(all_assertions.len() + 1)?;
+        Ok(Self::new_with_unchecked_assertions(subject, all_assertions))
     }
 
     /// If the optional assertion is present, returns the result of adding it to
     /// the envelope. Otherwise, returns the envelope unchanged.
     ///
     /// The assertion envelope must be a valid assertion envelope, or an
-    /// obscured variant (elided, encrypted, compressed) of one.
+    /// obscured variant (elided, encrypted, compressed) of one, and the node
+    /// must not already have [`EnvelopeLimits::DEFAULT_MAX_NODE_ASSERTIONS`]
+    /// assertions.
     pub fn add_optional_assertion_envelope(&self, assertion: Option<Self>) -> Result<Self> {
         match assertion {
             Some(assertion) => {
                 if !assertion.is_subject_assertion() && !assertion.is_subject_obscured() {
-                    bail!(EnvelopeError::InvalidFormat)
+                    bail!(EnvelopeError::InvalidFormat { message: "not an assertion envelope".to_string() })
                 }
+                self.try_add_valid_assertion_envelope(assertion)
+            },
+            None => Ok(self.clone()),
+        }
+    }
 
-                match self.case() {
-                    EnvelopeCase::Node { subject, assertions, .. } => {
-                        if !assertions.iter().any(|a| a.digest() == assertion.digest()) {
-                            let mut assertions = assertions.clone();
-                            assertions.push(assertion);
-                            Ok(Self::new_with_unchecked_assertions(subject.clone(), assertions))
-                        } else {
-                            Ok(self.clone())
-                        }
-                    },
-                    _ => Ok(Self::new_with_unchecked_assertions(self.subject(), vec![assertion])),
+    /// Adds an assertion envelope that is already known to be a valid assertion
+    /// envelope, or an obscured variant of one.
+    fn add_valid_assertion_envelope(&self, assertion: Self) -> Self {
+        match self.case() {
+            EnvelopeCase::Node { subject, assertions, .. } => {
+                if !assertions.iter().any(|a| a.digest() == assertion.digest()) {
+                    let mut assertions = assertions.clone();
+                    assertions.push(assertion);
+                    Self::new_with_unchecked_assertions(subject.clone(), assertions)
+                } else {
+                    self.clone()
                 }
             },
-            None => Ok(self.clone()),
+            _ => Self::new_with_unchecked_assertions(self.subject(), vec![assertion]),
         }
     }
 
+    /// Adds an assertion envelope that is already known to be a valid assertion
+    /// envelope, or an obscured variant of one.
+    ///
+    /// Returns `EnvelopeError::TooManyAssertions` if the node would have more
+    /// assertions than the default limit.
+    fn try_add_valid_assertion_envelope(&self, assertion: Self) -> Result<Self> {
+        if let EnvelopeCase::Node { assertions, .. } = self.case() {
+            if !assertions.iter().any(|a| a.digest() == assertion.digest()) {
+                EnvelopeLimits::default().check_node_length(assertions.len() + 2)?;
+            }
+        }
+        Ok(self.add_valid_assertion_envelope(assertion))
+    }
+
     /// If the optional object is present, returns the result of adding the
     /// assertion to the envelope. Otherwise, returns the envelope unchanged.
     pub fn add_optional_assertion(&self, predicate: impl EnvelopeEncodable, object: Option<impl EnvelopeEncodable>) -> Self {
         if let Some(object) = object {
-            self.add_assertion_envelope(Self::new_assertion(predicate, object)).unwrap()
+            self.add_assertion(predicate, object)
         } else {
             self.clone()
         }
@@ -75,12 +127,11 @@ impl Envelope {
     /// Returns a new `Envelope` with the given array of assertions added.
     ///
     /// - Parameter assertions: The assertions to add.
-    pub fn add_assertions(&self, envelopes: &[Self]) -> Self {
-        let mut e = self.clone();
-        for envelope in envelopes {
-            e = e.add_assertion_envelope(envelope.clone()).unwrap();
-        }
-        e.clone()
+    ///
+    /// Returns an error if any of the envelopes is not a valid assertion
+    /// envelope, or an obscured variant of one.
+    pub fn add_assertions(&self, envelopes: &[Self]) -> Result<Self> {
+        self.add_assertion_envelopes(envelopes)
     }
 }
 
@@ -117,7 +168,11 @@ impl Envelope {
         O: EnvelopeEncodable,
     {
         let assertion = Self::new_assertion(predicate, object);
-        self.add_optional_assertion_envelope_salted(Some(assertion), salted).unwrap()
+        if salted {
+            self.add_valid_assertion_envelope(assertion.add_salt())
+        } else {
+            self.add_valid_assertion_envelope(assertion)
+        }
     }
 
     /// Returns the result of adding the given assertion to the envelope, optionally salting it.
@@ -132,42 +187,37 @@ impl Envelope {
     /// the envelope, optionally salting it. Otherwise, returns the envelope unchanged.
     ///
     /// The assertion envelope must be a valid assertion envelope, or an
-    /// obscured variant (elided, encrypted, compressed) of one.
+    /// obscured variant (elided, encrypted, compressed) of one, and the node
+    /// must not already have [`EnvelopeLimits::DEFAULT_MAX_NODE_ASSERTIONS`]
+    /// assertions.
     pub fn add_optional_assertion_envelope_salted(&self, assertion: Option<Self>, salted: bool) -> Result<Self> {
         match assertion {
             Some(assertion) => {
                 if !assertion.is_subject_assertion() && !assertion.is_subject_obscured() {
-                    bail!(EnvelopeError::InvalidFormat)
+                    bail!(EnvelopeError::InvalidFormat { message: "not an assertion envelope".to_string() })
                 }
                 let envelope2 = if salted {
                     assertion.add_salt()
                 } else {
                     assertion
                 };
-
-                match self.case() {
-                    EnvelopeCase::Node { subject, assertions, .. } => {
-                        if !assertions.iter().any(|a| a.digest() == envelope2.digest()) {
-                            let mut assertions = assertions.clone();
-                            assertions.push(envelope2);
-                            Ok(Self::new_with_unchecked_assertions(subject.clone(), assertions))
-                        } else {
-                            Ok(self.clone())
-                        }
-                    },
-                    _ => Ok(Self::new_with_unchecked_assertions(self.subject(), vec![envelope2])),
-                }
+                self.try_add_valid_assertion_envelope(envelope2)
             },
             None => Ok(self.clone()),
         }
     }
 
-    pub fn add_assertions_salted(&self, assertions: &[Self], salted: bool) -> Self {
+    /// Returns the result of adding the given assertions to the envelope,
+    /// optionally salting each of them.
+    ///
+    /// Returns an error if any of the envelopes is not a valid assertion
+    /// envelope, or an obscured variant of one.
+    pub fn add_assertions_salted(&self, assertions: &[Self], salted: bool) -> Result<Self> {
         let mut e = self.clone();
         for assertion in assertions {
-            e = e.add_assertion_envelope_salted(assertion.clone(), salted).unwrap();
+            e = e.add_assertion_envelope_salted(assertion.clone(), salted)?;
         }
-        e.clone()
+        Ok(e)
     }
 }
 
@@ -179,7 +229,7 @@ impl Envelope {
         let assertions = self.assertions();
         let target = target.digest();
         if let Some(index) = assertions.iter().position(|a| a.digest() == target) {
-            let mut assertions = assertions.clone();
+            let mut assertions = assertions.to_vec();
             assertions.remove(index);
             if assertions.is_empty() {
                 self.subject()
@@ -198,7 +248,39 @@ impl Envelope {
     }
 
     /// Returns a new envelope with its subject replaced by the provided one.
+    ///
+    /// If this envelope is a node, the result has the same assertions, and its
+    /// digest is recalculated. If the new subject itself has assertions, they
+    /// are merged with this envelope's assertions. If this envelope is not a
+    /// node, the new subject is returned.
     pub fn replace_subject(&self, subject: Self) -> Self {
-        self.assertions().into_iter().fold(subject, |e, a| e.add_assertion_envelope(a).unwrap())
+        match self.case() {
+            EnvelopeCase::Node { assertions, .. } => {
+                let mut all_assertions = subject.assertions().to_vec();
+                for assertion in assertions {
+                    if !all_assertions.iter().any(|a| a.digest() == assertion.digest()) {
+                        all_assertions.push(assertion.clone());
+                    }
+                }
+                Self::new_with_unchecked_assertions(subject.subject(), all_assertions)
+            },
+            _ => subject,
+        }
+    }
+
+    /// Returns a new envelope whose subject is the result of applying `f` to
+    /// this envelope's subject, with the assertions unchanged.
+    ///
+    /// See [`Envelope::replace_subject`].
+    pub fn map_subject(&self, f: impl FnOnce(Self) -> Self) -> Self {
+        self.replace_subject(f(self.subject()))
+    }
+
+    /// Returns a new envelope whose subject is the result of applying the
+    /// fallible `f` to this envelope's subject, with the assertions unchanged.
+    ///
+    /// See [`Envelope::replace_subject`].
+    pub fn try_map_subject(&self, f: impl FnOnce(Self) -> Result<Self>) -> Result<Self> {
+        Ok(self.replace_subject(f(self.subject())?))
     }
 }
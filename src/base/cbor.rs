@@ -1,15 +1,19 @@
 use anyhow::{bail, Error, Result};
 use dcbor::prelude::*;
-use bc_components::{tags, Digest};
+use bc_components::{tags, Digest, DigestProvider};
 #[cfg(feature = "encrypt")]
 use bc_components::EncryptedMessage;
 #[cfg(feature = "compress")]
 use bc_components::Compressed;
-use crate::{Assertion, Envelope};
+use bc_ur::UR;
+use crate::{with_format_context, Assertion, Envelope, EnvelopeError, EnvelopeLimits, FormatContext};
 #[cfg(feature = "known_value")]
 use crate::extension::KnownValue;
 
+use unicode_normalization::is_nfc;
+
 use super::envelope::EnvelopeCase;
+use super::limits::{DecodeBudget, DecodeStats};
 
 /// Support for CBOR encoding and decoding of ``Envelope``.
 
@@ -17,7 +21,7 @@ use super::envelope::EnvelopeCase;
 /// the seven cases has a unique CBOR signature:
 ///
 /// * `.node` contains a CBOR array, the first element of which is the subject,
-/// followed by one or more assertions.
+/// followed by one or more assertions. The subject is never itself a node.
 /// * `.leaf` is tagged #6.24, which is the IANA tag for embedded CBOR.
 /// * `.wrapped` is tagged with the `envelope` tag.
 /// * `.assertion` is a single-element map `{predicate: object}`.
@@ -67,18 +71,394 @@ impl CBORTaggedEncodable for Envelope {
             EnvelopeCase::Compressed(compressed) => compressed.tagged_cbor(),
         }
     }
+
+    /// Returns the envelope's tagged CBOR encoding.
+    ///
+    /// If the envelope was created by [`Envelope::with_cached_encoding`], its
+    /// encoding is copied rather than encoded again.
+    fn tagged_cbor_data(&self) -> Vec<u8> {
+        if let Some(encoding) = self.cached_encoding() {
+            return encoding.to_vec();
+        }
+        let mut data = Vec::with_capacity(self.tagged_cbor_data_len());
+        self.cbor_data_into(&mut data);
+        data
+    }
+}
+
+/// The length of the head of the `envelope` tag.
+const ENVELOPE_TAG_HEAD_LEN: usize = 2;
+
+/// Support for streaming encoding of envelopes.
+impl Envelope {
+    /// Appends the envelope's tagged CBOR encoding to `out`.
+    ///
+    /// This produces the same bytes as `tagged_cbor().to_cbor_data()`, but
+    /// writes each element directly into `out` rather than building an
+    /// intermediate `CBOR` tree and concatenating the encodings of its
+    /// children at every level. The content of a leaf is copied only once, no
+    /// matter how deeply it is nested. The encodings of elements created by
+    /// [`Envelope::with_cached_encoding`] are copied.
+    pub fn cbor_data_into(&self, out: &mut Vec<u8>) {
+        write_head(MAJOR_TAGGED, tags::TAG_ENVELOPE, out);
+        self.untagged_cbor_data_into(out);
+    }
+
+    /// Returns the length of the envelope's tagged CBOR encoding, without
+    /// encoding it.
+    pub(crate) fn tagged_cbor_data_len(&self) -> usize {
+        ENVELOPE_TAG_HEAD_LEN + self.untagged_cbor_data_len()
+    }
+
+    fn untagged_cbor_data_len(&self) -> usize {
+        if let Some(encoding) = self.cached_encoding() {
+            return encoding.len() - ENVELOPE_TAG_HEAD_LEN;
+        }
+        match self.case() {
+            EnvelopeCase::Node { subject, assertions, digest: _ } => {
+                head_len(1 + assertions.len() as u64)
+                    + subject.untagged_cbor_data_len()
+                    + assertions.iter().map(Self::untagged_cbor_data_len).sum::<usize>()
+            }
+            EnvelopeCase::Leaf { cbor, digest: _ } => head_len(tags::TAG_LEAF) + cbor_len(cbor),
+            EnvelopeCase::Wrapped { envelope, digest: _ } => envelope.tagged_cbor_data_len(),
+            EnvelopeCase::Assertion(assertion) => {
+                head_len(1) + assertion.predicate().untagged_cbor_data_len() + assertion.object().untagged_cbor_data_len()
+            }
+            _ => cbor_len(&self.untagged_cbor()),
+        }
+    }
+
+    fn untagged_cbor_data_into(&self, out: &mut Vec<u8>) {
+        if let Some(encoding) = self.cached_encoding() {
+            out.extend_from_slice(&encoding[ENVELOPE_TAG_HEAD_LEN..]);
+            return;
+        }
+        match self.case() {
+            EnvelopeCase::Node { subject, assertions, digest: _ } => {
+                write_head(MAJOR_ARRAY, 1 + assertions.len() as u64, out);
+                subject.untagged_cbor_data_into(out);
+                for assertion in assertions {
+                    assertion.untagged_cbor_data_into(out);
+                }
+            }
+            EnvelopeCase::Leaf { cbor, digest: _ } => {
+                write_head(MAJOR_TAGGED, tags::TAG_LEAF, out);
+                write_cbor(cbor, out);
+            }
+            EnvelopeCase::Wrapped { envelope, digest: _ } => envelope.cbor_data_into(out),
+            EnvelopeCase::Assertion(assertion) => {
+                write_head(MAJOR_MAP, 1, out);
+                assertion.predicate().untagged_cbor_data_into(out);
+                assertion.object().untagged_cbor_data_into(out);
+            }
+            _ => write_cbor(&self.untagged_cbor(), out),
+        }
+    }
+}
+
+const MAJOR_UNSIGNED: u8 = 0;
+const MAJOR_NEGATIVE: u8 = 1;
+const MAJOR_BYTE_STRING: u8 = 2;
+const MAJOR_TEXT: u8 = 3;
+const MAJOR_ARRAY: u8 = 4;
+const MAJOR_MAP: u8 = 5;
+const MAJOR_TAGGED: u8 = 6;
+
+/// Appends the head of a CBOR data item, in its shortest form, to `out`.
+fn write_head(major_type: u8, value: u64, out: &mut Vec<u8>) {
+    let major = major_type << 5;
+    if value <= 23 {
+        out.push(major | value as u8);
+    } else if value <= u8::MAX as u64 {
+        out.extend_from_slice(&[major | 0x18, value as u8]);
+    } else if value <= u16::MAX as u64 {
+        out.push(major | 0x19);
+        out.extend_from_slice(&(value as u16).to_be_bytes());
+    } else if value <= u32::MAX as u64 {
+        out.push(major | 0x1a);
+        out.extend_from_slice(&(value as u32).to_be_bytes());
+    } else {
+        out.push(major | 0x1b);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+/// Returns the length of the head of a CBOR data item whose argument is
+/// `value`.
+fn head_len(value: u64) -> usize {
+    match value {
+        0..=23 => 1,
+        24..=0xff => 2,
+        0x100..=0xffff => 3,
+        0x1_0000..=0xffff_ffff => 5,
+        _ => 9,
+    }
+}
+
+/// Returns the length of `cbor.to_cbor_data()`, encoding only the items
+/// whose encoding is not a copy of their contents.
+fn cbor_len(cbor: &CBOR) -> usize {
+    match cbor.as_case() {
+        CBORCase::Unsigned(value) | CBORCase::Negative(value) => head_len(*value),
+        CBORCase::ByteString(bytes) => head_len(bytes.len() as u64) + bytes.len(),
+        CBORCase::Text(text) if is_nfc(text) => head_len(text.len() as u64) + text.len(),
+        CBORCase::Array(items) => head_len(items.len() as u64) + items.iter().map(cbor_len).sum::<usize>(),
+        CBORCase::Map(map) => {
+            head_len(map.len() as u64) + map.iter().map(|(key, value)| cbor_len(key) + cbor_len(value)).sum::<usize>()
+        }
+        CBORCase::Tagged(tag, item) => head_len(tag.value()) + cbor_len(item),
+        _ => cbor.to_cbor_data().len(),
+    }
+}
+
+/// Appends the encoding of `cbor` to `out`, producing the same bytes as
+/// `cbor.to_cbor_data()`.
+fn write_cbor(cbor: &CBOR, out: &mut Vec<u8>) {
+    match cbor.as_case() {
+        CBORCase::Unsigned(value) => write_head(MAJOR_UNSIGNED, *value, out),
+        CBORCase::Negative(value) => write_head(MAJOR_NEGATIVE, *value, out),
+        CBORCase::ByteString(bytes) => {
+            write_head(MAJOR_BYTE_STRING, bytes.len() as u64, out);
+            out.extend_from_slice(bytes);
+        }
+        // Text that is not already in NFC is normalized by the encoder.
+        CBORCase::Text(text) if is_nfc(text) => {
+            write_head(MAJOR_TEXT, text.len() as u64, out);
+            out.extend_from_slice(text.as_bytes());
+        }
+        CBORCase::Array(items) => {
+            write_head(MAJOR_ARRAY, items.len() as u64, out);
+            for item in items {
+                write_cbor(item, out);
+            }
+        }
+        CBORCase::Map(map) => {
+            write_head(MAJOR_MAP, map.len() as u64, out);
+            for (key, value) in map.iter() {
+                write_cbor(key, out);
+                write_cbor(value, out);
+            }
+        }
+        CBORCase::Tagged(tag, item) => {
+            write_head(MAJOR_TAGGED, tag.value(), out);
+            write_cbor(item, out);
+        }
+        _ => out.extend_from_slice(&cbor.to_cbor_data()),
+    }
 }
 
 impl CBORTaggedDecodable for Envelope {
+    /// Decodes an envelope from untagged CBOR, subject to the default
+    /// [`EnvelopeLimits`].
     fn from_untagged_cbor(cbor: CBOR) -> Result<Self> {
+        Self::from_untagged_cbor_with_limits(cbor, &EnvelopeLimits::default())
+    }
+
+    /// Decodes an envelope from tagged CBOR data, subject to the default
+    /// [`EnvelopeLimits`].
+    fn from_tagged_cbor_data(data: impl AsRef<[u8]>) -> Result<Self> {
+        Self::from_tagged_cbor_data_with_limits(data, &EnvelopeLimits::default())
+    }
+
+    /// Decodes an envelope from untagged CBOR data, subject to the default
+    /// [`EnvelopeLimits`].
+    fn from_untagged_cbor_data(data: impl AsRef<[u8]>) -> Result<Self> {
+        Self::decode_data(data.as_ref(), false, &mut DecodeBudget::new(&EnvelopeLimits::default()).parsed())
+    }
+}
+
+/// Parses the encoding of an envelope, first checking the lengths of its
+/// nodes against the limits, so that a node with too many assertions is
+/// rejected before they are allocated.
+fn parse_data(data: &[u8], tagged: bool, limits: &EnvelopeLimits) -> Result<CBOR> {
+    limits.check_node_headers(data, tagged)?;
+    CBOR::try_from_data(data).map_err(Error::msg)
+}
+
+impl Envelope {
+    /// Parses and decodes the encoding of an envelope with a budget for
+    /// parsed data.
+    ///
+    /// The parser only accepts canonical encodings, so the leaves of the
+    /// parsed CBOR are not checked again as they are decoded.
+    fn decode_data(data: &[u8], tagged: bool, budget: &mut DecodeBudget<'_>) -> Result<Self> {
+        debug_assert!(budget.is_parsed());
+        let cbor = parse_data(data, tagged, budget.limits())?;
+        if tagged {
+            Self::decode_tagged(&cbor, budget)
+        } else {
+            Self::decode_untagged(&cbor, budget)
+        }
+    }
+}
+
+/// Support for decoding envelopes subject to limits.
+impl Envelope {
+    /// Decodes an envelope from untagged CBOR, subject to the given limits.
+    ///
+    /// Elements are counted, and their memory estimated, as they are decoded,
+    /// and decoding stops with `EnvelopeError::LimitExceeded` as soon as
+    /// either exceeds its limit.
+    pub fn from_untagged_cbor_with_limits(cbor: CBOR, limits: &EnvelopeLimits) -> Result<Self> {
+        Self::decode_untagged(&cbor, &mut DecodeBudget::new(limits))
+    }
+
+    /// Decodes an envelope from tagged CBOR, subject to the given limits.
+    ///
+    /// See [`Envelope::from_untagged_cbor_with_limits`].
+    pub fn from_tagged_cbor_with_limits(cbor: CBOR, limits: &EnvelopeLimits) -> Result<Self> {
+        Self::decode_tagged(&cbor, &mut DecodeBudget::new(limits))
+    }
+
+    /// Decodes an envelope from tagged CBOR subject to the given limits, and
+    /// returns it with counts of the work done decoding it.
+    ///
+    /// Decoding reuses recently decoded elements for byte-identical copies,
+    /// so an encoding that repeats an element many times costs one digest
+    /// per distinct element. The result is the same as that of
+    /// [`Envelope::from_tagged_cbor_with_limits`].
+    pub fn from_tagged_cbor_with_stats(cbor: CBOR, limits: &EnvelopeLimits) -> Result<(Self, DecodeStats)> {
+        let mut budget = DecodeBudget::new(limits);
+        let envelope = Self::decode_tagged(&cbor, &mut budget)?;
+        Ok((envelope, budget.stats()))
+    }
+
+    /// Decodes an envelope from tagged CBOR data subject to the given limits,
+    /// and returns it with counts of the work done decoding it.
+    ///
+    /// See [`Envelope::from_tagged_cbor_with_stats`].
+    pub fn from_tagged_cbor_data_with_stats(data: impl AsRef<[u8]>, limits: &EnvelopeLimits) -> Result<(Self, DecodeStats)> {
+        let mut budget = DecodeBudget::new(limits).parsed();
+        let envelope = Self::decode_data(data.as_ref(), true, &mut budget)?;
+        Ok((envelope, budget.stats()))
+    }
+
+    /// Decodes an envelope from tagged CBOR data, subject to the given limits.
+    ///
+    /// See [`Envelope::from_untagged_cbor_with_limits`].
+    pub fn from_tagged_cbor_data_with_limits(data: impl AsRef<[u8]>, limits: &EnvelopeLimits) -> Result<Self> {
+        Self::decode_data(data.as_ref(), true, &mut DecodeBudget::new(limits).parsed())
+    }
+
+    /// Decodes an envelope from a UR string, subject to the given limits.
+    ///
+    /// See [`Envelope::from_ur_string`] and
+    /// [`Envelope::from_untagged_cbor_with_limits`].
+    pub fn from_ur_string_with_limits(ur_string: impl Into<String>, limits: &EnvelopeLimits) -> Result<Self> {
+        // URs are case-insensitive, and are uppercase in QR codes.
+        let ur_string = ur_string.into().to_lowercase();
+        let Some((ur_type, _)) = ur_string.strip_prefix("ur:").and_then(|rest| rest.split_once('/')) else {
+            bail!(EnvelopeError::NotUR { message: "expected `ur:<type>/<data>`".to_string() });
+        };
+        if ur_type != ENVELOPE_UR_TYPE {
+            bail!(EnvelopeError::WrongURType { ur_type: ur_type.to_string() });
+        }
+        let ur = UR::from_ur_string(ur_string.as_str()).map_err(|error| match error.downcast_ref::<bc_ur::Error>() {
+            Some(error) => EnvelopeError::NotUR { message: error.to_string() },
+            None => EnvelopeError::InvalidURCBOR { message: error.to_string() },
+        })?;
+        Self::from_ur_cbor_with_limits(ur.cbor(), limits)
+    }
+
+    /// Decodes an envelope from the CBOR of a UR.
+    ///
+    /// Envelope errors, such as exceeding the limits, are passed through
+    /// unchanged, and any other failure is reported as
+    /// `EnvelopeError::InvalidURCBOR`.
+    pub(crate) fn from_ur_cbor_with_limits(cbor: CBOR, limits: &EnvelopeLimits) -> Result<Self> {
+        // The UR's CBOR was parsed from its data.
+        Self::decode_untagged(&cbor, &mut DecodeBudget::new(limits).parsed()).map_err(|error| {
+            if error.downcast_ref::<EnvelopeError>().is_some() {
+                error
+            } else {
+                EnvelopeError::InvalidURCBOR { message: error.to_string() }.into()
+            }
+        })
+    }
+}
+
+/// The type of the UR of an envelope.
+pub(crate) const ENVELOPE_UR_TYPE: &str = "envelope";
+
+/// Support for Uniform Resources (URs) of envelopes.
+impl Envelope {
+    /// Returns the UR string of this envelope, `ur:envelope/...`.
+    ///
+    /// Unlike [`UREncodable::ur_string`](bc_ur::UREncodable::ur_string), this
+    /// does not require the tags to have been registered.
+    pub fn ur_string(&self) -> String {
+        UR::new(ENVELOPE_UR_TYPE, self.untagged_cbor()).unwrap().string()
+    }
+
+    /// Decodes an envelope from its UR string, subject to the default
+    /// [`EnvelopeLimits`].
+    ///
+    /// The string is case-insensitive, so the uppercase URs used in QR codes
+    /// are accepted.
+    ///
+    /// - Throws: `EnvelopeError::NotUR` if the string is not a single-part
+    /// UR, `EnvelopeError::WrongURType` if it is a UR of a type other than
+    /// `envelope`, and `EnvelopeError::InvalidURCBOR` if its content is not
+    /// the CBOR of an envelope. Envelope errors raised while decoding that
+    /// content, such as `EnvelopeError::InvalidFormat` or
+    /// `EnvelopeError::LimitExceeded`, are passed through unchanged.
+    pub fn from_ur_string(ur_string: impl Into<String>) -> Result<Self> {
+        Self::from_ur_string_with_limits(ur_string, &EnvelopeLimits::default())
+    }
+
+    fn decode_tagged(cbor: &CBOR, budget: &mut DecodeBudget<'_>) -> Result<Self> {
         match cbor.as_case() {
             CBORCase::Tagged(tag, item) => {
+                let cbor_tags = Self::cbor_tags();
+                if !cbor_tags.contains(tag) {
+                    bail!(CBORError::WrongTag(cbor_tags[0].clone(), tag.clone()))
+                }
+                Self::decode_untagged(item, budget)
+            }
+            _ => bail!(CBORError::WrongType),
+        }
+    }
+
+    pub(crate) fn decode_untagged(cbor: &CBOR, budget: &mut DecodeBudget<'_>) -> Result<Self> {
+        budget.descend()?;
+        let result = Self::decode_nested(cbor, budget);
+        budget.ascend();
+        result
+    }
+
+    fn decode_nested(cbor: &CBOR, budget: &mut DecodeBudget<'_>) -> Result<Self> {
+        // Only elements byte-identical to one already decoded, and so already
+        // checked, are reused.
+        if !budget.limits().reuse_duplicates || !is_reusable(cbor) {
+            return Self::decode_element(cbor, budget);
+        }
+        if let Some(envelope) = budget.reuse(cbor) {
+            return Ok(envelope);
+        }
+        let mark = budget.mark();
+        let envelope = Self::decode_element(cbor, budget)?;
+        budget.remember(cbor, &envelope, mark);
+        Ok(envelope)
+    }
+
+    fn decode_element(cbor: &CBOR, budget: &mut DecodeBudget<'_>) -> Result<Self> {
+        match cbor.as_case() {
+            CBORCase::Tagged(tag, item) => {
+                budget.charge(0)?;
                 match tag.value() {
                     tags::TAG_LEAF | tags::TAG_ENCODED_CBOR => {
+                        // The digest of a leaf covers its re-encoding, so a
+                        // leaf that does not re-encode to itself would have a
+                        // digest that disagrees with other implementations.
+                        // CBOR parsed from data is canonical already.
+                        if !budget.is_parsed() && CBOR::try_from_data(item.to_cbor_data()).ok().as_ref() != Some(item) {
+                            bail!(EnvelopeError::NonCanonicalLeaf)
+                        }
                         Ok(Self::new_leaf(item.clone()))
                     },
                     tags::TAG_ENVELOPE => {
-                        let envelope = Envelope::try_from(cbor)?;
+                        let envelope = Self::decode_tagged(cbor, budget)?;
                         Ok(Self::new_wrapped(envelope))
                     },
                     #[cfg(feature = "encrypt")]
@@ -93,34 +473,232 @@ impl CBORTaggedDecodable for Envelope {
                         let envelope = Self::new_with_compressed(compressed)?;
                         Ok(envelope)
                     },
-                    _ => bail!("unknown envelope tag: {}", tag.value()),
+                    _ => bail!(EnvelopeError::InvalidFormat { message: format!("unknown envelope tag: {}", tag.value()) }),
                 }
             }
             CBORCase::ByteString(bytes) => {
+                budget.charge(0)?;
                 Ok(Self::new_elided(Digest::from_data_ref(bytes)?))
             }
             CBORCase::Array(elements) => {
                 if elements.len() < 2 {
-                    bail!("node must have at least two elements")
+                    bail!(EnvelopeError::InvalidFormat { message: "node must have at least two elements".to_string() })
+                }
+                if matches!(elements[0].as_case(), CBORCase::Array(_)) {
+                    bail!(EnvelopeError::NestedNode)
                 }
-                let subject = Self::from_untagged_cbor(elements[0].clone())?;
-                let assertions: Vec<Envelope> = elements[1..]
+                budget.limits().check_node_length(elements.len())?;
+                budget.charge(elements.len())?;
+                let subject = Self::decode_untagged(&elements[0], budget)?;
+                let assertions = elements[1..]
                     .iter()
-                    .cloned()
-                    .map(Self::from_untagged_cbor)
+                    .map(|element| Self::decode_untagged(element, budget))
                     .collect::<Result<Vec<Self>, Error>>()?;
-                Ok(Self::new_with_assertions(subject, assertions)?)
+                let is_canonical = assertions.windows(2).all(|pair| pair[0].digest() < pair[1].digest());
+                let envelope = Self::new_with_assertions(subject, assertions)?;
+                if budget.is_strict() && !is_canonical {
+                    bail!(EnvelopeError::NonCanonicalAssertionOrder(envelope.digest().into_owned()))
+                }
+                Ok(envelope)
             }
-            CBORCase::Map(_) => {
-                let assertion = Assertion::try_from(cbor)?;
+            CBORCase::Map(map) => {
+                budget.charge(2)?;
+                let assertion = Assertion::from_map_with_budget(map, budget)?;
                 Ok(Self::new_with_assertion(assertion))
             }
             #[cfg(feature = "known_value")]
             CBORCase::Unsigned(value) => {
+                budget.charge(0)?;
                 let known_value = KnownValue::new(*value);
                 Ok(Self::new_with_known_value(known_value))
             }
-            _ => bail!("invalid envelope"),
+            _ => bail!(EnvelopeError::InvalidFormat { message: "invalid envelope".to_string() }),
+        }
+    }
+}
+
+/// The minimum size of the content of a leaf that is reused when it repeats.
+/// Smaller leaves are cheaper to decode than to compare.
+const REUSABLE_LEAF_SIZE: usize = 64;
+
+/// Returns whether an element decoded from `cbor` is remembered, so that
+/// copies of it can be reused: a leaf with large text or byte string content,
+/// or an assertion whose predicate and object are leaves, known values, or
+/// elided. Comparing such an element to a candidate copy costs at most the
+/// size of its leaves.
+fn is_reusable(cbor: &CBOR) -> bool {
+    fn is_large_leaf(item: &CBOR) -> bool {
+        match item.as_case() {
+            CBORCase::Text(text) => text.len() >= REUSABLE_LEAF_SIZE,
+            CBORCase::ByteString(bytes) => bytes.len() >= REUSABLE_LEAF_SIZE,
+            _ => false,
+        }
+    }
+
+    fn is_simple(element: &CBOR) -> bool {
+        match element.as_case() {
+            CBORCase::Tagged(tag, item) => tag.value() == tags::TAG_LEAF && !matches!(item.as_case(), CBORCase::Tagged(_, _)),
+            CBORCase::Unsigned(_) | CBORCase::ByteString(_) => true,
+            _ => false,
+        }
+    }
+
+    match cbor.as_case() {
+        CBORCase::Tagged(tag, item) => tag.value() == tags::TAG_LEAF && is_large_leaf(item),
+        CBORCase::Map(map) => map.len() == 1 && map.iter().all(|(predicate, object)| is_simple(predicate) && is_simple(object)),
+        _ => false,
+    }
+}
+
+/// Support for validated decoding of envelopes.
+impl Envelope {
+    /// Decodes an envelope from tagged CBOR, rejecting nodes whose assertions
+    /// are not already in canonical order.
+    ///
+    /// The assertions of a node are encoded in ascending order of their
+    /// digests, with no duplicates. `from_tagged_cbor` sorts assertions that
+    /// are out of order, so the decoded envelope's encoding can differ from
+    /// the bytes received. Use this instead when the exact encoding matters,
+    /// for example because a signature covered it.
+    ///
+    /// Returns `EnvelopeError::NonCanonicalAssertionOrder` with the digest of
+    /// the first node whose assertions are out of order or duplicated.
+    pub fn from_tagged_cbor_strict(cbor: CBOR) -> Result<Self> {
+        Self::from_tagged_cbor_strict_with_limits(cbor, &EnvelopeLimits::default())
+    }
+
+    /// Decodes an envelope from tagged CBOR subject to the given limits,
+    /// rejecting nodes whose assertions are not already in canonical order.
+    ///
+    /// See [`Envelope::from_tagged_cbor_strict`] and
+    /// [`Envelope::from_untagged_cbor_with_limits`].
+    pub fn from_tagged_cbor_strict_with_limits(cbor: CBOR, limits: &EnvelopeLimits) -> Result<Self> {
+        Self::decode_tagged(&cbor, &mut DecodeBudget::new(limits).strict())
+    }
+
+    /// Decodes an envelope from tagged CBOR data, rejecting nodes whose
+    /// assertions are not already in canonical order.
+    ///
+    /// See [`Envelope::from_tagged_cbor_strict`].
+    pub fn from_tagged_cbor_data_strict(data: impl AsRef<[u8]>) -> Result<Self> {
+        Self::decode_data(data.as_ref(), true, &mut DecodeBudget::new(&EnvelopeLimits::default()).strict().parsed())
+    }
+
+    /// Decodes an envelope from tagged CBOR, and validates it.
+    ///
+    /// In addition to the checks performed by `from_tagged_cbor`, this confirms
+    /// that:
+    ///
+    /// * Re-encoding the decoded envelope reproduces the original CBOR
+    ///   exactly.
+    /// * Every compressed element decompresses to an envelope whose digest
+    ///   matches the digest it declares, recursively.
+    ///
+    /// Encrypted elements cannot be validated without their key, and are
+    /// accepted as-is.
+    ///
+    /// Returns `EnvelopeError::InvalidElement` identifying the digest of the
+    /// first element that failed validation.
+    pub fn from_tagged_cbor_checked(cbor: CBOR) -> Result<Self> {
+        Self::from_tagged_cbor_checked_with_limits(cbor, &EnvelopeLimits::default())
+    }
+
+    /// Decodes an envelope from tagged CBOR subject to the given limits, and
+    /// validates it.
+    ///
+    /// See [`Envelope::from_tagged_cbor_checked`] and
+    /// [`Envelope::from_untagged_cbor_with_limits`].
+    pub fn from_tagged_cbor_checked_with_limits(cbor: CBOR, limits: &EnvelopeLimits) -> Result<Self> {
+        let envelope = Self::from_tagged_cbor_with_limits(cbor.clone(), limits)?;
+        if envelope.tagged_cbor() != cbor {
+            bail!(EnvelopeError::InvalidElement(envelope.digest().into_owned()));
+        }
+        envelope.check_element_digests()?;
+        Ok(envelope)
+    }
+
+    /// Decodes an envelope from tagged CBOR data, and validates it.
+    ///
+    /// See [`Envelope::from_tagged_cbor_checked`].
+    pub fn from_tagged_cbor_data_checked(data: impl AsRef<[u8]>) -> Result<Self> {
+        Self::from_tagged_cbor_checked(parse_data(data.as_ref(), true, &EnvelopeLimits::default())?)
+    }
+
+    /// Decodes an envelope from tagged CBOR data subject to the given limits,
+    /// and validates it.
+    ///
+    /// See [`Envelope::from_tagged_cbor_checked_with_limits`].
+    pub fn from_tagged_cbor_data_checked_with_limits(data: impl AsRef<[u8]>, limits: &EnvelopeLimits) -> Result<Self> {
+        Self::from_tagged_cbor_checked_with_limits(parse_data(data.as_ref(), true, limits)?, limits)
+    }
+
+    /// Round-trips the envelope through its tagged CBOR encoding, and returns
+    /// the envelope if the decoded envelope has the same digest.
+    ///
+    /// This is intended for test suites that build envelopes by hand. It also
+    /// checks that the streaming encoding matches the encoding of the
+    /// envelope's CBOR tree. Returns `EnvelopeError::EncodingMismatch`, whose
+    /// message shows the expected and actual results formatted with
+    /// `context`, if any of these checks fail.
+    pub fn check_encoding_opt(&self, context: Option<&FormatContext>) -> Result<Self> {
+        let cbor = self.tagged_cbor();
+        let data = self.tagged_cbor_data();
+        if data != cbor.to_cbor_data() {
+            bail!(EnvelopeError::EncodingMismatch {
+                reason: "the streaming encoding differs from the CBOR tree".to_string(),
+                expected: hex::encode(cbor.to_cbor_data()),
+                got: hex::encode(data),
+            });
+        }
+        let restored = match Self::from_tagged_cbor(cbor) {
+            Ok(restored) => restored,
+            Err(error) => bail!(EnvelopeError::EncodingMismatch {
+                reason: error.to_string(),
+                expected: self.format_opt(context),
+                got: self.diagnostic_opt(false, context),
+            }),
+        };
+        if self.digest() != restored.digest() {
+            bail!(EnvelopeError::EncodingMismatch {
+                reason: "digest did not match".to_string(),
+                expected: self.format_opt(context),
+                got: restored.format_opt(context),
+            });
+        }
+        Ok(self.clone())
+    }
+
+    /// Round-trips the envelope through its tagged CBOR encoding, formatting
+    /// any failure with the global format context.
+    ///
+    /// See [`Envelope::check_encoding_opt`].
+    pub fn check_encoding(&self) -> Result<Self> {
+        with_format_context!(|context| {
+            self.check_encoding_opt(Some(context))
+        })
+    }
+
+    fn check_element_digests(&self) -> Result<()> {
+        match self.case() {
+            EnvelopeCase::Node { subject, assertions, .. } => {
+                subject.check_element_digests()?;
+                for assertion in assertions {
+                    assertion.check_element_digests()?;
+                }
+                Ok(())
+            },
+            EnvelopeCase::Wrapped { envelope, .. } => envelope.check_element_digests(),
+            EnvelopeCase::Assertion(assertion) => {
+                assertion.predicate().check_element_digests()?;
+                assertion.object().check_element_digests()
+            },
+            #[cfg(feature = "compress")]
+            EnvelopeCase::Compressed(_) => {
+                let uncompressed = self.uncompress()
+                    .map_err(|_| EnvelopeError::InvalidElement(self.digest().into_owned()))?;
+                uncompressed.check_element_digests()
+            },
+            _ => Ok(()),
         }
     }
 }
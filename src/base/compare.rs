@@ -0,0 +1,185 @@
+use anyhow::{bail, Result};
+use bc_components::tags;
+
+use crate::{EnvelopeError, EnvelopeLimits};
+use super::limits::HeadReader;
+
+/// A location at which two encoded envelopes differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// The path from the root to the differing element, such as
+    /// `["wrapped", "assertion[1]", "object"]`.
+    pub path: Vec<String>,
+    /// The byte offset of the element in the first encoding, if present.
+    pub offset_a: Option<usize>,
+    /// The byte offset of the element in the second encoding, if present.
+    pub offset_b: Option<usize>,
+    /// The kind of the element in the first encoding (see
+    /// [`Envelope::case_name`](crate::Envelope::case_name)), or `"Absent"`.
+    pub kind_a: &'static str,
+    /// The kind of the element in the second encoding (see
+    /// [`Envelope::case_name`](crate::Envelope::case_name)), or `"Absent"`.
+    pub kind_b: &'static str,
+}
+
+/// The result of [`compare_encoded`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComparisonResult {
+    /// Whether the encodings are byte-for-byte identical.
+    pub identical: bool,
+    /// The locations at which the encodings differ, in encoding order.
+    pub divergences: Vec<Divergence>,
+    /// Whether there were more divergences than were reported.
+    pub truncated: bool,
+}
+
+/// Compares two encoded envelopes and reports where they differ.
+///
+/// If the encodings are identical, no further work is done. Otherwise both are
+/// walked in step, reading only the heads of their data items, skipping
+/// identical subtrees and reporting up to `max_differences` locations where
+/// they diverge. Leaves and obscured (elided, encrypted, or compressed)
+/// elements are treated as opaque: a difference within them is reported at
+/// the element itself.
+///
+/// Returns an error if either input is not a well-formed tagged envelope.
+pub fn compare_encoded(a: &[u8], b: &[u8], max_differences: usize) -> Result<ComparisonResult> {
+    if a == b {
+        return Ok(ComparisonResult { identical: true, divergences: Vec::new(), truncated: false });
+    }
+    let a = Encoding { data: a, root: untag_envelope(a)? };
+    let b = Encoding { data: b, root: untag_envelope(b)? };
+    let mut comparison = Comparison { a, b, max_differences, divergences: Vec::new(), truncated: false };
+    let mut path = Vec::new();
+    comparison.compare(Some(a.root), Some(b.root), &mut path);
+    Ok(ComparisonResult { identical: false, divergences: comparison.divergences, truncated: comparison.truncated })
+}
+
+/// Returns the offset of the content of a tagged envelope, checking that the
+/// data holds exactly one well-formed data item.
+fn untag_envelope(data: &[u8]) -> Result<usize> {
+    let mut reader = HeadReader::new(data);
+    if reader.skip().is_none() || reader.pos() != data.len() {
+        bail!(EnvelopeError::InvalidFormat { message: "malformed CBOR".to_string() });
+    }
+    reader.seek(0);
+    match reader.head() {
+        Some((6, tag)) if tag == tags::TAG_ENVELOPE => Ok(reader.pos()),
+        _ => bail!(EnvelopeError::InvalidFormat { message: "not a tagged envelope".to_string() }),
+    }
+}
+
+/// A well-formed envelope encoding, and the offset of its content.
+#[derive(Clone, Copy)]
+struct Encoding<'a> {
+    data: &'a [u8],
+    root: usize,
+}
+
+impl<'a> Encoding<'a> {
+    /// Returns the bytes of the data item at `offset`.
+    fn item(&self, offset: usize) -> &'a [u8] {
+        let mut reader = HeadReader::new(self.data);
+        reader.seek(offset);
+        // The whole encoding was checked to be well-formed.
+        let _ = reader.skip();
+        &self.data[offset..reader.pos()]
+    }
+
+    /// Returns the kind of the untagged envelope element at `offset`.
+    fn kind(&self, offset: usize) -> &'static str {
+        let mut reader = HeadReader::new(self.data);
+        reader.seek(offset);
+        match reader.head() {
+            Some((4, _)) => "Node",
+            Some((5, _)) => "Assertion",
+            Some((2, _)) => "Elided",
+            Some((0, _)) => "KnownValue",
+            Some((6, tag)) => match tag {
+                tags::TAG_LEAF | tags::TAG_ENCODED_CBOR => "Leaf",
+                tags::TAG_ENVELOPE => "Wrapped",
+                tags::TAG_ENCRYPTED => "Encrypted",
+                tags::TAG_COMPRESSED => "Compressed",
+                _ => "Invalid",
+            },
+            _ => "Invalid",
+        }
+    }
+
+    /// Returns the children of the untagged envelope element at `offset`,
+    /// with their labels and offsets.
+    fn children(&self, offset: usize) -> Vec<(String, usize)> {
+        let mut reader = HeadReader::new(self.data);
+        reader.seek(offset);
+        let mut children = Vec::new();
+        let mut child = |reader: &mut HeadReader<'_>, label: String| {
+            children.push((label, reader.pos()));
+            let _ = reader.skip();
+        };
+        match reader.head() {
+            Some((4, length)) => {
+                for index in 0..length {
+                    let label = if index == 0 { "subject".to_string() } else { format!("assertion[{}]", index - 1) };
+                    child(&mut reader, label);
+                }
+            },
+            Some((5, count)) => {
+                for _ in 0..count {
+                    child(&mut reader, "predicate".to_string());
+                    child(&mut reader, "object".to_string());
+                }
+            },
+            Some((6, tag)) if tag == tags::TAG_ENVELOPE => child(&mut reader, "wrapped".to_string()),
+            _ => {},
+        }
+        children
+    }
+}
+
+struct Comparison<'a> {
+    a: Encoding<'a>,
+    b: Encoding<'a>,
+    max_differences: usize,
+    divergences: Vec<Divergence>,
+    truncated: bool,
+}
+
+impl Comparison<'_> {
+    fn compare(&mut self, a: Option<usize>, b: Option<usize>, path: &mut Vec<String>) {
+        if self.truncated {
+            return;
+        }
+        let (kind_a, kind_b) = (a.map_or("Absent", |a| self.a.kind(a)), b.map_or("Absent", |b| self.b.kind(b)));
+        if let (Some(a), Some(b)) = (a, b) {
+            if self.a.item(a) == self.b.item(b) {
+                return;
+            }
+            let children_a = self.a.children(a);
+            let children_b = self.b.children(b);
+            // Elements nested too deeply to decode are compared as opaque.
+            if kind_a == kind_b && !children_a.is_empty() && path.len() < EnvelopeLimits::DEFAULT_MAX_DEPTH {
+                let count = children_a.len().max(children_b.len());
+                for index in 0..count {
+                    let child_a = children_a.get(index);
+                    let child_b = children_b.get(index);
+                    let label = child_a.or(child_b).map(|(label, _)| label.clone()).unwrap_or_default();
+                    path.push(label);
+                    self.compare(child_a.map(|(_, offset)| *offset), child_b.map(|(_, offset)| *offset), path);
+                    path.pop();
+                }
+                return;
+            }
+        }
+        if self.divergences.len() == self.max_differences {
+            self.truncated = true;
+            return;
+        }
+        self.divergences.push(Divergence {
+            path: path.clone(),
+            offset_a: a,
+            offset_b: b,
+            kind_a,
+            kind_b,
+        });
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use anyhow::Result;
+use bc_components::DigestProvider;
+
+use super::envelope::EnvelopeCase;
+use crate::{Envelope, EnvelopeEncodable, EnvelopeLimits};
+
+/// An accumulator for the assertions of a node that are produced by several
+/// threads at once.
+///
+/// The builder is a cheap handle to shared state: clones push to the same
+/// node, and [`ConcurrentNodeBuilder::push`] takes `&self`, so it can be used
+/// from a parallel iterator directly. Assertions are buffered in shards
+/// selected by the pushing thread, so threads rarely wait for each other.
+///
+/// [`ConcurrentNodeBuilder::finish`] merges the shards, removes duplicates,
+/// and sorts the assertions once, constructing the node (and calculating its
+/// digest) a single time. The result does not depend on the order in which
+/// the assertions were pushed, or on which threads pushed them.
+#[derive(Clone)]
+pub struct ConcurrentNodeBuilder {
+    subject: Envelope,
+    shards: Arc<[Mutex<Vec<Envelope>>]>,
+}
+
+impl ConcurrentNodeBuilder {
+    /// Creates a builder for a node with the given subject.
+    ///
+    /// If the subject is itself a node, its assertions are kept, as when
+    /// adding assertions to it.
+    pub fn new(subject: impl EnvelopeEncodable) -> Self {
+        let shard_count = std::thread::available_parallelism().map_or(1, |n| n.get()) * 4;
+        let shards: Arc<[Mutex<Vec<Envelope>>]> = (0..shard_count).map(|_| Mutex::new(Vec::new())).collect();
+        let subject = subject.into_envelope();
+        let subject = match subject.case() {
+            EnvelopeCase::Node { subject, assertions, .. } => {
+                shards[0].lock().unwrap_or_else(PoisonError::into_inner).extend(assertions.iter().cloned());
+                subject.clone()
+            },
+            _ => subject,
+        };
+        Self { subject, shards }
+    }
+
+    /// Adds the given assertion envelope.
+    ///
+    /// The assertion envelope must be a valid assertion envelope, or an
+    /// obscured variant (elided, encrypted, compressed) of one. This is checked
+    /// when the node is finished.
+    pub fn push(&self, assertion: impl EnvelopeEncodable) {
+        self.shard().push(assertion.into_envelope());
+    }
+
+    /// Adds the given assertion envelopes, taking the shard's lock once.
+    pub fn extend(&self, assertions: impl IntoIterator<Item = Envelope>) {
+        self.shard().extend(assertions);
+    }
+
+    /// Constructs the node from the subject and the assertions pushed by
+    /// every handle.
+    ///
+    /// Call this once every worker has finished pushing: assertions pushed by
+    /// other handles afterwards are not included. Assertions with the same
+    /// digest are included once; if they differ in structure (for example,
+    /// one is elided), the one kept is chosen by structure rather than by
+    /// the order in which they were pushed.
+    ///
+    /// Returns the subject unchanged if no assertions were pushed, or an error
+    /// if any of them is not an assertion envelope, or if there are more than
+    /// [`EnvelopeLimits::DEFAULT_MAX_NODE_ASSERTIONS`] distinct assertions.
+    pub fn finish(self) -> Result<Envelope> {
+        let mut assertions: Vec<Envelope> = Vec::new();
+        for shard in self.shards.iter() {
+            assertions.append(&mut shard.lock().unwrap_or_else(PoisonError::into_inner));
+        }
+        if assertions.is_empty() {
+            return Ok(self.subject);
+        }
+        assertions.sort_by(|a, b| {
+            a.digest().cmp(&b.digest())
+                .then_with(|| a.structural_digest().cmp(&b.structural_digest()))
+        });
+        assertions.dedup_by(|a, b| a.digest() == b.digest());
+        EnvelopeLimits::default().check_node_length(assertions.len() + 1)?;
+        Envelope::new_with_assertions(self.subject, assertions)
+    }
+
+    fn shard(&self) -> std::sync::MutexGuard<'_, Vec<Envelope>> {
+        let mut hasher = DefaultHasher::new();
+        std::thread::current().id().hash(&mut hasher);
+        let index = hasher.finish() as usize % self.shards.len();
+        // A panic while a shard is locked cannot leave it inconsistent, as
+        // it is only ever appended to or emptied.
+        self.shards[index].lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
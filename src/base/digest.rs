@@ -64,6 +64,23 @@ impl Envelope {
         result.into_inner()
     }
 
+    /// Returns the envelope's digest as a lowercase hexadecimal string.
+    pub fn digest_hex(&self) -> String {
+        self.digest().hex()
+    }
+
+    /// Returns the first `prefix_len` bytes of the envelope's digest as a
+    /// lowercase hexadecimal string.
+    ///
+    /// If `prefix_len` is greater than the size of the digest, the full digest
+    /// is returned. `tree_format` uses a prefix length of four bytes to
+    /// identify elements.
+    pub fn short_digest_hex(&self, prefix_len: usize) -> String {
+        let digest = self.digest();
+        let data = digest.data();
+        hex::encode(&data[..prefix_len.min(data.len())])
+    }
+
     /// Returns the set of all digests in the envelope.
     pub fn deep_digests(&self) -> HashSet<Digest> {
         self.digests(usize::MAX)
@@ -122,6 +139,9 @@ impl Envelope {
     /// the two envelope's digests. The means that two envelopes with certain structural
     /// differences (e.g., one envelope is partially elided and the other is not) will
     /// still test as equivalent.
+    ///
+    /// This is the comparison most code wants. It is *not* the comparison used
+    /// by `==` on `Envelope`, which uses [`Envelope::is_identical_to`].
     pub fn is_equivalent_to(&self, other: &Self) -> bool {
         self.digest() == other.digest()
     }
@@ -133,9 +153,15 @@ impl Envelope {
     /// thus they *must* have different structures) and a complexity of `O(m + n)` where
     /// `m` and `n` are the number of elements in each of the two envelopes when they
     /// *are* semantically equivalent.
+    ///
+    /// Two envelopes are identical if they are equivalent *and* each of their
+    /// elements is of the same case (e.g., an elided element is not identical
+    /// to its unelided counterpart). This is the comparison used by `==` on
+    /// `Envelope`, and is useful for detecting elision, encryption, or
+    /// compression of an envelope that is otherwise equivalent to another.
     pub fn is_identical_to(&self, other: &Self) -> bool {
         if !self.is_equivalent_to(other) {
-            return true;
+            return false;
         }
         self.structural_digest() == other.structural_digest()
     }
@@ -143,15 +169,31 @@ impl Envelope {
 
 /// Implement `PartialEq` for `Envelope` to allow for structural comparison.
 ///
-/// Note that we deliberately do *not* also implement `Eq` as this comparison
-/// for identicality is potentially expensive, and structures like `HashMap`
-/// require that `Eq` be implemented for its keys. This should be a fast/cheap
-/// operation.
+/// Envelopes with different digests are compared in `O(1)`. Only envelopes
+/// with the same digest have their structures compared, so using envelopes as
+/// keys of a `HashMap` or `HashSet`, which compare keys only when their hashes
+/// match, is cheap unless the keys are equivalent.
 ///
-/// If you want to use envelopes as keys in such structures, you can pre-compute
-/// an envelope's `structural_digest` and use that as the key.
+/// `==` is [`Envelope::is_identical_to`], so an envelope is not equal to an
+/// elided, encrypted, or compressed version of itself. To compare envelopes
+/// by digest, as the rest of the crate's semantics do, use
+/// [`Envelope::is_equivalent_to`]. If you want envelopes that differ only by
+/// elision, encryption, or compression to be the same key, use their digests
+/// as the keys instead.
 impl PartialEq for Envelope {
     fn eq(&self, other: &Self) -> bool {
         self.is_identical_to(other)
     }
 }
+
+impl Eq for Envelope {}
+
+/// Implement `Hash` for `Envelope` using only its digest.
+///
+/// An elided, encrypted, or compressed envelope therefore hashes the same as
+/// its revealed counterpart, although the two are not equal.
+impl std::hash::Hash for Envelope {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.digest().hash(state);
+    }
+}
@@ -3,13 +3,15 @@ use std::collections::HashSet;
 use anyhow::{bail, Result};
 use bc_components::{DigestProvider, Digest};
 #[cfg(feature = "encrypt")]
-use bc_components::{SymmetricKey, Nonce};
+use bc_components::SymmetricKey;
 #[cfg(feature = "encrypt")]
 use dcbor::prelude::*;
 
 use crate::{Assertion, Envelope, EnvelopeError};
 
 use super::envelope::EnvelopeCase;
+#[cfg(feature = "encrypt")]
+use super::random::random_nonce;
 
 /// An action to perform on a target set in an envelope.
 pub enum ObscureAction {
@@ -39,6 +41,38 @@ impl Envelope {
         }
     }
 
+    /// Returns a version of this envelope with its subject elided and its
+    /// assertions unchanged.
+    ///
+    /// If the envelope has no assertions, the envelope itself is its subject,
+    /// so the whole envelope is elided. The digest of the envelope is
+    /// unchanged.
+    pub fn elide_subject(&self) -> Self {
+        match self.case() {
+            EnvelopeCase::Node { subject, assertions, .. } => {
+                Self::new_with_unchecked_assertions(subject.elide(), assertions.clone())
+            },
+            _ => self.elide(),
+        }
+    }
+
+    /// Returns a version of this envelope with each of its assertions elided
+    /// individually and its subject unchanged.
+    ///
+    /// Because each assertion is elided separately, any of them can later be
+    /// revealed by passing the original to [`Envelope::replace_assertion`]. If
+    /// the envelope has no assertions, it is returned unchanged. The digest of
+    /// the envelope is unchanged.
+    pub fn elide_assertions(&self) -> Self {
+        match self.case() {
+            EnvelopeCase::Node { subject, assertions, .. } => {
+                let assertions = assertions.iter().map(|assertion| assertion.elide()).collect();
+                Self::new_with_unchecked_assertions(subject.clone(), assertions)
+            },
+            _ => self.clone(),
+        }
+    }
+
     /// Returns a version of this envelope with elements in the `target` set elided.
     ///
     /// - Parameters:
@@ -191,7 +225,7 @@ impl Envelope {
                 ObscureAction::Elide => self.elide(),
                 #[cfg(feature = "encrypt")]
                 ObscureAction::Encrypt(key) => {
-                    let message = key.encrypt_with_digest(self.tagged_cbor().to_cbor_data(), self_digest, None::<Nonce>);
+                    let message = key.encrypt_with_digest(self.tagged_cbor_data(), self_digest, Some(random_nonce()));
                     Self::new_with_encrypted(message).unwrap()
                 },
                 #[cfg(feature = "compress")]
@@ -200,21 +234,34 @@ impl Envelope {
         } else if let EnvelopeCase::Assertion(assertion) = self.case() {
             let predicate = assertion.predicate().elide_set_with_action(target, is_revealing, action);
             let object = assertion.object().elide_set_with_action(target, is_revealing, action);
+            // Elements with nothing obscured within them are kept as they
+            // are, along with their encodings if they have been serialized.
+            if predicate.is_same_element(&assertion.predicate()) && object.is_same_element(&assertion.object()) {
+                return self.clone();
+            }
             let elided_assertion = Assertion::new(predicate, object);
             assert!(&elided_assertion == assertion);
             Self::new_with_assertion(elided_assertion)
         } else if let EnvelopeCase::Node { subject, assertions, ..} = self.case() {
             let elided_subject = subject.elide_set_with_action(target, is_revealing, action);
             assert!(elided_subject.digest() == subject.digest());
-            let elided_assertions = assertions.iter().map(|assertion| {
+            let elided_assertions: Vec<Self> = assertions.iter().map(|assertion| {
                 let elided_assertion = assertion.elide_set_with_action(target, is_revealing, action);
                 assert!(elided_assertion.digest() == assertion.digest());
                 elided_assertion
             }).collect();
+            if elided_subject.is_same_element(subject)
+                && elided_assertions.iter().zip(assertions).all(|(elided, assertion)| elided.is_same_element(assertion))
+            {
+                return self.clone();
+            }
             Self::new_with_unchecked_assertions(elided_subject, elided_assertions)
         } else if let EnvelopeCase::Wrapped { envelope, .. } = self.case() {
             let elided_envelope = envelope.elide_set_with_action(target, is_revealing, action);
             assert!(elided_envelope.digest() == envelope.digest());
+            if elided_envelope.is_same_element(envelope) {
+                return self.clone();
+            }
             Self::new_wrapped(elided_envelope)
         } else {
             self.clone()
@@ -5,6 +5,7 @@ use bc_components::EncryptedMessage;
 #[cfg(feature = "compress")]
 use bc_components::Compressed;
 use dcbor::prelude::*;
+use smallvec::SmallVec;
 use crate::{base::Assertion, EnvelopeEncodable, EnvelopeError};
 #[cfg(feature = "known_value")]
 use crate::extension::KnownValue;
@@ -18,18 +19,72 @@ use std::rc::Rc as RefCounted;
 /// A flexible container for structured data.
 ///
 /// Envelopes are immutable. You create "mutations" by creating new envelopes from old envelopes.
-#[derive(Debug, Clone)]
-pub struct Envelope(RefCounted<EnvelopeCase>);
+///
+/// Envelopes are cheap to clone, as clones share their contents by reference
+/// counting. With the `multithreaded` feature enabled the reference count is
+/// atomic, making `Envelope` `Send` and `Sync` so it can be moved between
+/// threads or held in shared caches.
+#[derive(Clone)]
+pub struct Envelope(RefCounted<EnvelopeElement>);
+
+/// The shared contents of an envelope: its case, and its tagged encoding if
+/// it was created by [`Envelope::with_cached_encoding`].
+struct EnvelopeElement {
+    case: EnvelopeCase,
+    encoding: Option<RefCounted<[u8]>>,
+}
+
+// Every case of an envelope must be shareable between threads when the
+// `multithreaded` feature is enabled.
+#[cfg(feature = "multithreaded")]
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Envelope>();
+};
 
 impl Envelope {
     pub fn case(&self) -> &EnvelopeCase {
-        &self.0
+        &self.0.case
+    }
+
+    /// Returns whether the two envelopes share the same element, rather than
+    /// merely being identical.
+    pub(crate) fn is_same_element(&self, other: &Self) -> bool {
+        RefCounted::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Returns an envelope identical to this one that keeps its tagged
+    /// encoding, shared by its clones.
+    ///
+    /// Serializing the returned envelope, compressing or encrypting it, or
+    /// serializing an envelope that contains it copies the encoding rather
+    /// than encoding it again. This is worthwhile for an envelope that is
+    /// serialized repeatedly, at the cost of holding its encoding in memory
+    /// for as long as it is in use; other envelopes do not keep theirs.
+    pub fn with_cached_encoding(&self) -> Self {
+        if self.cached_encoding().is_some() {
+            return self.clone();
+        }
+        let encoding = RefCounted::from(self.tagged_cbor_data());
+        Self(RefCounted::new(EnvelopeElement { case: self.case().clone(), encoding: Some(encoding) }))
+    }
+
+    /// Returns the envelope's tagged encoding if it was created by
+    /// [`Envelope::with_cached_encoding`].
+    pub fn cached_encoding(&self) -> Option<&[u8]> {
+        self.0.encoding.as_deref()
+    }
+}
+
+impl std::fmt::Debug for Envelope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Envelope").field(self.case()).finish()
     }
 }
 
 impl From<EnvelopeCase> for Envelope {
     fn from(case: EnvelopeCase) -> Self {
-        Self(RefCounted::new(case))
+        Self(RefCounted::new(EnvelopeElement { case, encoding: None }))
     }
 }
 
@@ -39,7 +94,13 @@ impl From<&Envelope> for Envelope {
     }
 }
 
-#[derive(Debug)]
+impl From<Box<Envelope>> for Envelope {
+    fn from(envelope: Box<Envelope>) -> Self {
+        *envelope
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum EnvelopeCase {
     /// Represents an envelope with one or more assertions.
     Node { subject: Envelope, assertions: Vec<Envelope>, digest: Digest },
@@ -130,11 +191,28 @@ impl Envelope {
 
 /// Internal constructors
 impl Envelope {
+    /// The subject of a node is never itself a node: if `subject` is a node,
+    /// its assertions are merged with `unchecked_assertions` and its subject
+    /// becomes the subject of the result.
     pub(crate) fn new_with_unchecked_assertions(subject: Self, unchecked_assertions: Vec<Self>) -> Self {
         assert!(!unchecked_assertions.is_empty());
-        let mut sorted_assertions = unchecked_assertions;
+        let (subject, mut sorted_assertions) = match subject.case() {
+            EnvelopeCase::Node { subject: inner_subject, assertions, .. } => {
+                let mut merged = assertions.clone();
+                for assertion in unchecked_assertions {
+                    if !merged.iter().any(|a| a.digest() == assertion.digest()) {
+                        merged.push(assertion);
+                    }
+                }
+                (inner_subject.clone(), merged)
+            },
+            _ => (subject, unchecked_assertions),
+        };
         sorted_assertions.sort_by(|a, b| a.digest().cmp(&b.digest()));
-        let mut digests = vec![subject.digest().into_owned()];
+        // Most nodes have only a few assertions, so their digests are
+        // gathered without allocating.
+        let mut digests: SmallVec<[Digest; 5]> = SmallVec::new();
+        digests.push(subject.digest().into_owned());
         digests.extend(sorted_assertions.iter().map(|a| a.digest().into_owned()));
         let digest = Digest::from_digests(&digests);
         (EnvelopeCase::Node { subject, assertions: sorted_assertions, digest }).into()
@@ -142,7 +220,7 @@ impl Envelope {
 
     pub(crate) fn new_with_assertions(subject: Self, assertions: Vec<Self>) -> Result<Self> {
         if !assertions.iter().all(|a| a.is_subject_assertion() || a.is_subject_obscured()) {
-            bail!(EnvelopeError::InvalidFormat);
+            bail!(EnvelopeError::InvalidFormat { message: "not an assertion envelope".to_string() });
         }
         Ok(Self::new_with_unchecked_assertions(subject, assertions))
     }
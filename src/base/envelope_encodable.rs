@@ -5,7 +5,7 @@ use bc_components::EncryptedMessage;
 use bc_components::Compressed;
 #[cfg(any(feature = "encrypt", feature = "compress"))]
 use anyhow::{Error, Result};
-use dcbor::CBOR;
+use dcbor::{CBOREncodable, CBOR};
 
 use crate::{Assertion, Envelope};
 
@@ -46,9 +46,16 @@ impl TryFrom<Compressed> for Envelope {
     }
 }
 
+/// Creates a leaf from the CBOR.
+///
+/// If the CBOR is a tagged envelope and the
+/// [`TaggedLeafPolicy`](crate::TaggedLeafPolicy) is `Promote`, the envelope it
+/// encodes is used instead. The `Reject` policy is only enforced by
+/// [`Envelope::try_new_leaf`]: this conversion cannot fail, so it creates the
+/// leaf, which [`Envelope::is_suspicious_leaf`] flags.
 impl EnvelopeEncodable for CBOR {
     fn into_envelope(self) -> Envelope {
-        Envelope::new_leaf(self)
+        Envelope::new_leaf_promoting(self)
     }
 }
 
@@ -64,6 +71,29 @@ impl EnvelopeEncodable for &str {
     }
 }
 
+/// Creates a leaf whose CBOR is an array of the elements, in order.
+impl<T> EnvelopeEncodable for Vec<T> where T: CBOREncodable {
+    fn into_envelope(self) -> Envelope {
+        Envelope::new_leaf(self)
+    }
+}
+
+/// Creates a leaf whose CBOR is an array of the elements, in order.
+impl<T> EnvelopeEncodable for &[T] where T: CBOREncodable {
+    fn into_envelope(self) -> Envelope {
+        Envelope::new_leaf(self)
+    }
+}
+
+/// Creates a leaf whose CBOR is the map. Its entries are in the canonical
+/// order of their encoded keys, whatever order they were inserted in, so the
+/// leaf's digest depends only on its contents.
+impl EnvelopeEncodable for dcbor::Map {
+    fn into_envelope(self) -> Envelope {
+        Envelope::new_leaf(self)
+    }
+}
+
 macro_rules! impl_envelope_encodable {
     ($type:ty) => {
         impl EnvelopeEncodable for $type {
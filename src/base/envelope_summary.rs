@@ -31,9 +31,30 @@ impl EnvelopeSummary for CBOR {
                         .flanked_by("[", "]")
                 )
             }
-            CBORCase::Map(_) => Ok("Map".to_string()),
+            CBORCase::Map(map) => {
+                Ok(
+                    map
+                        .iter()
+                        .map(|(key, value)| Ok(format!(
+                            "{}: {}",
+                            key.envelope_summary(max_length, context)?,
+                            value.envelope_summary(max_length, context)?
+                        )))
+                        .collect::<Result<Vec<String>>>()?
+                        .join(", ")
+                        .flanked_by("Map{", "}")
+                )
+            }
             CBORCase::Simple(v) => Ok(v.to_string()),
-            CBORCase::Tagged(_, _) => Ok(self.summary_opt(context)),
+            CBORCase::Tagged(tag, item) => {
+                // Tags without a summarizer are displayed by name, if they have one.
+                if context.summarizer(tag.value()).is_none() {
+                    if let Some(name) = context.assigned_name_for_tag(tag) {
+                        return Ok(item.envelope_summary(max_length, context)?.flanked_by(&format!("{}(", name), ")"));
+                    }
+                }
+                Ok(self.summary_opt(context))
+            },
         }
     }
 }
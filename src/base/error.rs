@@ -1,3 +1,4 @@
+use bc_components::Digest;
 use thiserror::Error;
 
 /// Error returned when handling envelopes.
@@ -10,20 +11,32 @@ pub enum EnvelopeError {
     #[error("envelope was elided, so it cannot be compressed or encrypted")]
     AlreadyElided,
 
-    #[error("more than one assertion matches the predicate")]
-    AmbiguousPredicate,
+    #[error("more than one assertion ({count}) matches the predicate {predicate}")]
+    AmbiguousPredicate { predicate: String, count: usize },
+
+    #[error("invalid CBOR")]
+    CBOR(#[from] dcbor::CBORError),
+
+    #[error("envelope does not round-trip through its encoding: {reason}\n=== EXPECTED\n{expected}\n=== GOT\n{got}")]
+    EncodingMismatch { reason: String, expected: String, got: String },
 
     #[error("digest did not match")]
     InvalidDigest,
 
-    #[error("invalid format")]
-    InvalidFormat,
+    #[error("element failed validation: {0}")]
+    InvalidElement(Digest),
+
+    #[error("invalid format: {message}")]
+    InvalidFormat { message: String },
 
     #[error("a digest was expected but not found")]
     MissingDigest,
 
-    #[error("no assertion matches the predicate")]
-    NonexistentPredicate,
+    #[error("no assertion matches the predicate {predicate}")]
+    NonexistentPredicate { predicate: String },
+
+    #[error("no visible assertion matches the predicate {predicate}, but {} obscured elements might contain one", digests.len())]
+    ObscuredPredicate { predicate: String, digests: Vec<Digest> },
 
     #[error("cannot unwrap an envelope that was not wrapped")]
     NotWrapped,
@@ -34,6 +47,79 @@ pub enum EnvelopeError {
     #[error("the envelope's subject is not an assertion")]
     NotAssertion,
 
+    #[error("the subject of a node cannot itself be a node")]
+    NestedNode,
+
+    #[error("decoding exceeded {limit} after {elements} elements and an estimated {memory} bytes")]
+    LimitExceeded { limit: &'static str, elements: usize, memory: usize },
+
+    #[error("a node of array length {length} exceeds the limit of {limit} assertions")]
+    TooManyAssertions { length: usize, limit: usize },
+
+    #[error("envelope elements are nested deeper than the limit of {limit} levels")]
+    TooDeep { limit: usize },
+
+    #[error("a leaf cannot contain a tagged envelope; use wrap_envelope() to nest an envelope")]
+    EnvelopeTaggedLeaf,
+
+    #[error("no encrypted, compressed, or elided element matches the payload digest")]
+    NonexistentPayload,
+
+    #[error("a leaf's CBOR is not canonical dCBOR")]
+    NonCanonicalLeaf,
+
+    #[error("the assertions of node {0} are not in canonical order")]
+    NonCanonicalAssertionOrder(Digest),
+
+    #[error("no assertion has the given digest")]
+    NonexistentAssertion,
+
+    #[error("not a UR: {message}")]
+    NotUR { message: String },
+
+    #[error("expected a UR of type envelope, but found ur:{ur_type}")]
+    WrongURType { ur_type: String },
+
+    #[error("the UR does not contain a valid envelope: {message}")]
+    InvalidURCBOR { message: String },
+
+    #[error("the UR part was rejected: {message}")]
+    InvalidURPart { message: String },
+
+    #[error("invalid envelope notation at offset {offset}: {message}")]
+    InvalidNotation { message: String, offset: usize },
+
+    #[error("invalid path expression at offset {offset}: {message}")]
+    InvalidPathExpression { message: String, offset: usize },
+
+    #[error("path step {step} `{text}` selected nothing: {reason}")]
+    UnmatchedPathStep { step: usize, text: String, reason: String },
+
+    #[error("the digest prefix {prefix} matches {count} assertions")]
+    AmbiguousDigestPrefix { prefix: String, count: usize },
+
+    #[error("the summarizers for tags {} cannot be serialized", tags.iter().map(u64::to_string).collect::<Vec<_>>().join(", "))]
+    UnserializableSummarizers { tags: Vec<u64> },
+
+    #[error("the format context's tags were not registered with insert_tag, and cannot be serialized")]
+    UntrackedTags,
+
+    #[error("the sidecars have different values for the element {0}")]
+    SidecarConflict(Digest),
+
+
+    //
+    // Async Streams
+    //
+
+    #[cfg(feature = "async")]
+    #[error("frame size ({size} bytes) exceeds the limit ({limit} bytes)")]
+    FrameTooLarge { size: usize, limit: usize },
+
+    #[cfg(feature = "async")]
+    #[error("the stream ended in the middle of a frame")]
+    TruncatedFrame,
+
 
     //
     // Attachments Extension
@@ -64,6 +150,44 @@ pub enum EnvelopeError {
     #[error("cannot uncompress an envelope that was not compressed")]
     NotCompressed,
 
+    #[cfg(feature = "compress")]
+    #[error("could not uncompress the envelope")]
+    Compression(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    #[cfg(feature = "compress")]
+    #[error("uncompressed size ({size} bytes) exceeds the limit ({limit} bytes)")]
+    UncompressedSizeExceeded { size: usize, limit: usize },
+
+    #[cfg(feature = "compress")]
+    #[error("compressed data does not uncompress to its declared size ({declared} bytes)")]
+    UncompressedSizeMismatch { declared: usize },
+
+
+    //
+    // Parity Extension
+    //
+
+    #[cfg(feature = "parity")]
+    #[error("no parity assertion was found")]
+    ParityNotFound,
+
+    #[cfg(feature = "parity")]
+    #[error("block {block} has more byte errors than its parity can correct ({capacity})")]
+    ParityExceeded { block: usize, capacity: usize },
+
+
+    //
+    // Supersession Extension
+    //
+
+    #[cfg(feature = "known_value")]
+    #[error("more than one assertion with the predicate {predicate} supersedes the assertion {digest}")]
+    SupersessionFork { predicate: String, digest: Digest },
+
+    #[cfg(feature = "known_value")]
+    #[error("the assertions with the predicate {predicate} supersede one another in a cycle")]
+    SupersessionCycle { predicate: String },
+
 
     //
     // Symmetric Encryption Extension
@@ -77,6 +201,14 @@ pub enum EnvelopeError {
     #[error("cannot decrypt an envelope that was not encrypted")]
     NotEncrypted,
 
+    #[cfg(feature = "encrypt")]
+    #[error("{count} encrypted elements could not be decrypted with the key")]
+    UndecryptedElements { count: usize },
+
+    #[cfg(feature = "encrypt")]
+    #[error("cryptographic operation failed")]
+    Crypto(#[source] Box<dyn std::error::Error + Send + Sync>),
+
 
     //
     // Known Values Extension
@@ -95,6 +227,18 @@ pub enum EnvelopeError {
     #[error("unknown recipient")]
     UnknownRecipient,
 
+    #[cfg(feature = "recipient")]
+    #[error("a 'hasRecipient' assertion's object is not a sealed message: {message}")]
+    InvalidRecipient { message: String },
+
+    #[cfg(all(feature = "recipient", feature = "signature"))]
+    #[error("invalid delegation: {message}")]
+    InvalidDelegation { message: String },
+
+    #[cfg(all(feature = "recipient", feature = "signature"))]
+    #[error("the delegation expired at {valid_until}")]
+    ExpiredDelegation { valid_until: dcbor::Date },
+
 
     //
     // Public Key Signing Extension
@@ -104,6 +248,10 @@ pub enum EnvelopeError {
     #[error("could not verify a signature")]
     UnverifiedSignature,
 
+    #[cfg(feature = "signature")]
+    #[error("invalid signing ceremony: {message}")]
+    InvalidCeremony { message: String },
+
 
     //
     // SSKR Extension
@@ -135,3 +283,10 @@ pub enum EnvelopeError {
     #[error("unexpected response ID")]
     UnexpectedResponseID,
 }
+
+#[cfg(feature = "encrypt")]
+impl From<bc_crypto::Error> for EnvelopeError {
+    fn from(error: bc_crypto::Error) -> Self {
+        EnvelopeError::Crypto(Box::new(error))
+    }
+}
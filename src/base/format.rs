@@ -1,4 +1,4 @@
-use bc_components::XID;
+use bc_components::{DigestProvider, XID};
 use dcbor::prelude::*;
 use crate::{Envelope, Assertion, string_utils::StringUtils, FormatContext, with_format_context};
 #[cfg(feature = "known_value")]
@@ -10,8 +10,32 @@ use super::{EnvelopeSummary, envelope::EnvelopeCase};
 impl Envelope {
     /// Returns the envelope notation for this envelope.
     pub fn format_opt(&self, context: Option<&FormatContext>) -> String {
-        let context = context.cloned().unwrap_or(FormatContext::default());
-        self.format_item(&context).format(context.is_flat()).trim().to_string()
+        match context {
+            Some(context) => self.format_with_context(context),
+            None => self.format_with_context(&FormatContext::default()),
+        }
+    }
+
+    /// Returns the envelope notation for this envelope, using the given
+    /// context rather than the global one.
+    ///
+    /// Threads formatting with their own contexts, such as those of different
+    /// tenants with their own vocabularies, do not contend for the global
+    /// format context.
+    pub fn format_with_context(&self, context: &FormatContext) -> String {
+        self.format_item(context).format(context.is_flat()).trim().to_string()
+    }
+
+    /// Returns the envelope notation for this envelope, with each element for
+    /// which `predicate` returns `true` shown as `<redacted>`.
+    ///
+    /// This only affects the output: the envelope, and so its digest, is
+    /// unchanged, and redacted elements are not visited further. Use it to
+    /// log or display envelopes whose leaves of some types, such as dates or
+    /// byte strings, must not appear in the output. Unlike elision, the
+    /// structure of the envelope is still shown in full.
+    pub fn format_redacting(&self, predicate: impl Fn(&Envelope) -> bool, context: &FormatContext) -> String {
+        self.format_item_redacting(context, Some(&predicate)).format(context.is_flat()).trim().to_string()
     }
 
     /// Returns the envelope notation for this envelope.
@@ -19,7 +43,7 @@ impl Envelope {
     /// Uses the current format context.
     pub fn format(&self) -> String {
         with_format_context!(|context| {
-            self.format_opt(Some(context))
+            self.format_with_context(context)
         })
     }
 
@@ -28,18 +52,43 @@ impl Envelope {
     /// In flat format, the envelope is printed on a single line.
     pub fn format_flat(&self) -> String {
         with_format_context!(|context: &FormatContext| {
-            let context = context.clone().set_flat(true);
-            self.format_opt(Some(&context))
+            self.format_flat_opt(Some(context))
         })
     }
 
+    /// Returns the envelope notation for this envelope in flat format, using
+    /// the given context, or an empty one, rather than the global one.
+    ///
+    /// Unlike [`Envelope::format_flat`], this takes no lock, so it can be
+    /// called while the global format context is held.
+    pub fn format_flat_opt(&self, context: Option<&FormatContext>) -> String {
+        match context {
+            Some(context) => self.format_item(context).format(true).trim().to_string(),
+            None => self.format_item(&FormatContext::default()).format(true).trim().to_string(),
+        }
+    }
+
+    /// Returns the CBOR diagnostic notation for this envelope.
+    ///
+    /// If `annotate` is `true`, tagged values are annotated with the names of
+    /// their tags from the context, and known values, functions, and other
+    /// summarized types are shown with their summaries. Unlike
+    /// [`Envelope::format`], this shows the exact structure of the encoded
+    /// CBOR.
+    ///
+    /// See [RFC-8949 §8](https://www.rfc-editor.org/rfc/rfc8949.html#name-diagnostic-notation)
+    /// for information on CBOR diagnostic notation.
+    pub fn diagnostic_opt(&self, annotate: bool, context: Option<&FormatContext>) -> String {
+        self.tagged_cbor().diagnostic_opt(annotate, false, false, Some(context.unwrap_or(&FormatContext::default()).tags()))
+    }
+
     /// Returns the CBOR diagnostic notation for this envelope, with annotations.
     ///
     /// See [RFC-8949 §8](https://www.rfc-editor.org/rfc/rfc8949.html#name-diagnostic-notation)
     /// for information on CBOR diagnostic notation.
     pub fn diagnostic_annotated(&self) -> String {
         with_format_context!(|context: &FormatContext| {
-            self.tagged_cbor().diagnostic_opt(true, false, false, Some(context.tags()))
+            self.diagnostic_opt(true, Some(context))
         })
     }
 
@@ -91,51 +140,18 @@ pub enum EnvelopeFormatItem {
 }
 
 impl EnvelopeFormatItem {
-    fn flatten(&self) -> Vec<EnvelopeFormatItem> {
+    /// Appends the items of this item, with nested lists expanded, to `out`.
+    fn flatten_into<'a>(&'a self, out: &mut Vec<&'a EnvelopeFormatItem>) {
         match self {
-            EnvelopeFormatItem::List(items) => items.iter().flat_map(|i| i.flatten()).collect(),
-            _ => vec![self.clone()],
-        }
-    }
-
-    fn nicen(items: &[EnvelopeFormatItem]) -> Vec<EnvelopeFormatItem> {
-        let mut input = items.to_vec();
-        let mut result: Vec<EnvelopeFormatItem> = vec![];
-
-        while !input.is_empty() {
-            let current = input.remove(0);
-            if input.is_empty() {
-                result.push(current);
-                break;
-            }
-            if let EnvelopeFormatItem::End(end_string) = current.clone() {
-                if let EnvelopeFormatItem::Begin(begin_string) = input[0].clone() {
-                    result.push(EnvelopeFormatItem::End(format!("{} {}", end_string, begin_string)));
-                    result.push(EnvelopeFormatItem::Begin("".to_string()));
-                    input.remove(0);
-                } else {
-                    result.push(current);
-                }
-            } else {
-                result.push(current);
-            }
+            EnvelopeFormatItem::List(items) => items.iter().for_each(|item| item.flatten_into(out)),
+            _ => out.push(self),
         }
-
-        result
     }
 
-    fn indent(level: usize) -> String {
-        " ".repeat(level * 4)
-    }
-
-    fn add_space_at_end_if_needed(s: &str) -> String {
-        if s.is_empty() {
-            " ".to_string()
-        } else if s.ends_with(' ') {
-            s.to_string()
-        } else {
-            s.to_string() + " "
-        }
+    fn flatten(&self) -> Vec<&EnvelopeFormatItem> {
+        let mut items = Vec::new();
+        self.flatten_into(&mut items);
+        items
     }
 
     fn format(&self, is_flat: bool) -> String {
@@ -146,83 +162,101 @@ impl EnvelopeFormatItem {
     }
 
     fn format_flat(&self) -> String {
-        let mut line: String = "".to_string();
-        let items = self.flatten();
-        for item in items {
+        let mut line = String::new();
+        for item in self.flatten() {
             match item {
-                EnvelopeFormatItem::Begin(s) => {
+                EnvelopeFormatItem::Begin(s) | EnvelopeFormatItem::End(s) => {
                     if !line.ends_with(' ') {
-                        line += " ";
+                        line.push(' ');
                     }
-                    line += &s;
-                    line += " ";
+                    line.push_str(s);
+                    line.push(' ');
                 },
-                EnvelopeFormatItem::End(s) => {
-                    if !line.ends_with(' ') {
-                        line += " ";
-                    }
-                    line += &s;
-                    line += " ";
-                },
-                EnvelopeFormatItem::Item(s) => line += &s,
+                EnvelopeFormatItem::Item(s) => line.push_str(s),
                 EnvelopeFormatItem::Separator => {
-                    line = line.trim_end().to_string() + ", ";
+                    line.truncate(line.trim_end().len());
+                    line.push_str(", ");
                 },
-                EnvelopeFormatItem::List(items) => {
-                    for item in items {
-                        line += &item.format_flat();
-                    }
-                }
+                EnvelopeFormatItem::List(_) => unreachable!("lists are flattened"),
             }
         }
         line
     }
 
     fn format_hierarchical(&self) -> String {
-        let mut lines: Vec<String> = vec![];
-        let mut level = 0;
-        let mut current_line = "".to_string();
-        let items = Self::nicen(&self.flatten());
-        for item in items {
-            match item {
-                EnvelopeFormatItem::Begin(delimiter) => {
-                    if !delimiter.is_empty() {
-                        let c = if current_line.is_empty() {
-                            delimiter
-                        } else {
-                            Self::add_space_at_end_if_needed(&current_line) + &delimiter
-                        };
-                        lines.push(Self::indent(level) + &c + "\n");
-                    }
-                    level += 1;
-                    current_line = "".to_string();
-                }
+        let items = self.flatten();
+        let mut writer = HierarchicalWriter::default();
+        let mut index = 0;
+        while index < items.len() {
+            match items[index] {
+                EnvelopeFormatItem::Begin(delimiter) => writer.begin(delimiter),
                 EnvelopeFormatItem::End(delimiter) => {
-                    if !current_line.is_empty() {
-                        lines.push(Self::indent(level) + &current_line + "\n");
-                        current_line = "".to_string();
+                    // An end immediately followed by a begin shares its line,
+                    // as in `} [`.
+                    if let Some(EnvelopeFormatItem::Begin(begin)) = items.get(index + 1) {
+                        writer.end(&format!("{} {}", delimiter, begin));
+                        writer.begin("");
+                        index += 1;
+                    } else {
+                        writer.end(delimiter);
                     }
-                    level -= 1;
-                    lines.push(Self::indent(level) + &delimiter + "\n");
-                }
-                EnvelopeFormatItem::Item(string) => {
-                    current_line += &string;
-                }
-                EnvelopeFormatItem::Separator => {
-                    if !current_line.is_empty() {
-                        lines.push(Self::indent(level) + &current_line + "\n");
-                        current_line = "".to_string();
-                    }
-                }
-                EnvelopeFormatItem::List(_) => {
-                    lines.push("<list>".to_string());
-                }
+                },
+                EnvelopeFormatItem::Item(string) => writer.current_line.push_str(string),
+                EnvelopeFormatItem::Separator => writer.end_line(),
+                EnvelopeFormatItem::List(_) => unreachable!("lists are flattened"),
             }
+            index += 1;
         }
-        if !current_line.is_empty() {
-            lines.push(current_line);
+        writer.finish()
+    }
+}
+
+/// Renders flattened format items as indented lines in a single pass.
+#[derive(Default)]
+struct HierarchicalWriter {
+    output: String,
+    level: usize,
+    current_line: String,
+}
+
+impl HierarchicalWriter {
+    fn push_line(&mut self, line: &str) {
+        for _ in 0..self.level {
+            self.output.push_str("    ");
         }
-        lines.join("")
+        self.output.push_str(line);
+        self.output.push('\n');
+    }
+
+    fn end_line(&mut self) {
+        if !self.current_line.is_empty() {
+            let line = std::mem::take(&mut self.current_line);
+            self.push_line(&line);
+        }
+    }
+
+    fn begin(&mut self, delimiter: &str) {
+        if !delimiter.is_empty() {
+            let mut line = std::mem::take(&mut self.current_line);
+            if !line.is_empty() && !line.ends_with(' ') {
+                line.push(' ');
+            }
+            line.push_str(delimiter);
+            self.push_line(&line);
+        }
+        self.level += 1;
+        self.current_line.clear();
+    }
+
+    fn end(&mut self, delimiter: &str) {
+        self.end_line();
+        self.level -= 1;
+        self.push_line(delimiter);
+    }
+
+    fn finish(mut self) -> String {
+        self.output.push_str(&self.current_line);
+        self.output
     }
 }
 
@@ -326,14 +360,30 @@ impl EnvelopeFormat for CBOR {
 
 impl EnvelopeFormat for Envelope {
     fn format_item(&self, context: &FormatContext) -> EnvelopeFormatItem {
+        self.format_item_redacting(context, None)
+    }
+}
+
+/// The marker that stands for an element redacted by
+/// [`Envelope::format_redacting`].
+pub(crate) const REDACTED_MARKER: &str = "<redacted>";
+
+/// A predicate selecting the elements to redact while formatting.
+type Redactor<'a> = Option<&'a dyn Fn(&Envelope) -> bool>;
+
+impl Envelope {
+    fn format_item_redacting(&self, context: &FormatContext, redact: Redactor<'_>) -> EnvelopeFormatItem {
+        if redact.is_some_and(|redact| redact(self)) {
+            return EnvelopeFormatItem::Item(REDACTED_MARKER.to_string());
+        }
         match self.case() {
             EnvelopeCase::Leaf { cbor, .. } => cbor.format_item(context),
             EnvelopeCase::Wrapped { envelope, .. } => EnvelopeFormatItem::List(vec![
                 EnvelopeFormatItem::Begin("{".to_string()),
-                envelope.format_item(context),
+                envelope.format_item_redacting(context, redact),
                 EnvelopeFormatItem::End("}".to_string()),
             ]),
-            EnvelopeCase::Assertion(assertion) => assertion.format_item(context),
+            EnvelopeCase::Assertion(assertion) => format_assertion(assertion, context, redact),
             #[cfg(feature = "known_value")]
             EnvelopeCase::KnownValue { value, .. } => value.format_item(context),
             #[cfg(feature = "encrypt")]
@@ -343,7 +393,7 @@ impl EnvelopeFormat for Envelope {
             EnvelopeCase::Node { subject, assertions, .. } => {
                 let mut items: Vec<EnvelopeFormatItem> = Vec::new();
 
-                let subject_item = subject.format_item(context);
+                let subject_item = subject.format_item_redacting(context, redact);
                 let mut elided_count = 0;
                 #[cfg(feature = "encrypt")]
                 let mut encrypted_count = 0;
@@ -353,7 +403,14 @@ impl EnvelopeFormat for Envelope {
                 let mut type_assertion_items: Vec<Vec<EnvelopeFormatItem>> = Vec::new();
                 let mut assertion_items: Vec<Vec<EnvelopeFormatItem>> = Vec::new();
 
+                let folded_groups = context.fold()
+                    .map(|fold| folded_groups(assertions, fold.threshold))
+                    .unwrap_or_default();
+
                 for assertion in assertions {
+                    if assertion.as_predicate().is_some_and(|predicate| folded_groups.iter().any(|(p, _)| p.digest() == predicate.digest())) {
+                        continue;
+                    }
                     match assertion.case() {
                         EnvelopeCase::Elided(_) => {
                             elided_count += 1;
@@ -367,7 +424,7 @@ impl EnvelopeFormat for Envelope {
                             compressed_count += 1;
                         },
                         _ => {
-                            let item = vec![assertion.format_item(context)];
+                            let item = vec![assertion.format_item_redacting(context, redact)];
                             #[cfg(feature = "known_value")]
                             {
                                 let mut is_type_assertion = false;
@@ -389,6 +446,15 @@ impl EnvelopeFormat for Envelope {
                         },
                     }
                 }
+                for (predicate, objects) in folded_groups {
+                    let item = vec![fold_item(&predicate, &objects, context, redact)];
+                    #[cfg(feature = "known_value")]
+                    if predicate.subject().as_known_value() == Some(&known_values::IS_A) {
+                        type_assertion_items.push(item);
+                        continue;
+                    }
+                    assertion_items.push(item);
+                }
                 #[cfg(feature = "known_value")]
                 type_assertion_items.sort();
                 assertion_items.sort();
@@ -433,13 +499,54 @@ impl EnvelopeFormat for Envelope {
     }
 }
 
+/// The marker that stands for the objects omitted from folded assertions.
+pub(crate) const FOLD_MARKER: char = '…';
+
+/// Returns the predicates shared by more than `threshold` of the assertions,
+/// with the objects of the assertions that share each.
+fn folded_groups(assertions: &[Envelope], threshold: usize) -> Vec<(Envelope, Vec<Envelope>)> {
+    let mut groups: Vec<(Envelope, Vec<Envelope>)> = Vec::new();
+    for assertion in assertions {
+        if let (Some(predicate), Some(object)) = (assertion.as_predicate(), assertion.as_object()) {
+            match groups.iter_mut().find(|(p, _)| p.digest() == predicate.digest()) {
+                Some((_, objects)) => objects.push(object),
+                None => groups.push((predicate, vec![object])),
+            }
+        }
+    }
+    groups.retain(|(_, objects)| objects.len() > threshold);
+    groups
+}
+
+/// Formats assertions that share a predicate as the predicate, the number of
+/// assertions, a sample of their objects, and a marker for the rest.
+fn fold_item(predicate: &Envelope, objects: &[Envelope], context: &FormatContext, redact: Redactor<'_>) -> EnvelopeFormatItem {
+    let sample_size = context.fold().map_or(0, |fold| fold.sample_size).min(objects.len());
+    let mut object_items: Vec<EnvelopeFormatItem> = objects.iter().map(|object| object.format_item_redacting(context, redact)).collect();
+    object_items.sort();
+    let mut items = vec![
+        predicate.format_item_redacting(context, redact),
+        EnvelopeFormatItem::Item(format!(" (×{}): ", objects.len())),
+    ];
+    for object_item in object_items.into_iter().take(sample_size) {
+        items.push(object_item);
+        items.push(EnvelopeFormatItem::Item(", ".to_string()));
+    }
+    items.push(EnvelopeFormatItem::Item(format!("{}({} more)", FOLD_MARKER, objects.len() - sample_size)));
+    EnvelopeFormatItem::List(items)
+}
+
+fn format_assertion(assertion: &Assertion, context: &FormatContext, redact: Redactor<'_>) -> EnvelopeFormatItem {
+    EnvelopeFormatItem::List(vec![
+        assertion.predicate().format_item_redacting(context, redact),
+        EnvelopeFormatItem::Item(": ".to_string()),
+        assertion.object().format_item_redacting(context, redact),
+    ])
+}
+
 impl EnvelopeFormat for Assertion {
     fn format_item(&self, context: &FormatContext) -> EnvelopeFormatItem {
-        EnvelopeFormatItem::List(vec![
-            self.predicate().format_item(context),
-            EnvelopeFormatItem::Item(": ".to_string()),
-            self.object().format_item(context),
-        ])
+        format_assertion(self, context, None)
     }
 }
 
@@ -450,7 +557,7 @@ impl EnvelopeFormat for KnownValue {
             .known_values()
             .assigned_name(self)
             .map(|s| s.to_string())
-            .unwrap_or_else(|| self.name())
+            .unwrap_or_else(|| self.name_in(None))
             .flanked_by("'", "'")
         )
     }
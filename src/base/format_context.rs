@@ -1,17 +1,29 @@
-use bc_components::tags::*;
+use anyhow::{ bail, Result };
 use dcbor::prelude::*;
+use std::collections::{ BTreeMap, BTreeSet };
 use std::sync::{ Arc, Mutex, Once };
 #[cfg(feature = "known_value")]
-use crate::extension::known_values::{ KnownValuesStore, KNOWN_VALUES };
+use bc_components::tags::*;
+#[cfg(feature = "known_value")]
+use crate::extension::known_values::{ KnownValuesStore, KNOWN_VALUES, PREDEFINED_KNOWN_VALUES };
 
 #[cfg(feature = "expression")]
 use crate::extension::expressions::{
+    Function,
     FunctionsStore,
+    Parameter,
     ParametersStore,
     GLOBAL_FUNCTIONS,
     GLOBAL_PARAMETERS,
+    PREDEFINED_FUNCTIONS,
+    PREDEFINED_PARAMETERS,
 };
-use crate::{ string_utils::StringUtils, Envelope, KnownValue };
+use crate::EnvelopeError;
+use super::pseudonymize::{ PSEUDONYM_TAG_NAME, TAG_PSEUDONYM };
+#[cfg(feature = "known_value")]
+use crate::{ string_utils::StringUtils, KnownValue };
+#[cfg(feature = "expression")]
+use crate::Envelope;
 
 /// The envelope formatting functions take a `FormatContext` as an argument. This type
 /// defines information about CBOR tags, known values, functions and parameters that
@@ -35,6 +47,7 @@ use crate::{ string_utils::StringUtils, Envelope, KnownValue };
 #[derive(Clone)]
 pub struct FormatContext {
     flat: bool,
+    fold: Option<FoldOptions>,
     tags: TagsStore,
     #[cfg(feature = "known_value")]
     known_values: KnownValuesStore,
@@ -42,6 +55,43 @@ pub struct FormatContext {
     functions: FunctionsStore,
     #[cfg(feature = "expression")]
     parameters: ParametersStore,
+    /// Whether the standard tags have been registered.
+    standard_tags: bool,
+    /// The tags registered with `insert_tag`.
+    custom_tags: BTreeMap<TagValue, String>,
+    /// The tags whose summarizers were set with `set_summarizer`.
+    custom_summarizers: BTreeSet<TagValue>,
+    /// Whether the tags may have been changed other than with `insert_tag`
+    /// and `set_summarizer`, so that they cannot be recorded.
+    untracked_tags: bool,
+}
+
+/// Options for folding the assertions of a node that share a predicate when
+/// formatting.
+///
+/// When more than `threshold` assertions of a node share a predicate,
+/// envelope notation shows the predicate once, with the number of
+/// assertions, the first `sample_size` of their objects, and a marker for
+/// the rest:
+///
+/// ```text
+/// "catalog" [
+///     "item" (×200): "value 0", "value 1", …(198 more)
+/// ]
+/// ```
+///
+/// The tree format shows the first `sample_size` assertions, and a marker
+/// line for the rest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoldOptions {
+    pub threshold: usize,
+    pub sample_size: usize,
+}
+
+impl FoldOptions {
+    pub fn new(threshold: usize, sample_size: usize) -> Self {
+        Self { threshold, sample_size }
+    }
 }
 
 impl FormatContext {
@@ -54,6 +104,7 @@ impl FormatContext {
     ) -> Self {
         Self {
             flat,
+            fold: None,
             tags: tags.cloned().unwrap_or_default(),
             #[cfg(feature = "known_value")]
             known_values: known_values.cloned().unwrap_or_default(),
@@ -61,9 +112,30 @@ impl FormatContext {
             functions: functions.cloned().unwrap_or_default(),
             #[cfg(feature = "expression")]
             parameters: parameters.cloned().unwrap_or_default(),
+            standard_tags: false,
+            custom_tags: BTreeMap::new(),
+            custom_summarizers: BTreeSet::new(),
+            untracked_tags: tags.is_some(),
         }
     }
 
+    /// Creates a context with the standard tags, known values, functions, and
+    /// parameters, and the summarizers for the standard tags.
+    ///
+    /// Unlike the global format context, the result does not include anything
+    /// registered by the application.
+    pub fn standard() -> Self {
+        let mut context = Self::new(
+            false,
+            None,
+            #[cfg(feature = "known_value")] Some(&KnownValuesStore::new(PREDEFINED_KNOWN_VALUES.iter().cloned())),
+            #[cfg(feature = "expression")] Some(&FunctionsStore::new(PREDEFINED_FUNCTIONS.iter().cloned())),
+            #[cfg(feature = "expression")] Some(&ParametersStore::new(PREDEFINED_PARAMETERS.iter().cloned()))
+        );
+        register_tags_in(&mut context);
+        context
+    }
+
     pub fn is_flat(&self) -> bool {
         self.flat
     }
@@ -73,28 +145,240 @@ impl FormatContext {
         self
     }
 
+    /// Returns the options for folding assertions that share a predicate, if
+    /// folding is enabled.
+    pub fn fold(&self) -> Option<FoldOptions> {
+        self.fold
+    }
+
+    /// Sets the options for folding assertions that share a predicate, or
+    /// disables folding with `None`, as it is by default.
+    ///
+    /// Folded notation omits assertions, so it cannot be parsed. Folding is a
+    /// presentation option, and is not recorded by
+    /// [`FormatContext::to_cbor`].
+    pub fn set_fold(mut self, fold: Option<FoldOptions>) -> Self {
+        self.fold = fold;
+        self
+    }
+
     pub fn tags(&self) -> &TagsStore {
         &self.tags
     }
 
+    /// Returns the context's tags for changing them directly.
+    ///
+    /// Tags changed this way are not recorded by [`FormatContext::to_cbor`],
+    /// so it returns an error afterwards: use
+    /// [`FormatContext::insert_tag`] to register tags in a context that will
+    /// be saved.
     pub fn tags_mut(&mut self) -> &mut TagsStore {
+        self.untracked_tags = true;
         &mut self.tags
     }
 
+    /// Registers a CBOR tag, so that it is displayed by name when formatting.
+    pub fn insert_tag(&mut self, tag: Tag) {
+        if let Some(name) = tag.name() {
+            self.custom_tags.insert(tag.value(), name);
+        }
+        self.tags.insert(tag);
+    }
+
+    /// Sets the summarizer used to format values with the given tag.
+    ///
+    /// Summarizers cannot be serialized, so a context with summarizers set
+    /// this way cannot be saved with [`FormatContext::to_cbor`].
+    pub fn set_summarizer(&mut self, tag: TagValue, summarizer: CBORSummarizer) {
+        self.custom_summarizers.insert(tag);
+        self.tags.set_summarizer(tag, summarizer);
+    }
+
     #[cfg(feature = "known_value")]
     pub fn known_values(&self) -> &KnownValuesStore {
         &self.known_values
     }
 
+    /// Registers a known value, so that it is displayed by name when
+    /// formatting.
+    #[cfg(feature = "known_value")]
+    pub fn insert_known_value(&mut self, known_value: KnownValue) {
+        self.known_values.insert(known_value);
+        register_summarizers_in(self);
+    }
+
     #[cfg(feature = "expression")]
     pub fn functions(&self) -> &FunctionsStore {
         &self.functions
     }
 
+    /// Registers a function, so that it is displayed by name when formatting.
+    #[cfg(feature = "expression")]
+    pub fn insert_function(&mut self, function: Function) {
+        self.functions.insert(function);
+        register_summarizers_in(self);
+    }
+
     #[cfg(feature = "expression")]
     pub fn parameters(&self) -> &ParametersStore {
         &self.parameters
     }
+
+    /// Registers a parameter, so that it is displayed by name when formatting.
+    #[cfg(feature = "expression")]
+    pub fn insert_parameter(&mut self, parameter: Parameter) {
+        self.parameters.insert(parameter);
+        register_summarizers_in(self);
+    }
+}
+
+/// Support for saving a format context, so that envelopes are formatted the
+/// same way in different processes.
+///
+/// A snapshot records whether the context is flat, whether the standard tags
+/// are registered, the tags registered with [`FormatContext::insert_tag`], and
+/// the values and names of the context's known values, functions, and
+/// parameters. The tags of a context created with a `TagsStore`, or changed
+/// through [`FormatContext::tags_mut`], cannot be recorded, so such a context
+/// cannot be saved.
+impl FormatContext {
+    /// Returns a snapshot of the context as canonical CBOR, which does not
+    /// depend on the order in which its contents were registered.
+    ///
+    /// Returns `EnvelopeError::UnserializableSummarizers`, listing their tags,
+    /// if summarizers have been set with [`FormatContext::set_summarizer`],
+    /// and `EnvelopeError::UntrackedTags` if the context was created with a
+    /// `TagsStore` or its tags were changed through
+    /// [`FormatContext::tags_mut`].
+    pub fn to_cbor(&self) -> Result<CBOR> {
+        if self.untracked_tags {
+            bail!(EnvelopeError::UntrackedTags);
+        }
+        if !self.custom_summarizers.is_empty() {
+            bail!(EnvelopeError::UnserializableSummarizers {
+                tags: self.custom_summarizers.iter().copied().collect(),
+            });
+        }
+        let mut map = Map::new();
+        map.insert("flat", self.flat);
+        map.insert("standardTags", self.standard_tags);
+        map.insert("tags", names_map(self.custom_tags.iter().map(|(value, name)| (*value, name.as_str()))));
+        #[cfg(feature = "known_value")]
+        map.insert(
+            "knownValues",
+            names_map(
+                self.known_values
+                    .iter()
+                    .filter_map(|known_value| Some((known_value.value(), known_value.assigned_name()?)))
+            )
+        );
+        #[cfg(feature = "expression")]
+        {
+            map.insert(
+                "functions",
+                names_map(
+                    self.functions.iter().filter_map(|function| match function {
+                        Function::Known(value, Some(_)) => Some((*value, self.functions.assigned_name(function)?)),
+                        _ => None,
+                    })
+                )
+            );
+            map.insert(
+                "parameters",
+                names_map(
+                    self.parameters.iter().filter_map(|parameter| match parameter {
+                        Parameter::Known(value, Some(_)) => Some((*value, self.parameters.assigned_name(parameter)?)),
+                        _ => None,
+                    })
+                )
+            );
+        }
+        Ok(map.into())
+    }
+
+    /// Returns the digest of the context's snapshot, so that contexts can be
+    /// compared without comparing their snapshots.
+    ///
+    /// Contexts with the same fingerprint format envelopes the same way.
+    pub fn fingerprint(&self) -> Result<bc_components::Digest> {
+        Ok(bc_components::Digest::from_image(self.to_cbor()?.to_cbor_data()))
+    }
+
+    /// Creates a context from a snapshot returned by
+    /// [`FormatContext::to_cbor`].
+    pub fn from_cbor(cbor: &CBOR) -> Result<Self> {
+        let mut context = Self::default();
+        context.merge_cbor(cbor)?;
+        Ok(context)
+    }
+
+    /// Creates a context with the standard tags, known values, functions, and
+    /// parameters, and adds the contents of a snapshot returned by
+    /// [`FormatContext::to_cbor`], whose names replace the standard ones.
+    ///
+    /// Unlike the global format context, the result does not include anything
+    /// registered by the application.
+    pub fn from_cbor_over_defaults(cbor: &CBOR) -> Result<Self> {
+        let mut context = Self::standard();
+        context.merge_cbor(cbor)?;
+        Ok(context)
+    }
+
+    fn merge_cbor(&mut self, cbor: &CBOR) -> Result<()> {
+        let map = snapshot_map(cbor)?;
+        self.flat = map.extract("flat")?;
+        self.standard_tags |= map.extract::<_, bool>("standardTags")?;
+        if self.standard_tags {
+            register_standard_tags_in(&mut self.tags);
+        }
+        for (value, name) in names_from_map(map, "tags")? {
+            self.insert_tag(Tag::new(value, name));
+        }
+        #[cfg(feature = "known_value")]
+        for (value, name) in names_from_map(map, "knownValues")? {
+            self.known_values.insert(KnownValue::new_with_name(value, name));
+        }
+        #[cfg(feature = "expression")]
+        {
+            for (value, name) in names_from_map(map, "functions")? {
+                self.functions.insert(Function::new_known(value, Some(name)));
+            }
+            for (value, name) in names_from_map(map, "parameters")? {
+                self.parameters.insert(Parameter::new_known(value, Some(name)));
+            }
+        }
+        if self.standard_tags {
+            register_summarizers_in(self);
+        }
+        Ok(())
+    }
+}
+
+fn snapshot_map(cbor: &CBOR) -> Result<&Map> {
+    match cbor.as_case() {
+        CBORCase::Map(map) => Ok(map),
+        _ => bail!(EnvelopeError::InvalidFormat { message: "a format context snapshot must be a map".to_string() }),
+    }
+}
+
+/// Returns a map from values to names, in canonical order.
+fn names_map<'a>(names: impl Iterator<Item = (u64, &'a str)>) -> Map {
+    let mut map = Map::new();
+    for (value, name) in names {
+        map.insert(value, name);
+    }
+    map
+}
+
+/// Returns the values and names in the map under `key`, which is absent when
+/// the snapshot was made without the feature that provides them.
+fn names_from_map(map: &Map, key: &str) -> Result<Vec<(u64, String)>> {
+    let Some(names) = map.get::<_, CBOR>(key) else {
+        return Ok(Vec::new());
+    };
+    snapshot_map(&names)?.iter()
+        .map(|(value, name)| Ok((u64::try_from(value.clone())?, String::try_from(name.clone())?)))
+        .collect()
 }
 
 impl TagsStoreTrait for FormatContext {
@@ -161,13 +445,18 @@ impl LazyFormatContext {
             #[cfg(feature = "expression")]
             let parameters = parameters_binding.as_ref().unwrap();
 
-            let context = FormatContext::new(
+            let mut context = FormatContext::new(
                 false,
                 Some(tags),
                 #[cfg(feature = "known_value")] Some(known_values),
                 #[cfg(feature = "expression")] Some(functions),
                 #[cfg(feature = "expression")] Some(parameters)
             );
+            // The global tags are the standard tags, registered above, along
+            // with the tags defined by envelopes.
+            register_standard_tags_in(&mut context.tags);
+            context.standard_tags = true;
+            context.untracked_tags = false;
             *self.data.lock().unwrap() = Some(context);
         });
         self.data.lock().unwrap()
@@ -181,6 +470,8 @@ pub static GLOBAL_FORMAT_CONTEXT: LazyFormatContext = LazyFormatContext {
 };
 
 /// A macro to access the global format context.
+///
+/// The action is called with a reference to the global `FormatContext`.
 #[macro_export]
 macro_rules! with_format_context {
     ($action:expr) => {
@@ -193,6 +484,18 @@ macro_rules! with_format_context {
     };
 }
 
+/// A macro to mutably access the global format context.
+///
+/// The action is called with a mutable reference to the global
+/// `FormatContext`. This can be used by an application at startup to register
+/// its own tags, known values, functions, and parameters:
+///
+/// ```
+/// # use bc_envelope::prelude::full::*;
+/// with_format_context_mut!(|context: &mut FormatContext| {
+///     context.insert_tag(Tag::new(60000, "my-tag"));
+/// });
+/// ```
 #[macro_export]
 macro_rules! with_format_context_mut {
     ($action:expr) => {
@@ -205,15 +508,39 @@ macro_rules! with_format_context_mut {
     };
 }
 
+/// Registers the tags used by envelopes, and the summarizers used to format
+/// them, in the given format context.
 pub fn register_tags_in(context: &mut FormatContext) {
-    bc_components::register_tags_in(context.tags_mut());
+    register_standard_tags_in(&mut context.tags);
+    context.standard_tags = true;
+    register_summarizers_in(context);
+}
+
+/// Registers the tags of `bc_components`, and the tags defined by envelopes
+/// with their summarizers.
+fn register_standard_tags_in(tags: &mut TagsStore) {
+    bc_components::register_tags_in(tags);
+    tags.insert(Tag::new(TAG_PSEUDONYM, PSEUDONYM_TAG_NAME));
+    tags.set_summarizer(
+        TAG_PSEUDONYM,
+        Arc::new(|untagged_cbor: CBOR| {
+            let token = ByteString::try_from(untagged_cbor)?;
+            Ok(format!("Pseudonym({})", hex::encode(token.data().get(..4).unwrap_or_default())))
+        })
+    );
+}
 
+/// Sets the summarizers for the tagged values whose formatting depends on the
+/// context's known values, functions, and parameters.
+///
+/// The summarizers capture a copy of the context, so this is called again
+/// whenever one of those stores changes.
+#[allow(unused_variables)]
+fn register_summarizers_in(context: &mut FormatContext) {
     #[cfg(feature = "expression")]
     {
-        use crate::extension::expressions::{ Function, FunctionsStore, Parameter, ParametersStore };
-
         let functions = context.functions().clone();
-        context.tags_mut().set_summarizer(
+        context.tags.set_summarizer(
             TAG_FUNCTION,
             Arc::new(move |untagged_cbor: CBOR| {
                 let f = Function::from_untagged_cbor(untagged_cbor)?;
@@ -222,7 +549,7 @@ pub fn register_tags_in(context: &mut FormatContext) {
         );
 
         let parameters = context.parameters().clone();
-        context.tags_mut().set_summarizer(
+        context.tags.set_summarizer(
             TAG_PARAMETER,
             Arc::new(move |untagged_cbor: CBOR| {
                 let p = Parameter::from_untagged_cbor(untagged_cbor)?;
@@ -231,7 +558,7 @@ pub fn register_tags_in(context: &mut FormatContext) {
         );
 
         let known_values = context.known_values().clone();
-        context.tags_mut().set_summarizer(
+        context.tags.set_summarizer(
             TAG_KNOWN_VALUE,
             Arc::new(move |untagged_cbor: CBOR| {
                 Ok(
@@ -243,7 +570,7 @@ pub fn register_tags_in(context: &mut FormatContext) {
         );
 
         let cloned_context = context.clone();
-        context.tags_mut().set_summarizer(
+        context.tags.set_summarizer(
             TAG_REQUEST,
             Arc::new(move |untagged_cbor: CBOR| {
                 Ok(
@@ -255,7 +582,7 @@ pub fn register_tags_in(context: &mut FormatContext) {
         );
 
         let cloned_context = context.clone();
-        context.tags_mut().set_summarizer(
+        context.tags.set_summarizer(
             TAG_RESPONSE,
             Arc::new(move |untagged_cbor: CBOR| {
                 Ok(
@@ -267,7 +594,7 @@ pub fn register_tags_in(context: &mut FormatContext) {
         );
 
         let cloned_context = context.clone();
-        context.tags_mut().set_summarizer(
+        context.tags.set_summarizer(
             TAG_EVENT,
             Arc::new(move |untagged_cbor: CBOR| {
                 Ok(
@@ -285,3 +612,17 @@ pub fn register_tags() {
         register_tags_in(context);
     });
 }
+
+/// Registers a known value in the global known values store and the global
+/// format context, so that it is parsed and formatted by name.
+///
+/// This is intended for applications with their own vocabulary to call at
+/// startup. To format with a vocabulary without changing the global state,
+/// pass a `KnownValuesStore` to `FormatContext::new` and use `format_opt`.
+#[cfg(feature = "known_value")]
+pub fn register_known_value(known_value: KnownValue) {
+    KNOWN_VALUES.get().as_mut().unwrap().insert(known_value.clone());
+    with_format_context_mut!(|context: &mut FormatContext| {
+        context.insert_known_value(known_value);
+    });
+}
@@ -0,0 +1,108 @@
+use std::{cell::RefCell, collections::HashSet};
+
+use crate::{Envelope, with_format_context, FormatContext};
+
+use super::walk::EdgeType;
+
+/// Support for rendering envelopes as Mermaid or Graphviz DOT graphs.
+///
+/// Each element is a graph node identified by its short digest, and captioned
+/// with the same summary as the tree format. Because identical elements have
+/// identical digests, they appear once, and the graph is the envelope's
+/// digest tree with repeated subtrees shared.
+impl Envelope {
+    /// Returns the envelope as a Mermaid flowchart.
+    ///
+    /// If `hide_nodes` is true, node elements are omitted, as in the tree
+    /// format, and assertions hang directly from their subjects.
+    pub fn mermaid_format_opt(&self, hide_nodes: bool, context: Option<&FormatContext>) -> String {
+        let graph = self.graph_elements(hide_nodes, context);
+        let mut lines = vec!["graph LR".to_string()];
+        for (id, caption) in &graph.nodes {
+            lines.push(format!("    {}[\"{}\"]", id, caption.replace('"', "#quot;")));
+        }
+        for (from, to, label) in &graph.edges {
+            match label {
+                Some(label) => lines.push(format!("    {} -->|{}| {}", from, label, to)),
+                None => lines.push(format!("    {} --> {}", from, to)),
+            }
+        }
+        lines.join("\n")
+    }
+
+    /// Returns the envelope as a Mermaid flowchart, using the global format
+    /// context.
+    pub fn mermaid_format(&self, hide_nodes: bool) -> String {
+        with_format_context!(|context| {
+            self.mermaid_format_opt(hide_nodes, Some(context))
+        })
+    }
+
+    /// Returns the envelope as a Graphviz DOT digraph.
+    ///
+    /// If `hide_nodes` is true, node elements are omitted, as in the tree
+    /// format, and assertions hang directly from their subjects.
+    pub fn dot_format_opt(&self, hide_nodes: bool, context: Option<&FormatContext>) -> String {
+        let graph = self.graph_elements(hide_nodes, context);
+        let mut lines = vec!["digraph {".to_string()];
+        for (id, caption) in &graph.nodes {
+            lines.push(format!("    \"{}\" [label=\"{}\"];", id, caption.replace('\\', "\\\\").replace('"', "\\\"")));
+        }
+        for (from, to, label) in &graph.edges {
+            match label {
+                Some(label) => lines.push(format!("    \"{}\" -> \"{}\" [label=\"{}\"];", from, to, label)),
+                None => lines.push(format!("    \"{}\" -> \"{}\";", from, to)),
+            }
+        }
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+
+    /// Returns the envelope as a Graphviz DOT digraph, using the global
+    /// format context.
+    pub fn dot_format(&self, hide_nodes: bool) -> String {
+        with_format_context!(|context| {
+            self.dot_format_opt(hide_nodes, Some(context))
+        })
+    }
+
+    fn graph_elements(&self, hide_nodes: bool, context: Option<&FormatContext>) -> GraphElements {
+        let default_context = FormatContext::default();
+        let context = context.unwrap_or(&default_context);
+        let graph = RefCell::new(GraphElements::default());
+        let visitor = |envelope: Self, _: usize, incoming_edge: EdgeType, parent: Option<String>| -> _ {
+            let id = envelope.short_id();
+            let mut graph = graph.borrow_mut();
+            graph.add_node(&id, || envelope.summary(40, context));
+            if let Some(parent) = parent {
+                graph.add_edge(parent, id.clone(), incoming_edge.label());
+            }
+            Some(id)
+        };
+        self.walk(hide_nodes, &visitor);
+        graph.into_inner()
+    }
+}
+
+#[derive(Default)]
+struct GraphElements {
+    nodes: Vec<(String, String)>,
+    edges: Vec<(String, String, Option<&'static str>)>,
+    seen_nodes: HashSet<String>,
+    seen_edges: HashSet<(String, String, Option<&'static str>)>,
+}
+
+impl GraphElements {
+    fn add_node(&mut self, id: &str, caption: impl FnOnce() -> String) {
+        if self.seen_nodes.insert(id.to_string()) {
+            self.nodes.push((id.to_string(), caption()));
+        }
+    }
+
+    fn add_edge(&mut self, from: String, to: String, label: Option<&'static str>) {
+        let edge = (from, to, label);
+        if self.seen_edges.insert(edge.clone()) {
+            self.edges.push(edge);
+        }
+    }
+}
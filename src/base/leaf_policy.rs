@@ -0,0 +1,110 @@
+use std::sync::Mutex;
+
+use anyhow::{bail, Result};
+use bc_components::tags;
+use dcbor::prelude::*;
+
+use crate::{Envelope, EnvelopeError};
+
+use super::envelope::EnvelopeCase;
+
+/// What to do when a leaf is created from CBOR that is itself a tagged
+/// envelope.
+///
+/// Such a leaf is almost always a mistake: the author meant to nest the
+/// envelope with [`Envelope::wrap_envelope`], but encoded it first. The
+/// result formats plausibly, but has a different digest than the intended
+/// structure.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TaggedLeafPolicy {
+    /// Refuse to create the leaf with [`Envelope::try_new_leaf`]. The
+    /// infallible conversions, such as `Envelope::new`, create it.
+    #[default]
+    Reject,
+    /// Decode the tagged envelope and use it in place of the leaf.
+    Promote,
+}
+
+static TAGGED_LEAF_POLICY: Mutex<TaggedLeafPolicy> = Mutex::new(TaggedLeafPolicy::Reject);
+
+/// Returns the policy applied when a leaf is created from a tagged envelope.
+pub fn tagged_leaf_policy() -> TaggedLeafPolicy {
+    *TAGGED_LEAF_POLICY.lock().unwrap()
+}
+
+/// Sets the policy applied when a leaf is created from a tagged envelope.
+///
+/// The policy is shared by the whole process.
+pub fn set_tagged_leaf_policy(policy: TaggedLeafPolicy) {
+    *TAGGED_LEAF_POLICY.lock().unwrap() = policy;
+}
+
+/// The reason a leaf looks like an accidentally encoded envelope, as returned
+/// by [`Envelope::is_suspicious_leaf`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuspicionKind {
+    /// The leaf is a tagged envelope.
+    EnvelopeTagged,
+    /// The leaf is a byte string containing an encoded envelope.
+    EncodedEnvelope,
+    /// The leaf is a text string containing an envelope UR.
+    EnvelopeUR,
+}
+
+impl Envelope {
+    /// Creates a leaf envelope from the given CBOR, applying the
+    /// [`TaggedLeafPolicy`] if it is a tagged envelope.
+    ///
+    /// `Envelope::new` promotes `CBOR` subjects the same way, but creates the
+    /// leaf where this returns an error.
+    pub fn try_new_leaf(value: impl Into<CBOR>) -> Result<Self> {
+        let cbor: CBOR = value.into();
+        if let CBORCase::Tagged(tag, _) = cbor.as_case() {
+            if tag.value() == tags::TAG_ENVELOPE {
+                match tagged_leaf_policy() {
+                    TaggedLeafPolicy::Reject => bail!(EnvelopeError::EnvelopeTaggedLeaf),
+                    TaggedLeafPolicy::Promote => return Envelope::try_from(cbor),
+                }
+            }
+        }
+        Ok(Self::new_leaf(cbor))
+    }
+
+    /// Creates a leaf envelope from the given CBOR, promoting it if it is a
+    /// tagged envelope and the [`TaggedLeafPolicy`] is `Promote`. Otherwise,
+    /// including when the tagged envelope does not decode, creates the leaf.
+    pub(crate) fn new_leaf_promoting(cbor: CBOR) -> Self {
+        if tagged_leaf_policy() == TaggedLeafPolicy::Promote {
+            if let CBORCase::Tagged(tag, _) = cbor.as_case() {
+                if tag.value() == tags::TAG_ENVELOPE {
+                    if let Ok(envelope) = Envelope::try_from(cbor.clone()) {
+                        return envelope;
+                    }
+                }
+            }
+        }
+        Self::new_leaf(cbor)
+    }
+
+    /// Returns why the envelope's leaf looks like an accidentally encoded
+    /// envelope, or `None` if it doesn't or the envelope is not a leaf.
+    ///
+    /// This is intended for auditing existing data: decoding never rejects or
+    /// promotes such leaves, so their digests are preserved.
+    pub fn is_suspicious_leaf(&self) -> Option<SuspicionKind> {
+        let EnvelopeCase::Leaf { cbor, .. } = self.case() else {
+            return None;
+        };
+        match cbor.as_case() {
+            CBORCase::Tagged(tag, _) if tag.value() == tags::TAG_ENVELOPE => Some(SuspicionKind::EnvelopeTagged),
+            CBORCase::ByteString(bytes) => {
+                let cbor = CBOR::try_from_data(bytes).ok()?;
+                Envelope::try_from(cbor).ok().map(|_| SuspicionKind::EncodedEnvelope)
+            },
+            CBORCase::Text(text) if text.get(..12).is_some_and(|scheme| scheme.eq_ignore_ascii_case("ur:envelope/")) => {
+                Envelope::from_ur_string(text).ok().map(|_| SuspicionKind::EnvelopeUR)
+            },
+            _ => None,
+        }
+    }
+}
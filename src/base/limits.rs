@@ -0,0 +1,482 @@
+use std::collections::VecDeque;
+
+use anyhow::{bail, Result};
+use dcbor::CBOR;
+
+use crate::{Envelope, EnvelopeError};
+
+/// Limits applied when decoding and expanding envelopes received from
+/// untrusted sources.
+///
+/// The default limits are generous enough for ordinary use, while bounding the
+/// resources a maliciously crafted envelope can consume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnvelopeLimits {
+    /// The maximum number of bytes a compressed element may uncompress to.
+    pub max_uncompressed_size: usize,
+    /// The maximum number of bytes in one frame of an envelope stream.
+    pub max_frame_size: usize,
+    /// The maximum number of elements decoded from one encoding.
+    pub max_total_elements: usize,
+    /// The maximum estimated number of bytes allocated for the elements
+    /// decoded from one encoding.
+    ///
+    /// The estimate covers the envelope's structure: its elements, their
+    /// digests, and the lists of assertions of its nodes. It does not include
+    /// the content of leaves, which is held by the already-parsed CBOR.
+    pub max_estimated_memory: usize,
+    /// The maximum number of assertions of a single node.
+    ///
+    /// The limit is checked against the length of a node's array before its
+    /// elements are decoded, and, when decoding from data, before the data
+    /// is parsed, so a node that declares millions of assertions is rejected
+    /// without allocating them. The fallible methods that add assertions,
+    /// such as [`Envelope::add_assertion_envelopes`] and
+    /// [`Envelope::try_add_assertion`], hold the nodes they construct to the
+    /// default limit, so they can be decoded with the default limits; the
+    /// infallible ones, such as [`Envelope::add_assertion`], do not.
+    pub max_node_assertions: usize,
+    /// The maximum nesting depth of the elements decoded from one encoding.
+    ///
+    /// The envelope itself is at depth one, and each wrapped envelope, node
+    /// subject or assertion, and assertion predicate or object is one level
+    /// deeper than the element that contains it. Like the limit on
+    /// assertions, the limit is checked before the data is parsed.
+    pub max_depth: usize,
+    /// Whether decoding reuses a recently decoded element for a
+    /// byte-identical copy of it, rather than decoding and hashing the copy
+    /// again.
+    ///
+    /// Reuse does not change the decoded envelope, or how the other limits
+    /// are enforced, only the work done: see
+    /// [`DecodeStats::reused_duplicates`]. It is on by default.
+    pub reuse_duplicates: bool,
+}
+
+impl EnvelopeLimits {
+    /// The default maximum uncompressed size: 16 MiB.
+    pub const DEFAULT_MAX_UNCOMPRESSED_SIZE: usize = 16 * 1024 * 1024;
+
+    /// The default maximum frame size: 16 MiB.
+    pub const DEFAULT_MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+    /// The default maximum number of decoded elements: one million.
+    pub const DEFAULT_MAX_TOTAL_ELEMENTS: usize = 1_000_000;
+
+    /// The default maximum estimated memory for decoded elements: 256 MiB.
+    pub const DEFAULT_MAX_ESTIMATED_MEMORY: usize = 256 * 1024 * 1024;
+
+    /// The default maximum number of assertions of a single node: one
+    /// million.
+    pub const DEFAULT_MAX_NODE_ASSERTIONS: usize = 1_000_000;
+
+    /// The default maximum nesting depth: 1,000.
+    pub const DEFAULT_MAX_DEPTH: usize = 1_000;
+
+    /// Creates a new set of limits with the default values.
+    pub fn new() -> Self {
+        Self {
+            max_uncompressed_size: Self::DEFAULT_MAX_UNCOMPRESSED_SIZE,
+            max_frame_size: Self::DEFAULT_MAX_FRAME_SIZE,
+            max_total_elements: Self::DEFAULT_MAX_TOTAL_ELEMENTS,
+            max_estimated_memory: Self::DEFAULT_MAX_ESTIMATED_MEMORY,
+            max_node_assertions: Self::DEFAULT_MAX_NODE_ASSERTIONS,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            reuse_duplicates: true,
+        }
+    }
+
+    /// Returns these limits with the given maximum uncompressed size.
+    pub fn with_max_uncompressed_size(mut self, max_uncompressed_size: usize) -> Self {
+        self.max_uncompressed_size = max_uncompressed_size;
+        self
+    }
+
+    /// Returns these limits with the given maximum frame size.
+    pub fn with_max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+
+    /// Returns these limits with the given maximum number of decoded
+    /// elements.
+    pub fn with_max_total_elements(mut self, max_total_elements: usize) -> Self {
+        self.max_total_elements = max_total_elements;
+        self
+    }
+
+    /// Returns these limits with the given maximum estimated memory for
+    /// decoded elements.
+    pub fn with_max_estimated_memory(mut self, max_estimated_memory: usize) -> Self {
+        self.max_estimated_memory = max_estimated_memory;
+        self
+    }
+
+    /// Returns these limits with the given maximum number of assertions of a
+    /// single node.
+    pub fn with_max_node_assertions(mut self, max_node_assertions: usize) -> Self {
+        self.max_node_assertions = max_node_assertions;
+        self
+    }
+
+    /// Returns these limits with the given maximum nesting depth.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Returns these limits with reuse of duplicate elements turned on or
+    /// off.
+    pub fn with_reuse_duplicates(mut self, reuse_duplicates: bool) -> Self {
+        self.reuse_duplicates = reuse_duplicates;
+        self
+    }
+
+    /// Returns `EnvelopeError::TooManyAssertions` if a node with the given
+    /// array length, its subject and its assertions, exceeds the limit on
+    /// assertions.
+    pub(crate) fn check_node_length(&self, length: usize) -> Result<()> {
+        if length.saturating_sub(1) > self.max_node_assertions {
+            bail!(EnvelopeError::TooManyAssertions { length, limit: self.max_node_assertions });
+        }
+        Ok(())
+    }
+
+    /// Returns `EnvelopeError::TooDeep` if an element at the given depth
+    /// exceeds the limit on nesting.
+    pub(crate) fn check_depth(&self, depth: usize) -> Result<()> {
+        if depth > self.max_depth {
+            bail!(EnvelopeError::TooDeep { limit: self.max_depth });
+        }
+        Ok(())
+    }
+
+    /// Checks the lengths declared by the nodes of the envelope encoded in
+    /// `data` against the limit on assertions, and the nesting of its
+    /// elements against the limit on depth, before the data is parsed.
+    ///
+    /// Parsing allocates every element of an array, and recurses into every
+    /// nested item, so the heads of the elements are read directly from the
+    /// data. Only the envelope's own
+    /// structure is followed: the content of leaves, and of encrypted and
+    /// compressed elements, is skipped. Malformed data is left for the
+    /// parser to reject.
+    pub(crate) fn check_node_headers(&self, data: &[u8], tagged: bool) -> Result<()> {
+        let mut scanner = NodeScanner { reader: HeadReader::new(data), depth: 0, limits: self };
+        let result = if tagged { scanner.tagged_element() } else { scanner.element() };
+        match result {
+            Err(ScanStop::TooManyAssertions(length)) => self.check_node_length(length),
+            Err(ScanStop::TooDeep) => self.check_depth(scanner.depth),
+            Ok(()) | Err(ScanStop::Malformed) => Ok(()),
+        }
+    }
+}
+
+/// Why [`NodeScanner`] stopped before the end of the data.
+enum ScanStop {
+    /// The data is not well-formed CBOR.
+    Malformed,
+    /// A node declares this array length, which exceeds the limit.
+    TooManyAssertions(usize),
+    /// An element is nested deeper than the limit.
+    TooDeep,
+}
+
+/// Reads the heads of the data items of a CBOR encoding without parsing it.
+pub(crate) struct HeadReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> HeadReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// Returns the offset of the next data item.
+    pub(crate) fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Moves to the data item at `pos`.
+    pub(crate) fn seek(&mut self, pos: usize) {
+        self.pos = pos;
+    }
+
+    /// Reads the head of a data item, returning its major type and argument,
+    /// or `None` if the data is malformed.
+    pub(crate) fn head(&mut self) -> Option<(u8, u64)> {
+        let initial = *self.data.get(self.pos)?;
+        self.pos += 1;
+        let size = match initial & 0x1f {
+            info @ 0..=23 => return Some((initial >> 5, info as u64)),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            _ => return None,
+        };
+        let bytes = self.data.get(self.pos..self.pos + size)?;
+        self.pos += size;
+        Some((initial >> 5, bytes.iter().fold(0, |value, byte| value << 8 | *byte as u64)))
+    }
+
+    /// Skips one data item, of any type, or returns `None` if the data is
+    /// malformed.
+    pub(crate) fn skip(&mut self) -> Option<()> {
+        let mut pending: u64 = 1;
+        while pending > 0 {
+            pending -= 1;
+            let (major, value) = self.head()?;
+            match major {
+                2 | 3 => {
+                    let length = usize::try_from(value).ok()?;
+                    self.pos = self.pos.checked_add(length).filter(|pos| *pos <= self.data.len())?;
+                },
+                4 => pending = pending.saturating_add(value),
+                5 => pending = pending.saturating_add(value.saturating_mul(2)),
+                6 => pending += 1,
+                _ => {},
+            }
+            // Every pending item takes at least one byte.
+            if pending > (self.data.len() - self.pos) as u64 {
+                return None;
+            }
+        }
+        Some(())
+    }
+}
+
+/// Reads the heads of the node arrays of an encoded envelope without
+/// parsing it.
+struct NodeScanner<'a> {
+    reader: HeadReader<'a>,
+    depth: usize,
+    limits: &'a EnvelopeLimits,
+}
+
+impl NodeScanner<'_> {
+    fn head(&mut self) -> Result<(u8, u64), ScanStop> {
+        self.reader.head().ok_or(ScanStop::Malformed)
+    }
+
+    fn skip(&mut self) -> Result<(), ScanStop> {
+        self.reader.skip().ok_or(ScanStop::Malformed)
+    }
+
+    /// Scans a tagged envelope.
+    fn tagged_element(&mut self) -> Result<(), ScanStop> {
+        match self.head()? {
+            (6, tag) if tag == bc_components::tags::TAG_ENVELOPE => self.element(),
+            _ => Err(ScanStop::Malformed),
+        }
+    }
+
+    /// Scans an untagged envelope element.
+    fn element(&mut self) -> Result<(), ScanStop> {
+        self.depth += 1;
+        if self.depth > self.limits.max_depth {
+            return Err(ScanStop::TooDeep);
+        }
+        let start = self.reader.pos();
+        match self.head()? {
+            (4, length) => {
+                let length = usize::try_from(length).unwrap_or(usize::MAX);
+                if self.limits.check_node_length(length).is_err() {
+                    return Err(ScanStop::TooManyAssertions(length));
+                }
+                for _ in 0..length {
+                    self.element()?;
+                }
+            },
+            (5, count) => {
+                for _ in 0..count {
+                    self.element()?;
+                    self.element()?;
+                }
+            },
+            (6, tag) if tag == bc_components::tags::TAG_ENVELOPE => self.element()?,
+            (6, _) => self.skip()?,
+            (2 | 3, _) => {
+                self.reader.seek(start);
+                self.skip()?;
+            },
+            _ => {},
+        }
+        self.depth -= 1;
+        Ok(())
+    }
+}
+
+impl Default for EnvelopeLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Counts of the work done decoding one encoding, returned by
+/// [`Envelope::from_tagged_cbor_with_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DecodeStats {
+    /// The number of elements in the envelope, as counted against
+    /// [`EnvelopeLimits::max_total_elements`].
+    pub elements: usize,
+    /// The number of elements decoded and hashed. Elements that repeat a
+    /// recently decoded element are reused instead, so this can be far less
+    /// than `elements`.
+    pub decoded_elements: usize,
+    /// The number of times a recently decoded element was reused for a
+    /// duplicate.
+    pub reused_duplicates: usize,
+}
+
+/// Tracks the elements decoded from one encoding against the limits, so that
+/// decoding stops before it allocates more than they allow.
+///
+/// The budget also remembers a few recently decoded elements, so that an
+/// encoding that repeats an element many times, such as an envelope with
+/// thousands of identical assertions, costs one decoding and one digest per
+/// distinct element rather than per copy. Reused elements are charged as if
+/// they were decoded again, so limits are enforced identically. Reuse can be
+/// turned off with [`EnvelopeLimits::reuse_duplicates`].
+pub(crate) struct DecodeBudget<'a> {
+    limits: &'a EnvelopeLimits,
+    elements: usize,
+    memory: usize,
+    stats: DecodeStats,
+    recent: VecDeque<RecentElement>,
+    depth: usize,
+    strict: bool,
+    parsed: bool,
+}
+
+/// An element recently decoded from `cbor` at `depth`, and the charges made
+/// decoding it.
+struct RecentElement {
+    cbor: CBOR,
+    envelope: Envelope,
+    elements: usize,
+    memory: usize,
+    depth: usize,
+}
+
+impl<'a> DecodeBudget<'a> {
+    /// The estimated size of one element: its shared allocation, which holds
+    /// the element's case and digest.
+    const ELEMENT_SIZE: usize = std::mem::size_of::<super::envelope::EnvelopeCase>() + 2 * std::mem::size_of::<usize>();
+
+    /// The number of recently decoded elements remembered for reuse.
+    const RECENT_ELEMENTS: usize = 16;
+
+    pub(crate) fn new(limits: &'a EnvelopeLimits) -> Self {
+        Self { limits, elements: 0, memory: 0, stats: DecodeStats::default(), recent: VecDeque::new(), depth: 0, strict: false, parsed: false }
+    }
+
+    /// Returns this budget for a decoding that rejects nodes whose
+    /// assertions are not already in canonical order.
+    pub(crate) fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Returns whether the decoding rejects nodes whose assertions are not
+    /// already in canonical order, rather than sorting them.
+    pub(crate) fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Returns this budget for a decoding of CBOR parsed from data, whose
+    /// items the parser has already required to be canonical.
+    pub(crate) fn parsed(mut self) -> Self {
+        self.parsed = true;
+        self
+    }
+
+    /// Returns whether the CBOR being decoded was parsed from data, so that
+    /// its leaves are known to re-encode to themselves.
+    pub(crate) fn is_parsed(&self) -> bool {
+        self.parsed
+    }
+
+    pub(crate) fn limits(&self) -> &EnvelopeLimits {
+        self.limits
+    }
+
+    /// Accounts for an element about to be decoded, which refers to
+    /// `children` other elements, or returns `EnvelopeError::LimitExceeded`.
+    pub(crate) fn charge(&mut self, children: usize) -> Result<()> {
+        let memory = self.memory
+            .saturating_add(Self::ELEMENT_SIZE)
+            .saturating_add(children.saturating_mul(std::mem::size_of::<Envelope>()));
+        let limit = if self.elements >= self.limits.max_total_elements {
+            "max_total_elements"
+        } else if memory > self.limits.max_estimated_memory {
+            "max_estimated_memory"
+        } else {
+            self.elements += 1;
+            self.memory = memory;
+            self.stats.decoded_elements += 1;
+            return Ok(());
+        };
+        bail!(EnvelopeError::LimitExceeded { limit, elements: self.elements, memory: self.memory })
+    }
+
+    /// Enters an element nested one level deeper, or returns
+    /// `EnvelopeError::TooDeep`.
+    pub(crate) fn descend(&mut self) -> Result<()> {
+        self.depth += 1;
+        self.limits.check_depth(self.depth)
+    }
+
+    /// Leaves an element entered with [`DecodeBudget::descend`].
+    pub(crate) fn ascend(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Returns a recently decoded element that was decoded from `cbor`,
+    /// charging for it as if it were decoded again.
+    ///
+    /// Returns `None` if there is no such element, if it was decoded at a
+    /// shallower depth, or if charging for it would exceed the limits, in
+    /// which case the element must be decoded so that the limit is reported
+    /// at the same element as it would be without reuse.
+    pub(crate) fn reuse(&mut self, cbor: &CBOR) -> Option<Envelope> {
+        let index = self.recent.iter().position(|recent| recent.cbor == *cbor && recent.depth >= self.depth)?;
+        let recent = &self.recent[index];
+        let elements = self.elements + recent.elements;
+        let memory = self.memory.saturating_add(recent.memory);
+        if elements > self.limits.max_total_elements || memory > self.limits.max_estimated_memory {
+            return None;
+        }
+        self.elements = elements;
+        self.memory = memory;
+        self.stats.reused_duplicates += 1;
+        let recent = self.recent.remove(index)?;
+        let envelope = recent.envelope.clone();
+        self.recent.push_front(recent);
+        Some(envelope)
+    }
+
+    /// Returns the charges made so far, to pass to
+    /// [`DecodeBudget::remember`] once an element is decoded.
+    pub(crate) fn mark(&self) -> (usize, usize) {
+        (self.elements, self.memory)
+    }
+
+    /// Remembers an element decoded from `cbor` for reuse, along with the
+    /// charges made since `mark`.
+    pub(crate) fn remember(&mut self, cbor: &CBOR, envelope: &Envelope, mark: (usize, usize)) {
+        self.recent.truncate(Self::RECENT_ELEMENTS - 1);
+        self.recent.push_front(RecentElement {
+            cbor: cbor.clone(),
+            envelope: envelope.clone(),
+            elements: self.elements - mark.0,
+            memory: self.memory - mark.1,
+            depth: self.depth,
+        });
+    }
+
+    pub(crate) fn stats(&self) -> DecodeStats {
+        DecodeStats { elements: self.elements, ..self.stats }
+    }
+}
@@ -1,5 +1,8 @@
 pub mod assertion;
 pub mod assertions;
+
+/// Traits for types that contribute assertions to an envelope.
+pub mod assertion_provider;
 pub mod cbor;
 pub mod digest;
 pub mod envelope;
@@ -11,6 +14,15 @@ pub mod elide;
 
 pub mod error;
 
+/// Limits applied when expanding untrusted envelopes.
+pub mod limits;
+
+/// The source of randomness used to create envelopes.
+pub mod random;
+
+/// Safeguards against leaves that contain encoded envelopes.
+pub mod leaf_policy;
+
 pub mod envelope_encodable;
 pub use envelope_encodable::EnvelopeEncodable;
 
@@ -23,6 +35,19 @@ pub mod format;
 pub mod format_context;
 pub use format_context::*;
 pub mod tree_format;
+pub mod graph_format;
+
+/// Parsing of envelope notation.
+pub mod notation;
+
+/// Selection of elements by path expressions.
+pub mod path_expr;
+
+/// Measures of the structure of envelopes.
+pub mod stats;
+
+/// The registry entries that envelopes rely on.
+pub mod registry_deps;
 
 /// Types dealing with recursive walking of envelopes.
 ///
@@ -32,8 +57,62 @@ pub mod walk;
 pub mod wrap;
 pub mod envelope_summary;
 
+/// A declarative pipeline for building envelopes.
+pub mod pipeline;
+
+/// Assembly of nodes from assertions produced by several threads.
+#[cfg(feature = "multithreaded")]
+pub mod concurrent_builder;
+
+/// Translation between compact and URI predicates.
+pub mod predicate_context;
+
+/// Comparison of encoded envelopes.
+pub mod compare;
+
+/// Application metadata attached to the elements of envelopes.
+pub mod sidecar;
+
+/// Replacement of sensitive values with deterministic pseudonyms.
+pub mod pseudonymize;
+
+/// Storage of encrypted and compressed elements outside an envelope.
+#[cfg(any(feature = "encrypt", feature = "compress"))]
+pub mod payload;
+
+/// Multipart URs of envelopes.
+pub mod multipart_ur;
+
+/// Serialization of envelopes with serde.
+#[cfg(feature = "serde")]
+mod serde_impl;
+
+/// Asynchronous decoding of framed envelope streams.
+#[cfg(feature = "async")]
+pub mod stream;
+
 pub use assertion::Assertion;
+pub use assertion_provider::{AssertionProvider, FromAssertions};
 pub use envelope::Envelope;
 pub use error::EnvelopeError;
+pub use limits::{DecodeStats, EnvelopeLimits};
+pub use stats::EnvelopeStats;
+pub use registry_deps::{RegistryDeps, RegistryItem};
+pub use random::{RandomProvider, SecureRandomProvider};
+#[cfg(feature = "test-support")]
+pub use random::{reset_random_provider, set_random_provider, SeededRandomProvider};
+pub use leaf_policy::{set_tagged_leaf_policy, tagged_leaf_policy, SuspicionKind, TaggedLeafPolicy};
 pub use format_context::{FormatContext, GLOBAL_FORMAT_CONTEXT};
 pub use envelope_summary::EnvelopeSummary;
+pub use pipeline::EnvelopePipeline;
+pub use multipart_ur::EnvelopeURDecoder;
+#[cfg(feature = "multithreaded")]
+pub use concurrent_builder::ConcurrentNodeBuilder;
+pub use predicate_context::{PredicateContext, PredicateTerm};
+pub use compare::{compare_encoded, ComparisonResult, Divergence};
+pub use sidecar::{annotate_format, ElementSidecar, SidecarConflictPolicy};
+pub use pseudonymize::{PseudonymReplacement, PseudonymizationReport, RedactionPreset, TAG_PSEUDONYM};
+#[cfg(any(feature = "encrypt", feature = "compress"))]
+pub use payload::{DetachedPayload, PayloadKind};
+#[cfg(feature = "async")]
+pub use stream::EnvelopeStream;
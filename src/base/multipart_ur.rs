@@ -0,0 +1,138 @@
+use std::collections::HashSet;
+
+use anyhow::{bail, Result};
+use bc_ur::{MultipartDecoder, MultipartEncoder, UR};
+use dcbor::prelude::*;
+
+use crate::{Envelope, EnvelopeError, EnvelopeLimits};
+
+use super::cbor::ENVELOPE_UR_TYPE;
+
+/// Support for multipart URs, for envelopes too large for a single QR code.
+impl Envelope {
+    /// Returns the parts of this envelope's multipart UR, each of the form
+    /// `ur:envelope/<n>-<count>/...` and carrying a fragment of at most
+    /// `max_fragment_len` bytes of the envelope's encoding.
+    ///
+    /// The parts can be received in any order by an [`EnvelopeURDecoder`].
+    /// If the envelope fits in a single fragment, the result is its
+    /// single-part UR, [`Envelope::ur_string`].
+    ///
+    /// Panics if `max_fragment_len` is zero.
+    pub fn ur_parts(&self, max_fragment_len: usize) -> Vec<String> {
+        assert!(max_fragment_len > 0, "the maximum fragment length must not be zero");
+        let ur = UR::new(ENVELOPE_UR_TYPE, self.untagged_cbor()).unwrap();
+        let mut encoder = MultipartEncoder::new(&ur, max_fragment_len).unwrap();
+        if encoder.parts_count() == 1 {
+            return vec![ur.string()];
+        }
+        (0..encoder.parts_count()).map(|_| encoder.next_part().unwrap()).collect()
+    }
+}
+
+/// A decoder that reassembles an envelope from the parts of its multipart
+/// UR, as returned by [`Envelope::ur_parts`].
+///
+/// Parts may be received in any order, and duplicates are ignored. A part
+/// of another message is rejected without disturbing the parts already
+/// received, so a scanner that picks up a second QR code can carry on. Once
+/// enough parts have been received, the envelope is decoded, and its digests
+/// calculated, subject to the decoder's limits.
+///
+/// A single-part envelope UR is also accepted, and completes the decoder at
+/// once.
+#[derive(Default)]
+pub struct EnvelopeURDecoder {
+    decoder: MultipartDecoder,
+    limits: EnvelopeLimits,
+    part_count: Option<usize>,
+    received: HashSet<usize>,
+    envelope: Option<Envelope>,
+}
+
+impl EnvelopeURDecoder {
+    /// Creates a decoder that decodes the envelope subject to the default
+    /// [`EnvelopeLimits`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a decoder that decodes the envelope subject to the given
+    /// limits.
+    pub fn with_limits(limits: EnvelopeLimits) -> Self {
+        Self { limits, ..Self::default() }
+    }
+
+    /// Receives a part of the envelope's UR. The part is case-insensitive.
+    ///
+    /// Parts received once the envelope is complete are ignored.
+    ///
+    /// - Throws: `EnvelopeError::NotUR` if the part is not a UR,
+    /// `EnvelopeError::WrongURType` if it is a UR of a type other than
+    /// `envelope`, `EnvelopeError::InvalidURPart` if it is malformed or is a
+    /// part of another message than the parts already received, and
+    /// `EnvelopeError::InvalidURCBOR` if the reassembled message is not an
+    /// envelope.
+    pub fn receive(&mut self, part: &str) -> Result<()> {
+        if self.is_complete() {
+            return Ok(());
+        }
+        let part = part.to_lowercase();
+        let Some((ur_type, rest)) = part.strip_prefix("ur:").and_then(|rest| rest.split_once('/')) else {
+            bail!(EnvelopeError::NotUR { message: "expected `ur:<type>/<data>`".to_string() });
+        };
+        if ur_type != ENVELOPE_UR_TYPE {
+            bail!(EnvelopeError::WrongURType { ur_type: ur_type.to_string() });
+        }
+        let Some((sequence, _)) = rest.split_once('/') else {
+            // A single-part UR holds the whole envelope.
+            if self.part_count.is_some() {
+                bail!(EnvelopeError::InvalidURPart { message: "a single-part UR cannot complete a multipart UR".to_string() });
+            }
+            self.envelope = Some(Envelope::from_ur_string_with_limits(part, &self.limits)?);
+            return Ok(());
+        };
+        let Some((index, count)) = sequence.split_once('-')
+            .and_then(|(index, count)| Some((index.parse::<usize>().ok()?, count.parse::<usize>().ok()?)))
+        else {
+            bail!(EnvelopeError::NotUR { message: format!("invalid part sequence `{}`", sequence) });
+        };
+        self.decoder.receive(&part).map_err(|error| EnvelopeError::InvalidURPart { message: error.to_string() })?;
+        self.part_count = Some(count);
+        self.received.insert(index);
+        if self.decoder.is_complete() {
+            let ur = self.decoder.message()
+                .map_err(|error| EnvelopeError::InvalidURCBOR { message: error.to_string() })?
+                .expect("a complete decoder has a message");
+            self.envelope = Some(Envelope::from_ur_cbor_with_limits(ur.cbor(), &self.limits)?);
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the envelope has been reassembled.
+    pub fn is_complete(&self) -> bool {
+        self.envelope.is_some()
+    }
+
+    /// Returns an estimate of the fraction of the envelope received, from
+    /// 0.0 to 1.0.
+    ///
+    /// Fountain-coded parts beyond the first `count` may each carry several
+    /// fragments, so more parts than the fragment count are usually needed.
+    /// The estimate allows for this, and is 1.0 only once the envelope is
+    /// complete.
+    pub fn estimated_percent_complete(&self) -> f64 {
+        if self.is_complete() {
+            return 1.0;
+        }
+        match self.part_count {
+            Some(count) => (self.received.len() as f64 / (count as f64 * 1.75)).min(0.99),
+            None => 0.0,
+        }
+    }
+
+    /// Returns the envelope, once it has been reassembled.
+    pub fn envelope(&self) -> Option<&Envelope> {
+        self.envelope.as_ref()
+    }
+}
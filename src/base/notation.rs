@@ -0,0 +1,378 @@
+use anyhow::{Error, Result};
+use dcbor::prelude::*;
+
+use crate::{Envelope, EnvelopeError, EnvelopeLimits, FormatContext, with_format_context};
+use super::format::FOLD_MARKER;
+#[cfg(feature = "known_value")]
+use crate::extension::KnownValue;
+#[cfg(feature = "expression")]
+use crate::{Function, Parameter};
+
+/// Support for parsing envelope notation.
+impl Envelope {
+    /// Parses an envelope from envelope notation, as produced by
+    /// [`Envelope::format_opt`], resolving the names of known values,
+    /// functions, parameters, and tags in the given context.
+    ///
+    /// The notation may be hierarchical or flat. The supported elements are:
+    ///
+    /// * Strings (`"Hello."`, with `\n` for a newline), numbers, `true`,
+    ///   `false`, and `null`.
+    /// * Byte strings in diagnostic notation (`h'0102'`), arrays of values
+    ///   (`[1, "two"]`), and maps of values (`Map{1: "one", "two": 2}`).
+    /// * Dates (`2023-06-01`, or with a time), and values tagged with the name
+    ///   or number of a tag that has no summarizer (`date(1675814400)`).
+    /// * Known values (`'note'`), functions (`«add»`), and parameters
+    ///   (`❰lhs❱`).
+    /// * Wrapped envelopes (`{ … }`), assertions (`predicate: object`), and
+    ///   assertion lists (`subject [ … ]`), nested to a depth of up to
+    ///   [`EnvelopeLimits::DEFAULT_MAX_DEPTH`], as are arrays, maps, and
+    ///   tagged values.
+    ///
+    /// Parsing the notation of an envelope that has none of the elements
+    /// below returns an envelope with the same digest.
+    ///
+    /// `ELIDED`, `ENCRYPTED`, and `COMPRESSED` elements, and values whose
+    /// notation is a summary, such as `Bytes(16)`, `ARID(c66be27d)`, or
+    /// `Digest(8c34a5a3)`, cannot be reconstructed, and are errors. So are
+    /// folded assertions (`"item" (×200): "a", …(199 more)`), which are
+    /// produced when the context has [`FoldOptions`](crate::FoldOptions). Because
+    /// `{ "a": "b" } [ … ]` is the notation of both a node whose subject is an
+    /// assertion and a node whose subject is a wrapped assertion, it is always
+    /// parsed as the former. Envelope notation does not escape quotes, so a
+    /// string containing a quote followed by punctuation, as in `"a": b`,
+    /// cannot be parsed.
+    ///
+    /// Returns `EnvelopeError::InvalidNotation` with the byte offset of the
+    /// first error.
+    pub fn from_notation_opt(text: &str, context: Option<&FormatContext>) -> Result<Self> {
+        let default_context = FormatContext::default();
+        let mut parser = NotationParser { text, position: 0, depth: 0, context: context.unwrap_or(&default_context) };
+        let envelope = parser.parse_envelope()?;
+        parser.skip_whitespace();
+        if parser.position < text.len() {
+            return Err(parser.error("unexpected text after the envelope"));
+        }
+        Ok(envelope)
+    }
+
+    /// Parses an envelope from envelope notation, using the global format
+    /// context.
+    ///
+    /// See [`Envelope::from_notation_opt`].
+    pub fn from_notation(text: &str) -> Result<Self> {
+        with_format_context!(|context| {
+            Self::from_notation_opt(text, Some(context))
+        })
+    }
+}
+
+/// The words that stand for elements that cannot be reconstructed.
+const OBSCURED_WORDS: [&str; 3] = ["ELIDED", "ENCRYPTED", "COMPRESSED"];
+
+const FOLDED_MESSAGE: &str = "folded assertions cannot be reconstructed; format the envelope without folding";
+
+struct NotationParser<'a> {
+    text: &'a str,
+    position: usize,
+    depth: usize,
+    context: &'a FormatContext,
+}
+
+impl<'a> NotationParser<'a> {
+    fn error(&self, message: impl Into<String>) -> Error {
+        EnvelopeError::InvalidNotation { message: message.into(), offset: self.position }.into()
+    }
+
+    /// Parses a nested item with `parse`, failing if it is nested deeper
+    /// than [`EnvelopeLimits::DEFAULT_MAX_DEPTH`].
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= EnvelopeLimits::DEFAULT_MAX_DEPTH {
+            return Err(self.error(format!("elements are nested deeper than the limit of {} levels", EnvelopeLimits::DEFAULT_MAX_DEPTH)));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    /// Skips whitespace, then consumes `c` if it is next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.position += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if !self.eat(c) {
+            return Err(self.error(format!("expected `{}`", c)));
+        }
+        Ok(())
+    }
+
+    /// Consumes text up to and including `close`, returning it along with
+    /// the `open_len` bytes of the opening delimiter already consumed.
+    fn delimited(&mut self, open_len: usize, close: char) -> Result<&'a str> {
+        let start = self.position - open_len;
+        match self.rest().find(close) {
+            Some(end) => {
+                self.position += end + close.len_utf8();
+                Ok(&self.text[start..self.position])
+            },
+            None => Err(self.error(format!("expected `{}`", close))),
+        }
+    }
+
+    /// Consumes a word: text up to whitespace, punctuation, or a colon that
+    /// separates a predicate from its object. Colons within a word, as in
+    /// the time of a date, are kept.
+    fn word(&mut self) -> &'a str {
+        let rest = self.rest();
+        let len = rest
+            .char_indices()
+            .find(|&(index, c)| {
+                c.is_whitespace()
+                    || ",[]{}()".contains(c)
+                    || (c == ':' && rest[index + 1..].chars().next().is_none_or(char::is_whitespace))
+            })
+            .map_or(rest.len(), |(index, _)| index);
+        self.position += len;
+        &rest[..len]
+    }
+
+    /// Parses an element, or an assertion of two elements.
+    fn parse_envelope(&mut self) -> Result<Envelope> {
+        let element = self.parse_element()?;
+        self.skip_whitespace();
+        if self.rest().starts_with("(×") {
+            return Err(self.error(FOLDED_MESSAGE));
+        }
+        if self.eat(':') {
+            let object = self.parse_element()?;
+            return Ok(Envelope::new_assertion(element, object));
+        }
+        Ok(element)
+    }
+
+    /// Parses a subject, and the list of its assertions if it has one.
+    fn parse_element(&mut self) -> Result<Envelope> {
+        let subject = if self.eat('{') {
+            let inner = self.nested(Self::parse_envelope)?;
+            self.expect('}')?;
+            self.skip_whitespace();
+            if inner.is_subject_assertion() && self.peek() == Some('[') {
+                inner
+            } else {
+                inner.wrap_envelope()
+            }
+        } else {
+            self.parse_primary()?
+        };
+        if !self.eat('[') {
+            return Ok(subject);
+        }
+        let mut assertions = Vec::new();
+        loop {
+            while self.eat(',') {}
+            if self.eat(']') {
+                break;
+            }
+            let offset = self.position;
+            let assertion = self.nested(Self::parse_envelope)?;
+            if !assertion.is_subject_assertion() {
+                self.position = offset;
+                return Err(self.error("expected an assertion"));
+            }
+            assertions.push(assertion);
+        }
+        subject.add_assertion_envelopes(&assertions)
+    }
+
+    /// Parses a leaf or known value element.
+    fn parse_primary(&mut self) -> Result<Envelope> {
+        self.skip_whitespace();
+        match self.peek() {
+            #[cfg(feature = "known_value")]
+            Some('\'') => Ok(Envelope::new(self.parse_known_value()?)),
+            _ => Ok(Envelope::new(self.parse_value()?)),
+        }
+    }
+
+    #[cfg(feature = "known_value")]
+    fn parse_known_value(&mut self) -> Result<KnownValue> {
+        self.position += 1;
+        let start = self.position - 1;
+        let text = self.delimited(1, '\'')?;
+        KnownValue::parse(text, Some(self.context.known_values()))
+            .map_err(|error| EnvelopeError::InvalidNotation { message: error.to_string(), offset: start }.into())
+    }
+
+    /// Parses a value that can appear in a leaf.
+    fn parse_value(&mut self) -> Result<CBOR> {
+        self.skip_whitespace();
+        let start = self.position;
+        match self.peek() {
+            None => Err(self.error("expected an element")),
+            Some(FOLD_MARKER) => Err(self.error(FOLDED_MESSAGE)),
+            Some('"') => self.parse_text(),
+            Some('[') => {
+                self.position += 1;
+                let mut elements = Vec::new();
+                if !self.eat(']') {
+                    loop {
+                        elements.push(self.nested(Self::parse_value)?);
+                        if self.eat(']') {
+                            break;
+                        }
+                        self.expect(',')?;
+                    }
+                }
+                Ok(elements.into())
+            },
+            #[cfg(feature = "known_value")]
+            Some('\'') => Ok(self.parse_known_value()?.into()),
+            #[cfg(feature = "expression")]
+            Some('«') => {
+                self.position += '«'.len_utf8();
+                let text = self.delimited('«'.len_utf8(), '»')?;
+                Function::parse_strict(text, Some(self.context.functions()))
+                    .map(CBOR::from)
+                    .map_err(|error| EnvelopeError::InvalidNotation { message: error.to_string(), offset: start }.into())
+            },
+            #[cfg(feature = "expression")]
+            Some('❰') => {
+                self.position += '❰'.len_utf8();
+                let text = self.delimited('❰'.len_utf8(), '❱')?;
+                Parameter::parse_strict(text, Some(self.context.parameters()))
+                    .map(CBOR::from)
+                    .map_err(|error| EnvelopeError::InvalidNotation { message: error.to_string(), offset: start }.into())
+            },
+            Some('h') if self.rest().starts_with("h'") => {
+                self.position += 2;
+                let text = self.delimited(2, '\'')?;
+                let data = hex::decode(&text[2..text.len() - 1])
+                    .map_err(|_| EnvelopeError::InvalidNotation { message: "invalid byte string".to_string(), offset: start })?;
+                Ok(CBOR::to_byte_string(data))
+            },
+            Some(_) => {
+                let word = self.word();
+                if word.is_empty() {
+                    return Err(self.error("expected an element"));
+                }
+                if self.peek() == Some('(') {
+                    return self.parse_tagged(word, start);
+                }
+                if word == "Map" && self.eat('{') {
+                    return self.parse_map();
+                }
+                self.parse_word(word, start)
+            },
+        }
+    }
+
+    /// Parses the entries of a map, after its opening `Map{`.
+    fn parse_map(&mut self) -> Result<CBOR> {
+        let mut map = Map::new();
+        if !self.eat('}') {
+            loop {
+                let key = self.nested(Self::parse_value)?;
+                self.expect(':')?;
+                map.insert(key, self.nested(Self::parse_value)?);
+                if self.eat('}') {
+                    break;
+                }
+                self.expect(',')?;
+            }
+        }
+        Ok(map.into())
+    }
+
+    /// Parses a quoted string. The closing quote is the first that is
+    /// followed by the end of the text, by punctuation, or by the count of
+    /// folded assertions, so that strings can contain quotes, or failing
+    /// that, the first quote.
+    fn parse_text(&mut self) -> Result<CBOR> {
+        let body_start = self.position + 1;
+        let body = &self.text[body_start..];
+        let mut quotes = body.match_indices('"').map(|(index, _)| index);
+        let end = quotes.clone()
+            .find(|&index| {
+                let after = body[index + 1..].trim_start_matches([' ', '\t']);
+                after.is_empty() || after.starts_with([':', ',', '[', ']', '{', '}', ')', '\n', '\r']) || after.starts_with("(×")
+            })
+            .or_else(|| quotes.next())
+            .ok_or_else(|| self.error("unterminated string"))?;
+        self.position = body_start + end + 1;
+        Ok(body[..end].replace("\\n", "\n").into())
+    }
+
+    fn parse_tagged(&mut self, name: &str, start: usize) -> Result<CBOR> {
+        let tag_value = name.parse::<u64>().ok()
+            .or_else(|| self.context.tags().tag_for_name(name).map(|tag| tag.value()));
+        match tag_value {
+            Some(tag_value) if self.context.summarizer(tag_value).is_none() => {
+                self.position += 1;
+                let item = self.nested(Self::parse_value)?;
+                self.expect(')')?;
+                Ok(CBOR::to_tagged_value(tag_value, item))
+            },
+            _ => {
+                let end = self.rest().find(')').map(|end| self.position + end + 1).unwrap_or(self.text.len());
+                Err(EnvelopeError::InvalidNotation {
+                    message: format!("{} cannot be reconstructed from its summary", &self.text[start..end]),
+                    offset: start,
+                }.into())
+            },
+        }
+    }
+
+    fn parse_word(&self, word: &str, start: usize) -> Result<CBOR> {
+        let error = |message: String| -> Error { EnvelopeError::InvalidNotation { message, offset: start }.into() };
+        match word {
+            "true" => return Ok(true.into()),
+            "false" => return Ok(false.into()),
+            "null" => return Ok(CBOR::null()),
+            _ => {}
+        }
+        if OBSCURED_WORDS.contains(&word) {
+            return Err(error(format!("{} elements cannot be reconstructed", word)));
+        }
+        if let Ok(value) = word.parse::<u64>() {
+            return Ok(value.into());
+        }
+        if let Ok(value) = word.parse::<i128>() {
+            if let Ok(value) = i64::try_from(value) {
+                return Ok(value.into());
+            }
+            if let Ok(magnitude) = u64::try_from(-1 - value) {
+                return Ok(CBORCase::Negative(magnitude).into());
+            }
+        }
+        if let Ok(value) = word.parse::<f64>() {
+            return Ok(value.into());
+        }
+        if word.len() > 1 && word[1..].contains('-') {
+            if let Ok(date) = dcbor::Date::from_string(word) {
+                return Ok(date.into());
+            }
+        }
+        Err(error(format!("unrecognized element: {}", word)))
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::HashSet;
+
+use anyhow::{bail, Error, Result};
+use bc_components::{Digest, DigestProvider};
+
+use super::envelope::EnvelopeCase;
+use crate::{Envelope, EnvelopeError, FormatContext};
+#[cfg(feature = "known_value")]
+use crate::extension::KnownValue;
+
+/// Support for selecting elements of an envelope with path expressions.
+///
+/// A path expression is a sequence of steps separated by `/`, each applied to
+/// the elements selected by the step before it, starting with the envelope
+/// itself:
+///
+/// * `subject` selects the subject of each element.
+/// * `wrapped` selects the envelope wrapped in the subject of each element.
+/// * `assertion[…]` selects the assertions of each element whose predicate is
+///   a known value (`'isA'`), a string, quoted (`"firstName"`) or not
+///   (`firstName`), or whose own digest starts with the given hex digits
+///   (`Digest(8c34)`). `assertion[*]` selects all of the assertions.
+/// * `predicate` and `object` select the predicate or object of each
+///   assertion.
+///
+/// For example, `wrapped/assertion[firstName]/object` selects the object of
+/// the `"firstName"` assertion inside a signed envelope.
+impl Envelope {
+    /// Returns the digests of the elements selected by the path expression,
+    /// resolving the names of known values in the given context.
+    ///
+    /// Returns `EnvelopeError::InvalidPathExpression` with the byte offset of
+    /// the first error if the expression cannot be parsed,
+    /// `EnvelopeError::UnmatchedPathStep` with the number of the first step
+    /// that selects nothing, and `EnvelopeError::AmbiguousDigestPrefix` if a
+    /// digest prefix matches more than one assertion.
+    pub fn resolve_path_expr(&self, expr: &str, context: &FormatContext) -> Result<Vec<Digest>> {
+        let mut seen = HashSet::new();
+        Ok(self.path_matches(expr, context)?
+            .into_iter()
+            .map(|path_match| path_match.element.digest().into_owned())
+            .filter(|digest| seen.insert(digest.clone()))
+            .collect())
+    }
+
+    /// Returns a version of this envelope with the elements selected by the
+    /// path expression elided.
+    ///
+    /// See [`Envelope::resolve_path_expr`].
+    pub fn elide_removing_path(&self, expr: &str, context: &FormatContext) -> Result<Self> {
+        let target = self.resolve_path_expr(expr, context)?.into_iter().collect();
+        Ok(self.elide_removing_set(&target))
+    }
+
+    /// Returns a version of this envelope with everything elided except the
+    /// elements selected by the path expression, their contents, and the
+    /// elements that lead to them.
+    ///
+    /// See [`Envelope::resolve_path_expr`].
+    pub fn elide_revealing_path(&self, expr: &str, context: &FormatContext) -> Result<Self> {
+        let mut target = HashSet::new();
+        for path_match in self.path_matches(expr, context)? {
+            target.extend(path_match.ancestors);
+            target.extend(path_match.element.deep_digests());
+        }
+        Ok(self.elide_revealing_set(&target))
+    }
+
+    fn path_matches(&self, expr: &str, context: &FormatContext) -> Result<Vec<PathMatch>> {
+        let steps = PathParser { text: expr, position: 0, context }.parse_steps()?;
+        let mut matches = vec![PathMatch { element: self.clone(), ancestors: Vec::new() }];
+        for (index, step) in steps.iter().enumerate() {
+            matches = step.apply(&matches)?;
+            if matches.is_empty() {
+                bail!(EnvelopeError::UnmatchedPathStep {
+                    step: index + 1,
+                    text: step.text.clone(),
+                    reason: step.unmatched_reason(context),
+                });
+            }
+        }
+        Ok(matches)
+    }
+}
+
+/// An element selected by a path expression, with the digests of the
+/// elements that contain it, outermost first.
+struct PathMatch {
+    element: Envelope,
+    ancestors: Vec<Digest>,
+}
+
+impl PathMatch {
+    fn child(&self, element: Envelope) -> Self {
+        let mut ancestors = self.ancestors.clone();
+        ancestors.push(self.element.digest().into_owned());
+        Self { element, ancestors }
+    }
+
+    /// Returns the subject of the element, which is the element itself if it
+    /// is not a node.
+    fn subject(&self) -> Self {
+        match self.element.case() {
+            EnvelopeCase::Node { subject, .. } => self.child(subject.clone()),
+            _ => Self { element: self.element.clone(), ancestors: self.ancestors.clone() },
+        }
+    }
+}
+
+enum Selector {
+    All,
+    Predicate(Envelope),
+    DigestPrefix(String),
+}
+
+enum StepKind {
+    Subject,
+    Wrapped,
+    Assertion(Selector),
+    Predicate,
+    Object,
+}
+
+struct Step {
+    kind: StepKind,
+    text: String,
+}
+
+impl Step {
+    fn apply(&self, matches: &[PathMatch]) -> Result<Vec<PathMatch>> {
+        let mut selected = Vec::new();
+        for path_match in matches {
+            match &self.kind {
+                StepKind::Subject => selected.push(path_match.subject()),
+                StepKind::Wrapped => {
+                    let subject = path_match.subject();
+                    if let EnvelopeCase::Wrapped { envelope, .. } = subject.element.case() {
+                        selected.push(subject.child(envelope.clone()));
+                    }
+                },
+                StepKind::Assertion(selector) => {
+                    for assertion in path_match.element.assertions() {
+                        let is_selected = match selector {
+                            Selector::All => true,
+                            Selector::Predicate(predicate) => assertion
+                                .as_predicate()
+                                .is_some_and(|assertion_predicate| assertion_predicate.digest() == predicate.digest()),
+                            Selector::DigestPrefix(prefix) => assertion.digest_hex().starts_with(prefix.as_str()),
+                        };
+                        if is_selected {
+                            selected.push(path_match.child(assertion.clone()));
+                        }
+                    }
+                },
+                StepKind::Predicate => {
+                    if let Some(predicate) = path_match.element.as_predicate() {
+                        selected.push(path_match.child(predicate));
+                    }
+                },
+                StepKind::Object => {
+                    if let Some(object) = path_match.element.as_object() {
+                        selected.push(path_match.child(object));
+                    }
+                },
+            }
+        }
+        if let StepKind::Assertion(Selector::DigestPrefix(prefix)) = &self.kind {
+            if selected.len() > 1 {
+                bail!(EnvelopeError::AmbiguousDigestPrefix { prefix: prefix.clone(), count: selected.len() });
+            }
+        }
+        Ok(selected)
+    }
+
+    fn unmatched_reason(&self, context: &FormatContext) -> String {
+        match &self.kind {
+            StepKind::Subject => "no elements were selected".to_string(),
+            StepKind::Wrapped => "no selected element has a wrapped subject".to_string(),
+            StepKind::Assertion(Selector::All) => "no selected element has assertions".to_string(),
+            StepKind::Assertion(Selector::Predicate(predicate)) => {
+                format!("no assertion has the predicate {}", predicate.format_flat_opt(Some(context)))
+            },
+            StepKind::Assertion(Selector::DigestPrefix(prefix)) => {
+                format!("no assertion has a digest starting with {}", prefix)
+            },
+            StepKind::Predicate | StepKind::Object => "no selected element is an assertion".to_string(),
+        }
+    }
+}
+
+struct PathParser<'a> {
+    text: &'a str,
+    position: usize,
+    context: &'a FormatContext,
+}
+
+impl<'a> PathParser<'a> {
+    fn error(&self, message: impl Into<String>, offset: usize) -> Error {
+        EnvelopeError::InvalidPathExpression { message: message.into(), offset }.into()
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn parse_steps(&mut self) -> Result<Vec<Step>> {
+        let mut steps = Vec::new();
+        loop {
+            steps.push(self.parse_step()?);
+            self.skip_whitespace();
+            if self.position == self.text.len() {
+                return Ok(steps);
+            }
+            if !self.rest().starts_with('/') {
+                return Err(self.error("expected `/`", self.position));
+            }
+            self.position += 1;
+        }
+    }
+
+    fn parse_step(&mut self) -> Result<Step> {
+        self.skip_whitespace();
+        let start = self.position;
+        let rest = self.rest();
+        let name_len = rest
+            .find(|c: char| c == '/' || c == '[' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let name = &rest[..name_len];
+        self.position += name_len;
+        self.skip_whitespace();
+        let selector = if self.rest().starts_with('[') {
+            Some(self.parse_selector()?)
+        } else {
+            None
+        };
+        let kind = match (name, selector) {
+            ("", _) => return Err(self.error("expected a path step", start)),
+            ("assertion", Some(selector)) => StepKind::Assertion(selector),
+            ("assertion", None) => return Err(self.error("expected `[` after `assertion`", self.position)),
+            (_, Some(_)) => return Err(self.error(format!("`{}` does not take a selector", name), start)),
+            ("subject", None) => StepKind::Subject,
+            ("wrapped", None) => StepKind::Wrapped,
+            ("predicate", None) => StepKind::Predicate,
+            ("object", None) => StepKind::Object,
+            _ => {
+                return Err(self.error(
+                    format!("unknown step `{}`; expected subject, wrapped, assertion, predicate, or object", name),
+                    start,
+                ))
+            },
+        };
+        Ok(Step { kind, text: self.text[start..self.position].trim_end().to_string() })
+    }
+
+    /// Parses a bracketed assertion selector.
+    fn parse_selector(&mut self) -> Result<Selector> {
+        let open = self.position;
+        self.position += 1;
+        self.skip_whitespace();
+        let start = self.position;
+        let rest = self.rest();
+        let selector = match rest.chars().next() {
+            Some(quote @ ('\'' | '"')) => {
+                let end = rest[1..]
+                    .find(quote)
+                    .ok_or_else(|| self.error(format!("expected closing `{}`", quote), start))?;
+                let text = &rest[..end + 2];
+                self.position += text.len();
+                if quote == '"' {
+                    Selector::Predicate(Envelope::new(&text[1..text.len() - 1]))
+                } else {
+                    Selector::Predicate(self.known_value_predicate(text, start)?)
+                }
+            },
+            _ => {
+                let end = rest.find(']').ok_or_else(|| self.error("expected `]`", open))?;
+                let text = rest[..end].trim_end();
+                self.position += end;
+                match text {
+                    "" => return Err(self.error("expected a selector", start)),
+                    "*" => Selector::All,
+                    _ => match text.strip_prefix("Digest(").and_then(|text| text.strip_suffix(')')) {
+                        Some(prefix) => {
+                            if prefix.is_empty() || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+                                return Err(self.error("expected hex digits in a digest prefix", start));
+                            }
+                            Selector::DigestPrefix(prefix.to_ascii_lowercase())
+                        },
+                        None => Selector::Predicate(Envelope::new(text)),
+                    },
+                }
+            },
+        };
+        self.skip_whitespace();
+        if !self.rest().starts_with(']') {
+            return Err(self.error("expected `]`", self.position));
+        }
+        self.position += 1;
+        Ok(selector)
+    }
+
+    #[cfg(feature = "known_value")]
+    fn known_value_predicate(&self, text: &str, offset: usize) -> Result<Envelope> {
+        KnownValue::parse(text, Some(self.context.known_values()))
+            .map(Envelope::new)
+            .map_err(|error| self.error(error.to_string(), offset))
+    }
+
+    #[cfg(not(feature = "known_value"))]
+    fn known_value_predicate(&self, _text: &str, offset: usize) -> Result<Envelope> {
+        let _ = self.context;
+        Err(self.error("known values require the `known_value` feature", offset))
+    }
+}
@@ -0,0 +1,204 @@
+use anyhow::{bail, Error, Result};
+use bc_components::{Digest, DigestProvider};
+use dcbor::prelude::*;
+
+use crate::{Assertion, Envelope, EnvelopeError};
+
+use super::envelope::EnvelopeCase;
+
+/// The kind of an obscured element detached by [`Envelope::detach_payload`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadKind {
+    /// An encrypted element.
+    #[cfg(feature = "encrypt")]
+    Encrypted,
+    /// A compressed element.
+    #[cfg(feature = "compress")]
+    Compressed,
+}
+
+impl PayloadKind {
+    fn of(envelope: &Envelope) -> Option<Self> {
+        match envelope.case() {
+            #[cfg(feature = "encrypt")]
+            EnvelopeCase::Encrypted(_) => Some(Self::Encrypted),
+            #[cfg(feature = "compress")]
+            EnvelopeCase::Compressed(_) => Some(Self::Compressed),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "encrypt")]
+            Self::Encrypted => "encrypted",
+            #[cfg(feature = "compress")]
+            Self::Compressed => "compressed",
+        }
+    }
+
+    fn from_name(name: &str) -> Result<Self> {
+        match name {
+            #[cfg(feature = "encrypt")]
+            "encrypted" => Ok(Self::Encrypted),
+            #[cfg(feature = "compress")]
+            "compressed" => Ok(Self::Compressed),
+            _ => bail!(EnvelopeError::InvalidFormat { message: format!("unknown payload kind: {}", name) }),
+        }
+    }
+}
+
+/// An encrypted or compressed element removed from an envelope by
+/// [`Envelope::detach_payload`], for storage outside the envelope.
+///
+/// The payload encodes to CBOR as an array of its kind (`"encrypted"` or
+/// `"compressed"`), its digest, and the encoded element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetachedPayload {
+    kind: PayloadKind,
+    digest: Digest,
+    data: Vec<u8>,
+}
+
+impl DetachedPayload {
+    /// Returns the kind of the detached element.
+    pub fn kind(&self) -> PayloadKind {
+        self.kind
+    }
+
+    /// Returns the declared digest of the detached element, which is also
+    /// the digest of the elided element left in its place.
+    pub fn digest(&self) -> &Digest {
+        &self.digest
+    }
+
+    /// Returns the tagged CBOR encoding of the detached element.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Decodes the detached element, checking that it matches the declared
+    /// kind and digest.
+    pub fn envelope(&self) -> Result<Envelope> {
+        let envelope = Envelope::from_tagged_cbor_data_checked(&self.data)?;
+        if PayloadKind::of(&envelope) != Some(self.kind) {
+            bail!(EnvelopeError::InvalidFormat { message: format!("payload is not {}", self.kind.name()) });
+        }
+        if *envelope.digest() != self.digest {
+            bail!(EnvelopeError::InvalidDigest);
+        }
+        Ok(envelope)
+    }
+}
+
+impl From<DetachedPayload> for CBOR {
+    fn from(value: DetachedPayload) -> Self {
+        vec![
+            CBOR::from(value.kind.name()),
+            value.digest.into(),
+            CBOR::to_byte_string(value.data),
+        ].into()
+    }
+}
+
+impl TryFrom<CBOR> for DetachedPayload {
+    type Error = Error;
+
+    fn try_from(cbor: CBOR) -> Result<Self> {
+        let elements = cbor.try_into_array()?;
+        let [kind, digest, data]: [CBOR; 3] = elements.try_into()
+            .map_err(|_| EnvelopeError::InvalidFormat { message: "payload must be an array of three elements".to_string() })?;
+        Ok(Self {
+            kind: PayloadKind::from_name(&kind.try_into_text()?)?,
+            digest: digest.try_into()?,
+            data: data.try_into_byte_string()?,
+        })
+    }
+}
+
+/// Support for storing encrypted and compressed elements outside an envelope.
+impl Envelope {
+    /// Returns a version of this envelope with the encrypted or compressed
+    /// element with the given digest replaced by an elided element, along
+    /// with the removed element.
+    ///
+    /// The digest of the envelope is unchanged. Every occurrence of the
+    /// element is replaced.
+    ///
+    /// Returns `EnvelopeError::NonexistentPayload` if no encrypted or
+    /// compressed element has the digest.
+    pub fn detach_payload(&self, target: &Digest) -> Result<(Self, DetachedPayload)> {
+        let mut detached = None;
+        let envelope = self.replacing_element(target, &mut |element| {
+            let kind = PayloadKind::of(element)?;
+            detached.get_or_insert_with(|| DetachedPayload {
+                kind,
+                digest: target.clone(),
+                data: element.tagged_cbor_data(),
+            });
+            Some(element.elide())
+        });
+        match (envelope, detached) {
+            (Some(envelope), Some(payload)) => Ok((envelope, payload)),
+            _ => bail!(EnvelopeError::NonexistentPayload),
+        }
+    }
+
+    /// Returns a version of this envelope with the elided element matching
+    /// the payload's digest replaced by the payload's element, reversing
+    /// [`Envelope::detach_payload`].
+    ///
+    /// Returns `EnvelopeError::InvalidDigest` if the payload's element does
+    /// not have its declared digest, and `EnvelopeError::NonexistentPayload`
+    /// if no elided element has the digest.
+    pub fn attach_payload(&self, payload: &DetachedPayload) -> Result<Self> {
+        let element = payload.envelope()?;
+        self.replacing_element(payload.digest(), &mut |candidate| {
+            candidate.is_elided().then(|| element.clone())
+        })
+        .ok_or_else(|| EnvelopeError::NonexistentPayload.into())
+    }
+
+    /// Returns a version of this envelope with each element with the target
+    /// digest replaced by the result of `replace`, or `None` if nothing was
+    /// replaced. The replacements must have the same digest.
+    fn replacing_element(&self, target: &Digest, replace: &mut dyn FnMut(&Envelope) -> Option<Envelope>) -> Option<Self> {
+        if *self.digest() == *target {
+            if let Some(replacement) = replace(self) {
+                return Some(replacement);
+            }
+        }
+        match self.case() {
+            EnvelopeCase::Node { subject, assertions, .. } => {
+                let new_subject = subject.replacing_element(target, replace);
+                let new_assertions: Vec<Option<Self>> = assertions.iter()
+                    .map(|assertion| assertion.replacing_element(target, replace))
+                    .collect();
+                if new_subject.is_none() && new_assertions.iter().all(Option::is_none) {
+                    return None;
+                }
+                let assertions = new_assertions.into_iter().zip(assertions)
+                    .map(|(new_assertion, assertion)| new_assertion.unwrap_or_else(|| assertion.clone()))
+                    .collect();
+                Some(Self::new_with_unchecked_assertions(new_subject.unwrap_or_else(|| subject.clone()), assertions))
+            },
+            EnvelopeCase::Assertion(assertion) => {
+                let predicate = assertion.predicate();
+                let object = assertion.object();
+                let new_predicate = predicate.replacing_element(target, replace);
+                let new_object = object.replacing_element(target, replace);
+                if new_predicate.is_none() && new_object.is_none() {
+                    return None;
+                }
+                Some(Self::new_with_assertion(Assertion::new(
+                    new_predicate.unwrap_or(predicate),
+                    new_object.unwrap_or(object),
+                )))
+            },
+            EnvelopeCase::Wrapped { envelope, .. } => {
+                envelope.replacing_element(target, replace).map(Self::new_wrapped)
+            },
+            _ => None,
+        }
+    }
+}
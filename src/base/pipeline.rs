@@ -0,0 +1,124 @@
+use anyhow::Result;
+#[cfg(feature = "signature")]
+use bc_components::Signer;
+#[cfg(feature = "recipient")]
+use bc_components::Encrypter;
+
+use crate::{AssertionProvider, Envelope, EnvelopeEncodable};
+#[cfg(feature = "types")]
+use crate::extension::known_values;
+
+enum Step<'a> {
+    Assertion(Envelope),
+    Apply(Box<dyn FnOnce(Envelope) -> Result<Envelope> + 'a>),
+}
+
+/// A declarative sequence of operations to be performed on an envelope.
+///
+/// Chaining calls such as `add_assertion` constructs (and hashes) a new node
+/// for every call. A pipeline instead collects the operations and executes
+/// them in order when [`EnvelopePipeline::build`] is called, adding each run of
+/// consecutive assertions to the envelope with a single node construction.
+///
+/// The result is identical to performing the same operations by chaining the
+/// corresponding `Envelope` methods.
+pub struct EnvelopePipeline<'a> {
+    subject: Envelope,
+    steps: Vec<Step<'a>>,
+}
+
+impl<'a> EnvelopePipeline<'a> {
+    /// Creates a new pipeline with the given subject.
+    pub fn new(subject: impl EnvelopeEncodable) -> Self {
+        Self {
+            subject: subject.into_envelope(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Adds an assertion with the given predicate and object.
+    pub fn add_assertion(self, predicate: impl EnvelopeEncodable, object: impl EnvelopeEncodable) -> Self {
+        self.add_assertion_envelope(Envelope::new_assertion(predicate, object))
+    }
+
+    /// Adds an assertion with the given predicate and object, optionally
+    /// salting it.
+    #[cfg(feature = "salt")]
+    pub fn add_assertion_salted(self, predicate: impl EnvelopeEncodable, object: impl EnvelopeEncodable, salted: bool) -> Self {
+        let assertion = Envelope::new_assertion(predicate, object);
+        if salted {
+            self.add_assertion_envelope(assertion.add_salt())
+        } else {
+            self.add_assertion_envelope(assertion)
+        }
+    }
+
+    /// Adds the given assertion envelope.
+    ///
+    /// The assertion envelope must be a valid assertion envelope, or an
+    /// obscured variant (elided, encrypted, compressed) of one. This is checked
+    /// when the pipeline is built.
+    pub fn add_assertion_envelope(mut self, assertion: impl EnvelopeEncodable) -> Self {
+        self.steps.push(Step::Assertion(assertion.into_envelope()));
+        self
+    }
+
+    /// Adds the assertions contributed by the given provider.
+    pub fn add_assertions_from(mut self, provider: &dyn AssertionProvider) -> Self {
+        self.steps.extend(provider.assertions().into_iter().map(Step::Assertion));
+        self
+    }
+
+    /// Adds an `'isA'` type assertion.
+    #[cfg(feature = "types")]
+    pub fn add_type(self, object: impl EnvelopeEncodable) -> Self {
+        self.add_assertion(known_values::IS_A, object)
+    }
+
+    /// Wraps the envelope built so far.
+    pub fn wrap(self) -> Self {
+        self.apply(|envelope| Ok(envelope.wrap_envelope()))
+    }
+
+    /// Signs the subject of the envelope built so far.
+    ///
+    /// Equivalent to [`Envelope::add_signature`]. Call [`EnvelopePipeline::wrap`]
+    /// first to sign the entire envelope.
+    #[cfg(feature = "signature")]
+    pub fn sign(self, signer: &'a dyn Signer) -> Self {
+        self.apply(move |envelope| Ok(envelope.add_signature(signer)))
+    }
+
+    /// Encrypts the subject of the envelope built so far to the given
+    /// recipients.
+    ///
+    /// Equivalent to [`Envelope::encrypt_subject_to_recipients`].
+    #[cfg(feature = "recipient")]
+    pub fn encrypt_to_recipients(self, recipients: &[&'a dyn Encrypter]) -> Self {
+        let recipients = recipients.to_vec();
+        self.apply(move |envelope| envelope.encrypt_subject_to_recipients(&recipients))
+    }
+
+    fn apply(mut self, f: impl FnOnce(Envelope) -> Result<Envelope> + 'a) -> Self {
+        self.steps.push(Step::Apply(Box::new(f)));
+        self
+    }
+
+    /// Executes the operations in the pipeline and returns the resulting
+    /// envelope.
+    pub fn build(self) -> Result<Envelope> {
+        let mut envelope = self.subject;
+        let mut pending = Vec::new();
+        for step in self.steps {
+            match step {
+                Step::Assertion(assertion) => pending.push(assertion),
+                Step::Apply(f) => {
+                    envelope = envelope.add_assertion_envelopes(&pending)?;
+                    pending.clear();
+                    envelope = f(envelope)?;
+                }
+            }
+        }
+        envelope.add_assertion_envelopes(&pending)
+    }
+}
@@ -0,0 +1,216 @@
+use anyhow::{bail, Error, Result};
+use bc_components::URI;
+
+use crate::{Assertion, Envelope, EnvelopeError};
+#[cfg(feature = "known_value")]
+use crate::KnownValue;
+
+use super::envelope::EnvelopeCase;
+
+/// A single entry in a [`PredicateContext`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PredicateTerm {
+    /// The compact name of the predicate.
+    pub name: String,
+    /// The full URI of the predicate.
+    pub uri: URI,
+    /// The known value used for the predicate in compact form, if any.
+    #[cfg(feature = "known_value")]
+    pub known_value: Option<KnownValue>,
+}
+
+impl PredicateTerm {
+    /// Returns the compact form of this predicate.
+    ///
+    /// This is the known value if there is one, otherwise the name.
+    fn compact(&self) -> Envelope {
+        #[cfg(feature = "known_value")]
+        if let Some(known_value) = &self.known_value {
+            return Envelope::new(known_value.clone());
+        }
+        Envelope::new(self.name.as_str())
+    }
+
+    /// Returns whether the given predicate is a compact form of this term.
+    fn matches_compact(&self, predicate: &Envelope) -> bool {
+        #[cfg(feature = "known_value")]
+        if let (Some(known_value), Some(predicate)) = (&self.known_value, predicate.as_known_value()) {
+            return known_value == predicate;
+        }
+        matches!(predicate.extract_subject::<String>(), Ok(name) if name == self.name)
+    }
+}
+
+/// A mapping between compact predicates and full URI predicates.
+///
+/// Envelopes exchanged with systems such as JSON-LD based verifiable
+/// credentials often use full URIs as predicates, while envelopes produced
+/// by this crate generally use compact strings or known values. A
+/// `PredicateContext` translates between the two forms with
+/// [`Envelope::expand_predicates`] and [`Envelope::compact_predicates`].
+///
+/// A context can itself be encoded as an envelope so it can travel with the
+/// documents that use it:
+///
+/// ```text
+/// "PredicateContext" [
+///     "term": URI(https://schema.org/knows) [
+///         "name": "knows"
+///     ]
+/// ]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PredicateContext {
+    terms: Vec<PredicateTerm>,
+}
+
+impl PredicateContext {
+    /// Creates a new empty context.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the terms of this context.
+    pub fn terms(&self) -> &[PredicateTerm] {
+        &self.terms
+    }
+
+    /// Returns this context with a term mapping the given compact name to the
+    /// given URI.
+    pub fn add_term(mut self, name: impl Into<String>, uri: impl Into<URI>) -> Self {
+        self.terms.push(PredicateTerm {
+            name: name.into(),
+            uri: uri.into(),
+            #[cfg(feature = "known_value")]
+            known_value: None,
+        });
+        self
+    }
+
+    /// Returns this context with a term mapping the given compact name and
+    /// known value to the given URI.
+    ///
+    /// When compacting, the known value is used in preference to the name.
+    #[cfg(feature = "known_value")]
+    pub fn add_known_value_term(mut self, name: impl Into<String>, uri: impl Into<URI>, known_value: KnownValue) -> Self {
+        self.terms.push(PredicateTerm {
+            name: name.into(),
+            uri: uri.into(),
+            known_value: Some(known_value),
+        });
+        self
+    }
+
+    /// Returns the URI predicate for the given compact predicate, if any.
+    pub fn expand(&self, predicate: &Envelope) -> Option<Envelope> {
+        self.terms.iter()
+            .find(|term| term.matches_compact(predicate))
+            .map(|term| Envelope::new(term.uri.clone()))
+    }
+
+    /// Returns the compact predicate for the given URI predicate, if any.
+    pub fn compact(&self, predicate: &Envelope) -> Option<Envelope> {
+        let uri = predicate.extract_subject::<URI>().ok()?;
+        self.terms.iter()
+            .find(|term| term.uri == uri)
+            .map(|term| term.compact())
+    }
+}
+
+impl From<&PredicateContext> for Envelope {
+    fn from(context: &PredicateContext) -> Self {
+        context.terms.iter().fold(Envelope::new("PredicateContext"), |envelope, term| {
+            let term_envelope = Envelope::new(term.uri.clone())
+                .add_assertion("name", term.name.as_str());
+            #[cfg(feature = "known_value")]
+            let term_envelope = term_envelope.add_optional_assertion("knownValue", term.known_value.clone());
+            envelope.add_assertion("term", term_envelope)
+        })
+    }
+}
+
+impl From<PredicateContext> for Envelope {
+    fn from(context: PredicateContext) -> Self {
+        Envelope::from(&context)
+    }
+}
+
+impl TryFrom<Envelope> for PredicateContext {
+    type Error = Error;
+
+    fn try_from(envelope: Envelope) -> Result<Self> {
+        if envelope.extract_subject::<String>().ok().as_deref() != Some("PredicateContext") {
+            bail!(EnvelopeError::InvalidFormat { message: "not a predicate context".to_string() });
+        }
+        let mut context = PredicateContext::new();
+        for term in envelope.objects_for_predicate("term") {
+            let uri: URI = term.extract_subject()?;
+            let name: String = term.extract_object_for_predicate("name")?;
+            #[cfg(feature = "known_value")]
+            if let Some(known_value) = term.optional_object_for_predicate("knownValue")? {
+                let known_value = known_value.as_known_value()
+                    .ok_or(EnvelopeError::NotKnownValue)?
+                    .clone();
+                context = context.add_known_value_term(name, uri, known_value);
+                continue;
+            }
+            context = context.add_term(name, uri);
+        }
+        Ok(context)
+    }
+}
+
+/// Support for translating predicates between compact and URI forms.
+impl Envelope {
+    /// Returns this envelope with every compact predicate known to the
+    /// context replaced by its URI.
+    ///
+    /// Predicates are translated throughout the envelope, including inside
+    /// wrapped envelopes. Obscured elements are left unchanged. Because the
+    /// predicates change, so do the digests of every enclosing element.
+    pub fn expand_predicates(&self, context: &PredicateContext) -> Self {
+        self.translate_predicates(&mut |predicate| context.expand(predicate))
+    }
+
+    /// Returns this envelope with every URI predicate known to the context
+    /// replaced by its compact form, along with the URI predicates that are
+    /// not known to the context.
+    ///
+    /// Predicates are translated throughout the envelope, including inside
+    /// wrapped envelopes. Obscured elements are left unchanged. Because the
+    /// predicates change, so do the digests of every enclosing element.
+    /// Compacting an envelope expanded with the same context restores its
+    /// original digest, provided the original used the same compact form
+    /// (known value or name) as the context.
+    pub fn compact_predicates(&self, context: &PredicateContext) -> (Self, Vec<Self>) {
+        let mut untranslatable = Vec::new();
+        let compacted = self.translate_predicates(&mut |predicate| {
+            let compact = context.compact(predicate);
+            if compact.is_none() && predicate.extract_subject::<URI>().is_ok() {
+                untranslatable.push(predicate.clone());
+            }
+            compact
+        });
+        (compacted, untranslatable)
+    }
+
+    fn translate_predicates(&self, translate: &mut dyn FnMut(&Self) -> Option<Self>) -> Self {
+        match self.case() {
+            EnvelopeCase::Node { subject, assertions, .. } => {
+                let subject = subject.translate_predicates(translate);
+                let assertions = assertions.iter()
+                    .map(|assertion| assertion.translate_predicates(translate))
+                    .collect();
+                Self::new_with_unchecked_assertions(subject, assertions)
+            },
+            EnvelopeCase::Wrapped { envelope, .. } => Self::new_wrapped(envelope.translate_predicates(translate)),
+            EnvelopeCase::Assertion(assertion) => {
+                let predicate = assertion.predicate();
+                let predicate = translate(&predicate).unwrap_or_else(|| predicate.translate_predicates(translate));
+                let object = assertion.object().translate_predicates(translate);
+                Self::new_with_assertion(Assertion::new(predicate, object))
+            },
+            _ => self.clone(),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use bc_components::{Digest, DigestProvider, SymmetricKey};
+use bc_crypto::hash::hmac_sha256;
+use dcbor::prelude::*;
+
+use crate::{Assertion, Envelope, EnvelopeEncodable};
+#[cfg(feature = "known_value")]
+use crate::extension::known_values;
+
+use super::envelope::EnvelopeCase;
+
+/// The domain separation prefix for pseudonym tokens.
+const PSEUDONYM_DOMAIN: &[u8] = b"bc-envelope-pseudonym";
+
+/// The CBOR tag of the pseudonym tokens that replace values in pseudonymized
+/// envelopes, so that they cannot be mistaken for digests.
+///
+/// The value, the ASCII of `psdn`, is in the IANA first-come-first-served
+/// range, well clear of the tags registered for Blockchain Commons types.
+/// It is not yet registered, so it is private to this crate until it is.
+pub const TAG_PSEUDONYM: TagValue = 0x7073_646e;
+
+/// The name of [`TAG_PSEUDONYM`].
+pub(crate) const PSEUDONYM_TAG_NAME: &str = "pseudonym";
+
+/// A set of predicates whose objects are considered sensitive.
+#[derive(Debug, Clone, Default)]
+pub struct RedactionPreset {
+    predicates: Vec<Envelope>,
+}
+
+impl RedactionPreset {
+    /// Creates a new empty preset.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns this preset with the given predicate added.
+    pub fn add_predicate(mut self, predicate: impl EnvelopeEncodable) -> Self {
+        self.predicates.push(predicate.into_envelope());
+        self
+    }
+
+    /// Returns the predicates of this preset.
+    pub fn predicates(&self) -> &[Envelope] {
+        &self.predicates
+    }
+
+    /// Returns whether the given predicate is in this preset.
+    pub fn matches(&self, predicate: &Envelope) -> bool {
+        self.predicates.iter().any(|p| p.digest() == predicate.digest())
+    }
+}
+
+/// A value replaced by [`Envelope::pseudonymize`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PseudonymReplacement {
+    /// The predicate of the assertion whose object was replaced.
+    pub predicate: Envelope,
+    /// The digest of the original value.
+    pub original: Digest,
+    /// The pseudonym token that replaced it.
+    pub token: Digest,
+}
+
+/// The changes made by [`Envelope::pseudonymize`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PseudonymizationReport {
+    /// Every value that was replaced, in the order encountered.
+    pub replacements: Vec<PseudonymReplacement>,
+    /// The digests of the original signed wrappers whose content changed, and
+    /// whose signatures therefore no longer verify.
+    pub invalidated_signatures: Vec<Digest>,
+}
+
+impl PseudonymizationReport {
+    /// Returns whether nothing was replaced.
+    pub fn is_empty(&self) -> bool {
+        self.replacements.is_empty()
+    }
+}
+
+/// Support for replacing sensitive values with deterministic pseudonyms.
+impl Envelope {
+    /// Returns the pseudonym token for the given value under the given key.
+    ///
+    /// The token is `HMAC-SHA-256(key, "bc-envelope-pseudonym" || digest)`,
+    /// where `digest` is the value's envelope digest. It is therefore the same
+    /// for the same value and key in every envelope and on every run, while the
+    /// value cannot be recovered from it without the key.
+    pub fn pseudonym_token(value: &Envelope, key: &SymmetricKey) -> Digest {
+        let mut message = PSEUDONYM_DOMAIN.to_vec();
+        message.extend_from_slice(value.digest().data());
+        Digest::from_data(hmac_sha256(key.data(), message))
+    }
+
+    /// Returns a version of this envelope with the objects of assertions
+    /// whose predicates are in `preset` replaced by pseudonyms, along with a
+    /// report of the changes.
+    ///
+    /// A matching object that is a leaf is replaced by a leaf containing its
+    /// pseudonym token (see [`Envelope::pseudonym_token`]), as a byte string
+    /// tagged with [`TAG_PSEUDONYM`]. If the object is a node whose subject is a leaf, only the
+    /// subject is replaced. Objects that are not leaves, and elided,
+    /// encrypted, or compressed elements, are left in place, but wrapped
+    /// envelopes and the assertions of objects are searched too.
+    ///
+    /// Unlike elision, this changes the digests of the envelope and of every
+    /// element that contains a replaced value. A signed wrapper whose content
+    /// changes is reported in
+    /// [`PseudonymizationReport::invalidated_signatures`].
+    pub fn pseudonymize(&self, preset: &RedactionPreset, key: &SymmetricKey) -> (Self, PseudonymizationReport) {
+        let mut report = PseudonymizationReport::default();
+        let result = self.pseudonymize_element(preset, key, &mut report);
+        (result, report)
+    }
+
+    fn pseudonymize_element(&self, preset: &RedactionPreset, key: &SymmetricKey, report: &mut PseudonymizationReport) -> Self {
+        match self.case() {
+            EnvelopeCase::Node { subject, assertions, .. } => {
+                let new_subject = subject.pseudonymize_element(preset, key, report);
+                #[cfg(feature = "known_value")]
+                if subject.is_wrapped()
+                    && new_subject.digest() != subject.digest()
+                    && !self.assertions_with_predicate(known_values::SIGNED).is_empty()
+                {
+                    report.invalidated_signatures.push(self.digest().into_owned());
+                }
+                let assertions = assertions.iter()
+                    .map(|assertion| assertion.pseudonymize_element(preset, key, report))
+                    .collect();
+                Self::new_with_unchecked_assertions(new_subject, assertions)
+            },
+            EnvelopeCase::Wrapped { envelope, .. } => Self::new_wrapped(envelope.pseudonymize_element(preset, key, report)),
+            EnvelopeCase::Assertion(assertion) => {
+                let predicate = assertion.predicate();
+                let object = assertion.object();
+                let object = if preset.matches(&predicate) {
+                    object.pseudonymize_object(&predicate, preset, key, report)
+                } else {
+                    object.pseudonymize_element(preset, key, report)
+                };
+                Self::new_with_assertion(Assertion::new(predicate, object))
+            },
+            _ => self.clone(),
+        }
+    }
+
+    fn pseudonymize_object(&self, predicate: &Envelope, preset: &RedactionPreset, key: &SymmetricKey, report: &mut PseudonymizationReport) -> Self {
+        match self.case() {
+            EnvelopeCase::Leaf { .. } => {
+                let token = Self::pseudonym_token(self, key);
+                report.replacements.push(PseudonymReplacement {
+                    predicate: predicate.clone(),
+                    original: self.digest().into_owned(),
+                    token: token.clone(),
+                });
+                Self::new(CBOR::to_tagged_value(TAG_PSEUDONYM, CBOR::to_byte_string(token.data())))
+            },
+            EnvelopeCase::Node { subject, assertions, .. } if subject.is_leaf() => {
+                let subject = subject.pseudonymize_object(predicate, preset, key, report);
+                let assertions = assertions.iter()
+                    .map(|assertion| assertion.pseudonymize_element(preset, key, report))
+                    .collect();
+                Self::new_with_unchecked_assertions(subject, assertions)
+            },
+            _ => self.pseudonymize_element(preset, key, report),
+        }
+    }
+}
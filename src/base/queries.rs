@@ -1,3 +1,5 @@
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
 use anyhow::{bail, Error, Result};
 use bc_components::{Digest, DigestProvider};
 #[cfg(feature = "encrypt")]
@@ -5,7 +7,7 @@ use bc_components::EncryptedMessage;
 #[cfg(feature = "compress")]
 use bc_components::Compressed;
 use dcbor::prelude::*;
-use std::any::{Any, TypeId};
+use std::any::{type_name, Any, TypeId};
 
 use crate::{Assertion, Envelope, EnvelopeEncodable, EnvelopeError};
 #[cfg(feature = "known_value")]
@@ -17,7 +19,13 @@ use super::envelope::EnvelopeCase;
 impl Envelope {
     /// The envelope's subject.
     ///
-    /// For an envelope with no assertions, returns the same envelope.
+    /// For a node, returns the node's subject. For any other envelope,
+    /// including one with no assertions, returns the same envelope.
+    ///
+    /// The subject of a node is never itself a node. Adding assertions to a
+    /// node adds them to its existing assertions, and decoding rejects a node
+    /// whose subject is a node with `EnvelopeError::NestedNode`. To make a
+    /// node the subject of further assertions, wrap it first.
     pub fn subject(&self) -> Self {
         match self.case() {
             EnvelopeCase::Node { subject, .. } => subject.clone(),
@@ -26,10 +34,13 @@ impl Envelope {
     }
 
     /// The envelope's assertions.
-    pub fn assertions(&self) -> Vec<Self> {
+    ///
+    /// For a node, returns the node's assertions, sorted by digest. For any
+    /// other envelope, returns an empty slice.
+    pub fn assertions(&self) -> &[Self] {
         match self.case() {
-            EnvelopeCase::Node { assertions, .. } => assertions.clone(),
-            _ => vec![],
+            EnvelopeCase::Node { assertions, .. } => assertions,
+            _ => &[],
         }
     }
 
@@ -82,8 +93,16 @@ impl Envelope {
 
     /// The envelope's leaf CBOR object, or `None` if the envelope is not a leaf.
     pub fn as_leaf(&self) -> Option<CBOR> {
+        self.leaf_cbor().cloned()
+    }
+
+    /// A reference to the envelope's leaf CBOR object, or `None` if the
+    /// envelope is not a leaf.
+    ///
+    /// Unlike [`Envelope::as_leaf`], this does not clone the CBOR.
+    pub fn leaf_cbor(&self) -> Option<&CBOR> {
         match self.case() {
-            EnvelopeCase::Leaf { cbor, .. } => Some(cbor.clone()),
+            EnvelopeCase::Leaf { cbor, .. } => Some(cbor),
             _ => None,
         }
     }
@@ -108,6 +127,13 @@ impl Envelope {
         self.as_known_value().ok_or(EnvelopeError::NotKnownValue.into())
     }
 
+    /// The numeric value of the envelope's `KnownValue`, or `None` if the
+    /// envelope is not case `::KnownValue`.
+    #[cfg(feature = "known_value")]
+    pub fn known_value_code(&self) -> Option<u64> {
+        self.as_known_value().map(KnownValue::value)
+    }
+
     /// `true` if the envelope is case `::Leaf`, `false` otherwise.
     pub fn is_leaf(&self) -> bool {
         matches!(self.case(), EnvelopeCase::Leaf { .. })
@@ -233,9 +259,30 @@ impl Envelope {
         false
     }
 
+    /// Returns the name of the envelope's case, for use in logging and
+    /// diagnostics.
+    ///
+    /// One of `"Node"`, `"Leaf"`, `"Wrapped"`, `"Assertion"`, `"Elided"`,
+    /// `"KnownValue"`, `"Encrypted"`, or `"Compressed"`.
+    pub fn case_name(&self) -> &'static str {
+        match self.case() {
+            EnvelopeCase::Node { .. } => "Node",
+            EnvelopeCase::Leaf { .. } => "Leaf",
+            EnvelopeCase::Wrapped { .. } => "Wrapped",
+            EnvelopeCase::Assertion(_) => "Assertion",
+            EnvelopeCase::Elided(_) => "Elided",
+            #[cfg(feature = "known_value")]
+            EnvelopeCase::KnownValue { .. } => "KnownValue",
+            #[cfg(feature = "encrypt")]
+            EnvelopeCase::Encrypted(_) => "Encrypted",
+            #[cfg(feature = "compress")]
+            EnvelopeCase::Compressed(_) => "Compressed",
+        }
+    }
+
     /// Returns the envelope's subject, decoded as the given type.
     ///
-    /// If the encoded type doesn't match the given type, returns `Error::InvalidFormat`.
+    /// If the encoded type doesn't match the given type, returns `EnvelopeError::InvalidFormat`.
     pub fn extract_subject<T>(&self) -> Result<T>
     where
         T: Any + TryFrom<CBOR, Error = Error>,
@@ -247,13 +294,13 @@ impl Envelope {
         {
             if TypeId::of::<T>() == TypeId::of::<U>() {
                 let cloned: Box<dyn Any> = Box::new(value.clone());
-                let downcast = cloned
-                    .downcast::<T>()
-                    .unwrap();
-                Ok(*downcast)
-            } else {
-                bail!(EnvelopeError::InvalidFormat)
+                if let Ok(downcast) = cloned.downcast::<T>() {
+                    return Ok(*downcast);
+                }
             }
+            bail!(EnvelopeError::InvalidFormat {
+                message: format!("expected {}, found {}", type_name::<T>(), type_name::<U>()),
+            })
         }
 
         match self.case() {
@@ -274,11 +321,39 @@ impl Envelope {
         }
     }
 
+    /// Returns the envelope's subject, which must be a leaf holding an array,
+    /// with its elements decoded as the given type.
+    ///
+    /// Unlike `extract_subject::<Vec<T>>()`, which decodes an array leaf the
+    /// same way, returns `EnvelopeError::InvalidFormat` rather than panicking
+    /// if the leaf is not an array.
+    pub fn extract_subject_array<T>(&self) -> Result<Vec<T>>
+    where
+        T: TryFrom<CBOR, Error = Error>,
+    {
+        let cbor = self.subject().try_leaf()?;
+        if !matches!(cbor.as_case(), CBORCase::Array(_)) {
+            bail!(EnvelopeError::InvalidFormat { message: "expected an array".to_string() });
+        }
+        cbor.try_into_array()?.into_iter().map(T::try_from).collect()
+    }
+
+    /// Returns the envelope's subject, which must be a leaf holding a map.
+    ///
+    /// The values of the map can be decoded with [`Map::extract`].
+    pub fn extract_subject_map(&self) -> Result<Map> {
+        let cbor = self.subject().try_leaf()?;
+        if !matches!(cbor.as_case(), CBORCase::Map(_)) {
+            bail!(EnvelopeError::InvalidFormat { message: "expected a map".to_string() });
+        }
+        cbor.try_into_map()
+    }
+
     /// Returns all assertions with the given predicate. Match by comparing digests.
     pub fn assertions_with_predicate(&self, predicate: impl EnvelopeEncodable) -> Vec<Self> {
         let predicate = Envelope::new(predicate);
         self.assertions()
-            .into_iter()
+            .iter()
             .filter(|assertion| {
                 assertion
                     .subject()
@@ -286,6 +361,7 @@ impl Envelope {
                     .map(|p| p.digest() == predicate.digest())
                     .unwrap_or(false)
             })
+            .cloned()
             .collect()
     }
 
@@ -293,13 +369,14 @@ impl Envelope {
     ///
     /// Returns an error if there is no matching predicate or multiple matching predicates.
     pub fn assertion_with_predicate(&self, predicate: impl EnvelopeEncodable) -> Result<Self> {
-        let a = self.assertions_with_predicate(predicate);
+        let predicate = Envelope::new(predicate);
+        let a = self.assertions_with_predicate(predicate.clone());
         if a.is_empty() {
-            bail!(EnvelopeError::NonexistentPredicate);
+            bail!(EnvelopeError::NonexistentPredicate { predicate: predicate.format_flat_opt(None) });
         } else if a.len() == 1 {
             Ok(a[0].clone())
         } else {
-            bail!(EnvelopeError::AmbiguousPredicate);
+            bail!(EnvelopeError::AmbiguousPredicate { predicate: predicate.format_flat_opt(None), count: a.len() });
         }
     }
 
@@ -307,13 +384,14 @@ impl Envelope {
     ///
     /// Returns an error if there are multiple matching predicates.
     pub fn optional_assertion_with_predicate(&self, predicate: impl EnvelopeEncodable) -> Result<Option<Self>> {
-        let a = self.assertions_with_predicate(predicate);
+        let predicate = Envelope::new(predicate);
+        let a = self.assertions_with_predicate(predicate.clone());
         if a.is_empty() {
             Ok(None)
         } else if a.len() == 1 {
             Ok(Some(a[0].clone()))
         } else {
-            bail!(EnvelopeError::AmbiguousPredicate);
+            bail!(EnvelopeError::AmbiguousPredicate { predicate: predicate.format_flat_opt(None), count: a.len() });
         }
     }
 
@@ -321,21 +399,16 @@ impl Envelope {
     ///
     /// Returns an error if there is no matching predicate or multiple matching predicates.
     pub fn object_for_predicate(&self, predicate: impl EnvelopeEncodable) -> Result<Self> {
-        Ok(self.assertion_with_predicate(predicate)?.as_object().unwrap())
+        self.assertion_with_predicate(predicate)?.subject().try_object()
     }
 
     /// Returns the object of the assertion with the given predicate, or `None` if there is no matching predicate.
     ///
     /// Returns an error if there are multiple matching predicates.
     pub fn optional_object_for_predicate(&self, predicate: impl EnvelopeEncodable) -> Result<Option<Self>> {
-        let a = self.assertions_with_predicate(predicate);
-        if a.is_empty() {
-            Ok(None)
-        } else if a.len() == 1 {
-            Ok(Some(a[0].subject().as_object().unwrap()))
-        } else {
-            bail!(EnvelopeError::AmbiguousPredicate);
-        }
+        self.optional_assertion_with_predicate(predicate)?
+            .map(|a| a.subject().try_object())
+            .transpose()
     }
 
     /// Returns the object of the assertion, decoded as the given type.
@@ -361,8 +434,8 @@ impl Envelope {
     /// Returns an error if there is no matching predicate or multiple matching predicates.
     /// Returns an error if the encoded type doesn't match the given type.
     pub fn extract_object_for_predicate<T: TryFrom<CBOR, Error = Error> + 'static>(&self, predicate: impl EnvelopeEncodable) -> Result<T> {
-        self.assertion_with_predicate(predicate)?
-            .extract_object()
+        self.object_for_predicate(predicate)?
+            .extract_subject()
     }
 
     /// Returns the object of the assertion with the given predicate, or `None` if there is no matching predicate.
@@ -383,7 +456,7 @@ impl Envelope {
     pub fn objects_for_predicate(&self, predicate: impl EnvelopeEncodable) -> Vec<Self> {
         self.assertions_with_predicate(predicate)
             .into_iter()
-            .map(|a| a.as_object().unwrap())
+            .filter_map(|a| a.subject().as_object())
             .collect()
     }
 
@@ -398,6 +471,69 @@ impl Envelope {
             .collect::<Result<Vec<T>>>()
     }
 
+    /// Returns the object found by following a path of predicates from this
+    /// envelope.
+    ///
+    /// For each predicate in `path`, finds the single assertion with that
+    /// predicate and descends into its object. If the current envelope has no
+    /// such assertion but its subject is wrapped, as with a signed envelope,
+    /// the wrapped envelope is searched instead.
+    ///
+    /// Returns `EnvelopeError::NonexistentPredicate` if a predicate is not
+    /// found, and `EnvelopeError::AmbiguousPredicate` if more than one
+    /// assertion has it.
+    pub fn object_at_path<P: EnvelopeEncodable + Clone>(&self, path: &[P]) -> Result<Self> {
+        self.object_at_path_opt(path, false)
+    }
+
+    /// Returns the object found by following a path of predicates from this
+    /// envelope, as [`Envelope::object_at_path`].
+    ///
+    /// If `report_obscured` is `true` and a predicate is not found, but the
+    /// elements that were searched include elided, encrypted, or compressed
+    /// elements that might contain it, returns
+    /// `EnvelopeError::ObscuredPredicate` with the digests of those elements,
+    /// so that the caller knows what to request.
+    pub fn object_at_path_opt<P: EnvelopeEncodable + Clone>(&self, path: &[P], report_obscured: bool) -> Result<Self> {
+        let mut current = self.clone();
+        for predicate in path {
+            current = current.object_at_path_segment(Envelope::new(predicate.clone()), report_obscured)?;
+        }
+        Ok(current)
+    }
+
+    fn object_at_path_segment(&self, predicate: Envelope, report_obscured: bool) -> Result<Self> {
+        let mut obscured = Vec::new();
+        let mut current = self.clone();
+        loop {
+            if current.is_obscured() {
+                obscured.push(current.digest().into_owned());
+                break;
+            }
+            let matches = current.assertions_with_predicate(predicate.clone());
+            match matches.len() {
+                0 => {},
+                1 => return matches[0].subject().try_object(),
+                count => bail!(EnvelopeError::AmbiguousPredicate { predicate: predicate.format_flat_opt(None), count }),
+            }
+            obscured.extend(current.assertions().iter()
+                .filter(|assertion| assertion.is_obscured())
+                .map(|assertion| assertion.digest().into_owned()));
+            let subject = current.subject();
+            if !subject.is_wrapped() {
+                if subject.is_obscured() {
+                    obscured.push(subject.digest().into_owned());
+                }
+                break;
+            }
+            current = subject.unwrap_envelope()?;
+        }
+        if report_obscured && !obscured.is_empty() {
+            bail!(EnvelopeError::ObscuredPredicate { predicate: predicate.format_flat_opt(None), digests: obscured });
+        }
+        bail!(EnvelopeError::NonexistentPredicate { predicate: predicate.format_flat_opt(None) });
+    }
+
     /// Returns the number of structural elements in the envelope, including itself.
     pub fn elements_count(&self) -> usize {
         let mut result = 0;
@@ -0,0 +1,130 @@
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "encrypt")]
+use bc_components::Nonce;
+use bc_rand::RandomNumberGenerator;
+#[cfg(feature = "test-support")]
+use bc_rand::SeededRandomNumberGenerator;
+use rand_core::{CryptoRng, RngCore};
+
+/// A source of the random data used to create envelopes.
+///
+/// Every operation that needs randomness and is not given a random number
+/// generator, such as adding salt, generating content keys and nonces,
+/// sealing messages to recipients, signing with Schnorr signatures, and
+/// splitting with SSKR, draws it from the process-wide provider. By default
+/// this is [`SecureRandomProvider`].
+pub trait RandomProvider: Send + Sync {
+    /// Fills `data` with random bytes.
+    fn fill_random_data(&self, data: &mut [u8]);
+}
+
+/// The default [`RandomProvider`], which draws from the system's
+/// cryptographically secure random number generator.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SecureRandomProvider;
+
+impl RandomProvider for SecureRandomProvider {
+    fn fill_random_data(&self, data: &mut [u8]) {
+        bc_rand::fill_random_data(data);
+    }
+}
+
+/// A [`RandomProvider`] that produces the same sequence of bytes for the same
+/// seed, so that tests that create salted, encrypted, or signed envelopes
+/// are reproducible.
+///
+/// This is NOT cryptographically secure.
+#[cfg(feature = "test-support")]
+#[derive(Debug)]
+pub struct SeededRandomProvider(Mutex<SeededRandomNumberGenerator>);
+
+#[cfg(feature = "test-support")]
+impl SeededRandomProvider {
+    /// Creates a provider with the given seed. See
+    /// [`SeededRandomNumberGenerator::new`].
+    pub fn new(seed: [u64; 4]) -> Self {
+        Self(Mutex::new(SeededRandomNumberGenerator::new(seed)))
+    }
+}
+
+#[cfg(feature = "test-support")]
+impl RandomProvider for SeededRandomProvider {
+    fn fill_random_data(&self, data: &mut [u8]) {
+        self.0.lock().unwrap().fill_random_data(data);
+    }
+}
+
+static RANDOM_PROVIDER: Mutex<Option<Arc<dyn RandomProvider>>> = Mutex::new(None);
+
+/// Replaces the process-wide [`RandomProvider`].
+///
+/// This is for tests only, and is available only with the `test-support`
+/// feature: a deterministic provider makes every envelope created afterwards
+/// predictable, in every thread.
+#[cfg(feature = "test-support")]
+pub fn set_random_provider(provider: Arc<dyn RandomProvider>) {
+    *RANDOM_PROVIDER.lock().unwrap() = Some(provider);
+}
+
+/// Restores the default [`SecureRandomProvider`] after
+/// [`set_random_provider`].
+#[cfg(feature = "test-support")]
+pub fn reset_random_provider() {
+    *RANDOM_PROVIDER.lock().unwrap() = None;
+}
+
+/// A random number generator that draws from the process-wide
+/// [`RandomProvider`], consulting it on every call.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ProviderRandomNumberGenerator;
+
+impl RngCore for ProviderRandomNumberGenerator {
+    fn next_u32(&mut self) -> u32 {
+        let mut data = [0u8; 4];
+        self.fill_bytes(&mut data);
+        u32::from_le_bytes(data)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut data = [0u8; 8];
+        self.fill_bytes(&mut data);
+        u64::from_le_bytes(data)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        let provider = RANDOM_PROVIDER.lock().unwrap().clone();
+        match provider {
+            Some(provider) => provider.fill_random_data(dest),
+            None => SecureRandomProvider.fill_random_data(dest),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for ProviderRandomNumberGenerator {}
+
+impl RandomNumberGenerator for ProviderRandomNumberGenerator {}
+
+/// Returns `size` bytes from the process-wide [`RandomProvider`].
+pub(crate) fn random_data(size: usize) -> Vec<u8> {
+    ProviderRandomNumberGenerator.random_data(size)
+}
+
+/// Returns a nonce from the process-wide [`RandomProvider`].
+#[cfg(feature = "encrypt")]
+pub(crate) fn random_nonce() -> Nonce {
+    nonce_using(&mut ProviderRandomNumberGenerator)
+}
+
+/// Returns a nonce drawn from the given random number generator.
+#[cfg(feature = "encrypt")]
+pub(crate) fn nonce_using(rng: &mut impl RandomNumberGenerator) -> Nonce {
+    let mut data = [0u8; Nonce::NONCE_SIZE];
+    rng.fill_random_data(&mut data);
+    Nonce::from_data(data)
+}
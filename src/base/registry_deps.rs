@@ -0,0 +1,150 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use dcbor::prelude::*;
+#[cfg(any(feature = "known_value", feature = "expression"))]
+use bc_components::tags;
+
+use super::envelope::EnvelopeCase;
+use crate::{Envelope, FormatContext};
+#[cfg(feature = "known_value")]
+use crate::extension::KnownValue;
+#[cfg(feature = "expression")]
+use crate::{Function, Parameter};
+
+/// An entry of a registry that an envelope relies on to be formatted with
+/// names rather than numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RegistryItem {
+    /// A CBOR tag in the content of a leaf.
+    Tag(u64),
+    /// A known value, as an element or in the content of a leaf.
+    #[cfg(feature = "known_value")]
+    KnownValue(u64),
+    /// A function identified by number.
+    #[cfg(feature = "expression")]
+    Function(u64),
+    /// A parameter identified by number.
+    #[cfg(feature = "expression")]
+    Parameter(u64),
+}
+
+impl RegistryItem {
+    /// Returns whether the given context has a name for this item.
+    pub fn is_named_in(&self, context: &FormatContext) -> bool {
+        match self {
+            RegistryItem::Tag(value) => context.tags().tag_for_value(*value).is_some(),
+            #[cfg(feature = "known_value")]
+            RegistryItem::KnownValue(value) => context.known_values().assigned_name(&KnownValue::new(*value)).is_some(),
+            #[cfg(feature = "expression")]
+            RegistryItem::Function(value) => context.functions().assigned_name(&Function::new_known(*value, None)).is_some(),
+            #[cfg(feature = "expression")]
+            RegistryItem::Parameter(value) => context.parameters().assigned_name(&Parameter::new_known(*value, None)).is_some(),
+        }
+    }
+}
+
+/// The registry entries an envelope relies on, with the number of times each
+/// occurs.
+///
+/// Returned by [`Envelope::registry_dependencies`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RegistryDeps {
+    /// The items named by the standard registries, as in
+    /// [`FormatContext::standard`].
+    pub standard: BTreeMap<RegistryItem, usize>,
+    /// The items the standard registries do not name.
+    pub non_standard: BTreeMap<RegistryItem, usize>,
+}
+
+impl RegistryDeps {
+    /// Returns all of the items, in order.
+    pub fn items(&self) -> Vec<RegistryItem> {
+        let mut items: Vec<RegistryItem> = self.standard.keys().chain(self.non_standard.keys()).copied().collect();
+        items.sort();
+        items
+    }
+
+    /// Returns the items the given context has no name for, in order. These
+    /// are the items that would be formatted as numbers with the context.
+    pub fn missing_from(&self, context: &FormatContext) -> Vec<RegistryItem> {
+        self.items()
+            .into_iter()
+            .filter(|item| !item.is_named_in(context))
+            .collect()
+    }
+}
+
+/// Support for finding the registry entries an envelope relies on.
+impl Envelope {
+    /// Returns the known values, functions, parameters, and tags the envelope
+    /// uses, split into those the standard registries name and those they do
+    /// not, so that a receiver can be sent the names it needs.
+    ///
+    /// Tags are collected from the content of leaves, including the
+    /// parameters and functions of expressions. The contents of obscured
+    /// elements are not examined.
+    pub fn registry_dependencies(&self) -> RegistryDeps {
+        let counts = RefCell::new(BTreeMap::new());
+        self.walk(false, &|envelope, _, _, _: Option<()>| {
+            let mut counts = counts.borrow_mut();
+            match envelope.case() {
+                EnvelopeCase::Leaf { cbor, .. } => collect_cbor_dependencies(cbor, &mut counts),
+                #[cfg(feature = "known_value")]
+                EnvelopeCase::KnownValue { value, .. } => {
+                    *counts.entry(RegistryItem::KnownValue(value.value())).or_default() += 1;
+                },
+                _ => {},
+            }
+            None
+        });
+        let standard_context = FormatContext::standard();
+        let mut deps = RegistryDeps::default();
+        for (item, count) in counts.into_inner() {
+            if item.is_named_in(&standard_context) {
+                deps.standard.insert(item, count);
+            } else {
+                deps.non_standard.insert(item, count);
+            }
+        }
+        deps
+    }
+}
+
+fn collect_cbor_dependencies(cbor: &CBOR, counts: &mut BTreeMap<RegistryItem, usize>) {
+    match cbor.as_case() {
+        CBORCase::Array(items) => {
+            for item in items {
+                collect_cbor_dependencies(item, counts);
+            }
+        },
+        CBORCase::Map(map) => {
+            for (key, value) in map.iter() {
+                collect_cbor_dependencies(key, counts);
+                collect_cbor_dependencies(value, counts);
+            }
+        },
+        CBORCase::Tagged(tag, item) => {
+            let registry_item = match (tag.value(), item.as_case()) {
+                #[cfg(feature = "known_value")]
+                (tags::TAG_KNOWN_VALUE, CBORCase::Unsigned(value)) => Some(RegistryItem::KnownValue(*value)),
+                #[cfg(feature = "expression")]
+                (tags::TAG_FUNCTION, CBORCase::Unsigned(value)) => Some(RegistryItem::Function(*value)),
+                #[cfg(feature = "expression")]
+                (tags::TAG_PARAMETER, CBORCase::Unsigned(value)) => Some(RegistryItem::Parameter(*value)),
+                // Functions and parameters identified by name need no registry.
+                #[cfg(feature = "expression")]
+                (tags::TAG_FUNCTION | tags::TAG_PARAMETER, CBORCase::Text(_)) => None,
+                _ => {
+                    *counts.entry(RegistryItem::Tag(tag.value())).or_default() += 1;
+                    collect_cbor_dependencies(item, counts);
+                    None
+                },
+            };
+            if let Some(registry_item) = registry_item {
+                *counts.entry(registry_item).or_default() += 1;
+            }
+        },
+        _ => {},
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt;
+
+use dcbor::prelude::*;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::Envelope;
+
+/// Serializes an envelope as its UR string for human-readable formats, such
+/// as JSON, and as the bytes of its tagged CBOR encoding for binary formats.
+///
+/// The UR type is always `envelope`, whether or not the tags have been
+/// registered.
+impl Serialize for Envelope {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.ur_string())
+        } else {
+            serializer.serialize_bytes(&self.tagged_cbor_data())
+        }
+    }
+}
+
+/// Deserializes an envelope from its UR string for human-readable formats,
+/// and from the bytes of its tagged CBOR encoding for binary formats.
+///
+/// The envelope is decoded as by [`Envelope::from_ur_string`] or
+/// [`Envelope::from_tagged_cbor_data`], subject to the default limits, and a
+/// failure to decode it is reported as a custom error of the format.
+impl<'de> Deserialize<'de> for Envelope {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(EnvelopeVisitor)
+        } else {
+            deserializer.deserialize_bytes(EnvelopeVisitor)
+        }
+    }
+}
+
+struct EnvelopeVisitor;
+
+/// The most bytes preallocated for an envelope serialized as a sequence.
+const MAX_PREALLOCATED_BYTES: usize = 4096;
+
+impl<'de> de::Visitor<'de> for EnvelopeVisitor {
+    type Value = Envelope;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("an envelope UR string or tagged CBOR bytes")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Envelope, E> {
+        Envelope::from_ur_string(v).map_err(|error| E::custom(format!("invalid envelope UR: {}", error)))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Envelope, E> {
+        Envelope::from_tagged_cbor_data(v).map_err(|error| E::custom(format!("invalid envelope CBOR: {}", error)))
+    }
+
+    /// Formats that have no byte strings serialize bytes as a sequence.
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Envelope, A::Error> {
+        // The hint comes from the input, so it is not trusted to size the
+        // allocation.
+        let mut data = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(MAX_PREALLOCATED_BYTES));
+        while let Some(byte) = seq.next_element::<u8>()? {
+            data.push(byte);
+        }
+        self.visit_bytes(&data)
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{bail, Error, Result};
+use bc_components::{Digest, DigestProvider};
+use dcbor::prelude::*;
+
+use crate::{with_format_context, Envelope, EnvelopeError};
+
+/// How [`ElementSidecar::merge`] resolves an element that has different
+/// values in the two sidecars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SidecarConflictPolicy {
+    /// Keep the value already in the sidecar.
+    KeepExisting,
+    /// Replace the value with the one from the other sidecar.
+    Replace,
+    /// Fail with `EnvelopeError::SidecarConflict`, leaving the sidecar
+    /// unchanged.
+    Reject,
+}
+
+/// Ephemeral metadata attached to the elements of an envelope, such as
+/// whether a user interface shows them expanded, keyed by their digests.
+///
+/// The metadata is not part of the envelope, so attaching it changes neither
+/// the envelope nor its digest. Each element has at most one value, a small
+/// CBOR value whose meaning is up to the application.
+///
+/// A sidecar can itself be encoded as an envelope so it can be stored
+/// alongside the document it describes:
+///
+/// ```text
+/// "ElementSidecar" [
+///     Digest(13941b48): "bookmarked"
+///     Digest(78d666eb): "collapsed"
+/// ]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ElementSidecar {
+    entries: BTreeMap<Digest, CBOR>,
+}
+
+impl ElementSidecar {
+    /// Creates a new empty sidecar.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of elements with values.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no element has a value.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the digests of the elements and their values, in digest order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Digest, &CBOR)> {
+        self.entries.iter()
+    }
+
+    /// Sets the value of the given element, returning its previous value.
+    pub fn set(&mut self, element: &impl DigestProvider, value: impl Into<CBOR>) -> Option<CBOR> {
+        self.entries.insert(element.digest().into_owned(), value.into())
+    }
+
+    /// Returns the value of the given element, if any.
+    pub fn get_cbor(&self, element: &impl DigestProvider) -> Option<&CBOR> {
+        self.entries.get(&*element.digest())
+    }
+
+    /// Returns the value of the given element, if any, decoded as the given
+    /// type.
+    ///
+    /// Returns an error if the element has a value that is not of the type.
+    pub fn get<T: TryFrom<CBOR, Error = Error>>(&self, element: &impl DigestProvider) -> Result<Option<T>> {
+        self.get_cbor(element).cloned().map(T::try_from).transpose()
+    }
+
+    /// Removes the value of the given element, returning it.
+    pub fn remove(&mut self, element: &impl DigestProvider) -> Option<CBOR> {
+        self.entries.remove(&*element.digest())
+    }
+
+    /// Removes the values of the elements that are not in the given envelope,
+    /// for example after it has been re-issued without some of them, and
+    /// returns the number removed.
+    ///
+    /// The elements of the envelope are found as by [`Envelope::digests`]:
+    /// obscured elements are kept, but not their contents.
+    pub fn prune_to(&mut self, envelope: &Envelope) -> usize {
+        let digests = envelope.digests(usize::MAX);
+        let count = self.entries.len();
+        self.entries.retain(|digest, _| digests.contains(digest));
+        count - self.entries.len()
+    }
+
+    /// Adds the values from another sidecar, resolving elements that have
+    /// different values in the two with the given policy.
+    ///
+    /// - Throws: `EnvelopeError::SidecarConflict` for the first conflicting
+    /// element, in digest order, if the policy is
+    /// [`SidecarConflictPolicy::Reject`].
+    pub fn merge(&mut self, other: &ElementSidecar, policy: SidecarConflictPolicy) -> Result<()> {
+        if policy == SidecarConflictPolicy::Reject {
+            if let Some((digest, _)) = other.entries.iter().find(|(digest, value)| {
+                self.entries.get(*digest).is_some_and(|existing| existing != *value)
+            }) {
+                bail!(EnvelopeError::SidecarConflict(digest.clone()));
+            }
+        }
+        for (digest, value) in &other.entries {
+            if policy == SidecarConflictPolicy::KeepExisting && self.entries.contains_key(digest) {
+                continue;
+            }
+            self.entries.insert(digest.clone(), value.clone());
+        }
+        Ok(())
+    }
+}
+
+impl From<&ElementSidecar> for Envelope {
+    fn from(sidecar: &ElementSidecar) -> Self {
+        sidecar.entries.iter().fold(Envelope::new("ElementSidecar"), |envelope, (digest, value)| {
+            envelope.add_assertion(digest.clone(), value.clone())
+        })
+    }
+}
+
+impl From<ElementSidecar> for Envelope {
+    fn from(sidecar: ElementSidecar) -> Self {
+        Envelope::from(&sidecar)
+    }
+}
+
+impl TryFrom<Envelope> for ElementSidecar {
+    type Error = Error;
+
+    fn try_from(envelope: Envelope) -> Result<Self> {
+        if envelope.extract_subject::<String>().ok().as_deref() != Some("ElementSidecar") {
+            bail!(EnvelopeError::InvalidFormat { message: "not an element sidecar".to_string() });
+        }
+        let mut sidecar = ElementSidecar::new();
+        for assertion in envelope.assertions() {
+            let digest: Digest = assertion.try_predicate()?.extract_subject()?;
+            let value = assertion.try_object()?.as_leaf().ok_or_else(|| EnvelopeError::InvalidFormat {
+                message: "an element sidecar value is not a leaf".to_string(),
+            })?;
+            sidecar.entries.insert(digest, value);
+        }
+        Ok(sidecar)
+    }
+}
+
+/// Returns the tree format of the envelope, with the value of each element
+/// in the sidecar, as rendered by `render`, at the end of its line.
+///
+/// Uses the current format context. See [`Envelope::tree_format_annotated`].
+pub fn annotate_format(envelope: &Envelope, sidecar: &ElementSidecar, render: impl Fn(&CBOR) -> String) -> String {
+    let annotations: HashMap<Digest, String> = sidecar.iter()
+        .map(|(digest, value)| (digest.clone(), render(value)))
+        .collect();
+    with_format_context!(|context| {
+        envelope.tree_format_annotated(false, &annotations, context)
+    })
+}
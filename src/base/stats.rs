@@ -0,0 +1,97 @@
+use std::cell::RefCell;
+
+use super::envelope::EnvelopeCase;
+use crate::Envelope;
+
+/// Counts of the elements of an envelope, by case, and other measures of its
+/// structure.
+///
+/// Returned by [`Envelope::stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EnvelopeStats {
+    /// The total number of elements, as returned by
+    /// [`Envelope::elements_count`].
+    pub elements: usize,
+    /// The greatest depth of any element, where the envelope itself is at
+    /// depth zero and each subject, assertion, predicate, object, or wrapped
+    /// envelope is one deeper than the element that contains it.
+    pub max_depth: usize,
+    /// The number of nodes: subjects with one or more assertions.
+    pub nodes: usize,
+    /// The number of leaves, which hold CBOR values.
+    pub leaves: usize,
+    /// The number of wrapped envelopes.
+    pub wrapped: usize,
+    /// The number of known values.
+    pub known_values: usize,
+    /// The number of assertions, each counted once with its predicate and
+    /// object counted separately.
+    pub assertions: usize,
+    /// The number of encrypted elements.
+    pub encrypted: usize,
+    /// The number of elided elements.
+    pub elided: usize,
+    /// The number of compressed elements.
+    pub compressed: usize,
+    /// The size in bytes of the envelope's tagged CBOR encoding.
+    pub serialized_size: usize,
+}
+
+impl EnvelopeStats {
+    /// Returns the number of elements that are elided, encrypted, or
+    /// compressed.
+    pub fn obscured(&self) -> usize {
+        self.elided + self.encrypted + self.compressed
+    }
+}
+
+/// Support for measuring the structure of an envelope.
+impl Envelope {
+    /// Returns the counts of the envelope's elements by case, its greatest
+    /// depth, and the size of its encoding.
+    ///
+    /// The elements are counted in one walk of the envelope. The contents of
+    /// obscured elements are not counted. Useful for capacity planning, as
+    /// `serialized_size` is the size the envelope will have when serialized.
+    #[doc(alias = "structure_stats")]
+    pub fn stats(&self) -> EnvelopeStats {
+        let stats = RefCell::new(EnvelopeStats::default());
+        self.walk(false, &|envelope, level, _, _: Option<()>| {
+            let mut stats = stats.borrow_mut();
+            stats.elements += 1;
+            stats.max_depth = stats.max_depth.max(level);
+            match envelope.case() {
+                EnvelopeCase::Node { .. } => stats.nodes += 1,
+                EnvelopeCase::Leaf { .. } => stats.leaves += 1,
+                EnvelopeCase::Wrapped { .. } => stats.wrapped += 1,
+                EnvelopeCase::Assertion(_) => stats.assertions += 1,
+                EnvelopeCase::Elided(_) => stats.elided += 1,
+                #[cfg(feature = "known_value")]
+                EnvelopeCase::KnownValue { .. } => stats.known_values += 1,
+                #[cfg(feature = "encrypt")]
+                EnvelopeCase::Encrypted(_) => stats.encrypted += 1,
+                #[cfg(feature = "compress")]
+                EnvelopeCase::Compressed(_) => stats.compressed += 1,
+            }
+            None
+        });
+        let mut stats = stats.into_inner();
+        stats.serialized_size = self.tagged_cbor_data_len();
+        stats
+    }
+
+    /// Returns whether no part of the envelope is elided, encrypted, or
+    /// compressed.
+    pub fn is_fully_revealed(&self) -> bool {
+        match self.case() {
+            EnvelopeCase::Node { subject, assertions, .. } => {
+                subject.is_fully_revealed() && assertions.iter().all(|assertion| assertion.is_fully_revealed())
+            },
+            EnvelopeCase::Wrapped { envelope, .. } => envelope.is_fully_revealed(),
+            EnvelopeCase::Assertion(assertion) => {
+                assertion.predicate().is_fully_revealed() && assertion.object().is_fully_revealed()
+            },
+            _ => !self.is_obscured(),
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::{pin::Pin, task::{ready, Context, Poll}};
+
+use anyhow::Result;
+use dcbor::prelude::*;
+use futures_core::Stream;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use crate::{Envelope, EnvelopeError, EnvelopeLimits};
+
+/// The number of bytes in the length prefix of each frame.
+pub const FRAME_HEADER_SIZE: usize = 4;
+
+/// The number of bytes read from the underlying reader at a time.
+const READ_CHUNK_SIZE: usize = 8192;
+
+impl Envelope {
+    /// Returns the envelope's tagged CBOR encoding as a frame for an
+    /// [`EnvelopeStream`]: a 4-byte big-endian length followed by the
+    /// encoding.
+    ///
+    /// Panics if the encoding is larger than `u32::MAX` bytes.
+    pub fn framed_data(&self) -> Vec<u8> {
+        let data = self.tagged_cbor_data();
+        let size = u32::try_from(data.len()).expect("envelope too large for a frame");
+        let mut frame = Vec::with_capacity(FRAME_HEADER_SIZE + data.len());
+        frame.extend_from_slice(&size.to_be_bytes());
+        frame.extend_from_slice(&data);
+        frame
+    }
+}
+
+/// A stream of envelopes decoded from frames read from an asynchronous
+/// reader, such as a network connection.
+///
+/// Each frame is a 4-byte big-endian length followed by that many bytes of
+/// tagged envelope CBOR, as produced by [`Envelope::framed_data`]. Envelopes
+/// are decoded and validated as by
+/// [`Envelope::from_tagged_cbor_data_checked_with_limits`], with the stream's
+/// limits.
+///
+/// The stream yields:
+///
+/// * `Ok(envelope)` for each frame that decodes to a valid envelope.
+/// * `Err(_)` for a frame that does not decode to a valid envelope. The
+///   stream continues with the next frame.
+/// * `Err(EnvelopeError::FrameTooLarge)` for a frame whose declared length
+///   exceeds the limits, before any of it is read, and
+///   `Err(EnvelopeError::TruncatedFrame)` if the reader ends in the middle of
+///   a frame. Once the framing is corrupt the stream cannot resynchronize, so
+///   it ends after these errors, and after any error from the reader.
+/// * `None` when the reader ends between frames.
+///
+/// Partially read frames are buffered in the stream, so it is safe to drop a
+/// pending `next()` (for example, when `select!`ing with a timeout) and poll
+/// again later.
+#[derive(Debug)]
+pub struct EnvelopeStream<R> {
+    reader: R,
+    limits: EnvelopeLimits,
+    header: [u8; FRAME_HEADER_SIZE],
+    header_len: usize,
+    frame_size: Option<usize>,
+    frame: Vec<u8>,
+    finished: bool,
+}
+
+impl<R: AsyncRead + Unpin> EnvelopeStream<R> {
+    /// Creates a stream of the envelopes read from `reader`, enforcing the
+    /// frame size and decoding limits in `limits`.
+    pub fn new(reader: R, limits: EnvelopeLimits) -> Self {
+        Self {
+            reader,
+            limits,
+            header: [0; FRAME_HEADER_SIZE],
+            header_len: 0,
+            frame_size: None,
+            frame: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn finish(&mut self, error: impl Into<anyhow::Error>) -> Poll<Option<Result<Envelope>>> {
+        self.finished = true;
+        Poll::Ready(Some(Err(error.into())))
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for EnvelopeStream<R> {
+    type Item = Result<Envelope>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.finished {
+            return Poll::Ready(None);
+        }
+        loop {
+            match this.frame_size {
+                None => {
+                    let mut buf = ReadBuf::new(&mut this.header[this.header_len..]);
+                    if let Err(error) = ready!(Pin::new(&mut this.reader).poll_read(cx, &mut buf)) {
+                        return this.finish(error);
+                    }
+                    let read = buf.filled().len();
+                    if read == 0 {
+                        if this.header_len == 0 {
+                            this.finished = true;
+                            return Poll::Ready(None);
+                        }
+                        return this.finish(EnvelopeError::TruncatedFrame);
+                    }
+                    this.header_len += read;
+                    if this.header_len == FRAME_HEADER_SIZE {
+                        let size = u32::from_be_bytes(this.header) as usize;
+                        if size > this.limits.max_frame_size {
+                            return this.finish(EnvelopeError::FrameTooLarge { size, limit: this.limits.max_frame_size });
+                        }
+                        this.frame_size = Some(size);
+                    }
+                },
+                Some(size) if this.frame.len() < size => {
+                    let mut chunk = [0; READ_CHUNK_SIZE];
+                    let wanted = (size - this.frame.len()).min(READ_CHUNK_SIZE);
+                    let mut buf = ReadBuf::new(&mut chunk[..wanted]);
+                    if let Err(error) = ready!(Pin::new(&mut this.reader).poll_read(cx, &mut buf)) {
+                        return this.finish(error);
+                    }
+                    if buf.filled().is_empty() {
+                        return this.finish(EnvelopeError::TruncatedFrame);
+                    }
+                    this.frame.extend_from_slice(buf.filled());
+                },
+                Some(_) => {
+                    let frame = std::mem::take(&mut this.frame);
+                    this.header_len = 0;
+                    this.frame_size = None;
+                    return Poll::Ready(Some(Envelope::from_tagged_cbor_data_checked_with_limits(frame, &this.limits)));
+                },
+            }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections::HashSet, cell::RefCell};
+use std::collections::{HashMap, HashSet};
 
 use bc_components::{Digest, DigestProvider};
 
@@ -6,7 +6,7 @@ use crate::{Envelope, with_format_context, FormatContext};
 #[cfg(feature = "known_value")]
 use crate::{string_utils::StringUtils, extension::KnownValuesStore};
 
-use super::{walk::EdgeType, EnvelopeSummary, envelope::EnvelopeCase};
+use super::{walk::EdgeType, EnvelopeSummary, envelope::EnvelopeCase, format::FOLD_MARKER};
 
 /// Support for tree-formatting envelopes.
 impl Envelope {
@@ -14,29 +14,82 @@ impl Envelope {
         self.tree_format_with_target_opt(hide_nodes, &HashSet::new(), context)
     }
 
+    /// Returns the tree format of this envelope, using the given context
+    /// rather than the global one.
+    ///
+    /// See [`Envelope::format_with_context`].
+    pub fn tree_format_with_context(&self, hide_nodes: bool, context: &FormatContext) -> String {
+        self.tree_format_opt(hide_nodes, Some(context))
+    }
+
     pub fn tree_format(&self, hide_nodes: bool) -> String {
         with_format_context!(|context| {
-            self.tree_format_opt(hide_nodes, Some(context))
+            self.tree_format_with_context(hide_nodes, context)
         })
     }
 
     pub fn tree_format_with_target_opt(&self, hide_nodes: bool, highlighting_target: &HashSet<Digest>, context: Option<&FormatContext>) -> String {
-        let elements: RefCell<Vec<TreeElement>> = RefCell::new(Vec::new());
-        let visitor = |envelope: Self, level: usize, incoming_edge: EdgeType, _: Option<&()>| -> _ {
-            let elem = TreeElement::new(
+        let default_context = FormatContext::default();
+        let context = context.unwrap_or(&default_context);
+        self.tree_format_lines(hide_nodes, highlighting_target, &HashMap::new(), context)
+    }
+
+    /// Returns the tree format of this envelope, with the annotation for the
+    /// digest of each element, if any, at the end of its line.
+    ///
+    /// Annotations are an overlay: they are not part of the envelope, and do
+    /// not affect its digest. An annotation applies to every element with
+    /// its digest.
+    pub fn tree_format_annotated(&self, hide_nodes: bool, annotations: &HashMap<Digest, String>, context: &FormatContext) -> String {
+        self.tree_format_lines(hide_nodes, &HashSet::new(), annotations, context)
+    }
+
+    fn tree_format_lines(&self, hide_nodes: bool, highlighting_target: &HashSet<Digest>, annotations: &HashMap<Digest, String>, context: &FormatContext) -> String {
+        let options = TreeOptions { hide_nodes, highlighting_target, annotations, context };
+        let mut lines = Vec::new();
+        self.push_tree_lines(0, EdgeType::None, &mut lines, &options);
+        lines.iter().map(|line| line.string(context)).collect::<Vec<_>>().join("\n")
+    }
+
+    /// Appends the lines for this element and its descendants, at the same
+    /// levels and with the same edges as [`Envelope::walk`] visits them.
+    fn push_tree_lines(&self, level: usize, incoming_edge: EdgeType, lines: &mut Vec<TreeLine>, options: &TreeOptions<'_>) {
+        let next_level = if options.hide_nodes && self.is_node() {
+            level
+        } else {
+            lines.push(TreeLine::Element(TreeElement::new(
                 level,
-                envelope.clone(),
+                self.clone(),
                 incoming_edge,
-                !hide_nodes,
-                highlighting_target.contains(&envelope.digest()),
-            );
-            elements.borrow_mut().push(elem);
-            None
+                !options.hide_nodes,
+                options.highlighting_target.contains(&self.digest()),
+                options.annotations.get(&*self.digest()).cloned(),
+            )));
+            level + 1
         };
-        let s = self.clone();
-        s.walk(hide_nodes, &visitor);
-        let elements = elements.borrow();
-        elements.iter().map(|e| e.string(context.unwrap_or(&FormatContext::default()))).collect::<Vec<_>>().join("\n")
+        let edge = |edge_type: EdgeType| if options.hide_nodes { EdgeType::None } else { edge_type };
+        match self.case() {
+            EnvelopeCase::Node { subject, assertions, .. } => {
+                subject.push_tree_lines(next_level, edge(EdgeType::Subject), lines, options);
+                let assertion_level = if options.hide_nodes { next_level + 1 } else { next_level };
+                let folded = folded_tree_assertions(assertions, !options.hide_nodes, options.context);
+                for assertion in assertions {
+                    match folded.get(&*assertion.digest()) {
+                        Some(Some(marker)) => lines.push(TreeLine::Fold(assertion_level, marker.clone())),
+                        Some(None) => {},
+                        None => assertion.push_tree_lines(assertion_level, edge(EdgeType::Assertion), lines, options),
+                    }
+                }
+            },
+            EnvelopeCase::Wrapped { envelope, .. } => {
+                envelope.push_tree_lines(next_level, edge(EdgeType::Wrapped), lines, options);
+            },
+            EnvelopeCase::Assertion(assertion) => {
+                assertion.predicate().push_tree_lines(next_level, edge(EdgeType::Predicate), lines, options);
+                assertion.object().push_tree_lines(next_level, edge(EdgeType::Object), lines, options);
+            },
+            _ => {},
+        }
     }
 
     pub fn tree_format_with_target(&self, hide_nodes: bool, highlighting_target: &HashSet<Digest>) -> String {
@@ -46,9 +99,47 @@ impl Envelope {
     }
 }
 
+/// The options for formatting the lines of a tree.
+struct TreeOptions<'a> {
+    hide_nodes: bool,
+    highlighting_target: &'a HashSet<Digest>,
+    annotations: &'a HashMap<Digest, String>,
+    context: &'a FormatContext,
+}
+
+/// Returns the digests of the assertions of one node that are omitted by
+/// folding, each with the marker that stands for its group if it is the
+/// first omitted.
+fn folded_tree_assertions(assertions: &[Envelope], show_labels: bool, context: &FormatContext) -> HashMap<Digest, Option<String>> {
+    let mut folded = HashMap::new();
+    let Some(fold) = context.fold() else {
+        return folded;
+    };
+    let mut groups: Vec<(Envelope, Vec<&Envelope>)> = Vec::new();
+    for assertion in assertions {
+        if let Some(predicate) = assertion.as_predicate() {
+            match groups.iter_mut().find(|(p, _)| p.digest() == predicate.digest()) {
+                Some((_, group)) => group.push(assertion),
+                None => groups.push((predicate, vec![assertion])),
+            }
+        }
+    }
+    for (predicate, group) in groups.into_iter().filter(|(_, group)| group.len() > fold.threshold) {
+        let omitted = &group[fold.sample_size.min(group.len())..];
+        for (index, assertion) in omitted.iter().enumerate() {
+            let marker = (index == 0).then(|| {
+                let label = if show_labels { "pred " } else { "" };
+                format!("{}({} more) {}{}", FOLD_MARKER, omitted.len(), label, predicate.summary(40, context))
+            });
+            folded.insert(assertion.digest().into_owned(), marker);
+        }
+    }
+    folded
+}
+
 impl Envelope {
     pub fn short_id(&self) -> String {
-        self.digest().short_description()
+        self.short_digest_hex(4)
     }
 
     pub fn summary(&self, max_length: usize, context: &FormatContext) -> String {
@@ -71,6 +162,22 @@ impl Envelope {
     }
 }
 
+/// A line of the tree format: an element, or the marker that stands for the
+/// assertions omitted by folding.
+enum TreeLine {
+    Element(TreeElement),
+    Fold(usize, String),
+}
+
+impl TreeLine {
+    fn string(&self, context: &FormatContext) -> String {
+        match self {
+            TreeLine::Element(element) => element.string(context),
+            TreeLine::Fold(level, marker) => format!("{}{}", " ".repeat(level * 4), marker),
+        }
+    }
+}
+
 #[derive(Debug)]
 struct TreeElement {
     level: usize,
@@ -78,11 +185,12 @@ struct TreeElement {
     incoming_edge: EdgeType,
     show_id: bool,
     is_highlighted: bool,
+    annotation: Option<String>,
 }
 
 impl TreeElement {
-    fn new(level: usize, envelope: Envelope, incoming_edge: EdgeType, show_id: bool, is_highlighted: bool) -> Self {
-        Self { level, envelope, incoming_edge, show_id, is_highlighted }
+    fn new(level: usize, envelope: Envelope, incoming_edge: EdgeType, show_id: bool, is_highlighted: bool, annotation: Option<String>) -> Self {
+        Self { level, envelope, incoming_edge, show_id, is_highlighted, annotation }
     }
 
     fn string(&self, context: &FormatContext) -> String {
@@ -91,6 +199,7 @@ impl TreeElement {
             if self.show_id { Some(self.envelope.short_id()) } else { None },
             self.incoming_edge.label().map(|s| s.to_string()),
             Some(self.envelope.summary(40, context)),
+            self.annotation.as_ref().map(|annotation| format!("// {}", annotation)),
         ].into_iter().flatten().collect::<Vec<_>>().join(" ");
         let indent = " ".repeat(self.level * 4);
         format!("{}{}", indent, line)
@@ -98,3 +98,76 @@ impl Envelope {
         parent
     }
 }
+
+/// A visitor function for [`Envelope::par_walk`], which may be called from
+/// several threads at once.
+///
+/// Like [`Visitor`], it returns the parent to pass to the element's children,
+/// along with a value to collect.
+#[cfg(feature = "rayon")]
+pub type ParallelVisitor<'a, Parent, Output> =
+    dyn Fn(Envelope, usize, EdgeType, Option<Parent>) -> (Option<Parent>, Output) + Sync + 'a;
+
+/// Functions for walking an envelope in parallel.
+///
+/// The `rayon` feature enables the `multithreaded` feature, so that envelopes
+/// are reference counted with `Arc` and can be shared between threads.
+#[cfg(feature = "rayon")]
+impl Envelope {
+    /// Walks the envelope's structure, visiting the assertions of each node in
+    /// parallel, and returns the values returned by the visitor.
+    ///
+    /// The elements are visited as by [`Envelope::walk`] with `hide_nodes`
+    /// false, and the values are returned in that order, whatever the order
+    /// in which the visitor is called.
+    pub fn par_walk<Parent, Output>(&self, visit: &ParallelVisitor<'_, Parent, Output>) -> Vec<Output>
+    where
+        Parent: Clone + Send + Sync,
+        Output: Send,
+    {
+        let mut outputs = Vec::new();
+        self._par_walk(0, EdgeType::None, None, visit, &mut outputs);
+        outputs
+    }
+
+    fn _par_walk<Parent, Output>(
+        &self,
+        level: usize,
+        incoming_edge: EdgeType,
+        parent: Option<Parent>,
+        visit: &ParallelVisitor<'_, Parent, Output>,
+        outputs: &mut Vec<Output>,
+    )
+    where
+        Parent: Clone + Send + Sync,
+        Output: Send,
+    {
+        use rayon::prelude::*;
+
+        let (parent, output) = visit(self.clone(), level, incoming_edge, parent);
+        outputs.push(output);
+        let next_level = level + 1;
+        match self.case() {
+            EnvelopeCase::Node { subject, assertions, .. } => {
+                subject._par_walk(next_level, EdgeType::Subject, parent.clone(), visit, outputs);
+                let branches: Vec<Vec<Output>> = assertions
+                    .par_iter()
+                    .map(|assertion| {
+                        let mut branch = Vec::new();
+                        assertion._par_walk(next_level, EdgeType::Assertion, parent.clone(), visit, &mut branch);
+                        branch
+                    })
+                    .collect();
+                outputs.extend(branches.into_iter().flatten());
+            },
+            EnvelopeCase::Wrapped { envelope, .. } => {
+                envelope._par_walk(next_level, EdgeType::Wrapped, parent, visit, outputs);
+            },
+            EnvelopeCase::Assertion(assertion) => {
+                assertion.predicate()._par_walk(next_level, EdgeType::Predicate, parent.clone(), visit, outputs);
+                assertion.object()._par_walk(next_level, EdgeType::Object, parent, visit, outputs);
+            },
+            _ => {},
+        }
+    }
+}
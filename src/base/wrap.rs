@@ -6,14 +6,58 @@ use super::envelope::EnvelopeCase;
 
 /// Support for wrapping and unwrapping envelopes.
 impl Envelope {
-    /// Return a new envelope which wraps the current envelope.
-    pub fn wrap_envelope(&self) -> Self {
+    /// Returns a new envelope which wraps the current envelope.
+    ///
+    /// The whole envelope, including its assertions, becomes the subject of
+    /// the new envelope, so assertions added to the result apply to the
+    /// envelope as a whole. The digest of the wrapped envelope is the digest
+    /// of the digest of this envelope.
+    pub fn wrap(&self) -> Self {
         Self::new_wrapped(self.clone())
     }
 
+    /// Returns a new envelope which wraps the current envelope.
+    ///
+    /// This is the same as [`Envelope::wrap`].
+    pub fn wrap_envelope(&self) -> Self {
+        self.wrap()
+    }
+
+    /// Returns a new envelope whose subject is this envelope's subject,
+    /// wrapped, with this envelope's assertions left outside the wrapper.
+    ///
+    /// This is the shape needed to sign the subject alone while leaving
+    /// metadata assertions that can still be added or removed: unlike
+    /// [`Envelope::wrap`], the assertions do not contribute to the digest of
+    /// the wrapped subject. If this envelope is not a node, this is the same
+    /// as [`Envelope::wrap`].
+    pub fn wrap_subject(&self) -> Self {
+        self.map_subject(|subject| subject.wrap())
+    }
+
+    /// Returns a new envelope whose subject is the envelope inside this
+    /// envelope's wrapped subject, with this envelope's assertions kept.
+    ///
+    /// This reverses [`Envelope::wrap_subject`]. If the inner envelope has
+    /// assertions of its own, they are merged with the outer ones, as by
+    /// [`Envelope::replace_subject`].
+    ///
+    /// Returns an error if the subject is not a wrapped envelope.
+    pub fn unwrap_subject(&self) -> Result<Self> {
+        let inner = self.unwrap_envelope()?;
+        Ok(self.replace_subject(inner))
+    }
+
     /// Unwraps and returns the inner envelope.
     ///
-    /// Returns an error if this is not a wrapped envelope.
+    /// This operates on the envelope's subject, so assertions added after
+    /// wrapping are ignored: `envelope.wrap().add_assertion(...)
+    /// .unwrap_envelope()` returns `envelope`. Only one level of wrapping is
+    /// removed.
+    ///
+    /// Returns an error if the subject is not a wrapped envelope, including
+    /// when it is an obscured (elided, encrypted, or compressed) wrapped
+    /// envelope.
     pub fn unwrap_envelope(&self) -> Result<Self> {
         match self.subject().case() {
             EnvelopeCase::Wrapped { envelope, .. } => Ok(envelope.clone()),
@@ -0,0 +1,271 @@
+//! Self-description of the envelope encoding emitted by this crate.
+//!
+//! The constants in this module are the CBOR tag values this crate emits,
+//! re-exported from the `bc-components` tag registry. Envelope assertions are
+//! encoded as single-element CBOR maps, and so have no tag of their own.
+//!
+//! [`conformance_statement`] encodes this information, along with the crate
+//! version and enabled features, as an envelope suitable for exchange with a
+//! peer, and [`check_peer_conformance`] compares a peer's statement with our
+//! own.
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use bc_components::tags;
+use dcbor::TagValue;
+
+use crate::{Envelope, TAG_PSEUDONYM};
+
+/// The tag for an envelope.
+pub const TAG_ENVELOPE: TagValue = tags::TAG_ENVELOPE;
+
+/// The tag for a leaf (a CBOR value) envelope.
+pub const TAG_LEAF: TagValue = tags::TAG_LEAF;
+
+/// The tag for a wrapped envelope. This is the same as [`TAG_ENVELOPE`].
+pub const TAG_WRAPPED: TagValue = tags::TAG_ENVELOPE;
+
+/// The tag for a known value.
+pub const TAG_KNOWN_VALUE: TagValue = tags::TAG_KNOWN_VALUE;
+
+/// The tag for a digest.
+pub const TAG_DIGEST: TagValue = tags::TAG_DIGEST;
+
+/// The tag for an encrypted envelope.
+pub const TAG_ENCRYPTED: TagValue = tags::TAG_ENCRYPTED;
+
+/// The tag for a compressed envelope.
+pub const TAG_COMPRESSED: TagValue = tags::TAG_COMPRESSED;
+
+/// The tag for an expression function.
+pub const TAG_FUNCTION: TagValue = tags::TAG_FUNCTION;
+
+/// The tag for an expression parameter.
+pub const TAG_PARAMETER: TagValue = tags::TAG_PARAMETER;
+
+/// The tag for a request.
+pub const TAG_REQUEST: TagValue = tags::TAG_REQUEST;
+
+/// The tag for a response.
+pub const TAG_RESPONSE: TagValue = tags::TAG_RESPONSE;
+
+/// The name of the specification this crate implements.
+pub const SPECIFICATION: &str = "draft-mcnally-envelope";
+
+/// The names and values of the tags this crate emits.
+pub fn emitted_tags() -> Vec<(&'static str, TagValue)> {
+    vec![
+        ("envelope", TAG_ENVELOPE),
+        ("leaf", TAG_LEAF),
+        ("wrapped", TAG_WRAPPED),
+        ("known-value", TAG_KNOWN_VALUE),
+        ("digest", TAG_DIGEST),
+        ("encrypted", TAG_ENCRYPTED),
+        ("compressed", TAG_COMPRESSED),
+        ("function", TAG_FUNCTION),
+        ("parameter", TAG_PARAMETER),
+        ("request", TAG_REQUEST),
+        ("response", TAG_RESPONSE),
+        ("pseudonym", TAG_PSEUDONYM),
+    ]
+}
+
+/// The crate features that change what this crate emits or understands, each
+/// with whether it is enabled in this build.
+///
+/// Every feature in the `[features]` section of `Cargo.toml` is listed either
+/// here or in `LOCAL_FEATURES`, which a test checks.
+const WIRE_FEATURES: &[(&str, bool)] = &[
+    ("attachment", cfg!(feature = "attachment")),
+    ("compress", cfg!(feature = "compress")),
+    ("encrypt", cfg!(feature = "encrypt")),
+    ("expression", cfg!(feature = "expression")),
+    ("known_value", cfg!(feature = "known_value")),
+    ("parity", cfg!(feature = "parity")),
+    ("proof", cfg!(feature = "proof")),
+    ("recipient", cfg!(feature = "recipient")),
+    ("salt", cfg!(feature = "salt")),
+    ("signature", cfg!(feature = "signature")),
+    ("ssh", cfg!(feature = "ssh")),
+    ("sskr", cfg!(feature = "sskr")),
+    ("types", cfg!(feature = "types")),
+];
+
+/// The crate features that do not change what this crate emits or
+/// understands, and so are not declared to peers.
+#[cfg(test)]
+const LOCAL_FEATURES: &[&str] = &["async", "multithreaded", "rayon", "serde", "test-support"];
+
+/// The names of the crate features enabled in this build that change what
+/// this crate emits or understands.
+pub fn supported_features() -> Vec<&'static str> {
+    WIRE_FEATURES.iter().filter(|(_, enabled)| *enabled).map(|(name, _)| *name).collect()
+}
+
+/// Returns an envelope describing the encoding this crate emits.
+///
+/// ```text
+/// "bc-envelope" [
+///     "specification": "draft-mcnally-envelope"
+///     "version": "0.18.2"
+///     "feature": "compress"
+///     "tag": 200 [
+///         "name": "envelope"
+///     ]
+///     ...
+/// ]
+/// ```
+pub fn conformance_statement() -> Envelope {
+    let mut statement = Envelope::new("bc-envelope")
+        .add_assertion("specification", SPECIFICATION)
+        .add_assertion("version", env!("CARGO_PKG_VERSION"));
+    for feature in supported_features() {
+        statement = statement.add_assertion("feature", feature);
+    }
+    for (name, value) in emitted_tags() {
+        statement = statement.add_assertion("tag", Envelope::new(value).add_assertion("name", name));
+    }
+    statement
+}
+
+/// The result of comparing a peer's conformance statement with our own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerCapabilities {
+    /// The specification the peer implements.
+    pub specification: String,
+    /// The version of the peer's implementation.
+    pub version: String,
+    /// Features supported by both us and the peer.
+    pub shared_features: BTreeSet<String>,
+    /// Features we support that the peer does not.
+    pub missing_features: BTreeSet<String>,
+    /// Tags we emit that the peer does not declare.
+    pub missing_tags: BTreeSet<String>,
+    /// Tags the peer declares with a different value than ours, as
+    /// `(name, ours, theirs)`.
+    pub mismatched_tags: Vec<(String, TagValue, TagValue)>,
+}
+
+impl PeerCapabilities {
+    /// Returns `true` if the peer implements the same specification, supports
+    /// all of our features, and agrees with us on all tag values.
+    pub fn is_fully_compatible(&self) -> bool {
+        self.specification == SPECIFICATION
+            && self.missing_features.is_empty()
+            && self.missing_tags.is_empty()
+            && self.mismatched_tags.is_empty()
+    }
+}
+
+/// Parses a peer's conformance statement and compares it with our own.
+///
+/// Returns an error if the statement is malformed.
+pub fn check_peer_conformance(statement: &Envelope) -> Result<PeerCapabilities> {
+    let specification: String = statement.extract_object_for_predicate("specification")?;
+    let version: String = statement.extract_object_for_predicate("version")?;
+    let peer_features: BTreeSet<String> = statement
+        .extract_objects_for_predicate::<String>("feature")?
+        .into_iter()
+        .collect();
+    let mut peer_tags = Vec::new();
+    for tag in statement.objects_for_predicate("tag") {
+        let value: TagValue = tag.extract_subject()?;
+        let name: String = tag.extract_object_for_predicate("name")?;
+        peer_tags.push((name, value));
+    }
+
+    let mut shared_features = BTreeSet::new();
+    let mut missing_features = BTreeSet::new();
+    for feature in supported_features() {
+        if peer_features.contains(feature) {
+            shared_features.insert(feature.to_string());
+        } else {
+            missing_features.insert(feature.to_string());
+        }
+    }
+
+    let mut missing_tags = BTreeSet::new();
+    let mut mismatched_tags = Vec::new();
+    for (name, value) in emitted_tags() {
+        match peer_tags.iter().find(|(peer_name, _)| peer_name == name) {
+            Some((_, peer_value)) if *peer_value != value => {
+                mismatched_tags.push((name.to_string(), value, *peer_value));
+            },
+            Some(_) => {},
+            None => { missing_tags.insert(name.to_string()); },
+        }
+    }
+
+    Ok(PeerCapabilities {
+        specification,
+        version,
+        shared_features,
+        missing_features,
+        missing_tags,
+        mismatched_tags,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use dcbor::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn test_own_statement() {
+        let statement = conformance_statement();
+        let data = statement.tagged_cbor().to_cbor_data();
+        let decoded = Envelope::from_tagged_cbor_data(data).unwrap();
+        assert!(decoded.is_identical_to(&statement));
+
+        let capabilities = check_peer_conformance(&decoded).unwrap();
+        assert!(capabilities.is_fully_compatible());
+        assert_eq!(capabilities.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(capabilities.shared_features.len(), supported_features().len());
+    }
+
+    #[test]
+    fn test_features_are_classified() {
+        let manifest = include_str!("../Cargo.toml");
+        let (_, features_section) = manifest.split_once("[features]\n").unwrap();
+        let features: BTreeSet<&str> = features_section
+            .lines()
+            .take_while(|line| !line.starts_with('['))
+            .filter_map(|line| line.split_once(" = ").map(|(name, _)| name.trim()))
+            .filter(|name| *name != "default")
+            .collect();
+        for (name, _) in WIRE_FEATURES {
+            assert!(features.contains(name), "{} is not a crate feature", name);
+        }
+        for feature in features {
+            assert!(
+                WIRE_FEATURES.iter().any(|(name, _)| *name == feature) || LOCAL_FEATURES.contains(&feature),
+                "the {} feature is not classified in the conformance module",
+                feature
+            );
+        }
+    }
+
+    #[cfg(feature = "compress")]
+    #[test]
+    fn test_peer_missing_compression() {
+        let mut statement = Envelope::new("other-envelope")
+            .add_assertion("specification", SPECIFICATION)
+            .add_assertion("version", "1.0.0");
+        for feature in supported_features().into_iter().filter(|f| *f != "compress") {
+            statement = statement.add_assertion("feature", feature);
+        }
+        for (name, value) in emitted_tags().into_iter().filter(|(name, _)| *name != "compressed") {
+            statement = statement.add_assertion("tag", Envelope::new(value).add_assertion("name", name));
+        }
+
+        let capabilities = check_peer_conformance(&statement).unwrap();
+        assert!(!capabilities.is_fully_compatible());
+        assert_eq!(capabilities.missing_features, BTreeSet::from(["compress".to_string()]));
+        assert_eq!(capabilities.missing_tags, BTreeSet::from(["compressed".to_string()]));
+        assert!(capabilities.mismatched_tags.is_empty());
+        assert!(!capabilities.shared_features.contains("compress"));
+    }
+}
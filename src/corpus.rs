@@ -0,0 +1,182 @@
+//! A canonical corpus of envelopes for testing other implementations.
+//!
+//! [`corpus_fixtures`] builds a fixed set of envelopes covering the envelope
+//! cases and the main extensions. Keys, nonces, and random numbers are all
+//! fixed, so every run produces byte-for-byte identical envelopes.
+//!
+//! [`corpus_files`] renders each fixture in every form another
+//! implementation might check against:
+//!
+//! * `<name>.cbor`: the tagged CBOR encoding.
+//! * `<name>.ur`: the UR string.
+//! * `<name>.txt`: the envelope notation.
+//! * `<name>.tree`: the tree format.
+//! * `<name>.digest`: the hex digest of the envelope.
+//!
+//! [`corpus_manifest`] lists the SHA-256 of each file, so that a change to
+//! the encoding or formatting of any fixture shows up in review. The
+//! `generate_corpus` example writes the files and the manifest to a
+//! directory:
+//!
+//! ```text
+//! cargo run --example generate_corpus --features test-support -- --out corpus
+//! ```
+//!
+//! This module is only available with the `test-support` feature.
+
+use std::{cell::RefCell, rc::Rc};
+
+use bc_components::{ARID, DigestProvider, Nonce, PrivateKeyBase, SigningOptions, SSKRGroupSpec, SSKRSpec, SymmetricKey};
+use bc_rand::make_fake_random_number_generator;
+use dcbor::{CBORTaggedEncodable, Date};
+
+use crate::{
+    known_values,
+    Envelope,
+    ExpressionBehavior,
+    Request,
+    Response,
+    ResponseBehavior,
+};
+
+/// The name of the manifest written alongside the corpus files.
+pub const MANIFEST_FILE_NAME: &str = "MANIFEST";
+
+/// A named envelope in the corpus.
+#[derive(Debug, Clone)]
+pub struct CorpusFixture {
+    /// The name of the fixture, used as the stem of its file names.
+    pub name: &'static str,
+    /// The envelope.
+    pub envelope: Envelope,
+}
+
+impl CorpusFixture {
+    fn new(name: &'static str, envelope: Envelope) -> Self {
+        Self { name, envelope }
+    }
+}
+
+/// A file in the corpus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorpusFile {
+    /// The file name, such as `leaf.cbor`.
+    pub name: String,
+    /// The contents of the file.
+    pub contents: Vec<u8>,
+}
+
+fn bytes<const N: usize>(hex: &str) -> [u8; N] {
+    hex::decode(hex).unwrap().try_into().unwrap()
+}
+
+fn alice_private_key() -> PrivateKeyBase {
+    PrivateKeyBase::from_data(bytes::<16>("82f32c855d3d542256180810797e0073"))
+}
+
+fn content_key() -> SymmetricKey {
+    SymmetricKey::from_data(bytes("526afd95b2229c5381baec4a1788507a3c4a566ca5cce64543b46ad12aff0035"))
+}
+
+fn nonce() -> Nonce {
+    Nonce::from_data(bytes("4d785658f36c22fb5aed3ac0"))
+}
+
+fn request_id() -> ARID {
+    ARID::from_data(bytes("c66be27dbad7cd095ca77647406d07976dc0f35f0d4d654bb0e96dd227a1e9fc"))
+}
+
+fn signing_options() -> SigningOptions {
+    SigningOptions::Schnorr { rng: Rc::new(RefCell::new(make_fake_random_number_generator())) }
+}
+
+fn alice_knows_bob_and_carol() -> Envelope {
+    Envelope::new("Alice")
+        .add_assertion("knows", "Bob")
+        .add_assertion("knows", "Carol")
+}
+
+fn credential() -> Envelope {
+    Envelope::new(ARID::from_data(bytes("4676635a6e6068c2ef3ffd8ff726dd401fd341036e920f136a1d8af5e829496d")))
+        .add_assertion(known_values::IS_A, "Certificate of Completion")
+        .add_assertion(known_values::ISSUER, "Example Electrical Engineering Board")
+        .add_assertion("firstName", "James")
+        .add_assertion("lastName", "Maxwell")
+        .add_assertion("issueDate", Date::from_string("2020-01-01").unwrap())
+        .add_assertion("expirationDate", Date::from_string("2028-01-01").unwrap())
+        .add_assertion("certificateNumber", "123-456-789")
+        .add_assertion("continuingEducationUnits", 1)
+}
+
+/// Returns the envelopes in the corpus, in a fixed order.
+pub fn corpus_fixtures() -> Vec<CorpusFixture> {
+    let node = alice_knows_bob_and_carol();
+    let credential = credential();
+    let signed_credential = credential
+        .wrap_envelope()
+        .add_signature_opt(&alice_private_key(), Some(signing_options()), None);
+    let encrypted = node.wrap_envelope().encrypt_subject_opt(&content_key(), Some(nonce())).unwrap();
+    let spec = SSKRSpec::new(1, vec![SSKRGroupSpec::new(2, 3).unwrap()]).unwrap();
+    let shares = encrypted
+        .sskr_split_using(&spec, &content_key(), &mut make_fake_random_number_generator())
+        .unwrap();
+    let carol = Envelope::new_assertion("knows", "Carol");
+    let request = Request::new("getBalance", request_id())
+        .with_parameter("account", "Alice");
+    let response = Response::new_success(request_id())
+        .with_result(100);
+
+    let mut fixtures = vec![
+        CorpusFixture::new("leaf", Envelope::new("Hello.")),
+        CorpusFixture::new("known_value", Envelope::new(known_values::NOTE)),
+        CorpusFixture::new("assertion", Envelope::new_assertion("knows", "Bob")),
+        CorpusFixture::new("node", node.clone()),
+        CorpusFixture::new("wrapped", node.wrap_envelope()),
+        CorpusFixture::new("credential", credential),
+        CorpusFixture::new("signed", signed_credential),
+        CorpusFixture::new("encrypted", encrypted),
+    ];
+    for (index, share) in shares.into_iter().flatten().enumerate() {
+        let name = ["sskr_share_1", "sskr_share_2", "sskr_share_3"][index];
+        fixtures.push(CorpusFixture::new(name, share));
+    }
+    fixtures.extend([
+        CorpusFixture::new("elided", node.elide()),
+        CorpusFixture::new("elided_assertion", node.elide_removing_target(&carol)),
+        CorpusFixture::new("elided_subject", node.elide_removing_target(&node.subject())),
+        CorpusFixture::new("compressed", node.compress().unwrap()),
+        CorpusFixture::new("salted", node.add_salt_using(&mut make_fake_random_number_generator())),
+        CorpusFixture::new("request", request.into()),
+        CorpusFixture::new("response", response.into()),
+    ]);
+    fixtures
+}
+
+/// Returns the files of the corpus, five for each fixture, in a fixed order.
+///
+/// The envelope notation and tree format use the global format context.
+pub fn corpus_files() -> Vec<CorpusFile> {
+    crate::register_tags();
+    corpus_fixtures()
+        .into_iter()
+        .flat_map(|fixture| {
+            let envelope = &fixture.envelope;
+            [
+                ("cbor", envelope.tagged_cbor_data()),
+                ("ur", format!("{}\n", envelope.ur_string()).into_bytes()),
+                ("txt", format!("{}\n", envelope.format()).into_bytes()),
+                ("tree", format!("{}\n", envelope.tree_format(false)).into_bytes()),
+                ("digest", format!("{}\n", hex::encode(envelope.digest().data())).into_bytes()),
+            ]
+            .map(|(extension, contents)| CorpusFile { name: format!("{}.{}", fixture.name, extension), contents })
+        })
+        .collect()
+}
+
+/// Returns the manifest of the given corpus files: one line per file, of the
+/// hex SHA-256 of the contents, two spaces, and the file name.
+pub fn corpus_manifest(files: &[CorpusFile]) -> String {
+    files.iter()
+        .map(|file| format!("{}  {}\n", hex::encode(bc_crypto::sha256(&file.contents)), file.name))
+        .collect()
+}
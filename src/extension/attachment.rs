@@ -39,10 +39,12 @@ impl Assertion {
     /// - The attachment assertion's object is an envelope.
     /// - The attachment assertion's object has a `'vendor': String` assertion.
     /// - The attachment assertion's object has an optional `'conformsTo': String` assertion.
+    ///
+    /// Fails with `EnvelopeError::InvalidAttachment` otherwise.
     pub fn validate_attachment(&self) -> Result<()> {
-        let payload = self.attachment_payload()?;
-        let vendor = self.attachment_vendor()?;
-        let conforms_to: Option<String> = self.attachment_conforms_to()?;
+        let payload = self.attachment_payload().map_err(|_| EnvelopeError::InvalidAttachment)?;
+        let vendor = self.attachment_vendor().map_err(|_| EnvelopeError::InvalidAttachment)?;
+        let conforms_to: Option<String> = self.attachment_conforms_to().map_err(|_| EnvelopeError::InvalidAttachment)?;
         let assertion = Assertion::new_attachment(payload, vendor.as_str(), conforms_to.as_deref());
         let e: Envelope = assertion.to_envelope();
         if !e.is_equivalent_to(&self.clone().to_envelope()) {
@@ -142,6 +144,9 @@ impl Envelope {
         Result::Ok(matching_assertions)
     }
 
+    /// Returns all of the envelope's attachments.
+    ///
+    /// Returns an error if any of the attachments are invalid.
     pub fn attachments(&self) -> Result<Vec<Self>> {
         self.attachments_with_vendor_and_conforms_to(None::<&str>, None::<&str>)
     }
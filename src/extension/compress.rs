@@ -1,8 +1,32 @@
 use anyhow::{bail, Result};
-use bc_components::{Compressed, DigestProvider};
+use bc_components::{Compressed, Digest, DigestProvider};
 use dcbor::prelude::*;
+use miniz_oxide::inflate::TINFLStatus;
 
-use crate::{Envelope, EnvelopeError, base::envelope::EnvelopeCase};
+use crate::{Assertion, Envelope, EnvelopeError, EnvelopeLimits, base::envelope::EnvelopeCase};
+
+/// The trade-off between speed and size used when compressing an envelope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CompressionLevel {
+    /// The fastest compression, at the cost of size.
+    Fast,
+    /// The default balance of speed and size.
+    #[default]
+    Default,
+    /// The smallest output, at the cost of speed.
+    Best,
+}
+
+impl CompressionLevel {
+    /// The DEFLATE compression level used for this setting.
+    pub fn deflate_level(&self) -> u8 {
+        match self {
+            CompressionLevel::Fast => 1,
+            CompressionLevel::Default => 6,
+            CompressionLevel::Best => 9,
+        }
+    }
+}
 
 /// Support for compressing and uncompressing envelopes.
 impl Envelope {
@@ -10,13 +34,35 @@ impl Envelope {
     ///
     /// Returns the same envelope if it is already compressed.
     pub fn compress(&self) -> Result<Self> {
+        self.compress_with_level(CompressionLevel::Default)
+    }
+
+    /// Returns the compressed variant of this envelope, or this envelope
+    /// unchanged if compressing it would not make its encoding smaller.
+    ///
+    /// Tiny or high-entropy envelopes usually don't benefit from compression.
+    /// Callers can check `is_compressed()` on the result to learn whether
+    /// compression was worthwhile.
+    pub fn compress_if_smaller(&self) -> Result<Self> {
+        let compressed = self.compress()?;
+        if compressed.tagged_cbor_data_len() >= self.tagged_cbor_data_len() {
+            return Ok(self.clone());
+        }
+        Ok(compressed)
+    }
+
+    /// Returns the compressed variant of this envelope, using the given
+    /// compression level.
+    ///
+    /// Returns the same envelope if it is already compressed.
+    pub fn compress_with_level(&self, level: CompressionLevel) -> Result<Self> {
         match self.case() {
             EnvelopeCase::Compressed(_) => Ok(self.clone()),
             #[cfg(feature = "encrypt")]
             EnvelopeCase::Encrypted(_) => bail!(EnvelopeError::AlreadyEncrypted),
             EnvelopeCase::Elided(_) => bail!(EnvelopeError::AlreadyElided),
             _ => {
-                let compressed = Compressed::from_uncompressed_data(self.tagged_cbor().to_cbor_data(), Some(self.digest().into_owned()));
+                let compressed = compress_data(self.tagged_cbor_data(), level, self.digest().into_owned())?;
                 Ok(compressed.try_into()?)
             },
         }
@@ -24,15 +70,27 @@ impl Envelope {
 
     /// Returns the uncompressed variant of this envelope.
     ///
-    /// Returns the same envelope if it is already uncompressed.
+    /// The envelope is uncompressed subject to the default [`EnvelopeLimits`].
     pub fn uncompress(&self) -> Result<Self> {
+        self.uncompress_with_limits(&EnvelopeLimits::default())
+    }
+
+    /// Returns the uncompressed variant of this envelope, subject to the given
+    /// limits.
+    ///
+    /// The size declared by the compressed element is checked against the
+    /// limits before any data is inflated, and inflation stops as soon as the
+    /// output exceeds the declared size. The checksum of the inflated data is
+    /// verified before it is decoded, and the digest of the decoded envelope
+    /// is verified after.
+    pub fn uncompress_with_limits(&self, limits: &EnvelopeLimits) -> Result<Self> {
         if let EnvelopeCase::Compressed(compressed) = self.case() {
             if let Some(digest) = compressed.digest_ref_opt() {
                 if digest != self.digest().as_ref() {
                     bail!(EnvelopeError::InvalidDigest);
                 }
-                let uncompressed_data = compressed.uncompress()?;
-                let envelope = Envelope::from_tagged_cbor_data(uncompressed_data)?;
+                let uncompressed_data = inflate(compressed, limits)?;
+                let envelope = Envelope::from_tagged_cbor_data_with_limits(uncompressed_data, limits)?;
                 if envelope.digest().as_ref() != digest {
                     bail!(EnvelopeError::InvalidDigest);
                 }
@@ -61,11 +119,160 @@ impl Envelope {
     ///
     /// Returns the same envelope if its subject is already uncompressed.
     pub fn uncompress_subject(&self) -> Result<Self> {
+        self.uncompress_subject_with_limits(&EnvelopeLimits::default())
+    }
+
+    /// Returns this envelope with its subject uncompressed, subject to the
+    /// given limits.
+    ///
+    /// Returns the same envelope if its subject is already uncompressed.
+    pub fn uncompress_subject_with_limits(&self, limits: &EnvelopeLimits) -> Result<Self> {
         if self.subject().is_compressed() {
-            let subject = self.subject().uncompress()?;
+            let subject = self.subject().uncompress_with_limits(limits)?;
             Ok(self.replace_subject(subject))
         } else {
             Ok(self.clone())
         }
     }
+
+    /// Returns this envelope with every leaf compressed individually.
+    ///
+    /// Nodes, wrapped envelopes, and assertions are left uncompressed as
+    /// structural scaffolding, so parts of the tree can still be elided,
+    /// encrypted, or revealed individually. Obscured (elided, encrypted, or
+    /// already compressed) elements are left unchanged. The digest of the
+    /// result is the same as the digest of this envelope.
+    pub fn compress_tree(&self) -> Result<Self> {
+        self.transform_tree(&|envelope| match envelope.case() {
+            EnvelopeCase::Leaf { .. } => envelope.compress(),
+            _ => Ok(envelope.clone()),
+        })
+    }
+
+    /// Returns this envelope with every compressed element uncompressed.
+    ///
+    /// This is the inverse of [`Envelope::compress_tree`], and also
+    /// uncompresses elements that were compressed as a whole. Elided and
+    /// encrypted elements are left unchanged.
+    pub fn uncompress_tree(&self) -> Result<Self> {
+        self.transform_tree(&|envelope| match envelope.case() {
+            EnvelopeCase::Compressed(_) => envelope.uncompress()?.uncompress_tree(),
+            _ => Ok(envelope.clone()),
+        })
+    }
+
+    /// Returns this envelope with every compressed element uncompressed.
+    ///
+    /// This is the same as [`Envelope::uncompress_tree`].
+    pub fn uncompress_all(&self) -> Result<Self> {
+        self.uncompress_tree()
+    }
+
+    /// Returns this envelope with the object of every assertion whose encoding
+    /// is larger than `threshold` bytes compressed.
+    ///
+    /// Assertions are found throughout the envelope, including inside wrapped
+    /// envelopes and on subjects, predicates, and small objects. Objects that
+    /// are already obscured (elided, encrypted, or compressed) are left
+    /// unchanged. A threshold of `0` compresses every object. The digest of
+    /// the result is the same as the digest of this envelope.
+    pub fn compress_assertion_objects_larger_than(&self, threshold: usize) -> Result<Self> {
+        match self.case() {
+            EnvelopeCase::Node { subject, assertions, .. } => {
+                let subject = subject.compress_assertion_objects_larger_than(threshold)?;
+                let assertions = assertions.iter()
+                    .map(|assertion| assertion.compress_assertion_objects_larger_than(threshold))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Self::new_with_unchecked_assertions(subject, assertions))
+            },
+            EnvelopeCase::Wrapped { envelope, .. } => {
+                Ok(Self::new_wrapped(envelope.compress_assertion_objects_larger_than(threshold)?))
+            },
+            EnvelopeCase::Assertion(assertion) => {
+                let predicate = assertion.predicate().compress_assertion_objects_larger_than(threshold)?;
+                let object = assertion.object();
+                let object = if object.is_obscured() {
+                    object
+                } else if object.tagged_cbor_data_len() > threshold {
+                    object.compress()?
+                } else {
+                    object.compress_assertion_objects_larger_than(threshold)?
+                };
+                Ok(Self::new_with_assertion(Assertion::new(predicate, object)))
+            },
+            _ => Ok(self.clone()),
+        }
+    }
+
+    /// Rebuilds the structure of this envelope, applying `transform` to each
+    /// element that is not a node, wrapped envelope, or assertion.
+    fn transform_tree(&self, transform: &dyn Fn(&Self) -> Result<Self>) -> Result<Self> {
+        match self.case() {
+            EnvelopeCase::Node { subject, assertions, .. } => {
+                let subject = subject.transform_tree(transform)?;
+                let assertions = assertions.iter()
+                    .map(|assertion| assertion.transform_tree(transform))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Self::new_with_unchecked_assertions(subject, assertions))
+            },
+            EnvelopeCase::Wrapped { envelope, .. } => Ok(Self::new_wrapped(envelope.transform_tree(transform)?)),
+            EnvelopeCase::Assertion(assertion) => {
+                let predicate = assertion.predicate().transform_tree(transform)?;
+                let object = assertion.object().transform_tree(transform)?;
+                Ok(Self::new_with_assertion(Assertion::new(predicate, object)))
+            },
+            _ => transform(self),
+        }
+    }
+}
+
+/// Compresses the data at the given level.
+///
+/// As with `Compressed::from_uncompressed_data`, the data is stored
+/// uncompressed if compressing it would not make it smaller.
+fn compress_data(uncompressed_data: Vec<u8>, level: CompressionLevel, digest: Digest) -> Result<Compressed> {
+    let checksum = bc_crypto::hash::crc32(&uncompressed_data);
+    let uncompressed_size = uncompressed_data.len();
+    let compressed_data = miniz_oxide::deflate::compress_to_vec(&uncompressed_data, level.deflate_level());
+    let data = if !compressed_data.is_empty() && compressed_data.len() < uncompressed_size {
+        compressed_data
+    } else {
+        uncompressed_data
+    };
+    Compressed::new(checksum, uncompressed_size, data, Some(digest))
+}
+
+/// Inflates the data of a compressed element without trusting the size it
+/// declares.
+fn inflate(compressed: &Compressed, limits: &EnvelopeLimits) -> Result<Vec<u8>> {
+    let invalid = || EnvelopeError::InvalidFormat { message: "malformed compressed element".to_string() };
+    let elements = compressed.untagged_cbor().try_into_array().map_err(|_| invalid())?;
+    if elements.len() < 3 {
+        bail!(invalid());
+    }
+    let checksum: u32 = elements[0].clone().try_into().map_err(|_| invalid())?;
+    let declared: usize = elements[1].clone().try_into().map_err(|_| invalid())?;
+    let compressed_data = elements[2].clone().try_into_byte_string().map_err(|_| invalid())?;
+
+    if declared > limits.max_uncompressed_size {
+        bail!(EnvelopeError::UncompressedSizeExceeded { size: declared, limit: limits.max_uncompressed_size });
+    }
+
+    let uncompressed_data = if compressed_data.len() >= declared {
+        // The data was stored uncompressed.
+        compressed_data
+    } else {
+        miniz_oxide::inflate::decompress_to_vec_with_limit(&compressed_data, declared)
+            .map_err(|e| match e.status {
+                TINFLStatus::HasMoreOutput => EnvelopeError::UncompressedSizeMismatch { declared },
+                _ => EnvelopeError::Compression("corrupt compressed data".into()),
+            })?
+    };
+    if uncompressed_data.len() != declared {
+        bail!(EnvelopeError::UncompressedSizeMismatch { declared });
+    }
+    if bc_crypto::hash::crc32(&uncompressed_data) != checksum {
+        bail!(EnvelopeError::Compression("compressed data checksum mismatch".into()));
+    }
+    Ok(uncompressed_data)
 }
@@ -0,0 +1,150 @@
+//! Delegating access to an envelope encrypted to recipients.
+//!
+//! A recipient of an envelope encrypted with
+//! [`Envelope::encrypt_subject_to_recipients`] can authorize a delegate to
+//! decrypt it, without the relay that carries the envelope ever holding the
+//! content key. [`Envelope::prepare_delegation`] unseals the content key with
+//! the authorizer's private key and immediately seals it to the delegate, in a
+//! `"delegatedRecipient"` assertion whose object is signed by the authorizer
+//! and limits the delegation to the envelope's subject and to a period:
+//!
+//! ```text
+//! ENCRYPTED [
+//!     "delegatedRecipient": {
+//!         SealedMessage [
+//!             "delegationScope": Digest(c41a6372)
+//!             'validUntil': 2099-12-31
+//!         ]
+//!     } [
+//!         'signed': Signature
+//!     ]
+//!     'hasRecipient': SealedMessage
+//! ]
+//! ```
+//!
+//! The delegate calls [`Envelope::accept_delegation`], which finds a delegation
+//! signed by the authorizer, checks its period and scope, and replaces the
+//! delegation with a `'hasRecipient'` assertion, so the envelope can be
+//! decrypted with [`Envelope::decrypt_subject_to_recipient`] as usual.
+//!
+//! ```
+//! use bc_envelope::prelude::full::*;
+//! use bc_components::PrivateKeyBase;
+//!
+//! let alice = PrivateKeyBase::from_data(b"alice's key seed".to_vec());
+//! let bob = PrivateKeyBase::from_data(b"bob's key seed!!".to_vec());
+//!
+//! let message = Envelope::new("Meet at noon.").wrap_envelope();
+//! let encrypted = message.encrypt_subject_to_recipient(&alice.schnorr_public_key_base())?;
+//!
+//! // Alice authorizes Bob to read the message until the end of 2099.
+//! let delegated = encrypted.prepare_delegation(&alice, &bob.schnorr_public_key_base(), &Date::from_string("2099-12-31")?)?;
+//!
+//! // Bob checks that Alice authorized the delegation, and reads the message.
+//! let accepted = delegated.accept_delegation(&bob, &alice.schnorr_public_key_base())?;
+//! let decrypted = accepted.decrypt_subject_to_recipient(&bob)?.unwrap_envelope()?;
+//! assert_eq!(decrypted.extract_subject::<String>()?, "Meet at noon.");
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{bail, Result};
+use bc_components::{Digest, DigestProvider, Encrypter, PrivateKeyBase, SealedMessage, SymmetricKey, Verifier};
+use dcbor::{prelude::*, Date};
+
+use crate::{extension::known_values, Envelope, EnvelopeError};
+
+/// The predicate of the assertion that carries a delegation.
+///
+/// This and [`DELEGATION_SCOPE`] are strings rather than known values, as
+/// they are not in the known values registry.
+pub const DELEGATED_RECIPIENT: &str = "delegatedRecipient";
+
+/// The predicate of the assertion of a delegation that records the digest of
+/// the subject it gives access to.
+pub const DELEGATION_SCOPE: &str = "delegationScope";
+
+/// Support for delegating access to an envelope encrypted to recipients.
+impl Envelope {
+    /// Returns this envelope with a `"delegatedRecipient"` assertion
+    /// authorizing `delegate` to decrypt its subject until `valid_until`.
+    ///
+    /// The content key is unsealed from the envelope's `'hasRecipient'`
+    /// assertions with the authorizer's private key and sealed to the
+    /// delegate. The delegation is signed by the authorizer, and is limited
+    /// to the envelope's subject.
+    ///
+    /// - Throws: `EnvelopeError::NotEncrypted` if the subject is not encrypted,
+    /// and `EnvelopeError::UnknownRecipient` if the authorizer is not one of
+    /// the envelope's recipients.
+    pub fn prepare_delegation(
+        &self,
+        authorizer: &PrivateKeyBase,
+        delegate: &dyn Encrypter,
+        valid_until: &Date,
+    ) -> Result<Self> {
+        if !self.is_subject_encrypted() {
+            bail!(EnvelopeError::NotEncrypted);
+        }
+        let content_key_data = self.recipients()?
+            .iter()
+            .find_map(|sealed_message| sealed_message.decrypt(authorizer).ok())
+            .ok_or(EnvelopeError::UnknownRecipient)?;
+        let content_key = SymmetricKey::from_tagged_cbor_data(content_key_data)?;
+        let sealed_message = Self::seal_content_key(delegate, &content_key, None, None);
+        let authorization = Envelope::new(sealed_message)
+            .add_assertion(DELEGATION_SCOPE, self.subject().digest().into_owned())
+            .add_assertion(known_values::VALID_UNTIL, valid_until.clone())
+            .sign(authorizer);
+        Ok(self.add_assertion(DELEGATED_RECIPIENT, authorization))
+    }
+
+    /// Returns this envelope with the delegation to `delegate` replaced by a
+    /// `'hasRecipient'` assertion, so that the delegate can decrypt it with
+    /// [`Envelope::decrypt_subject_to_recipient`].
+    ///
+    /// Of the delegations to `delegate`, the first signed by `authorizer` is
+    /// accepted. It must not have expired, and must be for this envelope's
+    /// subject.
+    ///
+    /// - Throws: `EnvelopeError::UnknownRecipient` if there is no delegation
+    /// to `delegate`, `EnvelopeError::UnverifiedSignature` if none is signed
+    /// by `authorizer`, `EnvelopeError::ExpiredDelegation` if it has
+    /// expired, and `EnvelopeError::InvalidDelegation` if it is malformed or
+    /// for another subject.
+    pub fn accept_delegation(&self, delegate: &PrivateKeyBase, authorizer: &dyn Verifier) -> Result<Self> {
+        let candidates: Vec<_> = self
+            .assertions_with_predicate(DELEGATED_RECIPIENT)
+            .into_iter()
+            .filter_map(|assertion| {
+                let authorization = assertion.subject().as_object()?;
+                let sealed_message = authorization.unwrap_envelope().ok()?.extract_subject::<SealedMessage>().ok()?;
+                sealed_message.decrypt(delegate).ok()?;
+                Some((assertion, authorization, sealed_message))
+            })
+            .collect();
+        if candidates.is_empty() {
+            bail!(EnvelopeError::UnknownRecipient);
+        }
+        let (assertion, content, sealed_message) = candidates
+            .into_iter()
+            .find_map(|(assertion, authorization, sealed_message)| {
+                Some((assertion, authorization.verify(authorizer).ok()?, sealed_message))
+            })
+            .ok_or(EnvelopeError::UnverifiedSignature)?;
+
+        let valid_until = content.extract_object_for_predicate::<Date>(known_values::VALID_UNTIL)
+            .map_err(|_| EnvelopeError::InvalidDelegation { message: "the delegation has no 'validUntil' date".to_string() })?;
+        if valid_until <= Date::now() {
+            bail!(EnvelopeError::ExpiredDelegation { valid_until });
+        }
+        let scope = content.extract_object_for_predicate::<Digest>(DELEGATION_SCOPE)
+            .map_err(|_| EnvelopeError::InvalidDelegation { message: "the delegation has no scope".to_string() })?;
+        if scope != *self.subject().digest() {
+            bail!(EnvelopeError::InvalidDelegation { message: "the delegation is for another subject".to_string() });
+        }
+
+        Ok(self
+            .remove_assertion(assertion)
+            .add_assertion(known_values::HAS_RECIPIENT, sealed_message))
+    }
+}
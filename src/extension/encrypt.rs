@@ -1,10 +1,11 @@
 use std::borrow::Cow;
 
 use anyhow::{bail, Result};
-use bc_components::{SymmetricKey, Nonce, Digest, DigestProvider, tags};
+use bc_components::{SymmetricKey, Nonce, Digest, DigestProvider};
+use bc_rand::RandomNumberGenerator;
 use dcbor::prelude::*;
 
-use crate::{Envelope, EnvelopeError, base::envelope::EnvelopeCase};
+use crate::{Assertion, Envelope, EnvelopeError, base::{envelope::EnvelopeCase, random::{nonce_using, random_nonce}}};
 
 /// Support for encrypting and decrypting envelopes.
 impl Envelope {
@@ -23,8 +24,22 @@ impl Envelope {
         self.encrypt_subject_opt(key, None)
     }
 
+    /// Returns a new envelope with its subject encrypted, drawing the nonce
+    /// from the given random number generator.
+    ///
+    /// With a deterministic generator, such as
+    /// `bc_rand::make_fake_random_number_generator()`, the ciphertext is
+    /// reproducible, which is useful in tests. [`Envelope::encrypt_subject`]
+    /// instead draws the nonce from the process-wide random provider.
+    ///
+    /// - Throws: If the envelope is already encrypted.
+    pub fn encrypt_subject_using(&self, key: &SymmetricKey, rng: &mut impl RandomNumberGenerator) -> Result<Self> {
+        self.encrypt_subject_opt(key, Some(nonce_using(rng)))
+    }
+
     #[doc(hidden)]
     pub fn encrypt_subject_opt(&self, key: &SymmetricKey, test_nonce: Option<Nonce>) -> Result<Self> {
+        let nonce = test_nonce.unwrap_or_else(random_nonce);
         let result: Self;
         let original_digest: Cow<'_, Digest>;
 
@@ -33,35 +48,35 @@ impl Envelope {
                 if subject.is_encrypted() {
                     bail!(EnvelopeError::AlreadyEncrypted);
                 }
-                let encoded_cbor = subject.tagged_cbor().to_cbor_data();
+                let encoded_cbor = subject.tagged_cbor_data();
                 let digest = subject.digest();
-                let encrypted_message = key.encrypt_with_digest(encoded_cbor, digest, test_nonce);
+                let encrypted_message = key.encrypt_with_digest(encoded_cbor, digest, Some(&nonce));
                 let encrypted_subject = Self::new_with_encrypted(encrypted_message).unwrap();
                 result = Self::new_with_unchecked_assertions(encrypted_subject, assertions.clone());
                 original_digest = Cow::Borrowed(envelope_digest);
             }
-            EnvelopeCase::Leaf { cbor, digest } => {
-                let encoded_cbor = CBOR::to_tagged_value(tags::TAG_ENVELOPE, CBOR::to_tagged_value(tags::TAG_LEAF, cbor.clone())).to_cbor_data();
-                let encrypted_message = key.encrypt_with_digest(encoded_cbor, digest, test_nonce);
+            EnvelopeCase::Leaf { digest, .. } => {
+                let encoded_cbor = self.tagged_cbor_data();
+                let encrypted_message = key.encrypt_with_digest(encoded_cbor, digest, Some(&nonce));
                 result = Self::new_with_encrypted(encrypted_message).unwrap();
                 original_digest = Cow::Borrowed(digest);
             }
             EnvelopeCase::Wrapped { digest, .. } => {
-                let encoded_cbor = self.tagged_cbor().to_cbor_data();
-                let encrypted_message = key.encrypt_with_digest(encoded_cbor, digest, test_nonce);
+                let encoded_cbor = self.tagged_cbor_data();
+                let encrypted_message = key.encrypt_with_digest(encoded_cbor, digest, Some(&nonce));
                 result = Self::new_with_encrypted(encrypted_message).unwrap();
                 original_digest = Cow::Borrowed(digest);
             }
-            EnvelopeCase::KnownValue { value, digest } => {
-                let encoded_cbor = CBOR::to_tagged_value(tags::TAG_ENVELOPE, value.untagged_cbor()).to_cbor_data();
-                let encrypted_message = key.encrypt_with_digest(encoded_cbor, digest, test_nonce);
+            EnvelopeCase::KnownValue { digest, .. } => {
+                let encoded_cbor = self.tagged_cbor_data();
+                let encrypted_message = key.encrypt_with_digest(encoded_cbor, digest, Some(&nonce));
                 result = Self::new_with_encrypted(encrypted_message).unwrap();
                 original_digest = Cow::Borrowed(digest);
             }
             EnvelopeCase::Assertion(assertion) => {
                 let digest = assertion.digest();
-                let encoded_cbor = CBOR::to_tagged_value(tags::TAG_ENVELOPE, assertion.clone()).to_cbor_data();
-                let encrypted_message = key.encrypt_with_digest(encoded_cbor, &digest, test_nonce);
+                let encoded_cbor = self.tagged_cbor_data();
+                let encrypted_message = key.encrypt_with_digest(encoded_cbor, &digest, Some(&nonce));
                 result = Self::new_with_encrypted(encrypted_message).unwrap();
                 original_digest = digest;
             }
@@ -71,8 +86,8 @@ impl Envelope {
             #[cfg(feature = "compress")]
             EnvelopeCase::Compressed(compressed) => {
                 let digest = compressed.digest();
-                let encoded_cbor = CBOR::to_tagged_value(tags::TAG_ENVELOPE, compressed.tagged_cbor()).to_cbor_data();
-                let encrypted_message = key.encrypt_with_digest(encoded_cbor, &digest, test_nonce);
+                let encoded_cbor = self.tagged_cbor_data();
+                let encrypted_message = key.encrypt_with_digest(encoded_cbor, &digest, Some(&nonce));
                 result = Self::new_with_encrypted(encrypted_message).unwrap();
                 original_digest = digest;
             }
@@ -88,7 +103,7 @@ impl Envelope {
     pub fn decrypt_subject(&self, key: &SymmetricKey) -> Result<Self> {
         match self.subject().case() {
             EnvelopeCase::Encrypted(message) => {
-                let encoded_cbor = key.decrypt(message)?;
+                let encoded_cbor = key.decrypt(message).map_err(EnvelopeError::from)?;
                 let subject_digest = message.opt_digest().ok_or(EnvelopeError::MissingDigest)?;
                 let cbor = CBOR::try_from_data(encoded_cbor)?;
                 let result_subject = Self::from_tagged_cbor(cbor)?;
@@ -109,6 +124,60 @@ impl Envelope {
             _ => bail!(EnvelopeError::NotEncrypted)
         }
     }
+
+    /// Returns this envelope with every encrypted element that the given key
+    /// opens decrypted, wherever it is in the envelope.
+    ///
+    /// Elements that were encrypted inside other encrypted elements are
+    /// decrypted too. Encrypted elements that the key does not open are left
+    /// unchanged. The digest of the result is the same as the digest of this
+    /// envelope.
+    ///
+    /// Returns `EnvelopeError::InvalidDigest` if an element the key opens
+    /// does not have the digest it declares.
+    pub fn decrypt_all(&self, key: &SymmetricKey) -> Result<Self> {
+        match self.case() {
+            EnvelopeCase::Node { subject, assertions, .. } => {
+                let subject = subject.decrypt_all(key)?;
+                let assertions = assertions.iter()
+                    .map(|assertion| assertion.decrypt_all(key))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Self::new_with_unchecked_assertions(subject, assertions))
+            },
+            EnvelopeCase::Wrapped { envelope, .. } => Ok(Self::new_wrapped(envelope.decrypt_all(key)?)),
+            EnvelopeCase::Assertion(assertion) => {
+                let predicate = assertion.predicate().decrypt_all(key)?;
+                let object = assertion.object().decrypt_all(key)?;
+                Ok(Self::new_with_assertion(Assertion::new(predicate, object)))
+            },
+            EnvelopeCase::Encrypted(message) => {
+                let Ok(encoded_cbor) = key.decrypt(message) else {
+                    return Ok(self.clone());
+                };
+                let result = Self::from_tagged_cbor(CBOR::try_from_data(encoded_cbor)?)?;
+                if result.digest() != self.digest() {
+                    bail!(EnvelopeError::InvalidDigest);
+                }
+                result.decrypt_all(key)
+            },
+            _ => Ok(self.clone()),
+        }
+    }
+
+    /// Returns this envelope with every encrypted element decrypted with the
+    /// given key.
+    ///
+    /// Like [`Envelope::decrypt_all`], but returns
+    /// `EnvelopeError::UndecryptedElements` if any encrypted elements remain
+    /// because the key does not open them.
+    pub fn decrypt_all_strict(&self, key: &SymmetricKey) -> Result<Self> {
+        let result = self.decrypt_all(key)?;
+        let count = result.stats().encrypted;
+        if count > 0 {
+            bail!(EnvelopeError::UndecryptedElements { count });
+        }
+        Ok(result)
+    }
 }
 
 impl Envelope {
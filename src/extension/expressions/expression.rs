@@ -26,6 +26,45 @@ impl std::fmt::Display for Expression {
     }
 }
 
+/// Support for building expressions directly as envelopes.
+impl Envelope {
+    /// Creates an envelope whose subject is the given function.
+    ///
+    /// Add arguments with [`Envelope::add_parameter`]. This builds the same
+    /// envelope as [`Expression::new`] and [`ExpressionBehavior::with_parameter`].
+    pub fn new_function(function: impl Into<Function>) -> Self {
+        Envelope::new(function.into())
+    }
+
+    /// Returns a new envelope with an assertion of the given parameter and
+    /// value added.
+    pub fn add_parameter(&self, parameter: impl Into<Parameter>, value: impl EnvelopeEncodable) -> Self {
+        self.add_assertion(parameter.into(), value)
+    }
+
+    /// Returns a new envelope with an assertion of the given parameter and
+    /// value added, if the value is not `None`.
+    pub fn add_optional_parameter(&self, parameter: impl Into<Parameter>, value: Option<impl EnvelopeEncodable>) -> Self {
+        match value {
+            Some(value) => self.add_parameter(parameter, value),
+            None => self.clone(),
+        }
+    }
+
+    /// Returns the argument for the given parameter, decoded as the given
+    /// type.
+    ///
+    /// Returns `EnvelopeError::NonexistentPredicate` if there is no such
+    /// parameter, and an error if there is more than one, or if the argument
+    /// is not the given type.
+    pub fn parameter_value<T>(&self, parameter: impl Into<Parameter>) -> Result<T>
+    where
+        T: TryFrom<CBOR, Error = Error> + 'static,
+    {
+        self.extract_object_for_predicate(parameter.into())
+    }
+}
+
 pub trait ExpressionBehavior {
     //
     // Composition
@@ -81,8 +120,7 @@ impl ExpressionBehavior for Expression {
         parameter: impl Into<Parameter>,
         value: impl EnvelopeEncodable,
     ) -> Self {
-        let assertion = Envelope::new_assertion(parameter.into(), value.into_envelope());
-        self.envelope = self.envelope.add_assertion_envelope(assertion).unwrap();
+        self.envelope = self.envelope.add_parameter(parameter, value);
         self
     }
 
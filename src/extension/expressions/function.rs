@@ -2,9 +2,9 @@ use anyhow::{bail, Error, Result};
 use bc_components::tags;
 use dcbor::prelude::*;
 
-use crate::{string_utils::StringUtils, Envelope, EnvelopeEncodable};
+use crate::{string_utils::{parse_registry_text, quoted, RegistryText, StringUtils}, Envelope, EnvelopeEncodable, EnvelopeError};
 
-use super::FunctionsStore;
+use super::{FunctionsStore, GLOBAL_FUNCTIONS};
 
 #[derive(Clone, Debug, Eq)]
 pub enum FunctionName {
@@ -165,18 +165,79 @@ impl CBORTaggedDecodable for Function {
 }
 
 impl Function {
-    fn description(&self, functions: Option<&FunctionsStore>) -> String {
+    /// Returns the function as it appears in envelope notation, without the
+    /// flanking `«` and `»`.
+    ///
+    /// A known function is shown by its name in `functions` if it has one
+    /// there, otherwise by its own name or value. To use the functions of a
+    /// format context, pass `Some(context.functions())`. The `Display` form
+    /// uses no store.
+    pub fn description(&self, functions: Option<&FunctionsStore>) -> String {
         match self {
             Function::Known(_, _) => {
                 FunctionsStore::name_for_function(self, functions)
             },
             Function::Named(name) => {
-                format!("\"{}\"", name.value())
+                quoted(name.value())
             },
         }
     }
 }
 
+impl Function {
+    /// Parses a function from its textual form, resolving names in the given
+    /// store.
+    ///
+    /// The accepted forms are:
+    ///
+    /// * `name`: the assigned name of a function in `functions`. A name that is not
+    ///   in the store is parsed as a named function.
+    /// * `"name"`: a named function. Within the quotes, `"` and `\` are escaped
+    ///   with `\`, and any other character, including spaces, may appear.
+    /// * `3` or `function:3`: a known function with the given value.
+    ///
+    /// Any form may be flanked by `«` and `»`, as in formatted envelopes.
+    ///
+    /// Parsing the `Display` form of a named function, of a known function without
+    /// a name, or of a known function in `functions` returns an equal function.
+    pub fn parse(text: &str, functions: Option<&FunctionsStore>) -> Result<Self> {
+        Self::parse_opt(text, functions, false)
+    }
+
+    /// Parses a function from its textual form, as [`Function::parse`] does, but
+    /// fails if a bare name is not in the given store.
+    pub fn parse_strict(text: &str, functions: Option<&FunctionsStore>) -> Result<Self> {
+        Self::parse_opt(text, functions, true)
+    }
+
+    fn parse_opt(text: &str, functions: Option<&FunctionsStore>, strict: bool) -> Result<Self> {
+        let Some(parsed) = parse_registry_text(text, "«", "»", "function:") else {
+            bail!(EnvelopeError::InvalidFormat { message: format!("invalid function: {}", text) });
+        };
+        match parsed {
+            RegistryText::Value(value) => Ok(functions
+                .map(|functions| functions.function_for_value(value))
+                .unwrap_or_else(|| Self::from(value))),
+            RegistryText::Quoted(name) => Ok(Self::new_named(&name)),
+            RegistryText::Name(name) => match functions.and_then(|functions| functions.function_named(name)) {
+                Some(function) => Ok(function.clone()),
+                None if strict => bail!(EnvelopeError::InvalidFormat { message: format!("unknown function: {}", name) }),
+                None => Ok(Self::new_named(name)),
+            },
+        }
+    }
+}
+
+impl std::str::FromStr for Function {
+    type Err = Error;
+
+    /// Parses a function, resolving names in the global store of known
+    /// functions. See [`Function::parse`].
+    fn from_str(text: &str) -> Result<Self> {
+        Self::parse(text, GLOBAL_FUNCTIONS.get().as_ref())
+    }
+}
+
 impl std::fmt::Display for Function {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.description(None))
@@ -37,15 +37,18 @@ pub struct LazyFunctions {
     data: Mutex<Option<FunctionsStore>>,
 }
 
+/// The functions in the global store from the start.
+pub(crate) const PREDEFINED_FUNCTIONS: &[Function] = &[
+    ADD,
+    SUB,
+    MUL,
+    DIV,
+];
+
 impl LazyFunctions {
     pub fn get(&self) -> std::sync::MutexGuard<'_, Option<FunctionsStore>> {
         self.init.call_once(|| {
-            let m = FunctionsStore::new([
-                ADD,
-                SUB,
-                MUL,
-                DIV,
-            ]);
+            let m = FunctionsStore::new(PREDEFINED_FUNCTIONS.iter().cloned());
             *self.data.lock().unwrap() = Some(m);
         });
         self.data.lock().unwrap()
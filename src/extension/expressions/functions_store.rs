@@ -20,6 +20,11 @@ impl FunctionsStore {
         Self { dict }
     }
 
+    /// Adds a known function to the store, so that it is shown by name in
+    /// envelope notation and can be looked up by name and value.
+    ///
+    /// A function with the same value replaces the existing one. Named
+    /// functions carry their own names, and are not added.
     pub fn insert(&mut self, function: Function) {
         Self::_insert(function, &mut self.dict);
     }
@@ -34,6 +39,26 @@ impl FunctionsStore {
             .unwrap_or_else(|| function.name())
     }
 
+    /// Returns the functions in the store, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &Function> {
+        self.dict.keys()
+    }
+
+    /// Returns the function with the given assigned name, if any.
+    pub fn function_named(&self, name: &str) -> Option<&Function> {
+        self.dict.iter()
+            .find(|(_, assigned_name)| assigned_name.as_str() == name)
+            .map(|(function, _)| function)
+    }
+
+    /// Returns the function with the given value, with its assigned name if it
+    /// is in the store.
+    pub fn function_for_value(&self, value: u64) -> Function {
+        self.dict.get_key_value(&Function::from(value))
+            .map(|(function, _)| function.clone())
+            .unwrap_or_else(|| Function::from(value))
+    }
+
     pub fn name_for_function(function: &Function, functions: Option<&Self>) -> String {
         functions
             .and_then(|functions| functions.assigned_name(function))
@@ -42,12 +67,12 @@ impl FunctionsStore {
     }
 
     fn _insert(function: Function, dict: &mut HashMap<Function, String>) {
-        match function {
-            Function::Known(_, _) => {
-                let name = function.name();
-                dict.insert(function, name);
-            }
-            _ => panic!(),
+        if let Function::Known(_, _) = function {
+            let name = function.name();
+            // Remove any existing entry, so that its key, which carries the
+            // old name, is replaced too.
+            dict.remove(&function);
+            dict.insert(function, name);
         }
     }
 }
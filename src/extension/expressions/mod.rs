@@ -1,3 +1,37 @@
+//! Expressions, and the requests and responses that carry them.
+//!
+//! # Requests and responses
+//!
+//! A client sends a request whose body is an expression, identified by an
+//! ARID. The server evaluates it and answers with a response that carries the
+//! same ARID, which the client uses to match the response to its request.
+//!
+//! ```
+//! use bc_envelope::prelude::full::*;
+//! use bc_components::ARID;
+//!
+//! // The client asks the server to add two numbers.
+//! let id = ARID::from_data([0xc6; 32]);
+//! let body = Expression::new(functions::ADD)
+//!     .with_parameter(parameters::LHS, 2)
+//!     .with_parameter(parameters::RHS, 3);
+//! let request_envelope = Envelope::new_request(&id, body);
+//! assert_eq!(request_envelope.digest().hex(), "8f78f22dbade8ccb388e213717abe0fa28d1352b0dc6b62ac92d98e4bc3c5f83");
+//!
+//! // The server evaluates the request.
+//! let request = Request::try_from(request_envelope)?;
+//! assert_eq!(request.function(), &functions::ADD);
+//! let lhs: i32 = request.extract_object_for_parameter(parameters::LHS)?;
+//! let rhs: i32 = request.extract_object_for_parameter(parameters::RHS)?;
+//! let response_envelope = Envelope::new_response(request.id(), lhs + rhs);
+//!
+//! // The client matches the response to the request and reads the result.
+//! assert_eq!(response_envelope.response_id()?, id);
+//! let response = Response::try_from(response_envelope)?;
+//! assert_eq!(response.extract_result::<i32>()?, 5);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
 mod function;
 pub use function::Function;
 
@@ -1,9 +1,9 @@
 use anyhow::{bail, Error, Result};
 use bc_components::tags;
 use dcbor::prelude::*;
-use crate::{string_utils::StringUtils, Envelope, EnvelopeEncodable};
+use crate::{string_utils::{parse_registry_text, quoted, RegistryText, StringUtils}, Envelope, EnvelopeEncodable, EnvelopeError};
 
-use super::ParametersStore;
+use super::{ParametersStore, GLOBAL_PARAMETERS};
 
 #[derive(Clone, Debug, Eq)]
 pub enum ParameterName {
@@ -155,18 +155,79 @@ impl CBORTaggedDecodable for Parameter {
 }
 
 impl Parameter {
-    fn description(&self, parameters: Option<&ParametersStore>) -> String {
+    /// Returns the parameter as it appears in envelope notation, without the
+    /// flanking `❰` and `❱`.
+    ///
+    /// A known parameter is shown by its name in `parameters` if it has one
+    /// there, otherwise by its own name or value. To use the parameters of a
+    /// format context, pass `Some(context.parameters())`. The `Display` form
+    /// uses no store.
+    pub fn description(&self, parameters: Option<&ParametersStore>) -> String {
         match self {
             Parameter::Known(_, _) => {
                 ParametersStore::name_for_parameter(self, parameters)
             },
             Parameter::Named(name) => {
-                format!("\"{}\"", name.value())
+                quoted(name.value())
             },
         }
     }
 }
 
+impl Parameter {
+    /// Parses a parameter from its textual form, resolving names in the given
+    /// store.
+    ///
+    /// The accepted forms are:
+    ///
+    /// * `name`: the assigned name of a parameter in `parameters`. A name that is not
+    ///   in the store is parsed as a named parameter.
+    /// * `"name"`: a named parameter. Within the quotes, `"` and `\` are escaped
+    ///   with `\`, and any other character, including spaces, may appear.
+    /// * `3` or `parameter:3`: a known parameter with the given value.
+    ///
+    /// Any form may be flanked by `❰` and `❱`, as in formatted envelopes.
+    ///
+    /// Parsing the `Display` form of a named parameter, of a known parameter without
+    /// a name, or of a known parameter in `parameters` returns an equal parameter.
+    pub fn parse(text: &str, parameters: Option<&ParametersStore>) -> Result<Self> {
+        Self::parse_opt(text, parameters, false)
+    }
+
+    /// Parses a parameter from its textual form, as [`Parameter::parse`] does, but
+    /// fails if a bare name is not in the given store.
+    pub fn parse_strict(text: &str, parameters: Option<&ParametersStore>) -> Result<Self> {
+        Self::parse_opt(text, parameters, true)
+    }
+
+    fn parse_opt(text: &str, parameters: Option<&ParametersStore>, strict: bool) -> Result<Self> {
+        let Some(parsed) = parse_registry_text(text, "❰", "❱", "parameter:") else {
+            bail!(EnvelopeError::InvalidFormat { message: format!("invalid parameter: {}", text) });
+        };
+        match parsed {
+            RegistryText::Value(value) => Ok(parameters
+                .map(|parameters| parameters.parameter_for_value(value))
+                .unwrap_or_else(|| Self::from(value))),
+            RegistryText::Quoted(name) => Ok(Self::new_named(&name)),
+            RegistryText::Name(name) => match parameters.and_then(|parameters| parameters.parameter_named(name)) {
+                Some(parameter) => Ok(parameter.clone()),
+                None if strict => bail!(EnvelopeError::InvalidFormat { message: format!("unknown parameter: {}", name) }),
+                None => Ok(Self::new_named(name)),
+            },
+        }
+    }
+}
+
+impl std::str::FromStr for Parameter {
+    type Err = Error;
+
+    /// Parses a parameter, resolving names in the global store of known
+    /// parameters. See [`Parameter::parse`].
+    fn from_str(text: &str) -> Result<Self> {
+        Self::parse(text, GLOBAL_PARAMETERS.get().as_ref())
+    }
+}
+
 impl std::fmt::Display for Parameter {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.description(None))
@@ -25,14 +25,17 @@ pub struct LazyParameters {
     data: Mutex<Option<ParametersStore>>,
 }
 
+/// The parameters in the global store from the start.
+pub(crate) const PREDEFINED_PARAMETERS: &[Parameter] = &[
+    BLANK,
+    LHS,
+    RHS,
+];
+
 impl LazyParameters {
     pub fn get(&self) -> std::sync::MutexGuard<'_, Option<ParametersStore>> {
         self.init.call_once(|| {
-            let m = ParametersStore::new([
-                BLANK,
-                LHS,
-                RHS,
-            ]);
+            let m = ParametersStore::new(PREDEFINED_PARAMETERS.iter().cloned());
             *self.data.lock().unwrap() = Some(m);
         });
         self.data.lock().unwrap()
@@ -34,6 +34,26 @@ use super::Parameter;
             .unwrap_or_else(|| parameter.name())
     }
 
+    /// Returns the parameters in the store, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &Parameter> {
+        self.dict.keys()
+    }
+
+    /// Returns the parameter with the given assigned name, if any.
+    pub fn parameter_named(&self, name: &str) -> Option<&Parameter> {
+        self.dict.iter()
+            .find(|(_, assigned_name)| assigned_name.as_str() == name)
+            .map(|(parameter, _)| parameter)
+    }
+
+    /// Returns the parameter with the given value, with its assigned name if it
+    /// is in the store.
+    pub fn parameter_for_value(&self, value: u64) -> Parameter {
+        self.dict.get_key_value(&Parameter::from(value))
+            .map(|(parameter, _)| parameter.clone())
+            .unwrap_or_else(|| Parameter::from(value))
+    }
+
     pub fn name_for_parameter(parameter: &Parameter, parameters: Option<&Self>) -> String {
         parameters
             .and_then(|parameters| parameters.assigned_name(parameter))
@@ -67,6 +67,26 @@ impl Request {
     }
 }
 
+/// Support for building and reading request envelopes directly.
+impl Envelope {
+    /// Creates a request envelope with the given ID and body, as for a
+    /// [`Request`] with no note or date.
+    ///
+    /// The body is usually an expression, such as one built with
+    /// [`Envelope::new_function`].
+    pub fn new_request(id: impl AsRef<ARID>, body: impl EnvelopeEncodable) -> Self {
+        Envelope::new(CBOR::to_tagged_value(tags::TAG_REQUEST, id.as_ref().clone()))
+            .add_assertion(known_values::BODY, body)
+    }
+
+    /// Returns the ID of a request envelope.
+    pub fn request_id(&self) -> Result<ARID> {
+        self.subject().try_leaf()?
+            .try_into_expected_tagged_value(tags::TAG_REQUEST)?
+            .try_into()
+    }
+}
+
 impl ExpressionBehavior for Request {
     fn with_parameter(mut self, parameter: impl Into<Parameter>, value: impl EnvelopeEncodable) -> Self {
         self.body = self.body.with_parameter(parameter, value);
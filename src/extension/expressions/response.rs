@@ -38,6 +38,29 @@ impl Envelope {
     pub fn ok() -> Self {
         known_values::OK_VALUE.into_envelope()
     }
+
+    /// Creates a successful response envelope with the given ID, which
+    /// correlates it with the request of the same ID, and result.
+    pub fn new_response(id: impl AsRef<ARID>, result: impl EnvelopeEncodable) -> Self {
+        Response::new_success(id).with_result(result).into()
+    }
+
+    /// Creates a failed response envelope with the given ID, which correlates
+    /// it with the request of the same ID, and error.
+    pub fn new_error_response(id: impl AsRef<ARID>, error: impl EnvelopeEncodable) -> Self {
+        Response::new_failure(id).with_error(error).into()
+    }
+
+    /// Returns the ID of a response envelope: the ID of the request it
+    /// responds to.
+    ///
+    /// Returns an error if the envelope is not a response, or if it is an
+    /// early failure, which has no ID.
+    pub fn response_id(&self) -> Result<ARID> {
+        self.subject().try_leaf()?
+            .try_into_expected_tagged_value(tags::TAG_RESPONSE)?
+            .try_into()
+    }
 }
 
 impl Response {
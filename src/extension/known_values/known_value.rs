@@ -1,10 +1,12 @@
 use std::{fmt::{Formatter, Display}, borrow::Cow};
 
-use anyhow::{Result, Error};
+use anyhow::{bail, Result, Error};
 use bc_components::{tags, DigestProvider, Digest};
 use dcbor::prelude::*;
 
-use crate::{Envelope, EnvelopeEncodable};
+use crate::{string_utils::{parse_registry_text, RegistryText}, Envelope, EnvelopeEncodable, EnvelopeError};
+
+use super::{KnownValuesStore, KNOWN_VALUES};
 
 #[derive(Debug, Clone)]
 enum KnownValueName {
@@ -58,16 +60,77 @@ impl KnownValue {
 
     /// The human readable name.
     ///
-    /// Defaults to the numerical value if no name has been assigned.
+    /// If no name has been assigned, this is the name of the known value with
+    /// the same value in the global store of known values, or the numerical
+    /// value if there is none.
+    ///
+    /// A known value without an assigned name locks the global store, so a
+    /// thread holding the guard returned by `KNOWN_VALUES.get()` must use
+    /// [`KnownValue::name_in`] instead.
     pub fn name(&self) -> String {
+        match self.assigned_name {
+            Some(_) => self.name_in(None),
+            None => self.name_in(KNOWN_VALUES.get().as_ref()),
+        }
+    }
+
+    /// The human readable name, looking up a known value without an assigned
+    /// name in the given store rather than the global one.
+    pub fn name_in(&self, known_values: Option<&KnownValuesStore>) -> String {
         match &self.assigned_name {
             Some(KnownValueName::Static(name)) => name.to_string(),
             Some(KnownValueName::Dynamic(name)) => name.clone(),
-            None => self.value.to_string(),
+            None => known_values
+                .and_then(|known_values| known_values.assigned_name(self))
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| self.value.to_string()),
+        }
+    }
+
+    /// Returns the known value with the given assigned name in the global
+    /// store of known values, or `None` if there is none.
+    pub fn named(name: &str) -> Option<Self> {
+        KnownValuesStore::known_value_for_name(name, KNOWN_VALUES.get().as_ref())
+    }
+}
+
+impl KnownValue {
+    /// Parses a known value from its textual form, resolving names in the
+    /// given store.
+    ///
+    /// The accepted forms are:
+    ///
+    /// * `name`: the assigned name of a known value in `known_values`.
+    /// * `4` or `knownValue:4`: the known value with the given value, with its
+    ///   assigned name if it is in `known_values`.
+    ///
+    /// Either form may be flanked by `'`, as in formatted envelopes, and a
+    /// name that contains spaces, such as `'my value'`, must be.
+    ///
+    /// Parsing the `Display` form of a known value without a name, or of a
+    /// known value in `known_values`, returns an equal known value. Because a
+    /// known value cannot exist without its numeric value, a name that is not
+    /// in the store is always an error.
+    pub fn parse(text: &str, known_values: Option<&KnownValuesStore>) -> Result<Self> {
+        match parse_registry_text(text, "'", "'", "knownValue:") {
+            Some(RegistryText::Value(value)) => Ok(KnownValuesStore::known_value_for_raw_value(value, known_values)),
+            Some(RegistryText::Name(name)) => KnownValuesStore::known_value_for_name(name, known_values)
+                .ok_or_else(|| EnvelopeError::InvalidFormat { message: format!("unknown known value: {}", name) }.into()),
+            _ => bail!(EnvelopeError::InvalidFormat { message: format!("invalid known value: {}", text) }),
         }
     }
 }
 
+impl std::str::FromStr for KnownValue {
+    type Err = Error;
+
+    /// Parses a known value, resolving names in the global store of known
+    /// values. See [`KnownValue::parse`].
+    fn from_str(text: &str) -> Result<Self> {
+        Self::parse(text, KNOWN_VALUES.get().as_ref())
+    }
+}
+
 impl PartialEq for KnownValue {
     fn eq(&self, other: &Self) -> bool {
         self.value == other.value
@@ -82,13 +145,10 @@ impl std::hash::Hash for KnownValue {
     }
 }
 
+/// Displays the known value's name. See [`KnownValue::name`].
 impl Display for KnownValue {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match &self.assigned_name {
-            Some(KnownValueName::Static(name)) => write!(f, "{}", name),
-            Some(KnownValueName::Dynamic(name)) => write!(f, "{}", name),
-            None => write!(f, "{}", self.value),
-        }
+        write!(f, "{}", self.name())
     }
 }
 
@@ -0,0 +1,34 @@
+use bc_components::ARID;
+
+use crate::{Envelope, EnvelopeEncodable};
+
+use super::{known_values_registry as known_values, KnownValue};
+
+/// Support for adding assertions with standard known value predicates.
+impl Envelope {
+    /// Creates an assertion envelope with the given known value predicate and
+    /// object.
+    pub fn new_assertion_with_known_predicate(predicate: KnownValue, object: impl EnvelopeEncodable) -> Self {
+        Self::new_assertion(predicate, object)
+    }
+
+    /// Returns the result of adding a `'note'` assertion to the envelope.
+    pub fn add_note(&self, note: &str) -> Self {
+        self.add_assertion(known_values::NOTE, note)
+    }
+
+    /// Returns the result of adding an `'id'` assertion to the envelope.
+    pub fn add_id(&self, id: &ARID) -> Self {
+        self.add_assertion(known_values::ID, id.clone())
+    }
+
+    /// Returns the result of adding a `'hasName'` assertion to the envelope.
+    pub fn add_name(&self, name: &str) -> Self {
+        self.add_assertion(known_values::HAS_NAME, name)
+    }
+
+    /// Returns the result of adding a `'date'` assertion to the envelope.
+    pub fn add_date(&self, date: &dcbor::Date) -> Self {
+        self.add_assertion(known_values::DATE, date.clone())
+    }
+}
@@ -1,7 +1,7 @@
-use std::sync::{Once, Mutex};
+use std::sync::{Once, Mutex, MutexGuard};
 use paste::paste;
 
-use super::known_values_store::KnownValuesStore;
+use super::{known_value::KnownValue, known_values_store::KnownValuesStore};
 
 /// A macro that declares a known value at compile time.
 #[macro_export]
@@ -103,93 +103,100 @@ pub struct LazyKnownValues {
     data: Mutex<Option<KnownValuesStore>>,
 }
 
+/// The known values in the global store from the start.
+pub(crate) const PREDEFINED_KNOWN_VALUES: &[KnownValue] = &[
+    IS_A,
+    ID,
+    SIGNED,
+    NOTE,
+    HAS_RECIPIENT,
+    SSKR_SHARE,
+    CONTROLLER,
+    KEY,
+    DEREFERENCE_VIA,
+    ENTITY,
+    HAS_NAME,
+    LANGUAGE,
+    ISSUER,
+    HOLDER,
+    SALT,
+    DATE,
+    UNKNOWN_VALUE,
+    DIFF_EDITS,
+    VALID_FROM,
+    VALID_UNTIL,
+
+    ALLOW,
+    DENY,
+    ENDPOINT,
+    DELEGATE,
+    PROVENANCE,
+    PRIVATE_KEY,
+
+    PRIVILEGE_ALL,
+    PRIVILEGE_AUTH,
+    PRIVILEGE_SIGN,
+    PRIVILEGE_ENCRYPT,
+    PRIVILEGE_ELIDE,
+    PRIVILEGE_ISSUE,
+    PRIVILEGE_ACCESS,
+
+    PRIVILEGE_DELEGATE,
+    PRIVILEGE_VERIFY,
+    PRIVILEGE_UPDATE,
+    PRIVILEGE_TRANSFER,
+    PRIVILEGE_ELECT,
+    PRIVILEGE_BURN,
+    PRIVILEGE_REVOKE,
+
+    ATTACHMENT,
+    VENDOR,
+    CONFORMS_TO,
+
+    BODY,
+    RESULT,
+    ERROR,
+    OK_VALUE,
+    PROCESSING_VALUE,
+    SENDER,
+    SENDER_CONTINUATION,
+    RECIPIENT_CONTINUATION,
+    CONTENT,
+
+    SEED_TYPE,
+    PRIVATE_KEY_TYPE,
+    PUBLIC_KEY_TYPE,
+    MASTER_KEY_TYPE,
+
+    ASSET,
+    BITCOIN_VALUE,
+    ETHEREUM_VALUE,
+
+    NETWORK,
+    MAIN_NET_VALUE,
+    TEST_NET_VALUE,
+
+    BIP32_KEY_TYPE,
+    CHAIN_CODE,
+    DERIVATION_PATH_TYPE,
+    PARENT_PATH,
+    CHILDREN_PATH,
+    PARENT_FINGERPRINT,
+    PSBT_TYPE,
+    OUTPUT_DESCRIPTOR_TYPE,
+];
+
 impl LazyKnownValues {
-    pub fn get(&self) -> std::sync::MutexGuard<'_, Option<KnownValuesStore>> {
+    pub fn get(&self) -> MutexGuard<'_, Option<KnownValuesStore>> {
+        self.init();
+        self.data.lock().unwrap()
+    }
+
+    fn init(&self) {
         self.init.call_once(|| {
-            let m = KnownValuesStore::new([
-                IS_A,
-                ID,
-                SIGNED,
-                NOTE,
-                HAS_RECIPIENT,
-                SSKR_SHARE,
-                CONTROLLER,
-                KEY,
-                DEREFERENCE_VIA,
-                ENTITY,
-                HAS_NAME,
-                LANGUAGE,
-                ISSUER,
-                HOLDER,
-                SALT,
-                DATE,
-                UNKNOWN_VALUE,
-                DIFF_EDITS,
-                VALID_FROM,
-                VALID_UNTIL,
-
-                ALLOW,
-                DENY,
-                ENDPOINT,
-                DELEGATE,
-                PROVENANCE,
-                PRIVATE_KEY,
-
-                PRIVILEGE_ALL,
-                PRIVILEGE_AUTH,
-                PRIVILEGE_SIGN,
-                PRIVILEGE_ENCRYPT,
-                PRIVILEGE_ELIDE,
-                PRIVILEGE_ISSUE,
-                PRIVILEGE_ACCESS,
-
-                PRIVILEGE_DELEGATE,
-                PRIVILEGE_VERIFY,
-                PRIVILEGE_UPDATE,
-                PRIVILEGE_TRANSFER,
-                PRIVILEGE_ELECT,
-                PRIVILEGE_BURN,
-                PRIVILEGE_REVOKE,
-
-                ATTACHMENT,
-                VENDOR,
-                CONFORMS_TO,
-
-                BODY,
-                RESULT,
-                ERROR,
-                OK_VALUE,
-                PROCESSING_VALUE,
-                SENDER,
-                SENDER_CONTINUATION,
-                RECIPIENT_CONTINUATION,
-                CONTENT,
-
-                SEED_TYPE,
-                PRIVATE_KEY_TYPE,
-                PUBLIC_KEY_TYPE,
-                MASTER_KEY_TYPE,
-
-                ASSET,
-                BITCOIN_VALUE,
-                ETHEREUM_VALUE,
-
-                NETWORK,
-                MAIN_NET_VALUE,
-                TEST_NET_VALUE,
-
-                BIP32_KEY_TYPE,
-                CHAIN_CODE,
-                DERIVATION_PATH_TYPE,
-                PARENT_PATH,
-                CHILDREN_PATH,
-                PARENT_FINGERPRINT,
-                PSBT_TYPE,
-                OUTPUT_DESCRIPTOR_TYPE,
-            ]);
+            let m = KnownValuesStore::new(PREDEFINED_KNOWN_VALUES.iter().cloned());
             *self.data.lock().unwrap() = Some(m);
         });
-        self.data.lock().unwrap()
     }
 }
 
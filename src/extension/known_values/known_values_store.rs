@@ -29,6 +29,11 @@ impl KnownValuesStore {
         }
     }
 
+    /// Adds a known value to the store, so that it can be looked up by its raw
+    /// value and, if it has one, its assigned name.
+    ///
+    /// A known value with the same raw value replaces the existing one, and
+    /// the existing one's name no longer resolves to it.
     pub fn insert(&mut self, known_value: KnownValue) {
         Self::_insert(
             known_value,
@@ -46,7 +51,12 @@ impl KnownValuesStore {
     pub fn name(&self, known_value: KnownValue) -> String {
         self.assigned_name(&known_value)
             .map(|name| name.to_string())
-            .unwrap_or_else(|| known_value.name())
+            .unwrap_or_else(|| known_value.name_in(None))
+    }
+
+    /// Returns the known values in the store, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &KnownValue> {
+        self.known_values_by_raw_value.values()
     }
 
     pub fn known_value_named(&self, assigned_name: &str) -> Option<&KnownValue> {
@@ -70,7 +80,7 @@ impl KnownValuesStore {
         known_values
             .and_then(|known_values| known_values.assigned_name(&known_value))
             .map(|assigned_name| assigned_name.to_string())
-            .unwrap_or_else(|| known_value.name())
+            .unwrap_or_else(|| known_value.name_in(None))
     }
 
     fn _insert(
@@ -78,7 +88,11 @@ impl KnownValuesStore {
         known_values_by_raw_value: &mut HashMap<u64, KnownValue>,
         known_values_by_assigned_name: &mut HashMap<String, KnownValue>,
     ) {
-        known_values_by_raw_value.insert(known_value.value(), known_value.clone());
+        if let Some(old) = known_values_by_raw_value.insert(known_value.value(), known_value.clone()) {
+            if let Some(name) = old.assigned_name() {
+                known_values_by_assigned_name.remove(name);
+            }
+        }
         if let Some(name) = known_value.assigned_name() {
             known_values_by_assigned_name.insert(name.to_string(), known_value);
         }
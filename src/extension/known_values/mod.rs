@@ -7,3 +7,5 @@ pub use registry::*;
 
 pub mod known_values_store;
 pub use known_values_store::KnownValuesStore;
+
+pub mod known_value_assertions;
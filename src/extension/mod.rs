@@ -9,6 +9,8 @@ pub mod attachment;
 ///
 #[cfg(feature = "compress")]
 pub mod compress;
+#[cfg(feature = "compress")]
+pub use compress::CompressionLevel;
 
 ///
 /// Symmetric Encryption Extension
@@ -40,6 +42,14 @@ pub mod known_values;
 #[cfg(feature = "known_value")]
 pub use known_values::*;
 
+///
+/// Parity Extension
+///
+#[cfg(feature = "parity")]
+pub mod parity;
+#[cfg(feature = "parity")]
+pub use parity::{ParityLevel, RecoveryReport};
+
 ///
 /// Inclusion Proof Extension
 ///
@@ -52,12 +62,19 @@ pub mod proof;
 #[cfg(feature = "recipient")]
 pub mod recipient;
 
+///
+/// Recipient Delegation Extension
+///
+#[cfg(all(feature = "recipient", feature = "signature"))]
+pub mod delegation;
+
 ///
 /// Public Key Signing Extension
 ///
 #[cfg(feature = "signature")]
 pub mod signature;
-pub use signature::SignatureMetadata;
+#[cfg(feature = "signature")]
+pub use signature::{CeremonyIssue, CeremonyStep, SignatureMetadata};
 
 ///
 /// Salt Extension
@@ -77,6 +94,12 @@ pub mod ssh;
 #[cfg(feature = "sskr")]
 pub mod sskr;
 
+///
+/// Supersession Extension
+///
+#[cfg(feature = "known_value")]
+pub mod supersession;
+
 ///
 /// Types Extension
 ///
@@ -0,0 +1,419 @@
+use anyhow::{bail, Error, Result};
+use dcbor::prelude::*;
+
+use crate::{Envelope, EnvelopeError};
+
+/// The predicate of the assertion that carries an envelope's parity.
+pub const PARITY: &str = "parity";
+
+/// The number of bytes in a Reed-Solomon codeword over GF(256).
+const CODEWORD_SIZE: usize = 255;
+
+/// The bytes that begin the encoding of the parity assertion: a map of one
+/// entry, whose key is the leaf `"parity"`.
+const PARITY_ASSERTION_PREFIX: &[u8] = b"\xa1\xd8\xc9\x66parity";
+
+/// The bytes that begin the encoding of an envelope with parity: a tagged
+/// node of two elements, the wrapped envelope and the parity assertion.
+const PARITY_NODE_PREFIX: &[u8] = b"\xd8\xc8\x82";
+
+/// The amount of parity added by [`Envelope::add_parity`].
+///
+/// The protected content is divided into blocks, and each block is stored
+/// in a Reed-Solomon codeword of 255 bytes, some of which are parity. Each
+/// block can be corrected for up to half as many byte errors as it has
+/// parity bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ParityLevel {
+    /// 16 parity bytes per codeword (about 7% overhead), correcting up to 8
+    /// byte errors in each block of 239 bytes.
+    Low,
+    /// 32 parity bytes per codeword (about 14% overhead), correcting up to 16
+    /// byte errors in each block of 223 bytes.
+    #[default]
+    Medium,
+    /// 64 parity bytes per codeword (about 34% overhead), correcting up to 32
+    /// byte errors in each block of 191 bytes.
+    High,
+}
+
+impl ParityLevel {
+    /// The number of parity bytes in each codeword.
+    pub fn parity_bytes(&self) -> usize {
+        match self {
+            ParityLevel::Low => 16,
+            ParityLevel::Medium => 32,
+            ParityLevel::High => 64,
+        }
+    }
+
+    /// The number of content bytes in each codeword.
+    pub fn block_size(&self) -> usize {
+        CODEWORD_SIZE - self.parity_bytes()
+    }
+
+    fn from_parity_bytes(parity_bytes: usize) -> Result<Self> {
+        match parity_bytes {
+            16 => Ok(ParityLevel::Low),
+            32 => Ok(ParityLevel::Medium),
+            64 => Ok(ParityLevel::High),
+            _ => bail!(EnvelopeError::InvalidFormat { message: format!("unsupported parity: {} bytes per codeword", parity_bytes) }),
+        }
+    }
+}
+
+/// The outcome of [`Envelope::recover_with_parity`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecoveryReport {
+    blocks: usize,
+    corrected_blocks: usize,
+    corrections: usize,
+}
+
+impl RecoveryReport {
+    /// Returns the number of blocks the protected content was divided into.
+    pub fn blocks(&self) -> usize {
+        self.blocks
+    }
+
+    /// Returns the number of blocks that contained errors.
+    pub fn corrected_blocks(&self) -> usize {
+        self.corrected_blocks
+    }
+
+    /// Returns the number of bytes corrected, in both the content and the
+    /// parity.
+    pub fn corrections(&self) -> usize {
+        self.corrections
+    }
+
+    /// Returns whether the data was recovered without any corrections.
+    pub fn is_intact(&self) -> bool {
+        self.corrections == 0
+    }
+}
+
+/// The object of a parity assertion: the level, the size of the protected
+/// content, and the parity bytes of every block, in order.
+struct Parity {
+    level: ParityLevel,
+    size: usize,
+    bytes: Vec<u8>,
+}
+
+impl Parity {
+    fn new(level: ParityLevel, content: &[u8]) -> Self {
+        let bytes = content
+            .chunks(level.block_size())
+            .flat_map(|block| reed_solomon::encode(block, level.parity_bytes()))
+            .collect();
+        Self { level, size: content.len(), bytes }
+    }
+}
+
+impl From<Parity> for CBOR {
+    fn from(value: Parity) -> Self {
+        vec![
+            CBOR::from(value.level.parity_bytes()),
+            CBOR::from(value.size),
+            CBOR::to_byte_string(value.bytes),
+        ].into()
+    }
+}
+
+impl TryFrom<CBOR> for Parity {
+    type Error = Error;
+
+    fn try_from(cbor: CBOR) -> Result<Self> {
+        let elements = cbor.try_into_array()?;
+        let [parity_bytes, size, bytes]: [CBOR; 3] = elements.try_into()
+            .map_err(|_| EnvelopeError::InvalidFormat { message: "parity must be an array of three elements".to_string() })?;
+        let level = ParityLevel::from_parity_bytes(parity_bytes.try_into()?)?;
+        let size: usize = size.try_into()?;
+        let bytes = bytes.try_into_byte_string()?;
+        if bytes.len() != size.div_ceil(level.block_size()) * level.parity_bytes() {
+            bail!(EnvelopeError::InvalidFormat { message: "parity does not match the size of the protected content".to_string() });
+        }
+        Ok(Self { level, size, bytes })
+    }
+}
+
+/// Support for protecting stored envelopes against corruption.
+impl Envelope {
+    /// Returns this envelope wrapped, with a `'parity'` assertion carrying
+    /// Reed-Solomon parity computed over the wrapped envelope's tagged CBOR.
+    ///
+    /// The parity does not affect the wrapped envelope, which can be
+    /// retrieved with `unwrap_envelope()`. If the encoding of the result is
+    /// later damaged, [`Envelope::recover_with_parity`] can correct it.
+    pub fn add_parity(&self, level: ParityLevel) -> Self {
+        let parity = Parity::new(level, &self.tagged_cbor_data());
+        self.wrap_envelope().add_assertion(PARITY, CBOR::from(parity))
+    }
+
+    /// Decodes an envelope with parity, as returned by
+    /// [`Envelope::add_parity`], from its tagged CBOR, correcting any byte
+    /// errors in the protected envelope.
+    ///
+    /// The parity assertion is located by its encoding at the end of the
+    /// data, so it is found even when the protected envelope cannot be
+    /// decoded. The envelope is then always checked against the parity, as
+    /// damage to a leaf's contents can leave it decodable but different.
+    ///
+    /// Returns the envelope with parity, and a report of the corrections
+    /// applied. Returns `EnvelopeError::ParityNotFound` if the data does not
+    /// end with a readable parity assertion, and
+    /// `EnvelopeError::ParityExceeded` if a block has more byte errors than
+    /// its parity can correct.
+    pub fn recover_with_parity(data: impl AsRef<[u8]>) -> Result<(Self, RecoveryReport)> {
+        let data = data.as_ref();
+        let parity_start = data
+            .windows(PARITY_ASSERTION_PREFIX.len())
+            .rposition(|window| window == PARITY_ASSERTION_PREFIX)
+            .ok_or(EnvelopeError::ParityNotFound)?;
+        let parity = Self::parity_from_assertion_data(&data[parity_start..])
+            .map_err(|_| EnvelopeError::ParityNotFound)?;
+        if parity_start < PARITY_NODE_PREFIX.len() || parity_start - PARITY_NODE_PREFIX.len() != parity.size {
+            bail!(EnvelopeError::InvalidFormat { message: "the protected content does not have the size recorded with its parity".to_string() });
+        }
+
+        let mut content = data[PARITY_NODE_PREFIX.len()..parity_start].to_vec();
+        let parity_bytes = parity.level.parity_bytes();
+        let mut report = RecoveryReport { blocks: 0, corrected_blocks: 0, corrections: 0 };
+        for (index, (block, block_parity)) in content
+            .chunks_mut(parity.level.block_size())
+            .zip(parity.bytes.chunks(parity_bytes))
+            .enumerate()
+        {
+            let mut codeword = [&*block, block_parity].concat();
+            let corrections = reed_solomon::correct(&mut codeword, parity_bytes)
+                .ok_or(EnvelopeError::ParityExceeded { block: index, capacity: parity_bytes / 2 })?;
+            block.copy_from_slice(&codeword[..block.len()]);
+            report.blocks += 1;
+            if corrections > 0 {
+                report.corrected_blocks += 1;
+                report.corrections += corrections;
+            }
+        }
+
+        let envelope = Self::from_tagged_cbor_data(content)?;
+        Ok((envelope.add_parity(parity.level), report))
+    }
+
+    fn parity_from_assertion_data(data: &[u8]) -> Result<Parity> {
+        let assertion = Self::from_untagged_cbor(CBOR::try_from_data(data)?)?;
+        assertion.as_object()
+            .ok_or(EnvelopeError::NotAssertion)?
+            .try_leaf()?
+            .try_into()
+    }
+}
+
+/// A systematic Reed-Solomon code over GF(256), with the primitive
+/// polynomial x⁸ + x⁴ + x³ + x² + 1 and generator roots α⁰ through αⁿ⁻¹.
+///
+/// A codeword is the content followed by its parity, and is read as a
+/// polynomial whose first byte is the coefficient of the highest power.
+mod reed_solomon {
+    const PRIMITIVE_POLYNOMIAL: u16 = 0x11d;
+
+    const fn tables() -> ([u8; 512], [u8; 256]) {
+        let mut exp = [0u8; 512];
+        let mut log = [0u8; 256];
+        let mut x: u16 = 1;
+        let mut i = 0;
+        while i < 255 {
+            exp[i] = x as u8;
+            exp[i + 255] = x as u8;
+            log[x as usize] = i as u8;
+            x <<= 1;
+            if x & 0x100 != 0 {
+                x ^= PRIMITIVE_POLYNOMIAL;
+            }
+            i += 1;
+        }
+        (exp, log)
+    }
+
+    const EXP: [u8; 512] = tables().0;
+    const LOG: [u8; 256] = tables().1;
+
+    fn mul(a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            return 0;
+        }
+        EXP[LOG[a as usize] as usize + LOG[b as usize] as usize]
+    }
+
+    fn div(a: u8, b: u8) -> u8 {
+        if a == 0 {
+            return 0;
+        }
+        EXP[LOG[a as usize] as usize + 255 - LOG[b as usize] as usize]
+    }
+
+    /// Returns α raised to the given power, which may be negative.
+    fn alpha_pow(power: isize) -> u8 {
+        EXP[power.rem_euclid(255) as usize]
+    }
+
+    /// Evaluates a polynomial whose first coefficient is of the highest
+    /// power.
+    fn eval_high_first(polynomial: &[u8], x: u8) -> u8 {
+        polynomial.iter().fold(0, |acc, &coefficient| mul(acc, x) ^ coefficient)
+    }
+
+    /// Evaluates a polynomial whose first coefficient is of the lowest
+    /// power.
+    fn eval_low_first(polynomial: &[u8], x: u8) -> u8 {
+        polynomial.iter().rev().fold(0, |acc, &coefficient| mul(acc, x) ^ coefficient)
+    }
+
+    /// Returns the parity of the content: the remainder of dividing the
+    /// content, shifted by the parity size, by the generator polynomial.
+    pub(super) fn encode(content: &[u8], parity_size: usize) -> Vec<u8> {
+        let mut generator = vec![1u8];
+        for i in 0..parity_size {
+            let root = alpha_pow(i as isize);
+            let mut next = vec![0u8; generator.len() + 1];
+            for (j, &coefficient) in generator.iter().enumerate() {
+                next[j] ^= coefficient;
+                next[j + 1] ^= mul(coefficient, root);
+            }
+            generator = next;
+        }
+        let mut remainder = vec![0u8; parity_size];
+        for &byte in content {
+            let factor = byte ^ remainder[0];
+            remainder.rotate_left(1);
+            remainder[parity_size - 1] = 0;
+            for (r, &g) in remainder.iter_mut().zip(&generator[1..]) {
+                *r ^= mul(g, factor);
+            }
+        }
+        remainder
+    }
+
+    /// Corrects the codeword in place, returning the number of bytes
+    /// corrected, or `None` if it has more errors than the parity can
+    /// correct.
+    pub(super) fn correct(codeword: &mut [u8], parity_size: usize) -> Option<usize> {
+        let syndromes: Vec<u8> = (0..parity_size)
+            .map(|i| eval_high_first(codeword, alpha_pow(i as isize)))
+            .collect();
+        if syndromes.iter().all(|&s| s == 0) {
+            return Some(0);
+        }
+
+        // Find the error locator with the Berlekamp-Massey algorithm.
+        let mut locator = vec![1u8];
+        let mut previous = vec![1u8];
+        let mut errors = 0;
+        let mut shift = 1;
+        let mut previous_discrepancy = 1u8;
+        for n in 0..parity_size {
+            let discrepancy = (1..=errors).fold(syndromes[n], |acc, i| {
+                acc ^ mul(*locator.get(i).unwrap_or(&0), syndromes[n - i])
+            });
+            if discrepancy == 0 {
+                shift += 1;
+                continue;
+            }
+            let scale = div(discrepancy, previous_discrepancy);
+            let mut next = locator.clone();
+            next.resize(next.len().max(previous.len() + shift), 0);
+            for (i, &coefficient) in previous.iter().enumerate() {
+                next[i + shift] ^= mul(scale, coefficient);
+            }
+            if 2 * errors <= n {
+                previous = std::mem::replace(&mut locator, next);
+                errors = n + 1 - errors;
+                previous_discrepancy = discrepancy;
+                shift = 1;
+            } else {
+                locator = next;
+                shift += 1;
+            }
+        }
+        if 2 * errors > parity_size {
+            return None;
+        }
+
+        // Find the error positions with a Chien search: a byte whose power
+        // in the codeword polynomial is e has an error if Λ(α⁻ᵉ) = 0.
+        let last = codeword.len() - 1;
+        let positions: Vec<usize> = (0..codeword.len())
+            .filter(|&position| eval_low_first(&locator, alpha_pow(-((last - position) as isize))) == 0)
+            .collect();
+        if positions.len() != errors {
+            return None;
+        }
+
+        // Find the error magnitudes with the Forney algorithm.
+        let mut evaluator = vec![0u8; parity_size];
+        for (i, &s) in syndromes.iter().enumerate() {
+            for (j, &l) in locator.iter().enumerate().take(parity_size - i) {
+                evaluator[i + j] ^= mul(s, l);
+            }
+        }
+        let derivative: Vec<u8> = locator.iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, &coefficient)| if i % 2 == 1 { coefficient } else { 0 })
+            .collect();
+        for &position in &positions {
+            let x = alpha_pow((last - position) as isize);
+            let x_inverse = div(1, x);
+            let denominator = eval_low_first(&derivative, x_inverse);
+            if denominator == 0 {
+                return None;
+            }
+            codeword[position] ^= mul(x, div(eval_low_first(&evaluator, x_inverse), denominator));
+        }
+
+        // A pattern of errors beyond the parity's capacity can be mistaken for
+        // a correctable one; reject corrections that don't yield a codeword.
+        if (0..parity_size).any(|i| eval_high_first(codeword, alpha_pow(i as isize)) != 0) {
+            return None;
+        }
+        Some(errors)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn codeword(content_size: usize, parity_size: usize) -> Vec<u8> {
+            let content: Vec<u8> = (0..content_size).map(|i| (i * 37 + 11) as u8).collect();
+            let parity = encode(&content, parity_size);
+            [content, parity].concat()
+        }
+
+        #[test]
+        fn test_correct() {
+            for (content_size, parity_size) in [(239, 16), (223, 32), (10, 16), (1, 64)] {
+                let original = codeword(content_size, parity_size);
+                assert_eq!(correct(&mut original.clone(), parity_size), Some(0));
+                for errors in 1..=parity_size / 2 {
+                    let mut damaged = original.clone();
+                    for k in 0..errors {
+                        let position = (k * 97 + 5) % damaged.len();
+                        damaged[position] ^= (k as u8).wrapping_mul(29) | 1;
+                    }
+                    assert_eq!(correct(&mut damaged, parity_size), Some(errors));
+                    assert_eq!(damaged, original);
+                }
+            }
+        }
+
+        #[test]
+        fn test_too_many_errors() {
+            let original = codeword(223, 32);
+            let mut damaged = original.clone();
+            for position in (0..34).map(|k| k * 7) {
+                damaged[position] ^= 0x5a;
+            }
+            assert_eq!(correct(&mut damaged, 32), None);
+        }
+    }
+}
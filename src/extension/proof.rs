@@ -53,6 +53,41 @@ impl Envelope {
         let set = HashSet::from_iter(iter::once(target.digest().into_owned()));
         self.confirm_contains_set(&set, proof)
     }
+
+    /// Returns a proof that this envelope contains the target element, which
+    /// reveals the target.
+    ///
+    /// The proof has the same digest as this envelope. It reveals the target
+    /// and everything within it, along with the elements on the path from
+    /// the root to the target, and elides everything else. Unlike
+    /// [`Envelope::proof_contains_target`], which also elides the target, the
+    /// verifier does not need to know the target in advance.
+    ///
+    /// # Parameters
+    /// - `target`: The digest of the element of this envelope that the proof must reveal.
+    /// # Returns
+    /// The proof, or `None` if the target is not a revealed element of this envelope.
+    pub fn proof_contains(&self, target: &Digest) -> Option<Envelope> {
+        let target_envelope = self.find_revealed(target)?;
+        let mut reveal_set = self.reveal_set_of_set(&HashSet::from_iter(iter::once(target.clone())));
+        reveal_set.extend(target_envelope.digests(usize::MAX));
+        Some(self.elide_revealing_set(&reveal_set))
+    }
+
+    /// Confirms whether or not this envelope, as a proof returned by
+    /// [`Envelope::proof_contains`], reveals the target element.
+    ///
+    /// This does not check which envelope the proof is for: the verifier
+    /// must also check that the digest of the proof is the trusted root
+    /// digest.
+    ///
+    /// # Parameters
+    /// - `target`: The digest of the element that the proof must reveal.
+    /// # Returns
+    /// `true` if the target is a revealed element of this envelope, `false` otherwise.
+    pub fn confirm_contains(&self, target: &Digest) -> bool {
+        self.find_revealed(target).is_some()
+    }
 }
 
 impl Envelope {
@@ -94,6 +129,23 @@ impl Envelope {
         }
     }
 
+    fn find_revealed(&self, target: &Digest) -> Option<Envelope> {
+        if *self.digest() == *target && !self.is_obscured() {
+            return Some(self.clone());
+        }
+        match self.case() {
+            EnvelopeCase::Node { subject, assertions, .. } => {
+                iter::once(subject).chain(assertions).find_map(|element| element.find_revealed(target))
+            }
+            EnvelopeCase::Wrapped { envelope, .. } => envelope.find_revealed(target),
+            EnvelopeCase::Assertion(assertion) => {
+                assertion.predicate().find_revealed(target)
+                    .or_else(|| assertion.object().find_revealed(target))
+            }
+            _ => None,
+        }
+    }
+
     fn remove_all_found(&self, target: &mut HashSet<Digest>) {
         if target.contains(&self.digest()) {
             target.remove(&self.digest());
@@ -1,11 +1,51 @@
+//! Encrypting envelopes to the public keys of their recipients.
+//!
+//! # Encrypting to recipients
+//!
+//! [`Envelope::encrypt_subject_to_recipients`] encrypts an envelope's
+//! subject with a random content key, and adds a `'hasRecipient'` assertion
+//! with the content key sealed to each recipient. Any one recipient can
+//! decrypt the subject with their private key.
+//!
+//! ```
+//! use bc_envelope::prelude::full::*;
+//! use bc_components::PrivateKeyBase;
+//!
+//! let bob = PrivateKeyBase::from_data(b"bob's key seed!!".to_vec());
+//! let carol = PrivateKeyBase::from_data(b"carol's key seed".to_vec());
+//! let eve = PrivateKeyBase::from_data(b"eve's key seed!!".to_vec());
+//!
+//! let message = Envelope::new("Meet at noon.").wrap_envelope();
+//! assert_eq!(message.digest().hex(), "c41a63728da2bea5c6ba026778441c8e87852d96fd9de7bf3f0b5bec6dfd8f1f");
+//! let encrypted = message.encrypt_subject_to_recipients(&[
+//!     &bob.schnorr_public_key_base(),
+//!     &carol.schnorr_public_key_base(),
+//! ])?;
+//! assert_eq!(encrypted.recipients()?.len(), 2);
+//! assert_eq!(encrypted.subject().digest(), message.digest());
+//!
+//! for recipient in [&bob, &carol] {
+//!     let decrypted = encrypted.decrypt_subject_to_recipient(recipient)?.unwrap_envelope()?;
+//!     assert_eq!(decrypted.extract_subject::<String>()?, "Meet at noon.");
+//! }
+//! assert!(encrypted.decrypt_subject_to_recipient(&eve).is_err());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
 use crate::{Envelope, EnvelopeError};
+use crate::base::random::{nonce_using, random_data, random_nonce, ProviderRandomNumberGenerator};
 #[cfg(feature = "known_value")]
 use crate::extension::known_values;
 
 use anyhow::{bail, Result};
 use bc_components::{SealedMessage, SymmetricKey, Nonce, PrivateKeyBase, Encrypter};
+use bc_rand::RandomNumberGenerator;
 use dcbor::prelude::*;
 
+/// The size of the key material of the ephemeral key that seals a content
+/// key to a recipient.
+const EPHEMERAL_KEY_SIZE: usize = 32;
+
 /// Support for public key encryption.
 impl Envelope {
     /// Returns a new envelope with an added `hasRecipient: SealedMessage` assertion.
@@ -29,20 +69,29 @@ impl Envelope {
 
     /// Returns an array of `SealedMessage`s from all of the envelope's `hasRecipient` assertions.
     ///
-    /// - Throws: Throws an exception if any `hasRecipient` assertions do not have a `SealedMessage` as their object.
+    /// Assertions that are elided, and assertions whose objects are elided,
+    /// are skipped.
+    ///
+    /// - Throws: `EnvelopeError::InvalidRecipient` if any `hasRecipient` assertions do not have a `SealedMessage` as their object.
     pub fn recipients(&self) -> Result<Vec<SealedMessage>> {
         self
             .assertions_with_predicate(known_values::HAS_RECIPIENT)
             .into_iter()
-            .filter(|assertion| {
-                !assertion.as_object().unwrap().is_obscured()
-            })
-            .map(|assertion| {
-                assertion.as_object().unwrap().extract_subject::<SealedMessage>()
+            .filter_map(|assertion| assertion.subject().as_object())
+            .filter(|object| !object.is_obscured())
+            .map(|object| {
+                object.extract_subject::<SealedMessage>()
+                    .map_err(|error| EnvelopeError::InvalidRecipient { message: error.to_string() }.into())
             })
             .collect()
     }
 
+    /// Returns whether the envelope has any `hasRecipient` assertions,
+    /// including those whose objects are elided.
+    pub fn has_recipients(&self) -> bool {
+        !self.assertions_with_predicate(known_values::HAS_RECIPIENT).is_empty()
+    }
+
     /// Returns an new envelope with its subject encrypted and a `hasRecipient`
     /// assertion added for each of the `recipients`.
     ///
@@ -73,7 +122,7 @@ impl Envelope {
         test_nonce: Option<&Nonce>
     ) -> Result<Self>
     {
-        let content_key = SymmetricKey::new();
+        let content_key = SymmetricKey::new_using(&mut ProviderRandomNumberGenerator);
         let mut e = self.encrypt_subject(&content_key)?;
         for recipient in recipients {
             e = e.add_recipient_opt(*recipient, &content_key, test_key_material, test_nonce);
@@ -101,6 +150,49 @@ impl Envelope {
         self.encrypt_subject_to_recipients_opt(&[recipient], test_key_material, test_nonce)
     }
 
+    /// Returns a new envelope with its subject encrypted with the given
+    /// `content_key`, and a `hasRecipient` assertion added for each of the
+    /// `recipients`, drawing the nonces and ephemeral keys from `rng`.
+    ///
+    /// With a fixed content key and a deterministic generator, such as
+    /// `bc_rand::make_fake_random_number_generator()`, the result is
+    /// reproducible byte for byte, which is useful for test vectors.
+    /// [`Envelope::encrypt_subject_to_recipients`] instead generates all of
+    /// them randomly.
+    ///
+    /// - Throws: If the envelope is already encrypted.
+    #[cfg(feature = "encrypt")]
+    pub fn encrypt_subject_to_recipients_using(
+        &self,
+        recipients: &[&dyn Encrypter],
+        content_key: &SymmetricKey,
+        rng: &mut impl RandomNumberGenerator,
+    ) -> Result<Self>
+    {
+        let mut e = self.encrypt_subject_using(content_key, rng)?;
+        for recipient in recipients {
+            let key_material = rng.random_data(EPHEMERAL_KEY_SIZE);
+            e = e.add_recipient_opt(*recipient, content_key, Some(&key_material), Some(&nonce_using(rng)));
+        }
+        Ok(e)
+    }
+
+    /// Returns a new envelope with its subject encrypted with the given
+    /// `content_key`, and a `hasRecipient` assertion added for the
+    /// `recipient`, drawing the nonces and ephemeral key from `rng`.
+    ///
+    /// See [`Envelope::encrypt_subject_to_recipients_using`].
+    #[cfg(feature = "encrypt")]
+    pub fn encrypt_subject_to_recipient_using(
+        &self,
+        recipient: &dyn Encrypter,
+        content_key: &SymmetricKey,
+        rng: &mut impl RandomNumberGenerator,
+    ) -> Result<Self>
+    {
+        self.encrypt_subject_to_recipients_using(&[recipient], content_key, rng)
+    }
+
     #[cfg(feature = "encrypt")]
     fn first_plaintext_in_sealed_messages(sealed_messages: &[SealedMessage], private_key: &PrivateKeyBase) -> Result<Vec<u8>> {
         for sealed_message in sealed_messages {
@@ -140,9 +232,17 @@ impl Envelope {
     /// - Returns: The assertion envelope.
     fn make_has_recipient(recipient: &dyn Encrypter, content_key: &SymmetricKey, test_key_material: Option<&[u8]>, test_nonce: Option<&Nonce>) -> Self
     {
-        let sealed_message = SealedMessage::new_opt(content_key.to_cbor_data(), recipient, None::<Vec<u8>>, test_key_material, test_nonce);
+        let sealed_message = Self::seal_content_key(recipient, content_key, test_key_material, test_nonce);
         Self::new_assertion(known_values::HAS_RECIPIENT, sealed_message)
     }
+
+    /// Returns the `contentKey` encrypted to the recipient's public key.
+    pub(crate) fn seal_content_key(recipient: &dyn Encrypter, content_key: &SymmetricKey, test_key_material: Option<&[u8]>, test_nonce: Option<&Nonce>) -> SealedMessage
+    {
+        let key_material = test_key_material.map_or_else(|| random_data(EPHEMERAL_KEY_SIZE), <[u8]>::to_vec);
+        let nonce = test_nonce.cloned().unwrap_or_else(random_nonce);
+        SealedMessage::new_opt(content_key.to_cbor_data(), recipient, None::<Vec<u8>>, Some(key_material), Some(nonce))
+    }
 }
 
 impl Envelope {
@@ -1,19 +1,23 @@
 use std::ops::RangeInclusive;
 
-use crate::Envelope;
+use crate::{base::random::ProviderRandomNumberGenerator, Envelope};
 #[cfg(feature = "known_value")]
 use crate::extension::known_values;
 
 use anyhow::Result;
 use bc_components::Salt;
-use bc_rand::{RandomNumberGenerator, SecureRandomNumberGenerator};
-use dcbor::prelude::*;
+use bc_rand::RandomNumberGenerator;
 
 /// Support for decorrelation of envelopes using salt.
 impl Envelope {
     /// Add a number of bytes of salt generally proportionate to the size of the object being salted.
+    ///
+    /// Salting an assertion envelope adds the salt to the assertion itself,
+    /// so the assertion's digest changes, rather than to the node the
+    /// assertion is later added to.
+    #[doc(alias = "salted")]
     pub fn add_salt(&self) -> Self {
-        let mut rng = SecureRandomNumberGenerator;
+        let mut rng = ProviderRandomNumberGenerator;
         self.add_salt_using(&mut rng)
     }
 
@@ -26,12 +30,12 @@ impl Envelope {
     ///
     /// Returns an error if the number of bytes is less than 8.
     pub fn add_salt_with_len(&self, count: usize) -> Result<Self> {
-        let mut rng = SecureRandomNumberGenerator;
+        let mut rng = ProviderRandomNumberGenerator;
         self.add_salt_with_len_using(count, &mut rng)
     }
 
-    #[doc(hidden)]
-    /// Add a specified number of bytes of salt.
+    /// Add a specified number of bytes of salt, generated by the given random
+    /// number generator.
     ///
     /// Returns an error if the number of bytes is less than 8.
     pub fn add_salt_with_len_using(&self, count: usize, rng: &mut impl RandomNumberGenerator) -> Result<Self> {
@@ -43,24 +47,27 @@ impl Envelope {
     ///
     /// Returns an error if the minimum number of bytes is less than 8.
     pub fn add_salt_in_range(&self, range: RangeInclusive<usize>) -> Result<Self> {
-        let mut rng = SecureRandomNumberGenerator;
+        let mut rng = ProviderRandomNumberGenerator;
         self.add_salt_in_range_using(&range, &mut rng)
     }
 
-    #[doc(hidden)]
-    /// Add a number of bytes of salt chosen randomly from the given range.
+    /// Add a number of bytes of salt chosen randomly from the given range,
+    /// using the given random number generator.
     ///
     /// Returns an error if the minimum number of bytes is less than 8.
     pub fn add_salt_in_range_using(&self, range: &RangeInclusive<usize>, rng: &mut impl RandomNumberGenerator) -> Result<Self> {
         Ok(self.add_salt_instance(Salt::new_in_range_using(range, rng)?))
     }
 
-    #[doc(hidden)]
-    /// Add a deterministic amount of salt.
+    /// Add a number of bytes of salt generally proportionate to the size of
+    /// the object being salted, generated by the given random number
+    /// generator.
     ///
-    /// Only used for testing.
+    /// With a deterministic generator, such as
+    /// `bc_rand::make_fake_random_number_generator()`, the salt is
+    /// reproducible, which is useful in tests.
     pub fn add_salt_using(&self, rng: &mut impl RandomNumberGenerator) -> Self {
-        let salt = Salt::new_for_size_using(self.tagged_cbor().to_cbor_data().len(), rng);
+        let salt = Salt::new_for_size_using(self.tagged_cbor_data_len(), rng);
         self.add_salt_instance(salt)
     }
 }
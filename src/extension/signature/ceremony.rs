@@ -0,0 +1,155 @@
+use std::collections::HashSet;
+
+use anyhow::{bail, Result};
+use bc_components::{Signer, Verifier};
+use dcbor::Date;
+
+use crate::{Envelope, EnvelopeError};
+use crate::extension::known_values;
+
+use super::SignatureMetadata;
+
+/// The predicate of the signature metadata assertion carrying a layer's
+/// declared sequence number.
+pub const SEQUENCE: &str = "sequence";
+
+/// A problem found in one layer of a signing ceremony.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CeremonyIssue {
+    /// The layer's signatures carry no sequence metadata.
+    MissingMetadata,
+    /// The layer's sequence number is not greater than that of the layer
+    /// signed before it.
+    SequenceOutOfOrder,
+    /// The layer's sequence number is also used by another layer.
+    DuplicateSequence,
+}
+
+/// One layer of a signing ceremony, as reconstructed by
+/// [`Envelope::ceremony_report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CeremonyStep {
+    /// The number of wrapping layers between this layer and the outside of
+    /// the envelope. The last signer's layer has depth `0`.
+    pub depth: usize,
+    /// The sequence number declared in the layer's signature metadata.
+    pub sequence: Option<u64>,
+    /// The date declared in the layer's signature metadata.
+    pub date: Option<Date>,
+    /// The index of the first given verifier that verifies the layer's
+    /// signature, if any.
+    pub signer: Option<usize>,
+    /// Inconsistencies found in this layer.
+    pub issues: Vec<CeremonyIssue>,
+}
+
+/// Support for multi-party signing ceremonies, in which each party in turn
+/// wraps and signs the envelope signed by the previous party.
+impl Envelope {
+    /// Wraps this envelope and signs it, recording the signer's place in a
+    /// signing ceremony.
+    ///
+    /// The sequence number and date are added to the signature's metadata,
+    /// which is itself signed, so they cannot be altered without invalidating
+    /// the signature.
+    pub fn countersign_with_sequence(&self, signer: &dyn Signer, sequence: u64, date: Date) -> Self {
+        let metadata = SignatureMetadata::new()
+            .with_assertion(SEQUENCE, sequence)
+            .with_assertion(known_values::DATE, date);
+        self.wrap_envelope().add_signature_opt(signer, None, Some(metadata))
+    }
+
+    /// Reconstructs the layers of a signing ceremony, in the order they were
+    /// signed.
+    ///
+    /// Each layer is a wrapped envelope with one or more `'signed'`
+    /// assertions. The walk stops at the first subject that is not a signed,
+    /// wrapped envelope. Signatures are checked against `verifiers` only to
+    /// identify the signer of each layer; unverified layers are still
+    /// reported.
+    pub fn ceremony_report(&self, verifiers: &[&dyn Verifier]) -> Result<Vec<CeremonyStep>> {
+        let mut steps = Vec::new();
+        let mut layer = self.clone();
+        let mut depth = 0;
+        while layer.subject().is_wrapped() {
+            let signatures = layer.objects_for_predicate(known_values::SIGNED);
+            if signatures.is_empty() {
+                break;
+            }
+
+            let metadata = signatures.iter()
+                .filter(|signature| signature.subject().is_wrapped())
+                .find_map(|signature| signature.unwrap_envelope().ok());
+            let (sequence, date) = match &metadata {
+                Some(metadata) => (
+                    metadata.extract_optional_object_for_predicate::<u64>(SEQUENCE)?,
+                    metadata.extract_optional_object_for_predicate::<Date>(known_values::DATE)?,
+                ),
+                None => (None, None),
+            };
+
+            let mut signer = None;
+            for (index, verifier) in verifiers.iter().enumerate() {
+                if layer.has_signature_from(*verifier)? {
+                    signer = Some(index);
+                    break;
+                }
+            }
+
+            steps.push(CeremonyStep { depth, sequence, date, signer, issues: Vec::new() });
+            layer = layer.unwrap_envelope()?;
+            depth += 1;
+        }
+        steps.reverse();
+
+        let mut seen = HashSet::new();
+        let mut previous = None;
+        for step in steps.iter_mut() {
+            match step.sequence {
+                None => step.issues.push(CeremonyIssue::MissingMetadata),
+                Some(sequence) => {
+                    if !seen.insert(sequence) {
+                        step.issues.push(CeremonyIssue::DuplicateSequence);
+                    } else if previous.is_some_and(|previous| sequence <= previous) {
+                        step.issues.push(CeremonyIssue::SequenceOutOfOrder);
+                    }
+                    previous = Some(sequence);
+                },
+            }
+        }
+        Ok(steps)
+    }
+
+    /// Reconstructs the layers of a signing ceremony and checks that it is
+    /// consistent.
+    ///
+    /// Returns an error if any layer has an issue, if any layer was not signed
+    /// by one of `verifiers`, or, when `require_increasing_dates` is `true`, if
+    /// any layer lacks a date or is not dated strictly later than the layer
+    /// signed before it.
+    pub fn validate_ceremony(&self, verifiers: &[&dyn Verifier], require_increasing_dates: bool) -> Result<Vec<CeremonyStep>> {
+        let steps = self.ceremony_report(verifiers)?;
+        if steps.is_empty() {
+            bail!(EnvelopeError::InvalidCeremony { message: "envelope has no signed layers".to_string() });
+        }
+        let mut previous_date: Option<&Date> = None;
+        for step in &steps {
+            if let Some(issue) = step.issues.first() {
+                bail!(EnvelopeError::InvalidCeremony { message: format!("layer at depth {} has issue {:?}", step.depth, issue) });
+            }
+            if step.signer.is_none() {
+                bail!(EnvelopeError::UnverifiedSignature);
+            }
+            if require_increasing_dates {
+                let Some(date) = &step.date else {
+                    bail!(EnvelopeError::InvalidCeremony { message: format!("layer at depth {} has no date", step.depth) });
+                };
+                if previous_date.is_some_and(|previous| date <= previous) {
+                    bail!(EnvelopeError::InvalidCeremony { message: format!("layer at depth {} is not dated after the previous layer", step.depth) });
+                }
+                previous_date = Some(date);
+            }
+        }
+        Ok(steps)
+    }
+}
@@ -1,3 +1,101 @@
+//! Signing envelopes and verifying their signatures.
+//!
+//! # Signing and verifying
+//!
+//! [`Envelope::sign`] wraps an envelope and signs the wrapped envelope, so
+//! that the signature covers the assertions as well as the subject.
+//! [`Envelope::verify`] checks the signature and returns the envelope that was
+//! signed.
+//!
+//! ```
+//! use bc_envelope::prelude::full::*;
+//! use bc_components::PrivateKeyBase;
+//!
+//! let alice = PrivateKeyBase::from_data(b"alice's key seed".to_vec());
+//! let message = Envelope::new("Hello.").add_assertion("from", "Alice");
+//! assert_eq!(message.digest().hex(), "dd600c178303d6012399d4f14f65f82632a49d2d25a4cec4f2522d98a56f9d4a");
+//!
+//! let signed = message.sign(&alice);
+//! let verified = signed.verify(&alice.schnorr_public_key_base())?;
+//! assert_eq!(verified.digest(), message.digest());
+//! assert_eq!(verified.extract_subject::<String>()?, "Hello.");
+//!
+//! // A signature from anyone else does not verify.
+//! let mallory = PrivateKeyBase::from_data(b"mallory key seed".to_vec());
+//! assert!(signed.verify(&mallory.schnorr_public_key_base()).is_err());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! # Building and signing a credential
+//!
+//! A credential is an envelope whose subject is a unique identifier, with a
+//! type, assertions about the holder, and the signature of its issuer.
+//!
+//! ```
+//! use bc_envelope::prelude::full::*;
+//! use bc_components::{ARID, PrivateKeyBase};
+//!
+//! let issuer = PrivateKeyBase::from_data(b"issuer's keyseed".to_vec());
+//! let credential = Envelope::new(ARID::from_data([0x46; 32]))
+//!     .add_type("Certificate of Completion")
+//!     .add_assertion(known_values::ISSUER, "Example Electrical Engineering Board")
+//!     .add_assertion("firstName", "James")
+//!     .add_assertion("lastName", "Maxwell")
+//!     .add_assertion("issueDate", Date::from_string("2020-01-01")?)
+//!     .add_assertion("professionalDevelopmentHours", 15);
+//! assert_eq!(credential.digest().hex(), "a551f8e1133b2e079c541ae98be32740c34495a012625616c224f0d6d659a66d");
+//!
+//! let signed = credential
+//!     .sign(&issuer)
+//!     .add_assertion(known_values::NOTE, "Signed by Example Electrical Engineering Board");
+//!
+//! // The holder checks the issuer's signature and reads the credential.
+//! let verified = signed.verify(&issuer.schnorr_public_key_base())?;
+//! assert!(verified.is_identical_to(&credential));
+//! assert!(verified.has_type_envelope("Certificate of Completion"));
+//! assert_eq!(verified.extract_object_for_predicate::<String>("lastName")?, "Maxwell");
+//! assert_eq!(verified.extract_object_for_predicate::<u32>("professionalDevelopmentHours")?, 15);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! # Selective disclosure
+//!
+//! The holder of a signed credential can elide the parts they do not want to
+//! reveal. Elision preserves the digest tree, so the issuer's signature still
+//! verifies.
+//!
+//! ```
+//! use std::collections::HashSet;
+//!
+//! use bc_envelope::prelude::full::*;
+//! use bc_components::{ARID, PrivateKeyBase};
+//!
+//! let issuer = PrivateKeyBase::from_data(b"issuer's keyseed".to_vec());
+//! let credential = Envelope::new(ARID::from_data([0x46; 32]))
+//!     .add_assertion("firstName", "James")
+//!     .add_assertion("lastName", "Maxwell")
+//!     .add_assertion("birthDate", Date::from_string("1990-04-01")?);
+//! let signed = credential.sign(&issuer);
+//!
+//! // Elide the first name and birth date, revealing only the last name.
+//! let content = signed.unwrap_envelope()?;
+//! let target: HashSet<Digest> = ["firstName", "birthDate"]
+//!     .into_iter()
+//!     .map(|predicate| Ok(content.assertion_with_predicate(predicate)?.digest().into_owned()))
+//!     .collect::<anyhow::Result<_>>()?;
+//! let redacted = signed.elide_removing_set(&target);
+//!
+//! assert!(redacted.is_equivalent_to(&signed));
+//! let verified = redacted.verify(&issuer.schnorr_public_key_base())?;
+//! assert_eq!(verified.digest().hex(), "e5f50de7dd6209dadef747bc0239afc863ea71f01f3a3d9f26ef2d25259d4855");
+//! assert_eq!(verified.extract_object_for_predicate::<String>("lastName")?, "Maxwell");
+//! assert!(verified.object_for_predicate("firstName").is_err());
+//! assert_eq!(verified.stats().elided, 2);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
 pub mod signature_impl;
 pub mod signature_metadata;
 pub use signature_metadata::SignatureMetadata;
+pub mod ceremony;
+pub use ceremony::{CeremonyIssue, CeremonyStep};
@@ -1,12 +1,21 @@
+use std::{ cell::RefCell, rc::Rc };
+
 use anyhow::{ bail, Result };
-use bc_components::{ DigestProvider, Signature, Signer, SigningOptions, Verifier };
+use bc_components::{ Digest, DigestProvider, Signature, Signer, SigningOptions, Verifier };
 
-use crate::{ Envelope, EnvelopeEncodable, EnvelopeError };
+use crate::{ base::random::ProviderRandomNumberGenerator, Envelope, EnvelopeEncodable, EnvelopeError };
 #[cfg(feature = "known_value")]
 use crate::extension::known_values;
 
 use super::SignatureMetadata;
 
+/// The options for signers that are not given any, which draw the randomness
+/// of Schnorr signatures from the process-wide `RandomProvider`. Other kinds of
+/// signers ignore them.
+fn default_signing_options() -> Option<SigningOptions> {
+    Some(SigningOptions::Schnorr { rng: Rc::new(RefCell::new(ProviderRandomNumberGenerator)) })
+}
+
 /// Support for signing envelopes and verifying signatures.
 impl Envelope {
     /// Creates a signature for the envelope's subject and returns a new envelope with a `'signed': Signature` assertion.
@@ -34,6 +43,7 @@ impl Envelope {
         options: Option<SigningOptions>,
         metadata: Option<SignatureMetadata>
     ) -> Self {
+        let options = options.or_else(default_signing_options);
         let digest = *self.subject().digest().data();
         let mut signature = Envelope::new(
             private_key.sign_with_options(&digest as &dyn AsRef<[u8]>, options.clone()).unwrap()
@@ -269,7 +279,7 @@ impl Envelope {
 #[doc(hidden)]
 impl Envelope {
     fn is_signature_from_key(&self, signature: &Signature, key: &dyn Verifier) -> bool {
-        key.verify(signature, self.subject().digest().as_ref())
+        Self::is_verified_signature_for_digest(self.subject().digest().as_ref(), signature, key)
     }
 
     fn has_some_signature_from_key(&self, key: &dyn Verifier) -> Result<bool> {
@@ -329,16 +339,76 @@ impl Envelope {
 }
 
 impl Envelope {
+    /// Wraps the envelope and signs the wrapped envelope, so that the
+    /// signature covers its assertions as well as its subject.
     pub fn sign(&self, signer: &dyn Signer) -> Envelope {
         self.wrap_envelope().add_signature(signer)
     }
 
+    /// Verifies a signature made by [`Envelope::sign`], returning the
+    /// envelope that was signed.
     pub fn verify(&self, verifier: &dyn Verifier) -> Result<Envelope> {
         self.verify_signature_from(verifier)?.unwrap_envelope()
     }
 
+    /// Verifies a signature made by [`Envelope::sign`], returning the
+    /// envelope that was signed and the signature's metadata.
     pub fn verify_returning_metadata(&self, verifier: &dyn Verifier) -> Result<(Envelope, Envelope)> {
         let metadata = self.verify_signature_from_returning_metadata(verifier)?;
         Ok((self.unwrap_envelope()?, metadata))
     }
 }
+
+/// Support for signing and verifying digests without the envelope they
+/// identify.
+///
+/// A `'signed': Signature` assertion attests only the digest of the subject it
+/// is attached to. When an envelope is too large to hold in memory, its digest
+/// can be computed separately and signed here, and the resulting assertion
+/// attached later to an envelope with that subject digest.
+///
+/// To be consistent with [`Envelope::sign`], which wraps the envelope before
+/// signing it, pass the digest of the *wrapped* envelope, which is
+/// `Digest::from_digests(&[content_digest])`.
+impl Envelope {
+    /// Creates a signature of the given digest.
+    ///
+    /// This is the signature [`Envelope::add_signature`] would create for an
+    /// envelope whose subject has this digest.
+    pub fn sign_digest(digest: &Digest, signer: &dyn Signer) -> Result<Signature> {
+        signer.sign_with_options(digest, default_signing_options())
+    }
+
+    /// Creates a `'signed': Signature` assertion envelope for the given digest.
+    ///
+    /// The assertion may be added to any envelope whose subject has this
+    /// digest, after which `verify_signature_from` will succeed.
+    pub fn signature_assertion_for_digest(digest: &Digest, signer: &dyn Signer) -> Result<Self> {
+        let signature = Self::sign_digest(digest, signer)?;
+        Ok(Envelope::new_assertion(known_values::SIGNED, signature))
+    }
+
+    /// Returns whether the given signature is valid for the given digest.
+    pub fn is_verified_signature_for_digest(
+        digest: &Digest,
+        signature: &Signature,
+        public_key: &dyn Verifier
+    ) -> bool {
+        public_key.verify(signature, digest)
+    }
+
+    /// Checks whether the given signature is valid for the given digest.
+    ///
+    /// - Throws: Throws `EnvelopeError.unverifiedSignature` if the signature is
+    /// not valid.
+    pub fn verify_signature_for_digest(
+        digest: &Digest,
+        signature: &Signature,
+        public_key: &dyn Verifier
+    ) -> Result<()> {
+        if !Self::is_verified_signature_for_digest(digest, signature, public_key) {
+            bail!(EnvelopeError::UnverifiedSignature);
+        }
+        Ok(())
+    }
+}
@@ -1,3 +1,35 @@
+//! Splitting envelopes into shares with SSKR, and joining them.
+//!
+//! # Splitting and joining
+//!
+//! The envelope's subject is encrypted with a content key, which is split
+//! into shares with Sharded Secret Key Reconstruction (SSKR). Each share is
+//! added to a copy of the encrypted envelope. A quorum of the copies
+//! recovers the envelope.
+//!
+//! ```
+//! use bc_envelope::prelude::full::*;
+//! use bc_components::{SSKRGroupSpec, SSKRSpec, SymmetricKey};
+//!
+//! let secret = Envelope::new("My seed phrase.").add_assertion(known_values::NOTE, "Backup");
+//! assert_eq!(secret.digest().hex(), "7425eb121cde3d97a69a800576ee3caf7a783904bbb1e07f32386f74bf9313bb");
+//!
+//! // Split into one group of three shares, any two of which recover it.
+//! let content_key = SymmetricKey::new();
+//! let encrypted = secret.wrap_envelope().encrypt_subject(&content_key)?;
+//! let spec = SSKRSpec::new(1, vec![SSKRGroupSpec::new(2, 3)?])?;
+//! let shares: Vec<Envelope> = encrypted.sskr_split_flattened(&spec, &content_key)?;
+//! assert_eq!(shares.len(), 3);
+//!
+//! let recovered = Envelope::sskr_join(&[&shares[0], &shares[2]])?.unwrap_envelope()?;
+//! assert!(recovered.is_identical_to(&secret));
+//! assert_eq!(recovered.digest().hex(), "7425eb121cde3d97a69a800576ee3caf7a783904bbb1e07f32386f74bf9313bb");
+//!
+//! // One share is not enough.
+//! assert!(Envelope::sskr_join(&[&shares[1]]).is_err());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
 use std::collections::HashMap;
 
 use anyhow::{bail, Result};
@@ -5,7 +37,7 @@ pub use bc_components::{SSKRShare, SSKRSpec, SSKRGroupSpec, SSKRSecret, SSKRErro
 use bc_components::{sskr_generate_using, sskr_combine, SymmetricKey};
 use bc_rand::RandomNumberGenerator;
 
-use crate::{Envelope, EnvelopeError};
+use crate::{base::random::ProviderRandomNumberGenerator, Envelope, EnvelopeError};
 #[cfg(feature = "known_value")]
 use crate::extension::known_values;
 
@@ -32,7 +64,7 @@ impl Envelope {
     /// SSKR group, and the elements of each inner array are the envelope with a unique
     /// `sskrShare: SSKRShare` assertion added to each.
     pub fn sskr_split(&self, spec: &SSKRSpec, content_key: &SymmetricKey) -> Result<Vec<Vec<Envelope>>> {
-        let mut rng = bc_rand::SecureRandomNumberGenerator;
+        let mut rng = ProviderRandomNumberGenerator;
         self.sskr_split_using(spec, content_key, &mut rng)
     }
 
@@ -54,6 +86,25 @@ impl Envelope {
         Ok(self.sskr_split(spec, content_key)?.into_iter().flatten().collect())
     }
 
+    /// Splits the envelope into a set of SSKR shares, each of which also
+    /// carries the given metadata assertions in plaintext.
+    ///
+    /// The metadata, such as a name or date for the backup, is added to each
+    /// share alongside its `sskrShare` assertion and outside the encrypted
+    /// subject, so it can be read without any key material. It does not
+    /// affect the envelope recovered by [`Envelope::sskr_join`], which
+    /// ignores every assertion of the shares other than `sskrShare`, so
+    /// shares whose metadata has since been changed still join. Each share's
+    /// group and member indexes can be read from its `SSKRShare`.
+    ///
+    /// Returns an error if any of the metadata envelopes is not a valid
+    /// assertion envelope, or an obscured variant of one.
+    ///
+    /// See [`Envelope::sskr_split`].
+    pub fn sskr_split_with_metadata(&self, spec: &SSKRSpec, content_key: &SymmetricKey, metadata: &[Envelope]) -> Result<Vec<Vec<Envelope>>> {
+        self.add_assertion_envelopes(metadata)?.sskr_split(spec, content_key)
+    }
+
     #[doc(hidden)]
     /// Splits the envelope into a set of SSKR shares.
     ///
@@ -0,0 +1,155 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{bail, Result};
+use bc_components::{Digest, DigestProvider};
+use dcbor::Date;
+
+use crate::{extension::known_values, Envelope, EnvelopeEncodable, EnvelopeError};
+
+/// The predicate of the meta-assertion that records which assertion an
+/// assertion supersedes, by digest.
+pub const SUPERSEDES: &str = "supersedes";
+
+/// Support for recording the history of assertions whose objects change.
+///
+/// An assertion that replaces another carries two meta-assertions: a
+/// `"supersedes"` assertion whose object is the digest of the assertion it
+/// replaces, and a `'date'` assertion with the date the replacement took
+/// effect:
+///
+/// ```text
+/// "Alice" [
+///     {
+///         "address": "2 High St."
+///     } [
+///         "supersedes": Digest(d0aed6ec)
+///         'date': 2023-06-01
+///     ]
+///     "address": "1 Main St."
+/// ]
+/// ```
+///
+/// Assertions with the same predicate, each superseding the one before, form
+/// a chain, whose last assertion holds the current object. Because the chain
+/// refers to superseded assertions by digest, they can be elided without
+/// breaking it.
+impl Envelope {
+    /// Returns this envelope with a new assertion superseding the assertion
+    /// with the given digest.
+    ///
+    /// The new assertion has the same predicate as the superseded assertion,
+    /// the given object, and `"supersedes"` and `'date'` meta-assertions. If
+    /// `elide_superseded` is true, the superseded assertion is elided, so its
+    /// object is no longer revealed but its digest, and so the digest of the
+    /// chain, is preserved.
+    ///
+    /// Returns `EnvelopeError::NonexistentAssertion` if the envelope has no
+    /// assertion with the digest, and `EnvelopeError::NotAssertion` if that
+    /// assertion is obscured, as its predicate is then unknown.
+    pub fn supersede_assertion(
+        &self,
+        superseded_digest: &Digest,
+        new_object: impl EnvelopeEncodable,
+        date: Date,
+        elide_superseded: bool,
+    ) -> Result<Self> {
+        let superseded = self.assertions()
+            .iter()
+            .find(|assertion| *assertion.digest() == *superseded_digest)
+            .ok_or(EnvelopeError::NonexistentAssertion)?;
+        let predicate = superseded.subject().try_predicate()?;
+        let assertion = Envelope::new_assertion(predicate, new_object)
+            .add_assertion(SUPERSEDES, superseded_digest.clone())
+            .add_assertion(known_values::DATE, date);
+        let envelope = if elide_superseded {
+            self.elide_removing_target(superseded)
+        } else {
+            self.clone()
+        };
+        envelope.add_assertion_envelope(assertion)
+    }
+
+    /// Returns the history of the assertions with the given predicate, from
+    /// the original to the current assertion, each with the date it took
+    /// effect, if any.
+    ///
+    /// Superseded assertions that have been elided appear in the history as
+    /// elided elements. An empty history means there is no assertion with the
+    /// predicate.
+    ///
+    /// Returns `EnvelopeError::SupersessionFork` if more than one assertion
+    /// supersedes the same assertion, `EnvelopeError::SupersessionCycle` if
+    /// the assertions supersede one another in a cycle, and
+    /// `EnvelopeError::AmbiguousPredicate` if the assertions do not form a
+    /// single chain.
+    pub fn history_for_predicate(&self, predicate: impl EnvelopeEncodable) -> Result<Vec<(Self, Option<Date>)>> {
+        let predicate = Envelope::new(predicate);
+        let assertions = self.assertions_with_predicate(predicate.clone());
+        if assertions.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut superseded_by: HashMap<Digest, &Envelope> = HashMap::new();
+        for assertion in &assertions {
+            if let Some(superseded) = assertion.extract_optional_object_for_predicate::<Digest>(SUPERSEDES)? {
+                if superseded_by.insert(superseded.clone(), assertion).is_some() {
+                    bail!(EnvelopeError::SupersessionFork { predicate: predicate.format_flat_opt(None), digest: superseded });
+                }
+            }
+        }
+
+        // The chain starts at the one assertion that does not supersede
+        // another visible assertion with the predicate.
+        let visible: HashSet<Digest> = assertions.iter().map(|assertion| assertion.digest().into_owned()).collect();
+        let roots: Vec<&Envelope> = assertions.iter()
+            .filter(|assertion| {
+                match assertion.extract_optional_object_for_predicate::<Digest>(SUPERSEDES) {
+                    Ok(Some(superseded)) => !visible.contains(&superseded),
+                    _ => true,
+                }
+            })
+            .collect();
+        let root = match roots.as_slice() {
+            [] => bail!(EnvelopeError::SupersessionCycle { predicate: predicate.format_flat_opt(None) }),
+            [root] => *root,
+            _ => bail!(EnvelopeError::AmbiguousPredicate { predicate: predicate.format_flat_opt(None), count: roots.len() }),
+        };
+
+        let mut history = Vec::new();
+        if let Some(superseded) = root.extract_optional_object_for_predicate::<Digest>(SUPERSEDES)? {
+            if let Some(elided) = self.assertions().iter().find(|assertion| *assertion.digest() == superseded) {
+                history.push((elided.clone(), None));
+            }
+        }
+        let mut current = root;
+        loop {
+            history.push((current.clone(), current.extract_optional_object_for_predicate::<Date>(known_values::DATE)?));
+            match superseded_by.get(current.digest().as_ref()) {
+                Some(next) => current = next,
+                None => break,
+            }
+        }
+
+        // Any assertion not reached from the root supersedes, and is
+        // superseded by, another assertion in a cycle.
+        if history.iter().filter(|(assertion, _)| visible.contains(assertion.digest().as_ref())).count() != assertions.len() {
+            bail!(EnvelopeError::SupersessionCycle { predicate: predicate.format_flat_opt(None) });
+        }
+        Ok(history)
+    }
+
+    /// Returns the object of the current assertion with the given predicate:
+    /// the last in its history, which no other assertion supersedes.
+    ///
+    /// Returns `EnvelopeError::NonexistentPredicate` if there is no assertion
+    /// with the predicate, and the errors of
+    /// [`Envelope::history_for_predicate`] if the assertions do not form a
+    /// single chain.
+    pub fn current_object_for_predicate(&self, predicate: impl EnvelopeEncodable) -> Result<Self> {
+        let predicate = Envelope::new(predicate);
+        match self.history_for_predicate(predicate.clone())?.pop() {
+            Some((assertion, _)) => assertion.subject().try_object(),
+            None => bail!(EnvelopeError::NonexistentPredicate { predicate: predicate.format_flat_opt(None) }),
+        }
+    }
+}
@@ -28,15 +28,23 @@ impl Envelope {
     }
 
     /// Returns `true` if the envelope has an `'IsA'` type assertion with the given envelope `t`'s digest.
+    ///
+    /// Types that are elided, encrypted, or compressed never match, even if their digest does.
     pub fn has_type_envelope(&self, t: impl EnvelopeEncodable) -> bool {
         let e = t.into_envelope();
-        self.types().iter().any(|x| x.digest() == e.digest())
+        self.has_type_digest(&e)
     }
 
     /// Returns `true` if the envelope has an `'IsA'` type assertion with the given known value `t`.
+    ///
+    /// Types that are elided, encrypted, or compressed never match, even if their digest does.
     pub fn has_type(&self, t: &KnownValue) -> bool {
         let type_envelope: Envelope = t.clone().to_envelope();
-        self.types().iter().any(|x| x.digest() == type_envelope.digest())
+        self.has_type_digest(&type_envelope)
+    }
+
+    fn has_type_digest(&self, type_envelope: &Envelope) -> bool {
+        self.types().iter().any(|x| !x.is_obscured() && x.digest() == type_envelope.digest())
     }
 
     /// Succeeds if the envelope has an `'IsA'` type assertion with the given known value `t`.
@@ -58,11 +58,38 @@
 //! * [`Envelope::add_optional_assertion_envelope_salted`] Optionally adds an
 //!   assertion envelope to an envelope.
 //!
+//! ### Adding Assertions from Domain Types
+//!
+//! * [`Envelope::add_assertions_from`] Adds the assertions contributed by an
+//!   [`AssertionProvider`].
+//! * [`Envelope::extract_from_assertions`] Reconstructs a [`FromAssertions`]
+//!   type from an envelope's assertions.
+//!
+//! ### Building Envelopes in a Single Pass
+//!
+//! * [`EnvelopePipeline`] Collects a sequence of operations (assertions, types,
+//!   wrapping, signing, encryption) and executes them with a single node
+//!   construction per run of assertions.
+//! * [`ConcurrentNodeBuilder`] Accumulates the assertions of a node pushed
+//!   from several threads, and constructs the node once (requires the
+//!   `multithreaded` feature).
+//!
+//! ### Adding Assertions with Known Value Predicates
+//!
+//! * [`Envelope::new_assertion_with_known_predicate`] Creates an assertion
+//!   envelope with a known value predicate.
+//! * [`Envelope::add_note`] Adds a `'note'` assertion.
+//! * [`Envelope::add_id`] Adds an `'id'` assertion.
+//! * [`Envelope::add_name`] Adds a `'hasName'` assertion.
+//! * [`Envelope::add_date`] Adds a `'date'` assertion.
+//!
 //! # Removing and Replacing Assertions
 //!
 //! * [`Envelope::remove_assertion`] Removes an assertion from an envelope.
 //! * [`Envelope::replace_assertion`] Replaces an assertion in an envelope.
 //! * [`Envelope::replace_subject`] Replaces the subject of an envelope.
+//! * [`Envelope::map_subject`] Replaces the subject of an envelope with the
+//!   result of applying a function to it.
 //!
 //! # Queries
 //!
@@ -99,6 +126,7 @@
 //! * [`Envelope::is_encrypted`] Returns whether an envelope is encrypted.
 //! * [`Envelope::is_compressed`] Returns whether an envelope is compressed.
 //! * [`Envelope::is_elided`] Returns whether an envelope is elided.
+//! * [`Envelope::case_name`] Returns the name of an envelope's case.
 //!
 //! ### Determining the type of an envelope’s subject
 //!
@@ -125,6 +153,12 @@
 //!   with the matching predicate.
 //! * [`Envelope::elements_count`] Returns the number of elements in the
 //!   envelope.
+//! * [`Envelope::stats`] Returns the counts of the envelope's elements by
+//!   case, its greatest depth, and the size of its encoding.
+//! * [`Envelope::is_fully_revealed`] Returns whether no part of the envelope
+//!   is elided, encrypted, or compressed.
+//! * [`Envelope::registry_dependencies`] Returns the known values,
+//!   functions, parameters, and tags the envelope relies on.
 //!
 //! ### Extracting parts of envelopes as specific types
 //!
@@ -135,6 +169,11 @@
 //! * [`Envelope::extract_objects_for_predicate`] Returns the objects of all
 //!   assertions with the matching predicate, decoded as the given type.
 //!
+//! ### Comparing encoded envelopes
+//!
+//! * [`compare_encoded`] Reports where two encoded envelopes differ, without
+//!   parsing or decoding them.
+//!
 //! ### Other queries
 //!
 //! * [`Envelope::is_internal`] Returns whether an envelope is internal, that
@@ -144,9 +183,17 @@
 //!
 //! # Wrapping and Unwrapping Envelopes
 //!
+//! * [`Envelope::wrap`] Wraps an envelope in a new envelope.
 //! * [`Envelope::wrap_envelope`] Wraps an envelope in a new envelope.
 //! * [`Envelope::unwrap_envelope`] Unwraps an envelope.
 //!
+//! # Translating Predicates
+//!
+//! * [`Envelope::expand_predicates`] Replaces compact predicates with URIs
+//!   using a [`PredicateContext`].
+//! * [`Envelope::compact_predicates`] Replaces URI predicates with compact
+//!   predicates using a [`PredicateContext`].
+//!
 //! # Formatting Envelopes
 //!
 //! ### Envelope notation
@@ -160,6 +207,10 @@
 //! * [`Envelope::tree_format`] Formats an envelope in envelope tree notation.
 //! * [`Envelope::tree_format_with_target`] Formats an envelope in envelope tree
 //!   notation, highlighting a target set of elements.
+//! * [`Envelope::tree_format_annotated`] Formats an envelope in envelope tree
+//!   notation, with an annotation for each of a set of elements.
+//! * [`annotate_format`] Formats an envelope in envelope tree notation, with
+//!   the metadata in an [`ElementSidecar`].
 //!
 //! ### CBOR diagnostic notation
 //!
@@ -173,6 +224,15 @@
 //! * [`Envelope::hex_opt`] Formats an envelope in CBOR hexadecimal notation,
 //!   with optional annotations.
 //!
+//! ### Uniform Resources
+//!
+//! * [`Envelope::ur_string`] Returns the UR of an envelope.
+//! * [`Envelope::from_ur_string`] Decodes an envelope from its UR.
+//! * [`Envelope::ur_parts`] Returns the parts of the multipart UR of an
+//!   envelope, for envelopes too large for a single QR code.
+//! * [`EnvelopeURDecoder`] Reassembles an envelope from the parts of its
+//!   multipart UR, received in any order.
+//!
 //! # Working with the Digest Tree
 //!
 //! ### Semantic equivalence
@@ -186,6 +246,10 @@
 //!   envelope, down to its second level.
 //! * [`Envelope::is_equivalent_to`] Tests two envelopes for semantic
 //!   equivalence.
+//! * [`Envelope::digest_hex`] Returns the envelope's digest as a hexadecimal
+//!   string.
+//! * [`Envelope::short_digest_hex`] Returns a prefix of the envelope's digest
+//!   as a hexadecimal string.
 //!
 //! ### Structural identicality
 //!
@@ -234,6 +298,15 @@
 //! * [`Envelope::verify_signatures_from_threshold`] Checks whether the
 //!   envelope's subject has some threshold of signatures.
 //!
+//! ### Signing ceremonies
+//!
+//! * [`Envelope::countersign_with_sequence`] Wraps and signs an envelope,
+//!   recording the signer's sequence number and date.
+//! * [`Envelope::ceremony_report`] Reconstructs the ordered layers of a signing
+//!   ceremony, flagging inconsistencies.
+//! * [`Envelope::validate_ceremony`] Checks that a signing ceremony is
+//!   consistent, optionally requiring strictly increasing dates.
+//!
 //! ### Helpers
 //!
 //! * [`Envelope::signatures`] Returns an array of `Signature`s from all of the
@@ -271,12 +344,25 @@
 //! # Compression
 //!
 //! * [`Envelope::compress`] Returns the compressed variant of this envelope.
+//! * [`Envelope::compress_if_smaller`] Returns the compressed variant of this
+//!   envelope, or the envelope unchanged if compression doesn't help.
+//! * [`Envelope::compress_with_level`] Returns the compressed variant of this
+//!   envelope, using the given [`CompressionLevel`].
 //! * [`Envelope::uncompress`] Returns the uncompressed variant of this
 //!   envelope.
+//! * [`Envelope::uncompress_with_limits`] Returns the uncompressed variant of
+//!   this envelope, subject to the given [`EnvelopeLimits`].
 //! * [`Envelope::compress_subject`] Returns this envelope with its subject
 //!   compressed.
 //! * [`Envelope::uncompress_subject`] Returns this envelope with its subject
 //!   uncompressed.
+//! * [`Envelope::compress_tree`] Returns this envelope with every leaf
+//!   compressed individually.
+//! * [`Envelope::uncompress_tree`] Returns this envelope with every compressed
+//!   element uncompressed.
+//! * [`Envelope::uncompress_all`] Same as [`Envelope::uncompress_tree`].
+//! * [`Envelope::compress_assertion_objects_larger_than`] Returns this
+//!   envelope with every large assertion object compressed.
 //!
 //! # Eliding, Encrypting, or Compressing Parts of an Envelope
 //!
@@ -314,6 +400,12 @@
 //!     * [`Envelope::elide_array_with_action`]
 //!     * [`Envelope::elide_target_with_action`]
 //!
+//! * Selects elements with a path expression such as
+//!   `wrapped/assertion[firstName]/object`:
+//!     * [`Envelope::resolve_path_expr`]
+//!     * [`Envelope::elide_removing_path`]
+//!     * [`Envelope::elide_revealing_path`]
+//!
 //! * [`Envelope::unelide`] Returns the unelided variant of this envelope, given
 //!   the envelope that was elided.
 //!
@@ -374,12 +466,33 @@
 pub use anyhow::Result;
 
 pub mod base;
-pub use base::{Assertion, Envelope, EnvelopeEncodable, EnvelopeError};
-pub use base::{register_tags, register_tags_in, FormatContext, GLOBAL_FORMAT_CONTEXT};
+pub use base::{Assertion, AssertionProvider, FromAssertions, Envelope, EnvelopeEncodable, EnvelopeError, EnvelopeLimits, EnvelopePipeline};
+pub use base::{DecodeStats, EnvelopeStats, RegistryDeps, RegistryItem};
+pub use base::EnvelopeURDecoder;
+#[cfg(feature = "multithreaded")]
+pub use base::ConcurrentNodeBuilder;
+pub use base::{RandomProvider, SecureRandomProvider};
+#[cfg(feature = "test-support")]
+pub use base::{reset_random_provider, set_random_provider, SeededRandomProvider};
+pub use base::{register_tags, register_tags_in, FoldOptions, FormatContext, GLOBAL_FORMAT_CONTEXT};
+#[cfg(feature = "known_value")]
+pub use base::register_known_value;
+pub use base::{PredicateContext, PredicateTerm};
+pub use base::{set_tagged_leaf_policy, tagged_leaf_policy, SuspicionKind, TaggedLeafPolicy};
+pub use base::{compare_encoded, ComparisonResult, Divergence};
+pub use base::{PseudonymReplacement, PseudonymizationReport, RedactionPreset, TAG_PSEUDONYM};
+pub use base::{annotate_format, ElementSidecar, SidecarConflictPolicy};
+#[cfg(any(feature = "encrypt", feature = "compress"))]
+pub use base::{DetachedPayload, PayloadKind};
+#[cfg(feature = "async")]
+pub use base::EnvelopeStream;
 pub use base::elide::{self, ObscureAction};
 
 pub mod extension;
 pub mod prelude;
+pub mod conformance;
+#[cfg(feature = "test-support")]
+pub mod corpus;
 
 mod string_utils;
 
@@ -387,7 +500,13 @@ mod string_utils;
 use bc_components::{Signer, Verifier};
 
 #[cfg(feature = "signature")]
-pub use extension::SignatureMetadata;
+pub use extension::{CeremonyIssue, CeremonyStep, SignatureMetadata};
+
+#[cfg(feature = "compress")]
+pub use extension::CompressionLevel;
+
+#[cfg(feature = "parity")]
+pub use extension::{ParityLevel, RecoveryReport};
 
 #[cfg(feature = "recipient")]
 use bc_components::{PrivateKeyBase, PublicKeyBase};
@@ -1,47 +1,199 @@
-pub use crate::{
-    Envelope,
-    EnvelopeEncodable,
-    FormatContext,
-    with_format_context,
-    register_tags,
-    register_tags_in,
-};
+//! The items most programs need to work with envelopes.
+//!
+//! `use bc_envelope::prelude::*;` imports only names defined by this crate,
+//! so it can be glob-imported alongside the preludes of other crates,
+//! including `bc_components` and `dcbor`, without ambiguity. The foreign
+//! traits that provide envelope methods, such as
+//! [`DigestProvider::digest`](bc_components::DigestProvider::digest) and
+//! [`UREncodable::ur`](bc_ur::UREncodable::ur), are imported
+//! anonymously: their methods are available, but their names are not.
+//!
+//! [`full`] adds the format context and known value registries, the
+//! extension types, and the commonly used types of `bc_components`,
+//! `dcbor`, and `bc_ur`, each listed explicitly.
+//!
+//! [`legacy`] is the prelude of releases up to 0.18.2, which glob
+//! re-exported the `bc_ur` and `dcbor` preludes. It is deprecated and will be removed in
+//! the next release.
 
-#[cfg(feature = "known_value")]
-pub use crate::{
-    known_values,
-    KnownValue,
-    KnownValuesStore,
-};
+/// The envelope type.
+pub use crate::Envelope;
 
-#[cfg(feature = "signature")]
-pub use crate::SignatureMetadata;
-
-#[cfg(feature = "expression")]
+/// Conversions to and from envelopes.
 pub use crate::{
-    Function,
-    functions,
-    Parameter,
-    parameters,
-    Expression,
-    ExpressionBehavior,
-    IntoExpression,
-    Request,
-    RequestBehavior,
-    Response,
-    ResponseBehavior,
-    Event,
-    EventBehavior,
+    AssertionProvider,
+    EnvelopeEncodable,
+    FromAssertions,
 };
 
+/// The error returned when handling envelopes.
+pub use crate::EnvelopeError;
+
+/// Elision.
 pub use crate::elide::{
-    ObscureAction,
     self,
+    ObscureAction,
 };
 
-pub use bc_components::{
-    Digest,
-    DigestProvider,
+/// Access to the global format context.
+pub use crate::{
+    with_format_context,
+    with_format_context_mut,
 };
 
-pub use bc_ur::prelude::*;
+/// Foreign traits that provide envelope methods, imported anonymously.
+pub use bc_components::DigestProvider as _;
+pub use bc_ur::{URDecodable as _, UREncodable as _};
+pub use dcbor::{CBORDecodable as _, CBOREncodable as _, CBORTaggedDecodable as _, CBORTaggedEncodable as _};
+
+/// The minimal prelude, along with the registries, the extension types, and
+/// the commonly used types of the crates envelopes are built on.
+///
+/// The foreign types are listed explicitly. Some of them, such as `Digest`
+/// and `Tag`, have common names, so prefer the minimal prelude when
+/// glob-importing alongside other crates.
+pub mod full {
+    pub use super::*;
+
+    /// Envelope construction and formatting.
+    pub use crate::{
+        DecodeStats,
+        EnvelopeLimits,
+        EnvelopePipeline,
+        EnvelopeStats,
+        EnvelopeURDecoder,
+        FoldOptions,
+        FormatContext,
+        RegistryDeps,
+        RegistryItem,
+        register_tags,
+        register_tags_in,
+    };
+
+    /// Concurrent node construction.
+    #[cfg(feature = "multithreaded")]
+    pub use crate::ConcurrentNodeBuilder;
+
+    /// Known values and their registry.
+    #[cfg(feature = "known_value")]
+    pub use crate::{
+        known_values,
+        register_known_value,
+        KnownValue,
+        KnownValuesStore,
+    };
+
+    /// Signature metadata.
+    #[cfg(feature = "signature")]
+    pub use crate::SignatureMetadata;
+
+    /// Compression levels.
+    #[cfg(feature = "compress")]
+    pub use crate::CompressionLevel;
+
+    /// Parity levels.
+    #[cfg(feature = "parity")]
+    pub use crate::ParityLevel;
+
+    /// Expressions, requests, responses, and events.
+    #[cfg(feature = "expression")]
+    pub use crate::{
+        Function,
+        functions,
+        Parameter,
+        parameters,
+        Expression,
+        ExpressionBehavior,
+        IntoExpression,
+        Request,
+        RequestBehavior,
+        Response,
+        ResponseBehavior,
+        Event,
+        EventBehavior,
+    };
+
+    /// Digests, from `bc_components`.
+    pub use bc_components::{
+        Digest,
+        DigestProvider,
+    };
+
+    /// CBOR values and codecs, from `dcbor`.
+    pub use dcbor::{
+        ByteString,
+        CBOR,
+        CBORCase,
+        CBORCodable,
+        CBORDecodable,
+        CBOREncodable,
+        CBORError,
+        CBORTagged,
+        CBORTaggedCodable,
+        CBORTaggedDecodable,
+        CBORTaggedEncodable,
+        Date,
+        Map,
+        Tag,
+    };
+
+    /// Uniform Resources, from `bc_ur`.
+    pub use bc_ur::{
+        UR,
+        URCodable,
+        URDecodable,
+        UREncodable,
+    };
+}
+
+/// The prelude of earlier releases, up to 0.18.2.
+#[deprecated(since = "0.18.3", note = "use `prelude` or `prelude::full` instead")]
+pub mod legacy {
+    pub use crate::{
+        Envelope,
+        EnvelopeEncodable,
+        FormatContext,
+        with_format_context,
+        register_tags,
+        register_tags_in,
+    };
+
+    #[cfg(feature = "known_value")]
+    pub use crate::{
+        known_values,
+        KnownValue,
+        KnownValuesStore,
+    };
+
+    #[cfg(feature = "signature")]
+    pub use crate::SignatureMetadata;
+
+    #[cfg(feature = "expression")]
+    pub use crate::{
+        Function,
+        functions,
+        Parameter,
+        parameters,
+        Expression,
+        ExpressionBehavior,
+        IntoExpression,
+        Request,
+        RequestBehavior,
+        Response,
+        ResponseBehavior,
+        Event,
+        EventBehavior,
+    };
+
+    pub use crate::elide::{
+        ObscureAction,
+        self,
+    };
+
+    pub use bc_components::{
+        Digest,
+        DigestProvider,
+    };
+
+    pub use bc_ur::prelude::*;
+}
@@ -13,3 +13,68 @@ impl StringUtils for String {
         format!("{}{}{}", left, self, right)
     }
 }
+
+/// Returns the given name in double quotes, escaping `"` and `\` with `\`.
+#[cfg(feature = "expression")]
+pub fn quoted(name: &str) -> String {
+    let mut result = String::with_capacity(name.len() + 2);
+    result.push('"');
+    for c in name.chars() {
+        if c == '"' || c == '\\' {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result.push('"');
+    result
+}
+
+/// A registry item reference parsed by [`parse_registry_text`].
+#[cfg(feature = "known_value")]
+#[derive(Debug, PartialEq)]
+pub enum RegistryText<'a> {
+    /// A bare name, to be resolved in a registry.
+    Name(&'a str),
+    /// A double-quoted name, with escapes removed.
+    Quoted(String),
+    /// A numeric value, with or without the kind prefix.
+    Value(u64),
+}
+
+/// Parses the textual form of a registry item reference.
+///
+/// The text may be flanked by `left` and `right`, and a numeric value may be
+/// preceded by `prefix`. A bare name may contain spaces, but only when it is
+/// flanked, and not at its ends. Returns `None` if the text is malformed.
+#[cfg(feature = "known_value")]
+pub fn parse_registry_text<'a>(text: &'a str, left: &str, right: &str, prefix: &str) -> Option<RegistryText<'a>> {
+    let (text, flanked) = match (text.strip_prefix(left), text.strip_suffix(right)) {
+        (Some(inner), Some(_)) if inner.len() >= right.len() => (&inner[..inner.len() - right.len()], true),
+        (None, None) => (text, false),
+        _ => return None,
+    };
+    if let Some(inner) = text.strip_prefix('"') {
+        let mut name = String::new();
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => name.push(chars.next()?),
+                '"' => return chars.as_str().is_empty().then_some(RegistryText::Quoted(name)),
+                _ => name.push(c),
+            }
+        }
+        return None;
+    }
+    let digits = text.strip_prefix(prefix).unwrap_or(text);
+    if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+        return digits.parse().ok().map(RegistryText::Value);
+    }
+    if text.is_empty() || text.starts_with(char::is_whitespace) || text.ends_with(char::is_whitespace) {
+        return None;
+    }
+    let is_allowed_space = |c: char| flanked && c == ' ';
+    if text.chars().any(|c| (c.is_whitespace() && !is_allowed_space(c)) || c == '"' || c == '\\' || c == ':') {
+        return None;
+    }
+    Some(RegistryText::Name(text))
+}
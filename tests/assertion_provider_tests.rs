@@ -0,0 +1,53 @@
+#![cfg(feature = "types")]
+
+use bc_envelope::prelude::*;
+use bc_envelope::{EnvelopeError, EnvelopePipeline, known_values};
+use dcbor::CBOR;
+use hex_literal::hex;
+
+mod common;
+use crate::common::test_seed::*;
+
+fn alice_seed() -> Seed {
+    Seed::new_opt(
+        hex!("82f32c855d3d542256180810797e0073"),
+        "Alice's Seed",
+        "This is the note.",
+        Some(dcbor::Date::from_string("2024-01-01").unwrap()),
+    )
+}
+
+#[test]
+fn test_assertion_provider_round_trip() {
+    let seed = alice_seed();
+    let envelope = Envelope::new(CBOR::to_byte_string(seed.data()))
+        .add_type(known_values::SEED_TYPE)
+        .add_assertion("unrelated", "ignored")
+        .add_assertions_from(&seed.metadata()).unwrap()
+        .check_encoding().unwrap();
+
+    // Contributing the metadata is equivalent to the seed's own encoding.
+    let expected = Envelope::from(seed.clone()).add_assertion("unrelated", "ignored");
+    assert!(envelope.is_identical_to(&expected));
+
+    let metadata: SeedMetadata = envelope.extract_from_assertions().unwrap();
+    assert_eq!(metadata, seed.metadata());
+
+    let pipelined = EnvelopePipeline::new(CBOR::to_byte_string(seed.data()))
+        .add_type(known_values::SEED_TYPE)
+        .add_assertion("unrelated", "ignored")
+        .add_assertions_from(&seed.metadata())
+        .build().unwrap();
+    assert!(pipelined.is_identical_to(&envelope));
+}
+
+#[test]
+fn test_from_assertions_missing_field() {
+    let envelope = Envelope::new(CBOR::to_byte_string(alice_seed().data()))
+        .add_assertion(known_values::NOTE, "No name.");
+    let error = envelope.extract_from_assertions::<SeedMetadata>().unwrap_err();
+    match error.downcast_ref::<EnvelopeError>() {
+        Some(EnvelopeError::NonexistentPredicate { predicate }) => assert_eq!(predicate, "'hasName'"),
+        _ => panic!("unexpected error: {}", error),
+    }
+}
@@ -1,6 +1,7 @@
 #![cfg(feature = "attachment")]
 
 use bc_envelope::prelude::*;
+use bc_envelope::known_values;
 use indoc::indoc;
 
 mod common;
@@ -62,8 +63,54 @@ fn test_attachment() -> anyhow::Result<()> {
 
     let seed_envelope2 = seed.to_envelope();
     let attachments = seed_envelope.attachments()?;
-    let seed_envelope2 = seed_envelope2.add_assertions(&attachments);
+    let seed_envelope2 = seed_envelope2.add_assertions(&attachments).unwrap();
     assert!(seed_envelope2.is_equivalent_to(&seed_envelope));
 
     Ok(())
 }
+
+#[test]
+fn test_attachments_from_different_vendors() -> anyhow::Result<()> {
+    let envelope = Envelope::new("Alice")
+        .add_attachment("Data A", "com.example", None)
+        .add_attachment("Data B", "org.example", Some("https://example.org/b/v1"));
+
+    assert_eq!(envelope.attachments()?.len(), 2);
+
+    let attachments = envelope.attachments_with_vendor_and_conforms_to(Some("org.example"), None)?;
+    assert_eq!(attachments.len(), 1);
+    let attachment = &attachments[0];
+    assert_eq!(attachment.attachment_vendor()?, "org.example");
+    assert_eq!(attachment.attachment_conforms_to()?, Some("https://example.org/b/v1".to_string()));
+    assert_eq!(attachment.attachment_payload()?.extract_subject::<String>()?, "Data B");
+
+    let attachment = envelope.attachment_with_vendor_and_conforms_to(Some("com.example"), None)?;
+    assert_eq!(attachment.attachment_conforms_to()?, None);
+    assert_eq!(attachment.attachment_payload()?.extract_subject::<String>()?, "Data A");
+
+    // An attachment that does not conform to the filter's `conformsTo` is excluded.
+    assert!(envelope.attachments_with_vendor_and_conforms_to(Some("com.example"), Some("https://example.org/b/v1"))?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_attachment_missing_vendor() {
+    let is_invalid_attachment = |error: anyhow::Error| {
+        matches!(error.downcast_ref::<bc_envelope::EnvelopeError>(), Some(bc_envelope::EnvelopeError::InvalidAttachment))
+    };
+
+    let malformed = Envelope::new_assertion(known_values::ATTACHMENT, Envelope::new("Data").wrap_envelope());
+    assert!(is_invalid_attachment(malformed.validate_attachment().unwrap_err()));
+    assert!(malformed.attachment_vendor().is_err());
+
+    let envelope = Envelope::new("Alice")
+        .add_attachment("Data A", "com.example", None)
+        .add_assertion_envelope(malformed)
+        .unwrap();
+    assert!(is_invalid_attachment(envelope.attachments().unwrap_err()));
+    assert!(is_invalid_attachment(envelope.attachments_with_vendor_and_conforms_to(Some("com.example"), None).unwrap_err()));
+
+    // A payload that is not wrapped is also invalid.
+    let unwrapped = Envelope::new_assertion(known_values::ATTACHMENT, Envelope::new("Data").add_assertion(known_values::VENDOR, "com.example"));
+    assert!(is_invalid_attachment(unwrapped.validate_attachment().unwrap_err()));
+}
@@ -1,3 +1,2 @@
 pub mod test_data;
 pub mod test_seed;
-pub mod check_encoding;
@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 
 use bc_envelope::prelude::*;
+use bc_envelope::known_values;
 use hex_literal::hex;
 use bc_components::{PrivateKeyBase, PublicKeyBase, SymmetricKey, Nonce};
 
@@ -4,6 +4,7 @@ use anyhow::{bail, Error, Result};
 use bc_components::tags;
 use bc_ur::prelude::*;
 use bc_envelope::prelude::*;
+use bc_envelope::{EnvelopeError, known_values};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Seed {
@@ -62,6 +63,63 @@ impl Seed {
     pub fn set_creation_date(&mut self, creation_date: Option<impl AsRef<dcbor::Date>>) {
         self.creation_date = creation_date.map(|s| s.as_ref().clone());
     }
+
+    pub fn metadata(&self) -> SeedMetadata {
+        SeedMetadata {
+            name: self.name.clone(),
+            note: self.note.clone(),
+            creation_date: self.creation_date.clone(),
+        }
+    }
+}
+
+/// The descriptive metadata of a seed, carried as assertions on the seed's
+/// envelope. The name is required.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeedMetadata {
+    pub name: String,
+    pub note: String,
+    pub creation_date: Option<dcbor::Date>,
+}
+
+impl AssertionProvider for SeedMetadata {
+    fn assertions(&self) -> Vec<Envelope> {
+        let mut assertions = vec![Envelope::new_assertion(known_values::HAS_NAME, self.name.as_str())];
+        if !self.note.is_empty() {
+            assertions.push(Envelope::new_assertion(known_values::NOTE, self.note.as_str()));
+        }
+        if let Some(creation_date) = &self.creation_date {
+            assertions.push(Envelope::new_assertion(known_values::DATE, creation_date.clone()));
+        }
+        assertions
+    }
+}
+
+impl FromAssertions for SeedMetadata {
+    fn from_assertions(assertions: &[Envelope]) -> Result<Self> {
+        let mut name = None;
+        let mut note = String::new();
+        let mut creation_date = None;
+        for assertion in assertions {
+            let (Some(predicate), Some(object)) = (assertion.as_predicate(), assertion.as_object()) else {
+                continue;
+            };
+            let Some(predicate) = predicate.as_known_value() else {
+                continue;
+            };
+            if *predicate == known_values::HAS_NAME {
+                name = Some(object.extract_subject::<String>()?);
+            } else if *predicate == known_values::NOTE {
+                note = object.extract_subject::<String>()?;
+            } else if *predicate == known_values::DATE {
+                creation_date = Some(object.extract_subject::<dcbor::Date>()?);
+            }
+        }
+        let Some(name) = name else {
+            bail!(EnvelopeError::NonexistentPredicate { predicate: Envelope::new(known_values::HAS_NAME).format_flat() });
+        };
+        Ok(Self { name, note, creation_date })
+    }
 }
 
 impl CBORTagged for Seed {
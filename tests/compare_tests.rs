@@ -0,0 +1,70 @@
+use bc_envelope::prelude::*;
+use dcbor::CBOR;
+use bc_envelope::compare_encoded;
+
+mod common;
+use crate::common::test_data::*;
+
+#[test]
+fn test_compare_identical() {
+    let data = double_assertion_envelope().tagged_cbor().to_cbor_data();
+    let result = compare_encoded(&data, &data.clone(), 10).unwrap();
+    assert!(result.identical);
+    assert!(result.divergences.is_empty());
+    assert!(!result.truncated);
+}
+
+#[test]
+fn test_compare_single_leaf() {
+    let a = Envelope::new("Alice").add_assertion("knows", "Bob").wrap().add_assertion("note", "A note.");
+    let b = Envelope::new("Alice").add_assertion("knows", "Carol").wrap().add_assertion("note", "A note.");
+    let a_data = a.tagged_cbor().to_cbor_data();
+    let b_data = b.tagged_cbor().to_cbor_data();
+
+    let result = compare_encoded(&a_data, &b_data, 10).unwrap();
+    assert!(!result.identical);
+    assert!(!result.truncated);
+    assert_eq!(result.divergences.len(), 1);
+    let divergence = &result.divergences[0];
+    assert_eq!(divergence.path, vec!["subject", "wrapped", "assertion[0]", "object"]);
+    assert_eq!((divergence.kind_a, divergence.kind_b), ("Leaf", "Leaf"));
+
+    // The offsets locate the differing leaves in each encoding.
+    let bob = Envelope::new("Bob").untagged_cbor().to_cbor_data();
+    let carol = Envelope::new("Carol").untagged_cbor().to_cbor_data();
+    let offset_a = divergence.offset_a.unwrap();
+    let offset_b = divergence.offset_b.unwrap();
+    assert_eq!(&a_data[offset_a..offset_a + bob.len()], bob.as_slice());
+    assert_eq!(&b_data[offset_b..offset_b + carol.len()], carol.as_slice());
+}
+
+#[test]
+fn test_compare_structural_difference() {
+    let content = Envelope::new("Alice").add_assertion("knows", "Bob");
+    let a = content.tagged_cbor().to_cbor_data();
+    let b = content.wrap().tagged_cbor().to_cbor_data();
+    let result = compare_encoded(&a, &b, 10).unwrap();
+    assert_eq!(result.divergences.len(), 1);
+    assert!(result.divergences[0].path.is_empty());
+    assert_eq!((result.divergences[0].kind_a, result.divergences[0].kind_b), ("Node", "Wrapped"));
+
+    // Many differences are reported only up to the limit.
+    let a = (0..5).fold(Envelope::new("Alice"), |e, i| e.add_assertion(format!("p{}", i), "a"));
+    let b = (0..5).fold(Envelope::new("Alice"), |e, i| e.add_assertion(format!("p{}", i), "b"));
+    let result = compare_encoded(&a.tagged_cbor().to_cbor_data(), &b.tagged_cbor().to_cbor_data(), 2).unwrap();
+    assert_eq!(result.divergences.len(), 2);
+    assert!(result.truncated);
+
+    // A missing assertion is reported as absent on one side.
+    let a = Envelope::new("Alice").add_assertion("knows", "Bob");
+    let b = a.add_assertion("knows", "Carol");
+    let result = compare_encoded(&a.tagged_cbor().to_cbor_data(), &b.tagged_cbor().to_cbor_data(), 10).unwrap();
+    assert!(result.divergences.iter().any(|d| d.kind_a == "Absent" && d.offset_a.is_none()));
+
+    assert!(compare_encoded(&a.tagged_cbor().to_cbor_data(), &CBOR::from(1).to_cbor_data(), 10).is_err());
+
+    // Truncated or trailing data is not a well-formed envelope.
+    let data = a.tagged_cbor().to_cbor_data();
+    assert!(compare_encoded(&data, &data[..data.len() - 1], 10).is_err());
+    assert!(compare_encoded(&data, &[data.as_slice(), &[0x00]].concat(), 10).is_err());
+}
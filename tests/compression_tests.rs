@@ -1,11 +1,11 @@
 #![cfg(feature = "compress")]
-use bc_components::DigestProvider;
+use bc_components::{Compressed, DigestProvider};
 
 use dcbor::prelude::*;
 use bc_envelope::prelude::*;
+use bc_envelope::{CompressionLevel, EnvelopeError, EnvelopeLimits};
 
 mod common;
-use crate::common::check_encoding::*;
 
 #[cfg(feature = "signature")]
 use crate::common::test_data::*;
@@ -29,6 +29,36 @@ fn test_compress() {
     assert_eq!(uncompressed.structural_digest(), original.structural_digest());
 }
 
+#[test]
+fn test_checked_decoding() {
+    let original = Envelope::new(SOURCE).add_assertion("note", "compressed");
+    let compressed = original.compress().unwrap();
+    let data = compressed.tagged_cbor().to_cbor_data();
+    let decoded = Envelope::from_tagged_cbor_data_checked(data).unwrap();
+    assert!(decoded.is_identical_to(&compressed));
+
+    // A compressed payload whose declared digest doesn't match its contents.
+    let declared_digest = Envelope::new("Something else").digest().into_owned();
+    let corrupt = Compressed::from_uncompressed_data(
+        Envelope::new(SOURCE).tagged_cbor().to_cbor_data(),
+        Some(declared_digest.clone()),
+    );
+    let corrupt_envelope = Envelope::new("Alice")
+        .add_assertion_envelope(Envelope::new_assertion("knows", Envelope::try_from(corrupt).unwrap()))
+        .unwrap();
+
+    // Unchecked decoding accepts it.
+    let data = corrupt_envelope.tagged_cbor().to_cbor_data();
+    assert!(Envelope::from_tagged_cbor_data(data.clone()).is_ok());
+
+    // Checked decoding identifies the offending element.
+    let error = Envelope::from_tagged_cbor_data_checked(data).unwrap_err();
+    match error.downcast_ref::<EnvelopeError>() {
+        Some(EnvelopeError::InvalidElement(digest)) => assert_eq!(digest, &declared_digest),
+        _ => panic!("unexpected error: {}", error),
+    }
+}
+
 #[cfg(feature = "signature")]
 #[test]
 fn test_compress_subject() {
@@ -73,3 +103,196 @@ fn test_compress_subject() {
     assert_eq!(uncompressed.digest(), original.digest());
     assert_eq!(uncompressed.structural_digest(), original.structural_digest());
 }
+
+#[test]
+fn test_compression_levels() {
+    // A 1 MB payload with some redundancy.
+    let mut source = String::new();
+    let mut i: u64 = 0;
+    while source.len() < 1_000_000 {
+        source.push_str(&format!("{} ", i.wrapping_mul(7919) % 100_000));
+        i += 1;
+    }
+    let original = Envelope::new(source);
+
+    let fast = original.compress_with_level(CompressionLevel::Fast).unwrap().check_encoding().unwrap();
+    let default = original.compress_with_level(CompressionLevel::Default).unwrap().check_encoding().unwrap();
+    let best = original.compress_with_level(CompressionLevel::Best).unwrap().check_encoding().unwrap();
+
+    assert!(best.to_cbor_data().len() <= fast.to_cbor_data().len());
+    assert_eq!(default.to_cbor_data(), original.compress().unwrap().to_cbor_data());
+
+    for compressed in [fast, default, best] {
+        assert_eq!(compressed.digest(), original.digest());
+        let uncompressed = compressed.uncompress().unwrap();
+        assert!(uncompressed.is_identical_to(&original));
+    }
+}
+
+/// Returns the checksum, declared size, and data of a compressed envelope.
+fn compressed_fields(envelope: &Envelope) -> (u32, usize, Vec<u8>) {
+    let compressed = Compressed::from_tagged_cbor(envelope.untagged_cbor()).unwrap();
+    let elements = compressed.untagged_cbor().try_into_array().unwrap();
+    (
+        elements[0].clone().try_into().unwrap(),
+        elements[1].clone().try_into().unwrap(),
+        elements[2].clone().try_into_byte_string().unwrap(),
+    )
+}
+
+#[test]
+fn test_uncompress_limits() {
+    // A highly compressible payload inflates far beyond its compressed size.
+    let original = Envelope::new("0".repeat(1_000_000));
+    let compressed = original.compress().unwrap();
+    assert!(compressed.to_cbor_data().len() < 2_000);
+
+    // Normal payloads are unaffected.
+    assert!(compressed.uncompress().unwrap().is_identical_to(&original));
+    assert!(Envelope::new(SOURCE).compress().unwrap().uncompress().is_ok());
+
+    // The ceiling rejects the payload before it is inflated.
+    let limits = EnvelopeLimits::default().with_max_uncompressed_size(100_000);
+    let error = compressed.uncompress_with_limits(&limits).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<EnvelopeError>(),
+        Some(EnvelopeError::UncompressedSizeExceeded { limit: 100_000, .. })
+    ));
+    let node = Envelope::new(original.clone()).add_assertion("note", "zeros");
+    let node = node.compress_subject().unwrap();
+    assert!(node.uncompress_subject_with_limits(&limits).is_err());
+    assert!(node.uncompress_subject().is_ok());
+}
+
+#[test]
+fn test_uncompress_size_lies() {
+    let original = Envelope::new("0".repeat(100_000));
+    let compressed = original.compress().unwrap();
+    let (checksum, size, data) = compressed_fields(&compressed);
+    let digest = original.digest().into_owned();
+
+    // Claims to be smaller than it is.
+    let lying = Compressed::new(checksum, size / 2, data.clone(), Some(digest.clone())).unwrap();
+    let error = Envelope::try_from(lying).unwrap().uncompress().unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<EnvelopeError>(),
+        Some(EnvelopeError::UncompressedSizeMismatch { .. })
+    ));
+
+    // Claims to be larger than it is.
+    let lying = Compressed::new(checksum, size * 2, data, Some(digest)).unwrap();
+    let error = Envelope::try_from(lying).unwrap().uncompress().unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<EnvelopeError>(),
+        Some(EnvelopeError::UncompressedSizeMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_compress_if_smaller() {
+    // A highly compressible string is compressed.
+    let original = Envelope::new(SOURCE.repeat(10));
+    let compressed = original.compress_if_smaller().unwrap().check_encoding().unwrap();
+    assert!(compressed.is_compressed());
+    assert!(compressed.to_cbor_data().len() < original.to_cbor_data().len());
+    assert_eq!(compressed.digest(), original.digest());
+
+    // Random bytes don't compress, so the original is returned.
+    let original = Envelope::new(CBOR::to_byte_string(bc_rand::random_data(32)));
+    assert!(original.compress().unwrap().to_cbor_data().len() >= original.to_cbor_data().len());
+    let result = original.compress_if_smaller().unwrap();
+    assert!(!result.is_compressed());
+    assert!(result.is_identical_to(&original));
+}
+
+#[test]
+fn test_compress_tree() {
+    let original = Envelope::new("Alice")
+        .add_assertion("knows", Envelope::new("Bob").add_assertion("note", SOURCE))
+        .add_assertion("bio", SOURCE)
+        .wrap_envelope()
+        .add_assertion("issued", "today")
+        .elide_removing_target(&Envelope::new_assertion("issued", "today"));
+
+    let compressed = original.compress_tree().unwrap().check_encoding().unwrap();
+    assert_eq!(compressed.digest(), original.digest());
+    assert!(compressed.is_node());
+    assert!(compressed.subject().is_wrapped());
+    assert_eq!(compressed.format(), indoc::indoc! {r#"
+    {
+        COMPRESSED [
+            COMPRESSED: COMPRESSED
+            COMPRESSED: COMPRESSED [
+                COMPRESSED: COMPRESSED
+            ]
+        ]
+    } [
+        ELIDED
+    ]
+    "#}.trim());
+
+    // Individual compressed leaves can still be elided.
+    let elided = compressed.elide_removing_target(&Envelope::new(SOURCE));
+    assert_eq!(elided.digest(), original.digest());
+
+    let uncompressed = compressed.uncompress_tree().unwrap().check_encoding().unwrap();
+    assert!(uncompressed.is_identical_to(&original));
+
+    // Uncompressing also expands elements compressed as a whole.
+    let whole = original.compress().unwrap();
+    assert!(whole.uncompress_tree().unwrap().is_identical_to(&original));
+}
+
+#[test]
+fn test_compress_assertion_objects_larger_than() {
+    let original = Envelope::new("Alice")
+        .add_assertion("bio", SOURCE)
+        .add_assertion("knows", Envelope::new("Bob").add_assertion("bio", SOURCE).add_assertion("age", 42))
+        .add_assertion("name", "Alice")
+        .wrap()
+        .add_assertion("note", SOURCE);
+    let original_size = original.to_cbor_data().len();
+
+    let compressed = original.compress_assertion_objects_larger_than(100).unwrap().check_encoding().unwrap();
+    assert_eq!(compressed.digest(), original.digest());
+    assert!(compressed.to_cbor_data().len() < original_size);
+    assert_eq!(compressed.format(), indoc::indoc! {r#"
+    {
+        "Alice" [
+            "bio": COMPRESSED
+            "knows": COMPRESSED
+            "name": "Alice"
+        ]
+    } [
+        "note": COMPRESSED
+    ]
+    "#}.trim());
+
+    // Already-compressed objects are left as they are.
+    let again = compressed.compress_assertion_objects_larger_than(0).unwrap();
+    assert_eq!(again.digest(), original.digest());
+    assert_eq!(again.format(), indoc::indoc! {r#"
+    {
+        "Alice" [
+            "bio": COMPRESSED
+            "knows": COMPRESSED
+            "name": COMPRESSED
+        ]
+    } [
+        "note": COMPRESSED
+    ]
+    "#}.trim());
+
+    assert!(again.uncompress_all().unwrap().is_identical_to(&original));
+}
+
+#[cfg(feature = "encrypt")]
+#[test]
+fn test_compress_assertion_objects_skips_encrypted() {
+    let key = bc_components::SymmetricKey::new();
+    let secret = Envelope::new(SOURCE).encrypt_subject(&key).unwrap();
+    let original = Envelope::new("Alice").add_assertion("secret", secret.clone());
+    let compressed = original.compress_assertion_objects_larger_than(0).unwrap();
+    assert!(compressed.is_identical_to(&original));
+    assert!(compressed.object_for_predicate("secret").unwrap().is_encrypted());
+}
@@ -2,9 +2,9 @@ use dcbor::prelude::*;
 use indoc::indoc;
 use bc_components::Digest;
 use bc_envelope::prelude::*;
+use bc_envelope::{EnvelopeError, FormatContext, known_values};
 
 mod common;
-use crate::common::check_encoding::*;
 
 #[test]
 fn test_digest() {
@@ -123,3 +123,207 @@ fn test_3() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_decoding_errors() {
+    // A node array with only a subject.
+    let cbor = CBOR::to_tagged_value(200, vec![CBOR::to_tagged_value(201, "Hello.")]);
+    let error = Envelope::from_tagged_cbor(cbor).unwrap_err();
+    match error.downcast_ref::<EnvelopeError>() {
+        Some(EnvelopeError::InvalidFormat { message }) => assert_eq!(message, "node must have at least two elements"),
+        _ => panic!("unexpected error: {}", error),
+    }
+
+    // An assertion map with two elements.
+    let mut map = Map::new();
+    map.insert(1, 2);
+    map.insert(3, 4);
+    let cbor = CBOR::to_tagged_value(200, map);
+    let error = Envelope::from_tagged_cbor(cbor).unwrap_err();
+    assert!(matches!(error.downcast_ref::<EnvelopeError>(), Some(EnvelopeError::InvalidFormat { .. })));
+}
+
+#[test]
+fn test_streaming_encoding() {
+    let mut map = Map::new();
+    map.insert("b", vec![1u64, 2, 3]);
+    map.insert(1, -1);
+    map.insert(-1000, 1.5);
+    let leaves: Vec<CBOR> = vec![
+        0.into(), 23.into(), 24.into(), 255.into(), 256.into(), 65535.into(), 65536.into(),
+        u32::MAX.into(), u64::MAX.into(), (-1).into(), (-25).into(), i64::MIN.into(),
+        1.5.into(), f64::NAN.into(), true.into(), CBOR::null(),
+        "".into(), "Hello.".into(), "e\u{301}".into(), "é".into(),
+        CBOR::to_byte_string(vec![0u8; 24]), CBOR::to_byte_string(vec![7u8; 70000]),
+        map.into(), CBOR::to_tagged_value(100, vec![CBOR::from("nested")]),
+    ];
+    for leaf in leaves {
+        let e = Envelope::new(leaf.clone());
+        assert_eq!(e.tagged_cbor_data(), e.tagged_cbor().to_cbor_data(), "leaf: {}", leaf.diagnostic());
+    }
+
+    let payload = Envelope::new(CBOR::to_byte_string(vec![1u8; 1000]))
+        .add_assertion("note", "Hello")
+        .add_assertion(known_values::NOTE, "e\u{301}");
+    let mut e = payload.clone();
+    for i in 0..20 {
+        e = e.wrap_envelope().add_assertion("level", i);
+    }
+    let e = e
+        .add_assertion("elided", Envelope::new("secret").elide())
+        .add_assertion("compressed", payload.compress().unwrap())
+        .add_assertion(payload.encrypt_subject(&bc_components::SymmetricKey::new()).unwrap(), true);
+    let data = e.tagged_cbor_data();
+    assert_eq!(data, e.tagged_cbor().to_cbor_data());
+    assert!(Envelope::from_tagged_cbor_data(data).unwrap().is_identical_to(&e));
+
+    let mut out = vec![0xff];
+    e.cbor_data_into(&mut out);
+    assert_eq!(out[1..], e.tagged_cbor().to_cbor_data());
+}
+
+#[cfg(all(feature = "known_value", feature = "compress", feature = "encrypt"))]
+#[test]
+fn test_cached_encoding() {
+    let fresh = || Envelope::new("Payload")
+        .add_assertion("note", "Hello")
+        .add_assertion(known_values::NOTE, "e\u{301}");
+    let expected = fresh().tagged_cbor().to_cbor_data();
+
+    // Envelopes keep their encodings only when asked to.
+    assert_eq!(fresh().cached_encoding(), None);
+    let payload = fresh().with_cached_encoding();
+    assert!(payload.is_identical_to(&fresh()));
+    assert_eq!(payload.cached_encoding(), Some(expected.as_slice()));
+    assert_eq!(payload.clone().cached_encoding(), Some(expected.as_slice()));
+    assert_eq!(payload.tagged_cbor_data(), expected);
+    assert_eq!(payload.subject().cached_encoding(), None);
+
+    // Envelopes that contain an envelope with its encoding encode it the
+    // same way.
+    for inner in [payload.clone(), fresh()] {
+        let outer = inner.wrap_envelope().add_assertion("outer", inner.clone());
+        let expected = outer.tagged_cbor().to_cbor_data();
+        assert_eq!(outer.tagged_cbor_data(), expected);
+        let mut out = Vec::new();
+        outer.cbor_data_into(&mut out);
+        assert_eq!(out, expected);
+        assert_eq!(outer.with_cached_encoding().tagged_cbor_data(), expected);
+        assert_eq!(outer.compress().unwrap().uncompress().unwrap().tagged_cbor_data(), expected);
+        assert_eq!(outer.stats().serialized_size, expected.len());
+    }
+
+    // Encrypting a subject with its encoding encrypts the same bytes.
+    let key = bc_components::SymmetricKey::new();
+    let node = fresh().subject().with_cached_encoding().add_assertion_envelopes(fresh().assertions()).unwrap();
+    let encrypted = node.encrypt_subject(&key).unwrap();
+    assert!(encrypted.decrypt_subject(&key).unwrap().is_identical_to(&payload));
+
+    // Elision keeps the elements it does not change, and the result encodes
+    // as if it were built from scratch.
+    let elided = payload.elide_removing_target(&payload.assertions()[0]);
+    assert_eq!(elided.digest(), payload.digest());
+    assert_eq!(elided.tagged_cbor_data(), elided.tagged_cbor().to_cbor_data());
+    assert!(payload.elide_removing_target(&Envelope::new("absent")).is_identical_to(&payload));
+
+    // The encoding does not appear in the envelope's debug output.
+    assert!(format!("{:?}", Envelope::new(1).with_cached_encoding()).starts_with("Envelope(Leaf {"));
+}
+
+/// Compares the streaming encoder with encoding through a `CBOR` tree.
+///
+/// Run with `cargo test --release --test core_encoding_tests -- --ignored --nocapture`.
+#[test]
+#[ignore]
+fn bench_large_leaf_encoding() {
+    use std::time::Instant;
+
+    let mut e = Envelope::new(CBOR::to_byte_string(vec![0u8; 10 * 1024 * 1024]));
+    for i in 0..10 {
+        e = e.wrap_envelope().add_assertion("level", i);
+    }
+    let iterations = 20;
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        std::hint::black_box(e.tagged_cbor().to_cbor_data());
+    }
+    let tree = start.elapsed() / iterations;
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        std::hint::black_box(e.tagged_cbor_data());
+    }
+    let streaming = start.elapsed() / iterations;
+
+    println!("10 MB leaf wrapped 10 deep: CBOR tree {:?}, streaming {:?}", tree, streaming);
+}
+
+#[test]
+fn test_check_encoding_opt() {
+    let context = FormatContext::default();
+    let e = Envelope::new("Alice")
+        .add_assertion("knows", "Bob")
+        .wrap_envelope()
+        .check_encoding_opt(Some(&context))
+        .unwrap();
+    assert!(e.check_encoding_opt(None).unwrap().is_identical_to(&e));
+}
+
+#[test]
+fn test_non_canonical_leaf() {
+    // The integer 10 as a leaf, encoded canonically in one byte.
+    let envelope = Envelope::from_tagged_cbor_data(hex::decode("d8c8d8c90a").unwrap()).unwrap();
+    assert!(envelope.is_identical_to(&Envelope::new(10)));
+    assert_eq!(hex::encode(envelope.digest().data()), "01ba4719c80b6fe911b091a7c05124b64eeece964e09c058ef8f9805daca546b");
+
+    // The integer 10 encoded in two bytes is rejected by the CBOR decoder.
+    assert!(Envelope::from_tagged_cbor_data(hex::decode("d8c8d8c9180a").unwrap()).is_err());
+
+    // A leaf built in memory from text that is not in NFC re-encodes as
+    // different text, so it would not have a stable digest.
+    let cbor = CBOR::to_tagged_value(200, CBOR::to_tagged_value(201, CBOR::from(CBORCase::Text("Cafe\u{301}".to_string()))));
+    let error = Envelope::from_tagged_cbor(cbor).unwrap_err();
+    assert!(matches!(error.downcast_ref::<EnvelopeError>(), Some(EnvelopeError::NonCanonicalLeaf)));
+    let cbor = CBOR::to_tagged_value(200, CBOR::to_tagged_value(201, "Caf\u{e9}"));
+    assert!(Envelope::from_tagged_cbor(cbor).is_ok());
+}
+
+#[test]
+fn test_strict_assertion_order() {
+    let e = Envelope::new("Alice")
+        .add_assertion("knows", "Bob")
+        .add_assertion("knows", "Carol");
+    let encode_with = |assertions: Vec<CBOR>| {
+        let mut elements = vec![e.subject().untagged_cbor()];
+        elements.extend(assertions);
+        CBOR::to_tagged_value(200, CBOR::from(elements))
+    };
+    let assertions: Vec<CBOR> = e.assertions().iter().map(|a| a.untagged_cbor()).collect();
+
+    // The canonical encoding decodes either way.
+    let canonical = encode_with(assertions.clone());
+    assert_eq!(canonical, e.tagged_cbor());
+    assert!(Envelope::from_tagged_cbor_strict(canonical).unwrap().is_identical_to(&e));
+    assert!(Envelope::from_tagged_cbor_data_strict(e.tagged_cbor_data()).unwrap().is_identical_to(&e));
+
+    // Assertions out of order are sorted by the lenient decoder, but rejected
+    // by the strict decoder with the digest of the node.
+    let reversed = encode_with(assertions.iter().rev().cloned().collect());
+    assert!(Envelope::from_tagged_cbor(reversed.clone()).unwrap().is_identical_to(&e));
+    let error = Envelope::from_tagged_cbor_strict(reversed.clone()).unwrap_err();
+    match error.downcast_ref::<EnvelopeError>() {
+        Some(EnvelopeError::NonCanonicalAssertionOrder(digest)) => assert_eq!(digest, e.digest().as_ref()),
+        _ => panic!("unexpected error: {}", error),
+    }
+
+    // Duplicated assertions are not canonical either.
+    let duplicated = encode_with(vec![assertions[0].clone(), assertions[0].clone()]);
+    assert!(Envelope::from_tagged_cbor(duplicated.clone()).is_ok());
+    assert!(Envelope::from_tagged_cbor_strict(duplicated).is_err());
+
+    // Nodes nested in wrapped envelopes are checked too.
+    let wrapped = CBOR::to_tagged_value(200, CBOR::from(vec![reversed, Envelope::new_assertion("note", "x").untagged_cbor()]));
+    assert!(Envelope::from_tagged_cbor(wrapped.clone()).is_ok());
+    assert!(Envelope::from_tagged_cbor_strict(wrapped).is_err());
+}
@@ -1,8 +1,8 @@
 use bc_envelope::prelude::*;
+use bc_components::Digest;
 use indoc::indoc;
 
 mod common;
-use crate::common::check_encoding::*;
 
 #[test]
 fn test_predicate_enclosures() {
@@ -281,3 +281,74 @@ fn test_assertion_on_bare_assertion() {
     "#}.trim();
     assert_eq!(envelope.format(), expected_format);
 }
+
+#[test]
+fn test_wrap_and_unwrap() {
+    let envelope = Envelope::new("Hello.").add_assertion("note", "inner");
+
+    // Each level of wrapping has the digest of the digest of the level inside.
+    let once = envelope.wrap().check_encoding().unwrap();
+    let twice = once.wrap().check_encoding().unwrap();
+    assert_eq!(*once.digest(), Digest::from_digests(&[envelope.digest().into_owned()]));
+    assert_eq!(*twice.digest(), Digest::from_digests(&[once.digest().into_owned()]));
+    assert!(once.is_identical_to(&envelope.wrap_envelope()));
+
+    // Unwrapping removes one level at a time.
+    assert!(twice.unwrap_envelope().unwrap().is_identical_to(&once));
+    assert!(twice.unwrap_envelope().unwrap().unwrap_envelope().unwrap().is_identical_to(&envelope));
+    assert!(envelope.unwrap_envelope().is_err());
+
+    // Unwrapping operates on the subject, ignoring assertions on the wrapper.
+    let annotated = once.add_assertion("note", "outer");
+    assert!(annotated.unwrap_envelope().unwrap().is_identical_to(&envelope));
+}
+
+#[test]
+fn test_wrap_subject() {
+    let envelope = Envelope::new("Hello.")
+        .add_assertion("note", "metadata")
+        .add_assertion("date", "2024-01-01");
+
+    // Only the subject is wrapped, and the assertions stay outside.
+    let wrapped = envelope.wrap_subject().check_encoding().unwrap();
+    assert_eq!(wrapped.format(), indoc! {r#"
+    {
+        "Hello."
+    } [
+        "date": "2024-01-01"
+        "note": "metadata"
+    ]
+    "#}.trim());
+    assert_eq!(wrapped.assertions().len(), 2);
+    assert!(wrapped.subject().is_identical_to(&Envelope::new("Hello.").wrap()));
+    assert!(wrapped.unwrap_envelope().unwrap().is_identical_to(&Envelope::new("Hello.")));
+
+    // The digests differ from those of a full wrap, whose subject covers the
+    // assertions.
+    let full = envelope.wrap();
+    assert_ne!(wrapped.digest(), full.digest());
+    assert_ne!(wrapped.subject().digest(), full.subject().digest());
+
+    // Assertions added afterwards do not change the digest of the wrapped
+    // subject, so a signature on it stays valid.
+    let annotated = wrapped.add_assertion("reviewed", true);
+    assert_eq!(annotated.subject().digest(), wrapped.subject().digest());
+    assert_eq!(annotated.assertions().len(), 3);
+
+    // Unwrapping the subject restores the original envelope.
+    assert!(wrapped.unwrap_subject().unwrap().is_identical_to(&envelope));
+    assert!(envelope.unwrap_subject().is_err());
+
+    // A leaf is simply wrapped.
+    assert!(Envelope::new("Hello.").wrap_subject().is_identical_to(&Envelope::new("Hello.").wrap()));
+}
+
+#[cfg(feature = "encrypt")]
+#[test]
+fn test_unwrap_encrypted_wrapper() {
+    let key = bc_components::SymmetricKey::new();
+    let encrypted = Envelope::new("Hello.").wrap().encrypt_subject(&key).unwrap();
+    assert!(encrypted.unwrap_envelope().is_err());
+    let decrypted = encrypted.decrypt_subject(&key).unwrap();
+    assert_eq!(decrypted.unwrap_envelope().unwrap().extract_subject::<String>().unwrap(), "Hello.");
+}
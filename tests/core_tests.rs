@@ -1,10 +1,12 @@
 use bc_envelope::prelude::*;
+use bc_envelope::{EnvelopeStats, KnownValue, known_values, register_tags};
+use bc_components::Digest;
+use dcbor::CBOR;
 use bc_components::DigestProvider;
 use indoc::indoc;
 
 mod common;
 use crate::common::test_data::*;
-use crate::common::check_encoding::*;
 
 // A previous version of the Envelope spec used tag #6.24 ("Encoded CBOR Item") as
 // the header for the Envelope `leaf` case. Unfortunately, this was not a correct
@@ -239,6 +241,23 @@ fn test_wrapped() {
     );
 }
 
+#[test]
+fn test_unwrap_envelope() {
+    let e = hello_envelope();
+    assert!(e.wrap_envelope().unwrap_envelope().unwrap().is_identical_to(&e));
+    assert!(double_wrapped_envelope().unwrap_envelope().unwrap().is_identical_to(&wrapped_envelope()));
+
+    // Assertions on the wrapper are ignored.
+    let wrapper = e.wrap_envelope().add_assertion("note", "wrapper");
+    assert!(wrapper.unwrap_envelope().unwrap().is_identical_to(&e));
+
+    // Only wrapped subjects can be unwrapped.
+    for not_wrapped in [e.clone(), e.add_assertion("note", "node"), e.wrap_envelope().elide()] {
+        let error = not_wrapped.unwrap_envelope().unwrap_err();
+        assert!(matches!(error.downcast_ref::<EnvelopeError>(), Some(EnvelopeError::NotWrapped)));
+    }
+}
+
 #[test]
 fn test_double_wrapped() {
     let e = double_wrapped_envelope().check_encoding().unwrap();
@@ -315,6 +334,152 @@ fn test_digest_leaf() {
     );
 }
 
+#[test]
+fn test_digest_hex() {
+    let e = hello_envelope();
+    assert_eq!(e.digest_hex(), "8cc96cdb771176e835114a0f8936690b41cfed0df22d014eedd64edaea945d59");
+    assert_eq!(e.short_digest_hex(4), "8cc96cdb");
+    assert_eq!(e.short_digest_hex(4), e.short_id());
+    assert_eq!(e.short_digest_hex(0), "");
+    assert_eq!(e.short_digest_hex(100), e.digest_hex());
+}
+
+#[test]
+fn test_equivalence_and_identity() {
+    let e1 = single_assertion_envelope();
+    let e2 = single_assertion_envelope();
+    assert!(e1.is_equivalent_to(&e2));
+    assert!(e1.is_identical_to(&e2));
+    assert_eq!(e1, e2);
+
+    let e3 = double_assertion_envelope();
+    assert!(!e1.is_equivalent_to(&e3));
+    assert!(!e1.is_identical_to(&e3));
+    assert_ne!(e1, e3);
+
+    let elided = e1.elide_removing_target(&e1.subject());
+    assert!(e1.is_equivalent_to(&elided));
+    assert!(!e1.is_identical_to(&elided));
+    assert_ne!(e1, elided);
+}
+
+#[test]
+fn test_node_assertions_order() {
+    // However a node's assertions are added, they are stored in digest order,
+    // which determines its digest and encoding.
+    for count in [4, 5] {
+        let assertions: Vec<Envelope> = (0..count).map(|i| Envelope::new_assertion(i, i)).collect();
+        let subject = Envelope::new("subject");
+        let batch = subject.add_assertion_envelopes(&assertions).unwrap();
+        let incremental = assertions.iter().rev()
+            .fold(subject.clone(), |envelope, assertion| envelope.add_assertion_envelope(assertion).unwrap());
+        assert!(batch.is_identical_to(&incremental));
+
+        let mut sorted = assertions.clone();
+        sorted.sort_by(|a, b| a.digest().cmp(&b.digest()));
+        assert_eq!(batch.assertions(), sorted.as_slice());
+
+        let mut digests = vec![subject.digest().into_owned()];
+        digests.extend(sorted.iter().map(|a| a.digest().into_owned()));
+        assert_eq!(*batch.digest(), Digest::from_digests(&digests));
+
+        let mut elements = vec![subject.untagged_cbor()];
+        elements.extend(sorted.iter().map(|a| a.untagged_cbor()));
+        assert_eq!(batch.tagged_cbor_data(), CBOR::to_tagged_value(200, elements).to_cbor_data());
+        let decoded = Envelope::from_tagged_cbor_data(batch.tagged_cbor_data()).unwrap();
+        assert!(decoded.is_identical_to(&batch));
+
+        // Removing an assertion keeps the rest in order.
+        let removed = batch.remove_assertion(sorted[0].clone());
+        assert_eq!(removed.assertions(), &sorted[1..]);
+        assert!(removed.is_identical_to(&subject.add_assertion_envelopes(&sorted[1..]).unwrap()));
+    }
+}
+
+#[test]
+fn test_envelopes_in_hash_set() {
+    use std::collections::HashSet;
+    use std::hash::{BuildHasher, RandomState};
+
+    // Duplicates, however they were produced, are one entry.
+    let e1 = double_assertion_envelope();
+    let decoded = Envelope::from_tagged_cbor_data(e1.tagged_cbor_data()).unwrap();
+    let messages = HashSet::from([e1.clone(), e1.clone(), decoded, double_assertion_envelope()]);
+    assert_eq!(messages.len(), 1);
+
+    // An elided envelope hashes the same as the envelope it came from, but is
+    // a different entry, as it is not identical.
+    let elided = e1.elide_removing_target(&e1.subject());
+    let hasher = RandomState::new();
+    assert_eq!(hasher.hash_one(&e1), hasher.hash_one(&elided));
+    let messages = HashSet::from([e1.clone(), elided.clone(), hello_envelope()]);
+    assert_eq!(messages.len(), 3);
+    assert!(messages.contains(&elided));
+}
+
+#[cfg(feature = "encrypt")]
+#[test]
+fn test_encrypted_equivalence() {
+    let e1 = hello_envelope();
+    let e2 = e1.encrypt_subject(&fake_content_key()).unwrap();
+    assert!(e1.is_equivalent_to(&e2));
+    assert!(!e1.is_identical_to(&e2));
+    assert_ne!(e1, e2);
+}
+
+#[cfg(feature = "salt")]
+#[test]
+fn test_salted_equivalence() {
+    let e1 = hello_envelope().add_salt();
+    let e2 = hello_envelope().add_salt();
+    assert!(!e1.is_equivalent_to(&e2));
+    assert!(!e1.is_identical_to(&e2));
+    assert_ne!(e1, e2);
+}
+
+#[test]
+fn test_replace_subject() {
+    let e1 = double_assertion_envelope();
+    let e2 = e1.replace_subject(Envelope::new("Bob"));
+    let expected = Envelope::new("Bob")
+        .add_assertion("knows", "Carol")
+        .add_assertion("knows", "Bob");
+    assert!(e2.is_identical_to(&expected));
+    assert_eq!(e2.assertions().len(), 2);
+
+    // The new subject's own assertions are merged.
+    let e3 = e1.replace_subject(Envelope::new("Bob").add_assertion("knows", "Dan"));
+    assert_eq!(e3.assertions().len(), 3);
+    assert!(e3.subject().is_identical_to(&Envelope::new("Bob")));
+
+    // A non-node envelope is simply replaced.
+    let e4 = hello_envelope().replace_subject(Envelope::new("Bob"));
+    assert!(e4.is_identical_to(&Envelope::new("Bob")));
+}
+
+#[test]
+fn test_map_subject() {
+    let e1 = double_assertion_envelope();
+    let e2 = e1.map_subject(|subject| subject.wrap_envelope());
+    assert_eq!(e2.assertions().len(), 2);
+    assert!(e2.subject().is_wrapped());
+    assert_ne!(e1.digest(), e2.digest());
+    assert!(e2.is_identical_to(&e1.replace_subject(Envelope::new("Alice").wrap_envelope())));
+
+    let e3 = e1.try_map_subject(|subject| subject.unwrap_envelope());
+    assert!(e3.is_err());
+}
+
+#[cfg(feature = "encrypt")]
+#[test]
+fn test_try_map_subject() {
+    let e1 = double_assertion_envelope();
+    let e2 = e1.try_map_subject(|subject| subject.encrypt_subject(&fake_content_key())).unwrap();
+    assert_eq!(e2.assertions().len(), 2);
+    assert!(e2.subject().is_encrypted());
+    assert!(e1.is_equivalent_to(&e2));
+}
+
 #[test]
 fn test_unknown_leaf() {
     crate::register_tags();
@@ -324,3 +489,369 @@ fn test_unknown_leaf() {
     let expected = "555({1: h'6fc4981e8da778332bf93342f3f77d3a'})";
     assert_eq!(e.format(), expected);
 }
+
+#[cfg(feature = "multithreaded")]
+#[test]
+fn test_envelope_is_send_and_sync() {
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+    assert_send::<Envelope>();
+    assert_sync::<Envelope>();
+
+    let envelope = double_assertion_envelope();
+    let digest = envelope.digest().into_owned();
+    let handle = std::thread::spawn(move || envelope.digest().into_owned());
+    assert_eq!(handle.join().unwrap(), digest);
+
+    // An envelope decoded on one thread can be used on another, and held
+    // across an await in a future that must be `Send`.
+    let data = double_assertion_envelope().tagged_cbor_data();
+    let decoded = std::thread::spawn(move || Envelope::from_tagged_cbor_data(data).unwrap())
+        .join()
+        .unwrap();
+    fn assert_send_future<F: std::future::Future + Send>(future: F) -> F { future }
+    let future = assert_send_future(async move {
+        std::future::ready(()).await;
+        decoded.digest().into_owned()
+    });
+    let handle = std::thread::spawn(move || {
+        let mut future = std::pin::pin!(future);
+        let mut context = std::task::Context::from_waker(std::task::Waker::noop());
+        match std::future::Future::poll(future.as_mut(), &mut context) {
+            std::task::Poll::Ready(digest) => digest,
+            std::task::Poll::Pending => panic!("the future should be ready"),
+        }
+    });
+    assert_eq!(handle.join().unwrap(), digest);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_par_walk() {
+    use bc_envelope::base::walk::EdgeType;
+
+    let assertions: Vec<Envelope> = (0..2_000)
+        .map(|i| Envelope::new_assertion(i, Envelope::new(format!("object {}", i)).wrap_envelope()))
+        .collect();
+    let envelope = Envelope::new("subject").add_assertion_envelopes(&assertions).unwrap();
+
+    // Each element is visited with its depth, edge, and the digest of its
+    // parent, in the order of `walk`.
+    type Visit = (Digest, usize, EdgeType, Option<Digest>);
+    let visits = std::cell::RefCell::new(Vec::<Visit>::new());
+    envelope.walk(false, &|element, level, edge, parent: Option<Digest>| {
+        visits.borrow_mut().push((element.digest().into_owned(), level, edge, parent));
+        Some(element.digest().into_owned())
+    });
+    let parallel_visits = envelope.par_walk(&|element: Envelope, level, edge, parent: Option<Digest>| {
+        let digest = element.digest().into_owned();
+        (Some(digest.clone()), (digest, level, edge, parent))
+    });
+    assert_eq!(parallel_visits.len(), envelope.elements_count());
+    assert_eq!(parallel_visits, visits.into_inner());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_concurrent_node_builder() {
+    use bc_envelope::ConcurrentNodeBuilder;
+    use rayon::prelude::*;
+
+    let assertions: Vec<Envelope> = (0..10_000)
+        .map(|i| Envelope::new_assertion(format!("field {}", i), i))
+        .collect();
+    let expected = Envelope::new("catalog").add_assertion_envelopes(&assertions).unwrap();
+
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(8).build().unwrap();
+    for _ in 0..3 {
+        let builder = ConcurrentNodeBuilder::new("catalog");
+        pool.install(|| {
+            // Every assertion is pushed twice, from whichever threads run
+            // the chunks, and the duplicates are removed.
+            assertions.par_iter().for_each(|assertion| builder.push(assertion.clone()));
+            assertions.par_chunks(100).for_each(|chunk| builder.extend(chunk.iter().cloned()));
+        });
+        let envelope = builder.finish().unwrap();
+        assert_eq!(envelope.digest(), expected.digest());
+        assert!(envelope.is_identical_to(&expected));
+    }
+
+    // A node subject keeps its assertions, and a builder with no pushed
+    // assertions returns its subject.
+    let node = ConcurrentNodeBuilder::new(expected.clone());
+    node.push(assertions[0].clone());
+    assert!(node.finish().unwrap().is_identical_to(&expected));
+    assert!(ConcurrentNodeBuilder::new("catalog").finish().unwrap().is_identical_to(&Envelope::new("catalog")));
+
+    let invalid = ConcurrentNodeBuilder::new("catalog");
+    invalid.push("not an assertion");
+    assert!(invalid.finish().is_err());
+}
+
+#[test]
+fn test_case_predicates() {
+    let leaf = hello_envelope();
+    assert!(leaf.is_leaf() && !leaf.is_internal());
+    assert_eq!(leaf.case_name(), "Leaf");
+
+    let node = single_assertion_envelope();
+    assert!(node.is_node() && node.is_internal());
+    assert_eq!(node.case_name(), "Node");
+
+    let wrapped = leaf.wrap();
+    assert!(wrapped.is_wrapped() && wrapped.is_internal());
+    assert_eq!(wrapped.case_name(), "Wrapped");
+
+    let assertion = assertion_envelope();
+    assert!(assertion.is_assertion() && assertion.is_internal());
+    assert_eq!(assertion.case_name(), "Assertion");
+
+    let elided = leaf.elide();
+    assert!(elided.is_elided() && elided.is_obscured());
+    assert_eq!(elided.case_name(), "Elided");
+
+    #[cfg(feature = "known_value")]
+    {
+        let known_value = known_value_envelope();
+        assert!(known_value.is_known_value() && !known_value.is_internal());
+        assert_eq!(known_value.case_name(), "KnownValue");
+    }
+
+    #[cfg(feature = "encrypt")]
+    {
+        let encrypted = leaf.encrypt_subject(&fake_content_key()).unwrap();
+        assert!(encrypted.is_encrypted() && encrypted.is_obscured());
+        assert_eq!(encrypted.case_name(), "Encrypted");
+    }
+
+    #[cfg(feature = "compress")]
+    {
+        let compressed = leaf.compress().unwrap();
+        assert!(compressed.is_compressed() && compressed.is_obscured());
+        assert_eq!(compressed.case_name(), "Compressed");
+    }
+}
+
+#[cfg(all(feature = "known_value", feature = "encrypt", feature = "compress"))]
+#[test]
+fn test_predicate_matrix() {
+    const PREDICATES: [&str; 15] = [
+        "is_leaf", "is_node", "is_wrapped", "is_known_value", "is_assertion",
+        "is_encrypted", "is_compressed", "is_elided", "is_obscured",
+        "is_subject_assertion", "is_subject_encrypted", "is_subject_compressed",
+        "is_subject_elided", "is_subject_obscured", "is_internal",
+    ];
+    fn predicates(e: &Envelope) -> [bool; 15] {
+        [
+            e.is_leaf(), e.is_node(), e.is_wrapped(), e.is_known_value(), e.is_assertion(),
+            e.is_encrypted(), e.is_compressed(), e.is_elided(), e.is_obscured(),
+            e.is_subject_assertion(), e.is_subject_encrypted(), e.is_subject_compressed(),
+            e.is_subject_elided(), e.is_subject_obscured(), e.is_internal(),
+        ]
+    }
+
+    let leaf = hello_envelope();
+    let key = fake_content_key();
+    let with_assertion = |subject: Envelope| subject.add_assertion("knows", "Bob");
+    let (t, f) = (true, false);
+    let rows = [
+        //                                                             leaf node wrap kv   asrt enc  comp eld  obsc s_as s_en s_co s_el s_ob intl
+        ("leaf", leaf.clone(),                                        [t,   f,   f,   f,   f,   f,   f,   f,   f,   f,   f,   f,   f,   f,   f]),
+        ("node", single_assertion_envelope(),                         [f,   t,   f,   f,   f,   f,   f,   f,   f,   f,   f,   f,   f,   f,   t]),
+        ("wrapped", leaf.wrap(),                                      [f,   f,   t,   f,   f,   f,   f,   f,   f,   f,   f,   f,   f,   f,   t]),
+        ("known value", known_value_envelope(),                       [f,   f,   f,   t,   f,   f,   f,   f,   f,   f,   f,   f,   f,   f,   f]),
+        ("assertion", assertion_envelope(),                           [f,   f,   f,   f,   t,   f,   f,   f,   f,   t,   f,   f,   f,   f,   t]),
+        ("encrypted", leaf.encrypt_subject(&key).unwrap(),            [f,   f,   f,   f,   f,   t,   f,   f,   t,   f,   t,   f,   f,   t,   f]),
+        ("compressed", leaf.compress().unwrap(),                      [f,   f,   f,   f,   f,   f,   t,   f,   t,   f,   f,   t,   f,   t,   f]),
+        ("elided", leaf.elide(),                                      [f,   f,   f,   f,   f,   f,   f,   t,   t,   f,   f,   f,   t,   t,   f]),
+        ("node with encrypted subject",
+            with_assertion(leaf.encrypt_subject(&key).unwrap()),      [f,   t,   f,   f,   f,   f,   f,   f,   f,   f,   t,   f,   f,   t,   t]),
+        ("node with compressed subject",
+            with_assertion(leaf.compress().unwrap()),                 [f,   t,   f,   f,   f,   f,   f,   f,   f,   f,   f,   t,   f,   t,   t]),
+        ("node with elided subject",
+            with_assertion(leaf.elide()),                             [f,   t,   f,   f,   f,   f,   f,   f,   f,   f,   f,   f,   t,   t,   t]),
+    ];
+
+    for (name, envelope, expected) in rows {
+        let actual = predicates(&envelope);
+        for (i, predicate) in PREDICATES.iter().enumerate() {
+            assert_eq!(actual[i], expected[i], "{} of {}", predicate, name);
+        }
+    }
+}
+
+#[test]
+fn test_subject_and_assertions_accessors() {
+    let leaf = hello_envelope();
+    assert!(leaf.assertions().is_empty());
+    assert!(leaf.subject().is_identical_to(&leaf));
+
+    let node = double_assertion_envelope();
+    assert_eq!(node.assertions().len(), 2);
+    assert!(node.subject().is_identical_to(&Envelope::new("Alice")));
+    for assertion in node.assertions() {
+        assert!(assertion.is_assertion());
+    }
+
+    let wrapped = node.wrap();
+    assert!(wrapped.assertions().is_empty());
+    assert!(wrapped.subject().is_identical_to(&wrapped));
+}
+
+#[cfg(feature = "known_value")]
+#[test]
+fn test_leaf_cbor() {
+    let leaf = hello_envelope();
+    assert_eq!(leaf.leaf_cbor(), Some(&CBOR::from(PLAINTEXT_HELLO)));
+    assert_eq!(leaf.leaf_cbor().cloned(), leaf.as_leaf());
+
+    assert_eq!(known_value_envelope().leaf_cbor(), None);
+    assert_eq!(double_assertion_envelope().leaf_cbor(), None);
+    assert_eq!(leaf.wrap_envelope().leaf_cbor(), None);
+    assert_eq!(leaf.elide().leaf_cbor(), None);
+}
+
+#[test]
+fn test_predicate_and_object_accessors() {
+    let assertion = assertion_envelope();
+    assert!(assertion.as_predicate().unwrap().is_identical_to(&Envelope::new("knows")));
+    assert!(assertion.as_object().unwrap().is_identical_to(&Envelope::new("Bob")));
+
+    // Only the assertion case has a predicate and object, even if a node's
+    // subject is an assertion.
+    let node = single_assertion_envelope();
+    assert!(node.as_predicate().is_none());
+    assert!(node.as_object().is_none());
+    assert!(node.try_predicate().is_err());
+    assert!(node.try_object().is_err());
+    let node = assertion.add_assertion("note", "An assertion with an assertion.");
+    assert!(node.as_predicate().is_none());
+    assert!(node.subject().as_predicate().is_some());
+
+    // The accessors work on the elements handed out by walks.
+    let predicates = std::cell::RefCell::new(Vec::new());
+    let visitor = |envelope: Envelope, _: usize, _: bc_envelope::base::walk::EdgeType, parent: Option<()>| -> Option<()> {
+        if let Some(predicate) = envelope.as_predicate() {
+            predicates.borrow_mut().push(predicate);
+        }
+        parent
+    };
+    double_assertion_envelope().walk(false, &visitor);
+    assert_eq!(predicates.borrow().len(), 2);
+}
+
+#[test]
+fn test_diagnostic_opt() {
+    let e = single_assertion_envelope();
+
+    assert_eq!(e.diagnostic_opt(false, None), e.diagnostic());
+    assert_eq!(e.diagnostic_opt(false, None),
+    indoc! {r#"
+    200(
+        [
+            201("Alice"),
+            {
+                201("knows"):
+                201("Bob")
+            }
+        ]
+    )
+    "#}.trim()
+    );
+
+    // Without tag names in the context, annotations are omitted.
+    assert_eq!(e.diagnostic_opt(true, None), e.diagnostic());
+
+    let annotated = with_format_context!(|context| e.diagnostic_opt(true, Some(context)));
+    assert_eq!(annotated, e.diagnostic_annotated());
+    assert_eq!(annotated,
+    indoc! {r#"
+    200(   / envelope /
+        [
+            201("Alice"),   / leaf /
+            {
+                201("knows"):   / leaf /
+                201("Bob")   / leaf /
+            }
+        ]
+    )
+    "#}.trim()
+    );
+}
+
+#[test]
+fn test_assertion_from_envelope_references() {
+    let predicate = Envelope::new("knows");
+    let object = Envelope::new("Bob").add_assertion("age", 30);
+    let expected = bc_envelope::Assertion::new_with_envelopes(predicate.clone(), object.clone());
+
+    let assertions = [
+        bc_envelope::Assertion::new("knows", object.clone()),
+        bc_envelope::Assertion::new(&predicate, &object),
+        bc_envelope::Assertion::new(Box::new(predicate.clone()), Box::new(object.clone())),
+    ];
+    for assertion in assertions {
+        assert_eq!(assertion.digest_ref(), expected.digest_ref());
+        assert!(assertion.predicate().is_identical_to(&predicate));
+        assert!(assertion.object().is_identical_to(&object));
+    }
+
+    let e = Envelope::new_assertion(&predicate, Box::new(object.clone()));
+    assert!(e.is_identical_to(&Envelope::new(expected.clone())));
+    assert_eq!(Envelope::new("Alice").add_assertion(&predicate, &object).format(), indoc! {r#"
+    "Alice" [
+        "knows": "Bob" [
+            "age": 30
+        ]
+    ]
+    "#}.trim());
+}
+
+#[cfg(all(feature = "encrypt", feature = "known_value", feature = "compress"))]
+#[test]
+fn test_stats() {
+    let key = bc_components::SymmetricKey::new();
+    let e = Envelope::new("Alice")
+        .add_assertion_envelope(Envelope::new_assertion("knows", "Bob").encrypt_subject(&key).unwrap()).unwrap()
+        .add_assertion(known_values::NOTE, "x")
+        .add_assertion("age", Envelope::new(30).elide())
+        .add_assertion("wrapped", Envelope::new("w").wrap_envelope());
+
+    let stats = e.stats();
+    assert_eq!(stats.elements, e.elements_count());
+    assert_eq!(stats, EnvelopeStats {
+        elements: 13,
+        max_depth: 3,
+        nodes: 1,
+        leaves: 5,
+        wrapped: 1,
+        known_values: 1,
+        assertions: 3,
+        encrypted: 1,
+        elided: 1,
+        compressed: 0,
+        serialized_size: e.tagged_cbor_data().len(),
+    });
+    assert_eq!(stats.obscured(), 2);
+    assert!(!e.is_fully_revealed());
+
+    let e = double_assertion_envelope();
+    assert_eq!(e.stats().obscured(), 0);
+    assert!(e.is_fully_revealed());
+    let target = e.assertions().iter().map(|assertion| assertion.as_object().unwrap().digest().into_owned()).take(1).collect();
+    let elided = e.elide_removing_set(&target);
+    assert!(elided.is_equivalent_to(&e));
+    assert_eq!(elided.stats().elided, 1);
+    assert!(!elided.is_fully_revealed());
+}
+
+#[cfg(feature = "compress")]
+#[test]
+fn test_structure_stats() {
+    let e = double_assertion_envelope()
+        .add_assertion("photo", Envelope::new("x".repeat(100)).compress().unwrap());
+    let stats = e.stats();
+    assert_eq!((stats.nodes, stats.leaves, stats.assertions), (1, 6, 3));
+    assert_eq!((stats.encrypted, stats.compressed, stats.elided), (0, 1, 0));
+    assert_eq!(stats.serialized_size, e.tagged_cbor_data().len());
+}
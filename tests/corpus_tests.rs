@@ -0,0 +1,31 @@
+#![cfg(feature = "test-support")]
+
+use bc_envelope::corpus::{corpus_files, corpus_fixtures, corpus_manifest};
+use bc_envelope::prelude::*;
+
+#[test]
+fn test_corpus_is_deterministic() {
+    assert_eq!(corpus_files(), corpus_files());
+}
+
+#[test]
+fn test_corpus_round_trips() {
+    bc_envelope::register_tags();
+    for fixture in corpus_fixtures() {
+        let envelope = &fixture.envelope;
+        let decoded = Envelope::from_tagged_cbor_data(envelope.tagged_cbor_data()).unwrap();
+        assert!(decoded.is_identical_to(envelope), "{}", fixture.name);
+        let decoded = Envelope::from_ur_string(envelope.ur_string()).unwrap();
+        assert!(decoded.is_identical_to(envelope), "{}", fixture.name);
+    }
+}
+
+#[test]
+fn test_corpus_matches_manifest() {
+    // If this fails after an intentional change to the encoding or format,
+    // regenerate the manifest with
+    // `cargo run --example generate_corpus --features test-support -- --out <dir>`
+    // and copy `<dir>/MANIFEST` to `tests/corpus/MANIFEST`.
+    let manifest = corpus_manifest(&corpus_files());
+    assert_eq!(manifest, include_str!("corpus/MANIFEST"));
+}
@@ -1,14 +1,15 @@
 #![cfg(feature = "encrypt")]
 
-use bc_components::SymmetricKey;
+use bc_components::{EncryptedMessage, SymmetricKey};
+use bc_rand::{make_fake_random_number_generator, SeededRandomNumberGenerator};
 use bc_ur::prelude::*;
 use indoc::indoc;
 
 use bc_envelope::prelude::*;
+use bc_envelope::known_values;
 
 mod common;
 use crate::common::test_data::*;
-use crate::common::check_encoding::*;
 
 #[test]
 fn plaintext() {
@@ -256,6 +257,69 @@ fn test_multi_recipient() {
     assert!(received_envelope.decrypt_subject_to_recipient(&alice_private_key()).is_err());
 }
 
+#[cfg(feature = "recipient")]
+#[test]
+fn test_recipients() {
+    let content_key = SymmetricKey::new();
+    let envelope = hello_envelope()
+        .encrypt_subject(&content_key).unwrap()
+        .add_recipient(&alice_public_key(), &content_key)
+        .add_recipient(&bob_public_key(), &content_key)
+        .add_recipient(&carol_public_key(), &content_key);
+    assert!(envelope.has_recipients());
+    assert!(!hello_envelope().has_recipients());
+    assert_eq!(envelope.recipients().unwrap().len(), 3);
+
+    // Eliding the object of one `hasRecipient` assertion hides that
+    // recipient.
+    let first = envelope.assertions()[0].clone();
+    let elided = envelope.elide_removing_target(&first.as_object().unwrap());
+    assert!(elided.has_recipients());
+    let recipients = elided.recipients().unwrap();
+    assert_eq!(recipients.len(), 2);
+    for recipient in &recipients {
+        assert!(envelope.recipients().unwrap().contains(recipient));
+    }
+
+    // As does eliding the whole assertion.
+    assert_eq!(envelope.elide_removing_target(&first).recipients().unwrap().len(), 2);
+
+    // An object that is not a sealed message is an error.
+    let malformed = envelope.add_assertion(known_values::HAS_RECIPIENT, "Bob");
+    let error = malformed.recipients().unwrap_err();
+    assert!(matches!(error.downcast_ref::<EnvelopeError>(), Some(EnvelopeError::InvalidRecipient { .. })));
+}
+
+#[cfg(feature = "recipient")]
+#[test]
+fn test_recipient_encryption_using() {
+    let fixed = || hello_envelope()
+        .encrypt_subject_to_recipient_using(&bob_public_key(), &fake_content_key(), &mut make_fake_random_number_generator())
+        .unwrap();
+
+    // Fixed keys and a deterministic generator give a fixed encoding.
+    let e = fixed();
+    assert_eq!(e.tagged_cbor_data(), fixed().tagged_cbor_data());
+    let message = e.subject().extract_subject::<EncryptedMessage>().unwrap();
+    assert_eq!(hex::encode(message.nonce().data()), "7eb559bbbf6cce2632cf9f19");
+    assert_eq!(hex::encode(message.ciphertext()), "04923431c9ba3493fdd00e");
+    assert_eq!(e.format(), indoc! {r#"
+    ENCRYPTED [
+        'hasRecipient': SealedMessage
+    ]
+    "#}.trim());
+    let decrypted = e.decrypt_subject_to_recipient(&bob_private_key()).unwrap();
+    assert!(decrypted.subject().is_identical_to(&hello_envelope()));
+
+    // The nonces and ephemeral key do not change the subject's digest.
+    let other = hello_envelope()
+        .encrypt_subject_to_recipient_using(&bob_public_key(), &fake_content_key(), &mut SeededRandomNumberGenerator::new([1, 2, 3, 4]))
+        .unwrap();
+    assert_ne!(other.tagged_cbor_data(), e.tagged_cbor_data());
+    assert_eq!(other.subject().digest(), e.subject().digest());
+    assert_eq!(e.subject().digest(), hello_envelope().digest());
+}
+
 #[cfg(all(feature = "signature", feature = "recipient"))]
 #[test]
 fn test_visible_signature_multi_recipient() {
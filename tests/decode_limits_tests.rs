@@ -0,0 +1,311 @@
+use bc_envelope::prelude::*;
+use dcbor::{CBOR, CBORCase, Map};
+use bc_envelope::{EnvelopeError, EnvelopeLimits};
+#[cfg(feature = "multithreaded")]
+use bc_envelope::ConcurrentNodeBuilder;
+
+mod common;
+use crate::common::test_data::*;
+
+/// Returns tagged CBOR for a node with `count` assertions, each with a leaf
+/// predicate and object, built directly so that no envelope is constructed.
+fn wide_node_cbor(count: u64) -> CBOR {
+    let leaf = |value: u64| CBOR::to_tagged_value(201, value);
+    let mut elements = vec![leaf(0)];
+    for i in 0..count {
+        let mut map = Map::new();
+        map.insert(leaf(i), leaf(i));
+        elements.push(map.into());
+    }
+    CBOR::to_tagged_value(200, elements)
+}
+
+fn limit_exceeded(error: &anyhow::Error) -> (&'static str, usize, usize) {
+    match error.downcast_ref::<EnvelopeError>() {
+        Some(EnvelopeError::LimitExceeded { limit, elements, memory }) => (limit, *elements, *memory),
+        _ => panic!("unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn test_million_leaf_envelope() {
+    // One node, 500,000 assertions, and 1,000,001 leaves.
+    let cbor = wide_node_cbor(500_000);
+
+    let limits = EnvelopeLimits::default().with_max_total_elements(10_000);
+    let error = Envelope::from_tagged_cbor_with_limits(cbor.clone(), &limits).unwrap_err();
+    let (limit, elements, memory) = limit_exceeded(&error);
+    assert_eq!(limit, "max_total_elements");
+    assert_eq!(elements, 10_000);
+    assert!(memory < 10 * 1024 * 1024);
+    assert!(error.to_string().contains("after 10000 elements"));
+
+    // The node's list of assertions alone would exceed 1 MiB, so decoding
+    // stops before anything is allocated.
+    let limits = EnvelopeLimits::default().with_max_estimated_memory(1024 * 1024);
+    let error = Envelope::from_tagged_cbor_with_limits(cbor.clone(), &limits).unwrap_err();
+    assert_eq!(limit_exceeded(&error), ("max_estimated_memory", 0, 0));
+
+    let limits = EnvelopeLimits::default().with_max_estimated_memory(16 * 1024 * 1024);
+    let error = Envelope::from_tagged_cbor_with_limits(cbor.clone(), &limits).unwrap_err();
+    let (limit, elements, memory) = limit_exceeded(&error);
+    assert_eq!(limit, "max_estimated_memory");
+    assert!(elements > 1 && elements < 1_000_000);
+    assert!(memory <= 16 * 1024 * 1024);
+
+    // The default limits also stop it, through every entry point.
+    let error = Envelope::from_tagged_cbor(cbor.clone()).unwrap_err();
+    assert_eq!(limit_exceeded(&error).0, "max_total_elements");
+    assert_eq!(limit_exceeded(&error).1, EnvelopeLimits::DEFAULT_MAX_TOTAL_ELEMENTS);
+}
+
+#[test]
+fn test_entry_points_share_limits() {
+    bc_envelope::register_tags();
+    let e = double_assertion_envelope().wrap_envelope().add_assertion("note", "wrapped");
+    assert_eq!(e.elements_count(), 13);
+    let exact = EnvelopeLimits::default().with_max_total_elements(13);
+    let short = EnvelopeLimits::default().with_max_total_elements(12);
+
+    let cbor = e.tagged_cbor();
+    let data = e.tagged_cbor_data();
+    let ur = e.ur_string();
+    let CBORCase::Tagged(_, untagged) = cbor.as_case() else { panic!() };
+
+    type Decoder<'a> = &'a dyn Fn(&EnvelopeLimits) -> anyhow::Result<Envelope>;
+    let decoders: [Decoder<'_>; 5] = [
+        &|limits| Envelope::from_tagged_cbor_with_limits(cbor.clone(), limits),
+        &|limits| Envelope::from_untagged_cbor_with_limits(untagged.clone(), limits),
+        &|limits| Envelope::from_tagged_cbor_data_with_limits(&data, limits),
+        &|limits| Envelope::from_tagged_cbor_data_checked_with_limits(&data, limits),
+        &|limits| Envelope::from_ur_string_with_limits(&ur, limits),
+    ];
+    for decode in decoders {
+        assert!(decode(&exact).unwrap().is_identical_to(&e));
+        let error = decode(&short).unwrap_err();
+        let (limit, elements, _) = limit_exceeded(&error);
+        assert_eq!((limit, elements), ("max_total_elements", 12));
+    }
+
+    // Uncompressing decodes the content subject to the same limits.
+    let compressed = e.compress().unwrap();
+    assert!(compressed.uncompress_with_limits(&exact).unwrap().is_identical_to(&e));
+    let error = compressed.uncompress_with_limits(&short).unwrap_err();
+    assert_eq!(limit_exceeded(&error).0, "max_total_elements");
+}
+
+#[test]
+fn test_fixtures_decode_under_default_limits() {
+    bc_envelope::register_tags();
+    let fixtures = [
+        hello_envelope(),
+        known_value_envelope(),
+        assertion_envelope(),
+        single_assertion_envelope(),
+        double_assertion_envelope(),
+        wrapped_envelope(),
+        double_wrapped_envelope(),
+    ];
+    for e in fixtures {
+        let decoded = Envelope::from_tagged_cbor_data(e.tagged_cbor_data()).unwrap();
+        assert!(decoded.is_identical_to(&e));
+        assert!(Envelope::from_ur_string(e.ur_string()).unwrap().is_identical_to(&e));
+    }
+}
+
+#[test]
+fn test_duplicate_elements_are_reused() {
+    // One node with 10,000 assertions that share a large object.
+    let object = CBOR::to_tagged_value(201, "A".repeat(1000));
+    let mut elements = vec![CBOR::to_tagged_value(201, "subject")];
+    for i in 0..10_000u64 {
+        let mut map = Map::new();
+        map.insert(CBOR::to_tagged_value(201, i), object.clone());
+        elements.push(map.into());
+    }
+    let cbor = CBOR::to_tagged_value(200, elements);
+
+    let limits = EnvelopeLimits::default();
+    let expected = Envelope::from_tagged_cbor_with_limits(cbor.clone(), &limits).unwrap();
+    let (e, stats) = Envelope::from_tagged_cbor_with_stats(cbor.clone(), &limits).unwrap();
+    assert!(e.is_identical_to(&expected));
+    assert_eq!(e.digest(), expected.digest());
+    assert_eq!(e.assertions().len(), 10_000);
+
+    // Every element is counted, but the object is decoded once.
+    assert_eq!(stats.elements, 1 + 1 + 10_000 * 3);
+    assert_eq!(stats.reused_duplicates, 9_999);
+    assert_eq!(stats.decoded_elements, stats.elements - stats.reused_duplicates);
+
+    // Without reuse, every element is decoded, with the same result.
+    let unoptimized = EnvelopeLimits::default().with_reuse_duplicates(false);
+    let (e, unoptimized_stats) = Envelope::from_tagged_cbor_with_stats(cbor.clone(), &unoptimized).unwrap();
+    assert!(e.is_identical_to(&expected));
+    assert_eq!(unoptimized_stats.elements, stats.elements);
+    assert_eq!(unoptimized_stats.reused_duplicates, 0);
+    assert_eq!(unoptimized_stats.decoded_elements, stats.elements);
+    let data = cbor.to_cbor_data();
+    assert!(Envelope::from_tagged_cbor_data_with_limits(&data, &unoptimized).unwrap().is_identical_to(&expected));
+
+    // Reused elements count against the limits as though decoded.
+    let short = EnvelopeLimits::default().with_max_total_elements(stats.elements - 1);
+    let error = Envelope::from_tagged_cbor_with_stats(cbor.clone(), &short).unwrap_err();
+    assert_eq!(limit_exceeded(&error), limit_exceeded(&Envelope::from_tagged_cbor_with_limits(cbor, &short).unwrap_err()));
+}
+
+fn too_many_assertions(error: &anyhow::Error) -> (usize, usize) {
+    match error.downcast_ref::<EnvelopeError>() {
+        Some(EnvelopeError::TooManyAssertions { length, limit }) => (*length, *limit),
+        _ => panic!("unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn test_node_assertion_limit() {
+    let limits = EnvelopeLimits::default().with_max_node_assertions(10);
+    let envelope = Envelope::from_tagged_cbor_with_limits(wide_node_cbor(10), &limits).unwrap();
+    assert_eq!(envelope.assertions().len(), 10);
+
+    // The error reports the node's array length, its subject included.
+    let cbor = wide_node_cbor(11);
+    let error = Envelope::from_tagged_cbor_with_limits(cbor.clone(), &limits).unwrap_err();
+    assert_eq!(too_many_assertions(&error), (12, 10));
+    let error = Envelope::from_tagged_cbor_data_with_limits(cbor.to_cbor_data(), &limits).unwrap_err();
+    assert_eq!(too_many_assertions(&error), (12, 10));
+    assert_eq!(error.to_string(), "a node of array length 12 exceeds the limit of 10 assertions");
+
+    // Nested nodes are held to the limit too.
+    let nested = Envelope::new("Alice").add_assertion("knows", Envelope::from_tagged_cbor(wide_node_cbor(11)).unwrap());
+    let error = Envelope::from_tagged_cbor_data_with_limits(nested.tagged_cbor_data(), &limits).unwrap_err();
+    assert_eq!(too_many_assertions(&error), (12, 10));
+}
+
+#[test]
+fn test_huge_node_header_fails_fast() {
+    // A node that declares ten million assertions, but holds only one.
+    let mut data = vec![0xd8, 0xc8, 0x9a, 0x00, 0x98, 0x96, 0x80];
+    data.extend(wide_node_cbor(1).to_cbor_data()[3..].iter());
+    let error = Envelope::from_tagged_cbor_data(&data).unwrap_err();
+    assert_eq!(too_many_assertions(&error), (10_000_000, EnvelopeLimits::DEFAULT_MAX_NODE_ASSERTIONS));
+    let error = Envelope::from_tagged_cbor_data_with_limits(&data, &EnvelopeLimits::default()).unwrap_err();
+    assert_eq!(too_many_assertions(&error), (10_000_000, EnvelopeLimits::DEFAULT_MAX_NODE_ASSERTIONS));
+    let error = Envelope::from_untagged_cbor_data(&data[2..]).unwrap_err();
+    assert_eq!(too_many_assertions(&error), (10_000_000, EnvelopeLimits::DEFAULT_MAX_NODE_ASSERTIONS));
+}
+
+/// Returns a node with as many assertions as the default limit allows, built
+/// cheaply from elided assertions.
+fn full_node() -> Envelope {
+    let elided = |i: usize| {
+        let mut data = [0u8; 32];
+        data[..8].copy_from_slice(&(i as u64).to_be_bytes());
+        CBOR::to_byte_string(data)
+    };
+    let limit = EnvelopeLimits::DEFAULT_MAX_NODE_ASSERTIONS;
+    let mut elements = vec![CBOR::to_tagged_value(201, "Alice")];
+    elements.extend((0..limit).map(elided));
+    let limits = EnvelopeLimits::default().with_max_total_elements(limit * 2);
+    Envelope::from_tagged_cbor_with_limits(CBOR::to_tagged_value(200, elements), &limits).unwrap()
+}
+
+#[test]
+fn test_construction_past_node_assertion_limit() {
+    let limit = EnvelopeLimits::DEFAULT_MAX_NODE_ASSERTIONS;
+    let full = full_node();
+    assert_eq!(full.assertions().len(), limit);
+
+    let assertion = Envelope::new_assertion("knows", "Bob");
+    let error = full.add_assertion_envelope(assertion.clone()).unwrap_err();
+    assert_eq!(too_many_assertions(&error), (limit + 2, limit));
+    let error = full.add_assertion_envelopes(std::slice::from_ref(&assertion)).unwrap_err();
+    assert_eq!(too_many_assertions(&error), (limit + 2, limit));
+
+    // An assertion already present does not add to the node.
+    let present = full.assertions()[0].clone();
+    assert!(full.add_assertion_envelope(present.clone()).unwrap().is_identical_to(&full));
+    assert!(full.add_assertion_envelope_salted(present, false).unwrap().is_identical_to(&full));
+
+    #[cfg(feature = "salt")]
+    {
+        let error = full.add_assertion_envelope_salted(assertion.clone(), true).unwrap_err();
+        assert_eq!(too_many_assertions(&error), (limit + 2, limit));
+    }
+
+    let error = full.try_add_assertion("knows", "Bob").unwrap_err();
+    assert_eq!(too_many_assertions(&error), (limit + 2, limit));
+
+    // The infallible methods build the node, which can only be decoded with
+    // a higher limit.
+    let over = full.add_assertion("knows", "Bob");
+    assert_eq!(over.assertions().len(), limit + 1);
+    assert!(Envelope::from_tagged_cbor_data(over.tagged_cbor_data()).is_err());
+}
+
+/// Returns tagged CBOR data for `depth` nested wrapped envelopes.
+fn deeply_wrapped_data(depth: usize) -> Vec<u8> {
+    let mut data = [0xd8, 0xc8].repeat(depth);
+    data.extend(Envelope::new("Alice").untagged_cbor().to_cbor_data());
+    data
+}
+
+fn too_deep(error: &anyhow::Error) -> usize {
+    match error.downcast_ref::<EnvelopeError>() {
+        Some(EnvelopeError::TooDeep { limit }) => *limit,
+        _ => panic!("unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn test_depth_limit() {
+    let limits = EnvelopeLimits::default().with_max_depth(4);
+    let envelope = Envelope::from_tagged_cbor_data_with_limits(deeply_wrapped_data(4), &limits).unwrap();
+    assert_eq!(envelope.unwrap_envelope().unwrap().unwrap_envelope().unwrap().unwrap_envelope().unwrap().extract_subject::<String>().unwrap(), "Alice");
+
+    // Checked both before and after parsing.
+    let data = deeply_wrapped_data(5);
+    let error = Envelope::from_tagged_cbor_data_with_limits(&data, &limits).unwrap_err();
+    assert_eq!(too_deep(&error), 4);
+    let cbor = CBOR::try_from_data(&data).unwrap();
+    let error = Envelope::from_tagged_cbor_with_limits(cbor, &limits).unwrap_err();
+    assert_eq!(too_deep(&error), 4);
+    assert_eq!(error.to_string(), "envelope elements are nested deeper than the limit of 4 levels");
+
+    // Assertions add to the depth, and an element reused from a shallower
+    // position is held to the limit at its new one.
+    let bob = Envelope::new("Bob");
+    let nested = Envelope::new("Alice").add_assertion("knows", bob.clone()).add_assertion(bob.wrap_envelope().wrap_envelope(), "friend");
+    let error = Envelope::from_tagged_cbor_data_with_limits(nested.tagged_cbor_data(), &limits).unwrap_err();
+    assert_eq!(too_deep(&error), 4);
+    assert!(Envelope::from_tagged_cbor_data_with_limits(nested.tagged_cbor_data(), &limits.with_max_depth(5)).is_ok());
+}
+
+#[test]
+fn test_hostile_nesting_fails_fast() {
+    // Far deeper than the parser could recurse without exhausting the stack.
+    let data = deeply_wrapped_data(1_000_000);
+    let error = Envelope::from_tagged_cbor_data(&data).unwrap_err();
+    assert_eq!(too_deep(&error), EnvelopeLimits::DEFAULT_MAX_DEPTH);
+
+    let mut data = vec![0xd8, 0xc8];
+    data.extend([0x82].repeat(1_000_000));
+    let error = Envelope::from_tagged_cbor_data(&data).unwrap_err();
+    assert_eq!(too_deep(&error), EnvelopeLimits::DEFAULT_MAX_DEPTH);
+}
+
+#[cfg(feature = "multithreaded")]
+#[test]
+fn test_concurrent_construction_past_node_assertion_limit() {
+    let limit = EnvelopeLimits::DEFAULT_MAX_NODE_ASSERTIONS;
+    let full = full_node();
+    let builder = ConcurrentNodeBuilder::new("Alice");
+    builder.extend(full.assertions().iter().cloned());
+    // Duplicates are not counted.
+    builder.push(full.assertions()[1].clone());
+    assert!(builder.finish().unwrap().is_identical_to(&full));
+
+    let builder = ConcurrentNodeBuilder::new("Alice");
+    builder.extend(full.assertions().iter().cloned());
+    builder.push(Envelope::new_assertion("knows", "Bob"));
+    let error = builder.finish().unwrap_err();
+    assert_eq!(too_many_assertions(&error), (limit + 2, limit));
+}
@@ -0,0 +1,108 @@
+#![cfg(all(feature = "recipient", feature = "signature"))]
+use bc_envelope::prelude::full::*;
+use bc_envelope::extension::delegation::DELEGATED_RECIPIENT;
+use bc_components::PrivateKeyBase;
+
+mod common;
+use crate::common::test_data::*;
+
+fn date(s: &str) -> Date {
+    Date::from_string(s).unwrap()
+}
+
+fn error_of(result: anyhow::Result<Envelope>) -> EnvelopeError {
+    let error = result.unwrap_err();
+    match error.downcast::<EnvelopeError>() {
+        Ok(error) => error,
+        Err(error) => panic!("unexpected error: {}", error),
+    }
+}
+
+fn encrypted_message() -> Envelope {
+    Envelope::new("Meet at noon.")
+        .wrap_envelope()
+        .encrypt_subject_to_recipients(&[&alice_public_key(), &carol_public_key()])
+        .unwrap()
+}
+
+#[test]
+fn test_delegation() {
+    let encrypted = encrypted_message();
+
+    // The relay carries the delegation, but cannot decrypt the message.
+    let delegated = encrypted.prepare_delegation(&alice_private_key(), &bob_public_key(), &date("2099-12-31")).unwrap();
+    assert_eq!(delegated.assertions_with_predicate(DELEGATED_RECIPIENT).len(), 1);
+    assert!(matches!(error_of(delegated.decrypt_subject_to_recipient(&bob_private_key())), EnvelopeError::UnknownRecipient));
+
+    // Bob accepts the delegation, and can then decrypt the message as a
+    // recipient.
+    let accepted = delegated.accept_delegation(&bob_private_key(), &alice_public_key()).unwrap();
+    assert!(accepted.assertions_with_predicate(DELEGATED_RECIPIENT).is_empty());
+    assert_eq!(accepted.recipients().unwrap().len(), 3);
+    let message = accepted.decrypt_subject_to_recipient(&bob_private_key()).unwrap().unwrap_envelope().unwrap();
+    assert_eq!(message.extract_subject::<String>().unwrap(), "Meet at noon.");
+
+    // Only a recipient can delegate.
+    let not_a_recipient = PrivateKeyBase::new();
+    assert!(matches!(
+        error_of(encrypted.prepare_delegation(&not_a_recipient, &bob_public_key(), &date("2099-12-31"))),
+        EnvelopeError::UnknownRecipient
+    ));
+
+    // Only the delegate can accept.
+    assert!(matches!(
+        error_of(delegated.accept_delegation(&carol_private_key(), &alice_public_key())),
+        EnvelopeError::UnknownRecipient
+    ));
+}
+
+#[test]
+fn test_expired_delegation() {
+    let delegated = encrypted_message()
+        .prepare_delegation(&alice_private_key(), &bob_public_key(), &date("2020-01-01"))
+        .unwrap();
+    match error_of(delegated.accept_delegation(&bob_private_key(), &alice_public_key())) {
+        EnvelopeError::ExpiredDelegation { valid_until } => assert_eq!(valid_until, date("2020-01-01")),
+        error => panic!("unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn test_delegation_from_non_authorizer() {
+    // Carol is a recipient, but Bob only accepts delegations from Alice.
+    let delegated = encrypted_message()
+        .prepare_delegation(&carol_private_key(), &bob_public_key(), &date("2099-12-31"))
+        .unwrap();
+    assert!(matches!(
+        error_of(delegated.accept_delegation(&bob_private_key(), &alice_public_key())),
+        EnvelopeError::UnverifiedSignature
+    ));
+    assert!(delegated.accept_delegation(&bob_private_key(), &carol_public_key()).is_ok());
+
+    // With delegations to Bob from both Carol and Alice, whichever comes
+    // first, Bob accepts the one signed by the authorizer he trusts.
+    let both = delegated.prepare_delegation(&alice_private_key(), &bob_public_key(), &date("2099-12-31")).unwrap();
+    assert_eq!(both.assertions_with_predicate(DELEGATED_RECIPIENT).len(), 2);
+    for authorizer in [alice_public_key(), carol_public_key()] {
+        let accepted = both.accept_delegation(&bob_private_key(), &authorizer).unwrap();
+        assert_eq!(accepted.assertions_with_predicate(DELEGATED_RECIPIENT).len(), 1);
+        assert!(accepted.decrypt_subject_to_recipient(&bob_private_key()).is_ok());
+    }
+
+    // A delegation for another message is rejected, even though it was
+    // signed by Alice.
+    let alice_only = Envelope::new("Meet at one.")
+        .wrap_envelope()
+        .encrypt_subject_to_recipient(&alice_public_key())
+        .unwrap();
+    let delegation = alice_only
+        .prepare_delegation(&alice_private_key(), &bob_public_key(), &date("2099-12-31"))
+        .unwrap()
+        .assertion_with_predicate(DELEGATED_RECIPIENT)
+        .unwrap();
+    let misplaced = encrypted_message().add_assertion_envelope(delegation).unwrap();
+    assert!(matches!(
+        error_of(misplaced.accept_delegation(&bob_private_key(), &alice_public_key())),
+        EnvelopeError::InvalidDelegation { .. }
+    ));
+}
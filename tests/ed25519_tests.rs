@@ -3,7 +3,6 @@ use indoc::indoc;
 
 mod common;
 use crate::common::test_data::*;
-use crate::common::check_encoding::*;
 
 #[test]
 fn test_ed25519_signed_plaintext() {
@@ -3,7 +3,6 @@ use bc_envelope::prelude::*;
 use indoc::indoc;
 
 mod common;
-use crate::common::check_encoding::*;
 
 fn basic_envelope() -> Envelope {
     Envelope::new("Hello.")
@@ -24,6 +23,27 @@ fn double_assertion_envelope() -> Envelope {
         .add_assertion("knows", "Carol")
 }
 
+#[test]
+fn test_elided_assertion_identity() {
+    let e1 = double_assertion_envelope();
+    let assertion = Envelope::new_assertion("knows", "Carol");
+    let e2 = e1.elide_removing_target(&assertion);
+
+    // Same digest, so equivalent...
+    assert!(e1.is_equivalent_to(&e2));
+    assert_eq!(e1.digest(), e2.digest());
+
+    // ...but one of the assertions is elided, so not identical.
+    assert!(!e1.is_identical_to(&e2));
+    assert_ne!(e1.structural_digest(), e2.structural_digest());
+    assert_ne!(e1, e2);
+
+    // Eliding the same element again produces an identical envelope.
+    let e3 = e1.elide_removing_target(&assertion);
+    assert!(e2.is_identical_to(&e3));
+    assert_eq!(e2, e3);
+}
+
 #[test]
 fn test_envelope_elision() -> anyhow::Result<()> {
     let e1 = basic_envelope();
@@ -476,3 +496,66 @@ fn test_targeted_remove() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_elide_subject_and_assertions() -> anyhow::Result<()> {
+    let original = Envelope::new("Alice")
+        .add_assertion("knows", "Bob")
+        .add_assertion("knows", "Carol")
+        .add_assertion("age", 30);
+
+    let elided_subject = original.elide_subject();
+    assert!(elided_subject.is_equivalent_to(&original));
+    assert!(elided_subject.subject().is_elided());
+    assert_eq!(elided_subject.assertions().len(), 3);
+    assert!(elided_subject.assertions().iter().all(|assertion| !assertion.is_elided()));
+    assert_eq!(elided_subject.format(), indoc! {r#"
+    ELIDED [
+        "age": 30
+        "knows": "Bob"
+        "knows": "Carol"
+    ]
+    "#}.trim());
+
+    let elided_assertions = original.elide_assertions();
+    assert!(elided_assertions.is_equivalent_to(&original));
+    assert!(!elided_assertions.subject().is_elided());
+    assert_eq!(elided_assertions.assertions().len(), 3);
+    assert!(elided_assertions.assertions().iter().all(|assertion| assertion.is_elided()));
+    assert_eq!(elided_assertions.format(), indoc! {r#"
+    "Alice" [
+        ELIDED (3)
+    ]
+    "#}.trim());
+
+    // Assertions can be revealed one at a time.
+    let knows_bob = Envelope::new_assertion("knows", "Bob");
+    let revealed = elided_assertions.replace_assertion(knows_bob.clone(), knows_bob)?;
+    assert!(revealed.is_equivalent_to(&original));
+    assert_eq!(revealed.format(), indoc! {r#"
+    "Alice" [
+        "knows": "Bob"
+        ELIDED (2)
+    ]
+    "#}.trim());
+
+    // Envelopes without assertions.
+    assert!(basic_envelope().elide_subject().is_elided());
+    assert!(basic_envelope().elide_assertions().is_identical_to(&basic_envelope()));
+
+    #[cfg(feature = "signature")]
+    {
+        use crate::common::test_data::{alice_private_key, alice_public_key};
+
+        let signed = original.sign(&alice_private_key());
+        let signatures = signed.assertions().to_vec();
+        for elided in [elided_subject, elided_assertions] {
+            let elided_signed = elided.wrap_envelope().add_assertions(&signatures)?;
+            assert!(elided_signed.is_equivalent_to(&signed));
+            let verified = elided_signed.verify(&alice_public_key())?;
+            assert!(verified.is_identical_to(&elided));
+        }
+    }
+
+    Ok(())
+}
@@ -1,10 +1,11 @@
 #![cfg(feature = "encrypt")]
 use bc_envelope::prelude::*;
+use bc_envelope::known_values;
+use dcbor::{CBOR, CBORError};
 use bc_components::{DigestProvider, SymmetricKey, Nonce, EncryptedMessage};
 use hex_literal::hex;
 
 mod common;
-use crate::common::check_encoding::*;
 
 fn basic_envelope() -> Envelope {
     Envelope::new("Hello.")
@@ -80,3 +81,123 @@ fn test_encrypted() {
 //     let e2 =
 //         e1.sign(alice_private_key())
 // }
+
+#[test]
+fn test_decrypt_error_source() {
+    fn decrypt(envelope: &Envelope, key: &SymmetricKey) -> Result<Envelope, Box<dyn std::error::Error>> {
+        let envelope = envelope.decrypt_subject(key)?;
+        Ok(envelope)
+    }
+
+    let encrypted = basic_envelope().encrypt_subject(&symmetric_key()).unwrap();
+    let error = encrypted.decrypt_subject(&SymmetricKey::new()).unwrap_err();
+    let envelope_error = error.downcast_ref::<bc_envelope::EnvelopeError>().unwrap();
+    assert!(matches!(envelope_error, bc_envelope::EnvelopeError::Crypto(_)));
+    assert_eq!(envelope_error.to_string(), "cryptographic operation failed");
+    assert!(std::error::Error::source(envelope_error).is_some());
+
+    assert!(decrypt(&encrypted, &SymmetricKey::new()).is_err());
+    assert!(decrypt(&encrypted, &symmetric_key()).unwrap().is_equivalent_to(&basic_envelope()));
+
+    let error = basic_envelope().decrypt_subject(&symmetric_key()).unwrap_err();
+    assert!(matches!(error.downcast_ref::<bc_envelope::EnvelopeError>(), Some(bc_envelope::EnvelopeError::NotEncrypted)));
+}
+
+#[test]
+fn test_error_conversions() {
+    fn decrypt(message: &EncryptedMessage, key: &SymmetricKey) -> Result<Vec<u8>, bc_envelope::EnvelopeError> {
+        Ok(key.decrypt(message)?)
+    }
+
+    fn decode(data: &[u8]) -> Result<CBOR, bc_envelope::EnvelopeError> {
+        let cbor = CBOR::try_from_data(data).map_err(|error| error.downcast::<CBORError>().unwrap())?;
+        Ok(cbor)
+    }
+
+    let message = symmetric_key().encrypt(b"Hello.", None::<Nonce>, None::<Nonce>);
+    assert_eq!(decrypt(&message, &symmetric_key()).unwrap(), b"Hello.");
+    let error = decrypt(&message, &SymmetricKey::new()).unwrap_err();
+    assert!(matches!(error, bc_envelope::EnvelopeError::Crypto(_)));
+
+    assert!(decode(&[0x01]).is_ok());
+    let error = decode(&[0x01, 0x02]).unwrap_err();
+    assert!(matches!(error, bc_envelope::EnvelopeError::CBOR(_)));
+    assert!(std::error::Error::source(&error).is_some());
+}
+
+#[test]
+fn test_decrypt_all() {
+    let alice = Envelope::new("Alice")
+        .add_assertion("knows", "Bob")
+        .add_assertion("age", 30);
+    let original = Envelope::new("Document")
+        .add_assertion("author", alice.clone())
+        .add_assertion("title", "Minutes")
+        .add_assertion("note", "Confidential")
+        .wrap_envelope()
+        .add_assertion("verifiedBy", "Carol");
+
+    // Encrypt three scattered elements with one key, including one inside
+    // another encrypted element, and a fourth with a different key.
+    let key = symmetric_key();
+    let other_key = SymmetricKey::new();
+    let knows_bob = alice.assertion_with_predicate("knows").unwrap();
+    let title = original.unwrap_envelope().unwrap().assertion_with_predicate("title").unwrap();
+    let encrypted = original
+        .elide_removing_target_with_action(&knows_bob, &ObscureAction::Encrypt(key.clone()))
+        .elide_removing_array_with_action(&[&alice, &title], &ObscureAction::Encrypt(key.clone()))
+        .elide_removing_target_with_action(&Envelope::new("Carol"), &ObscureAction::Encrypt(other_key.clone()))
+        .check_encoding().unwrap();
+    assert!(encrypted.is_equivalent_to(&original));
+    assert_eq!(encrypted.stats().encrypted, 3);
+
+    // Decrypting with the key restores what it opens, leaving the rest.
+    let decrypted = encrypted.decrypt_all(&key).unwrap();
+    assert_eq!(decrypted.digest(), original.digest());
+    assert_eq!(decrypted.stats().encrypted, 1);
+    let error = encrypted.decrypt_all_strict(&key).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<bc_envelope::EnvelopeError>(),
+        Some(bc_envelope::EnvelopeError::UndecryptedElements { count: 1 })
+    ));
+
+    // With both keys, the envelope is restored exactly.
+    let restored = decrypted.decrypt_all_strict(&other_key).unwrap();
+    assert!(restored.is_identical_to(&original));
+    assert_eq!(restored.format(), original.format());
+
+    // An envelope with nothing encrypted is unchanged.
+    assert!(original.decrypt_all_strict(&key).unwrap().is_identical_to(&original));
+}
+
+#[test]
+fn test_encrypt_subject_using() {
+    use bc_rand::{make_fake_random_number_generator, RandomNumberGenerator};
+
+    // The same generator state produces the same ciphertext.
+    let e1 = double_assertion_envelope();
+    let encrypt = || e1.encrypt_subject_using(&symmetric_key(), &mut make_fake_random_number_generator()).unwrap();
+    let encrypted = encrypt().check_encoding().unwrap();
+    assert_eq!(encrypted.tagged_cbor_data(), encrypt().tagged_cbor_data());
+    assert_eq!(encrypted.digest(), e1.digest());
+    assert!(encrypted.subject().is_encrypted());
+
+    // The nonce is drawn from the generator.
+    let mut data = [0u8; Nonce::NONCE_SIZE];
+    make_fake_random_number_generator().fill_random_data(&mut data);
+    let expected = e1.encrypt_subject_opt(&symmetric_key(), Some(Nonce::from_data(data))).unwrap();
+    assert!(encrypted.is_identical_to(&expected));
+
+    // Advancing the generator changes the ciphertext but not the digest.
+    let mut rng = make_fake_random_number_generator();
+    let first = e1.encrypt_subject_using(&symmetric_key(), &mut rng).unwrap();
+    let second = e1.encrypt_subject_using(&symmetric_key(), &mut rng).unwrap();
+    assert_ne!(first.tagged_cbor_data(), second.tagged_cbor_data());
+    assert_eq!(first.digest(), second.digest());
+
+    let decrypted = encrypted.decrypt_subject(&symmetric_key()).unwrap();
+    assert!(decrypted.is_identical_to(&e1));
+    assert!(e1.encrypt_subject(&symmetric_key()).unwrap()
+        .encrypt_subject_using(&symmetric_key(), &mut rng)
+        .is_err());
+}
@@ -0,0 +1,323 @@
+#![cfg(feature = "expression")]
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_envelope::{EnvelopeError, Expression, ExpressionBehavior, FormatContext, Function, KnownValue, Parameter, RegistryItem, Request, RequestBehavior, Response, ResponseBehavior, functions, known_values, parameters};
+use bc_envelope::extension::expressions::{FunctionsStore, ParametersStore};
+use indoc::indoc;
+
+fn is_invalid_format(error: &anyhow::Error) -> bool {
+    matches!(error.downcast_ref::<EnvelopeError>(), Some(EnvelopeError::InvalidFormat { .. }))
+}
+
+#[test]
+fn test_function_text_round_trip() {
+    let store = FunctionsStore::new([
+        functions::ADD, functions::SUB, functions::MUL, functions::DIV, functions::NEG,
+        functions::LT, functions::LE, functions::GT, functions::GE, functions::EQ,
+        functions::NE, functions::AND, functions::OR, functions::XOR, functions::NOT,
+    ]);
+    for value in 0..=100u64 {
+        let function = store.function_for_value(value);
+        let text = function.to_string();
+        assert_eq!(Function::parse(&text, Some(&store)).unwrap(), function);
+        assert_eq!(Function::parse_strict(&text, Some(&store)).unwrap(), function);
+        assert_eq!(Function::parse(&format!("«{}»", text), Some(&store)).unwrap(), function);
+    }
+    for name in ["foo", "my function", "say \"hi\"", "back\\slash", "7", "add", ""] {
+        let function = Function::new_named(name);
+        let text = function.to_string();
+        assert_eq!(Function::parse(&text, Some(&store)).unwrap(), function, "{}", text);
+        assert_eq!(Function::parse_strict(&text, None).unwrap(), function, "{}", text);
+    }
+}
+
+#[test]
+fn test_function_from_str() {
+    assert_eq!("add".parse::<Function>().unwrap(), functions::ADD);
+    assert_eq!("«add»".parse::<Function>().unwrap(), functions::ADD);
+    assert_eq!("function:1".parse::<Function>().unwrap(), functions::ADD);
+    assert_eq!("1".parse::<Function>().unwrap().to_string(), "add");
+
+    // The numeric fallback.
+    let function: Function = "function:7".parse().unwrap();
+    assert_eq!(function, Function::from(7));
+    assert_eq!(function.to_string(), "7");
+
+    // An unknown bare name is a named function, unless parsing strictly.
+    assert_eq!("foo".parse::<Function>().unwrap(), Function::new_named("foo"));
+    assert_eq!("«\"my function\"»".parse::<Function>().unwrap(), Function::new_named("my function"));
+    assert!(is_invalid_format(&Function::parse_strict("foo", None).unwrap_err()));
+    assert!(is_invalid_format(&Function::parse_strict("le", Some(&FunctionsStore::new([functions::ADD]))).unwrap_err()));
+
+    for text in ["", "«add", "add»", "parameter:1", "my function", "\"unterminated", "\"a\" b"] {
+        assert!(is_invalid_format(&text.parse::<Function>().unwrap_err()), "{}", text);
+    }
+}
+
+#[test]
+fn test_parameter_text_round_trip() {
+    let store = ParametersStore::new([parameters::BLANK, parameters::LHS, parameters::RHS]);
+    for value in 0..=100u64 {
+        let parameter = store.parameter_for_value(value);
+        let text = parameter.to_string();
+        assert_eq!(Parameter::parse(&text, Some(&store)).unwrap(), parameter);
+        assert_eq!(Parameter::parse_strict(&text, Some(&store)).unwrap(), parameter);
+    }
+    for name in ["foo", "my parameter", "say \"hi\"", "3", "lhs"] {
+        let parameter = Parameter::new_named(name);
+        let text = parameter.to_string();
+        assert_eq!(Parameter::parse(&text, Some(&store)).unwrap(), parameter, "{}", text);
+        assert_eq!(Parameter::parse_strict(&text, None).unwrap(), parameter, "{}", text);
+    }
+}
+
+#[test]
+fn test_parameter_from_str() {
+    assert_eq!("lhs".parse::<Parameter>().unwrap(), parameters::LHS);
+    assert_eq!("❰lhs❱".parse::<Parameter>().unwrap(), parameters::LHS);
+    assert_eq!("_".parse::<Parameter>().unwrap(), parameters::BLANK);
+    assert_eq!("parameter:3".parse::<Parameter>().unwrap(), parameters::RHS);
+    assert_eq!("parameter:99".parse::<Parameter>().unwrap().to_string(), "99");
+    assert_eq!("❰\"my parameter\"❱".parse::<Parameter>().unwrap(), Parameter::new_named("my parameter"));
+    assert_eq!("foo".parse::<Parameter>().unwrap(), Parameter::new_named("foo"));
+    assert!(is_invalid_format(&Parameter::parse_strict("foo", None).unwrap_err()));
+    assert!(is_invalid_format(&"function:3".parse::<Parameter>().unwrap_err()));
+}
+
+#[test]
+fn test_custom_functions_in_format_context() {
+    let my_func = Function::new_with_static_name(5000, "myFunc");
+    let expression = Envelope::from(Expression::new(5000u64).with_parameter(parameters::LHS, 2));
+
+    // Without the function registered, it is shown by value. None of these
+    // contexts have parameters, so `lhs` is shown by value too.
+    let mut context = FormatContext::default();
+    bc_envelope::register_tags_in(&mut context);
+    assert_eq!(expression.format_opt(Some(&context)), indoc! {r#"
+    «5000» [
+        ❰2❱: 2
+    ]
+    "#}.trim());
+    assert_eq!(Function::from(5000u64).description(None), "5000");
+
+    // A context with the function shows it by name.
+    let mut functions = FunctionsStore::default();
+    functions.insert(my_func.clone());
+    let mut context = FormatContext::new(false, None, None, Some(&functions), None);
+    bc_envelope::register_tags_in(&mut context);
+    assert_eq!(expression.format_opt(Some(&context)), indoc! {r#"
+    «myFunc» [
+        ❰2❱: 2
+    ]
+    "#}.trim());
+    assert_eq!(Function::from(5000u64).description(Some(context.functions())), "myFunc");
+
+    // Functions can also be added to an existing context.
+    let mut context = FormatContext::default();
+    bc_envelope::register_tags_in(&mut context);
+    context.insert_function(Function::new_with_static_name(5000, "otherFunc"));
+    assert!(expression.format_opt(Some(&context)).starts_with("«otherFunc»"));
+
+    // Registering a value again replaces its name, and named functions need
+    // no registration.
+    functions.insert(Function::new_with_static_name(5000, "renamed"));
+    functions.insert(Function::new_named("named"));
+    assert_eq!(functions.function_for_value(5000).name(), "renamed");
+    assert!(functions.function_named("myFunc").is_none());
+    assert!(functions.function_named("named").is_none());
+}
+
+#[test]
+fn test_new_function_and_add_parameter() {
+    bc_envelope::register_tags();
+
+    let envelope = Envelope::new_function(functions::ADD)
+        .add_parameter(parameters::LHS, 2)
+        .add_parameter(parameters::RHS, 3)
+        .add_optional_parameter("note", None::<&str>);
+    assert_eq!(envelope.format(), indoc! {r#"
+    «add» [
+        ❰lhs❱: 2
+        ❰rhs❱: 3
+    ]
+    "#}.trim());
+
+    let expression = Expression::new(functions::ADD)
+        .with_parameter(parameters::LHS, 2)
+        .with_parameter(parameters::RHS, 3);
+    assert!(envelope.is_identical_to(expression.expression_envelope()));
+
+    let parsed = Expression::try_from(envelope).unwrap();
+    assert_eq!(parsed.function(), &functions::ADD);
+    assert_eq!(parsed.extract_object_for_parameter::<i32>(parameters::RHS).unwrap(), 3);
+
+    let envelope = Envelope::new_function("greet")
+        .add_optional_parameter("name", Some("Alice"));
+    assert_eq!(envelope.format(), indoc! {r#"
+    «"greet"» [
+        ❰"name"❱: "Alice"
+    ]
+    "#}.trim());
+}
+
+#[test]
+fn test_function_call_cbor_round_trip() {
+    bc_envelope::register_tags();
+
+    let envelope = Envelope::new_function(functions::ADD)
+        .add_parameter(parameters::LHS, 2)
+        .add_parameter(parameters::RHS, 3);
+
+    // Parameter predicates are tagged, so they are distinct from ordinary
+    // predicates with the same name.
+    let assertion = envelope.assertion_with_predicate(parameters::LHS).unwrap();
+    let predicate = assertion.as_predicate().unwrap();
+    let (tag, _) = predicate.leaf_cbor().unwrap().clone().try_into_tagged_value().unwrap();
+    assert_eq!(tag.value(), bc_components::tags::TAG_PARAMETER);
+    assert!(envelope.assertion_with_predicate("lhs").is_err());
+
+    let decoded = Envelope::from_tagged_cbor_data(envelope.tagged_cbor_data()).unwrap();
+    assert!(decoded.is_identical_to(&envelope));
+    assert_eq!(decoded.format(), indoc! {r#"
+    «add» [
+        ❰lhs❱: 2
+        ❰rhs❱: 3
+    ]
+    "#}.trim());
+    let expression = Expression::try_from(decoded).unwrap();
+    assert_eq!(expression.function(), &functions::ADD);
+    assert_eq!(expression.extract_object_for_parameter::<i32>(parameters::LHS).unwrap(), 2);
+}
+
+#[test]
+fn test_standard_parameters_in_format() {
+    bc_envelope::register_tags();
+
+    // Parameters given only by value are shown by the names in the global
+    // store, as are the constants themselves.
+    let envelope = Envelope::new_function(functions::SUB)
+        .add_parameter(Parameter::from(parameters::BLANK_VALUE), "x")
+        .add_parameter(Parameter::from(parameters::LHS_VALUE), 5)
+        .add_parameter(parameters::RHS, 3);
+    assert_eq!(envelope.format(), indoc! {r#"
+    «sub» [
+        ❰_❱: "x"
+        ❰lhs❱: 5
+        ❰rhs❱: 3
+    ]
+    "#}.trim());
+    assert_eq!(Parameter::from(parameters::LHS_VALUE), parameters::LHS);
+    assert_eq!(Parameter::from("lhs"), Parameter::new_named("lhs"));
+    assert_ne!(Parameter::from("lhs"), parameters::LHS);
+
+    // A value with no registered name is shown by number.
+    let envelope = Envelope::new_function(functions::SUB)
+        .add_parameter(Parameter::from(99u64), 1);
+    assert_eq!(envelope.format(), indoc! {r#"
+    «sub» [
+        ❰99❱: 1
+    ]
+    "#}.trim());
+}
+
+#[test]
+fn test_parameter_value() {
+    let envelope = Envelope::new_function(functions::ADD)
+        .add_parameter(parameters::LHS, 2)
+        .add_parameter(parameters::RHS, 3)
+        .add_parameter("label", "sum");
+
+    let lhs: i32 = envelope.parameter_value(parameters::LHS).unwrap();
+    let rhs: i32 = envelope.parameter_value(parameters::RHS).unwrap();
+    assert_eq!(lhs + rhs, 5);
+    assert_eq!(envelope.parameter_value::<String>("label").unwrap(), "sum");
+
+    let error = envelope.parameter_value::<i32>(parameters::BLANK).unwrap_err();
+    assert!(matches!(error.downcast_ref::<EnvelopeError>(), Some(EnvelopeError::NonexistentPredicate { .. })));
+    assert!(envelope.parameter_value::<String>(parameters::LHS).is_err());
+}
+
+#[test]
+fn test_request_response_envelopes() {
+    bc_envelope::register_tags();
+
+    let id = ARID::from_data(hex_literal::hex!("c66be27dbad7cd095ca77647406d07976dc0f35f0d4d654bb0e96dd227a1e9fc"));
+    let body = Envelope::new_function(functions::ADD)
+        .add_parameter(parameters::LHS, 2)
+        .add_parameter(parameters::RHS, 3);
+    let request = Envelope::new_request(&id, body.clone());
+    assert_eq!(request.format(), indoc! {r#"
+    request(ARID(c66be27d)) [
+        'body': «add» [
+            ❰lhs❱: 2
+            ❰rhs❱: 3
+        ]
+    ]
+    "#}.trim());
+    assert_eq!(request.request_id().unwrap(), id);
+    let parsed = Request::try_from(request.clone()).unwrap();
+    assert_eq!(parsed.id(), &id);
+    assert!(Envelope::from(parsed).is_identical_to(&request));
+
+    // A server answers with the request's ID, which the client uses to
+    // correlate the response.
+    let request_id = request.request_id().unwrap();
+    let response = Envelope::new_response(&request_id, 5);
+    assert_eq!(response.format(), indoc! {r#"
+    response(ARID(c66be27d)) [
+        'result': 5
+    ]
+    "#}.trim());
+    assert_eq!(response.response_id().unwrap(), id);
+    assert_eq!(Response::try_from(response).unwrap().extract_result::<i32>().unwrap(), 5);
+
+    let error_response = Envelope::new_error_response(&request_id, "Overflow");
+    assert_eq!(error_response.format(), indoc! {r#"
+    response(ARID(c66be27d)) [
+        'error': "Overflow"
+    ]
+    "#}.trim());
+    assert_eq!(error_response.response_id().unwrap(), id);
+    assert!(Response::try_from(error_response).unwrap().is_err());
+
+    // An early failure has no ID, and a request is not a response.
+    let early_failure: Envelope = Response::new_early_failure().into();
+    assert!(early_failure.response_id().is_err());
+    assert!(request.response_id().is_err());
+    assert!(Envelope::new_response(&id, Envelope::ok()).request_id().is_err());
+}
+
+#[test]
+fn test_registry_dependencies() {
+    let custom = KnownValue::new(999_999);
+    let envelope = Envelope::from(
+        Expression::new(functions::ADD)
+            .with_parameter(parameters::LHS, 2)
+            .with_parameter(parameters::RHS, dcbor::Date::from_string("2024-01-01").unwrap())
+    )
+        .add_assertion(known_values::NOTE, "Sum")
+        .add_assertion(custom.clone(), "Custom")
+        .add_assertion(Parameter::new_named("name"), "Named");
+
+    let deps = envelope.registry_dependencies();
+    assert_eq!(deps.standard, [
+        (RegistryItem::Tag(1), 1),
+        (RegistryItem::KnownValue(known_values::NOTE.value()), 1),
+        (RegistryItem::Function(1), 1),
+        (RegistryItem::Parameter(2), 1),
+        (RegistryItem::Parameter(3), 1),
+    ].into_iter().collect());
+    assert_eq!(deps.non_standard, [(RegistryItem::KnownValue(999_999), 1)].into_iter().collect());
+
+    // Only the custom known value would be formatted as a number.
+    assert_eq!(deps.missing_from(&FormatContext::standard()), vec![RegistryItem::KnownValue(999_999)]);
+    let mut context = FormatContext::standard();
+    context.insert_known_value(KnownValue::new_with_name(999_999u64, "custom".to_string()));
+    assert!(deps.missing_from(&context).is_empty());
+
+    // Without any registries, everything is missing.
+    assert_eq!(deps.missing_from(&FormatContext::default()), deps.items());
+    assert!(!envelope.format_opt(Some(&FormatContext::standard())).contains("'custom'"));
+    assert!(envelope.format_opt(Some(&context)).contains("'custom': \"Custom\""));
+}
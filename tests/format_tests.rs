@@ -1,4 +1,6 @@
 use bc_envelope::prelude::*;
+use bc_envelope::{Expression, ExpressionBehavior, EnvelopeLimits, FoldOptions, FormatContext, Function, KnownValue, Parameter, functions, known_values, parameters};
+use dcbor::{CBOR, CBORCase, Date, Map};
 
 #[cfg(feature = "known_value")]
 use bc_components::{Digest, ARID};
@@ -10,7 +12,6 @@ use hex_literal::hex;
 
 mod common;
 use crate::common::test_data::*;
-use crate::common::check_encoding::*;
 
 #[cfg(feature = "signature")]
 use bc_rand::make_fake_random_number_generator;
@@ -840,3 +841,409 @@ fn test_redacted_credential() {
     "#}.trim());
     assert_eq!(warranty.elements_count(), warranty.tree_format(false).split('\n').count());
 }
+
+#[cfg(feature = "known_value")]
+#[test]
+fn test_custom_format_context_registration() {
+    use dcbor::prelude::*;
+
+    with_format_context_mut!(|context: &mut FormatContext| {
+        context.insert_tag(Tag::new(60000, "my-tag"));
+        context.insert_known_value(KnownValue::new_with_name(70000u64, "myValue".to_string()));
+    });
+
+    let envelope = Envelope::new(CBOR::to_tagged_value(60000, "Hello."))
+        .add_assertion(KnownValue::new(70000), "World.");
+    assert_eq!(envelope.format(), indoc! {r#"
+    my-tag("Hello.") [
+        'myValue': "World."
+    ]
+    "#}.trim());
+    assert_eq!(envelope.tree_format(false), indoc! {r#"
+    e9d24775 NODE
+        14aca132 subj my-tag("Hello.")
+        d526e7b8 ASSERTION
+            7af80758 pred 'myValue'
+            69b2e487 obj "World."
+    "#}.trim());
+}
+
+#[test]
+fn test_format_wide_envelope() {
+    let assertions: Vec<Envelope> = (0..5000)
+        .map(|i| Envelope::new_assertion(format!("p{}", i), i))
+        .collect();
+    let e = Envelope::new("Alice")
+        .add_assertion_envelopes(&assertions)
+        .unwrap()
+        .wrap_envelope()
+        .add_assertion("note", "wide");
+
+    let start = std::time::Instant::now();
+    let formatted = e.format();
+    let flat = e.format_flat();
+    let elapsed = start.elapsed();
+
+    assert_eq!(formatted.lines().count(), 5000 + 6);
+    assert!(formatted.contains("\n        \"p4999\": 4999\n"));
+    assert!(flat.contains("\"p0\": 0, \"p1\": 1, "));
+    assert!(elapsed < std::time::Duration::from_secs(1), "formatting took {:?}", elapsed);
+}
+
+#[test]
+fn test_graph_format() {
+    let envelope = single_assertion_envelope();
+    assert_eq!(envelope.dot_format(false), indoc! {r#"
+    digraph {
+        "8955db5e" [label="NODE"];
+        "13941b48" [label="\"Alice\""];
+        "78d666eb" [label="ASSERTION"];
+        "db7dd21c" [label="\"knows\""];
+        "13b74194" [label="\"Bob\""];
+        "8955db5e" -> "13941b48" [label="subj"];
+        "8955db5e" -> "78d666eb";
+        "78d666eb" -> "db7dd21c" [label="pred"];
+        "78d666eb" -> "13b74194" [label="obj"];
+    }
+    "#}.trim());
+    assert_eq!(envelope.dot_format(true), indoc! {r#"
+    digraph {
+        "13941b48" [label="\"Alice\""];
+        "78d666eb" [label="ASSERTION"];
+        "db7dd21c" [label="\"knows\""];
+        "13b74194" [label="\"Bob\""];
+        "13941b48" -> "78d666eb";
+        "78d666eb" -> "db7dd21c";
+        "78d666eb" -> "13b74194";
+    }
+    "#}.trim());
+    assert_eq!(envelope.mermaid_format(false), indoc! {r##"
+    graph LR
+        8955db5e["NODE"]
+        13941b48["#quot;Alice#quot;"]
+        78d666eb["ASSERTION"]
+        db7dd21c["#quot;knows#quot;"]
+        13b74194["#quot;Bob#quot;"]
+        8955db5e -->|subj| 13941b48
+        8955db5e --> 78d666eb
+        78d666eb -->|pred| db7dd21c
+        78d666eb -->|obj| 13b74194
+    "##}.trim());
+}
+
+#[test]
+fn test_graph_format_counts() {
+    // "Alice" [ "knows": "Bob", "knows": "Carol" ]: the shared "knows"
+    // predicate is a single graph node with an edge from each assertion.
+    let dot = double_assertion_envelope().dot_format(false);
+    let nodes = dot.lines().filter(|line| line.contains("[label=") && !line.contains("->")).count();
+    let edges = dot.lines().filter(|line| line.contains("->")).count();
+    assert_eq!(nodes, 7);
+    assert_eq!(edges, 7);
+
+    let elided = double_assertion_envelope().elide_removing_target(&Envelope::new("Alice"));
+    assert!(elided.dot_format(false).contains(r#""13941b48" [label="ELIDED"];"#));
+}
+
+#[cfg(all(feature = "known_value", feature = "expression"))]
+#[test]
+fn test_notation_round_trip() {
+    bc_envelope::register_tags();
+
+    let credential = Envelope::new("James Maxwell")
+        .add_assertion(known_values::IS_A, "Certificate of Completion")
+        .add_assertion("issueDate", Date::from_string("2020-01-01").unwrap())
+        .add_assertion("lastSeen", Date::from_string("2023-06-01T12:30:00Z").unwrap())
+        .add_assertion("units", 1)
+        .add_assertion("balance", -42)
+        .add_assertion("ratio", 0.75)
+        .add_assertion("verified", true)
+        .add_assertion("scores", CBOR::from(vec![1, 2, 3]))
+        .add_assertion("motto", "Line one\nLine \"two\" done")
+        .add_assertion(known_values::NOTE, Envelope::new("nested").add_assertion("depth", 2))
+        .add_assertion_envelope(
+            Envelope::new_assertion("knows", "Bob")
+                .add_assertion(known_values::NOTE, Envelope::new_assertion("since", 2010).add_assertion("source", "memory"))
+        ).unwrap()
+        .wrap_envelope()
+        .add_assertion(known_values::ISSUER, Envelope::new("Example Board").wrap_envelope());
+    let request = Envelope::new_function(functions::ADD)
+        .add_parameter(parameters::LHS, 2)
+        .add_parameter(parameters::RHS, 3);
+
+    for envelope in [credential, request, Envelope::new(known_values::NOTE), Envelope::new_assertion("a", "b").wrap_envelope()] {
+        let notation = envelope.format();
+        let parsed = Envelope::from_notation(&notation).unwrap();
+        assert_eq!(parsed.digest(), envelope.digest(), "{}", notation);
+        let parsed = Envelope::from_notation(&envelope.format_flat()).unwrap();
+        assert_eq!(parsed.digest(), envelope.digest(), "{}", envelope.format_flat());
+    }
+
+    // A map is not mistaken for a wrapped assertion.
+    let mut map = Map::new();
+    map.insert("knows", "Bob");
+    let envelope = Envelope::new(map.clone());
+    assert_eq!(envelope.format(), r#"Map{"knows": "Bob"}"#);
+    assert!(Envelope::from_notation(&envelope.format()).unwrap().is_identical_to(&envelope));
+    let envelope = Envelope::new("Alice").add_assertion("data", map).add_assertion("empty", Map::new());
+    assert!(Envelope::from_notation(&envelope.format()).unwrap().is_identical_to(&envelope));
+    assert!(Envelope::from_notation(&envelope.format_flat()).unwrap().is_identical_to(&envelope));
+    assert!(!Envelope::from_notation(r#"{"knows": "Bob"}"#).unwrap().is_leaf());
+
+    let parsed = Envelope::from_notation(indoc! {r#"
+    "Alice" [
+        "knows": "Bob"
+        "data": h'0102'
+    ]
+    "#}).unwrap();
+    assert!(parsed.is_identical_to(
+        &Envelope::new("Alice")
+            .add_assertion("knows", "Bob")
+            .add_assertion("data", CBOR::to_byte_string([1, 2]))
+    ));
+}
+
+#[cfg(all(feature = "known_value", feature = "expression"))]
+#[test]
+fn test_notation_errors() {
+    bc_envelope::register_tags();
+
+    fn offset(text: &str) -> usize {
+        match Envelope::from_notation(text).unwrap_err().downcast_ref::<EnvelopeError>() {
+            Some(EnvelopeError::InvalidNotation { offset, .. }) => *offset,
+            error => panic!("unexpected error: {:?}", error),
+        }
+    }
+
+    let elided = Envelope::new("Alice").add_assertion("knows", "Bob").elide_removing_target(&Envelope::new_assertion("knows", "Bob"));
+    assert_eq!(offset(&elided.format()), 14);
+    assert_eq!(offset(&Envelope::new(ARID::from_data(hex!("c66be27dbad7cd095ca77647406d07976dc0f35f0d4d654bb0e96dd227a1e9fc"))).format()), 0);
+    assert_eq!(offset(&Envelope::new(CBOR::to_byte_string([1, 2])).format()), 0);
+    assert_eq!(offset("'noSuchValue'"), 0);
+    assert_eq!(offset("\"Alice\" [ \"Bob\" ]"), 10);
+    assert_eq!(offset("\"Alice\" [ \"knows\": \"Bob\""), 24);
+    assert_eq!(offset("\"Alice\" 42"), 8);
+    assert_eq!(offset("\"unterminated"), 0);
+}
+
+#[test]
+fn test_notation_depth_limit() {
+    let limit = EnvelopeLimits::DEFAULT_MAX_DEPTH;
+    let error_offset = |text: &str| match Envelope::from_notation_opt(text, None).unwrap_err().downcast_ref::<EnvelopeError>() {
+        Some(EnvelopeError::InvalidNotation { offset, .. }) => *offset,
+        error => panic!("unexpected error: {:?}", error),
+    };
+
+    // Wrapped envelopes and arrays can be nested up to the limit.
+    let wrapped = format!("{}1{}", "{".repeat(limit), "}".repeat(limit));
+    let mut envelope = Envelope::from_notation_opt(&wrapped, None).unwrap();
+    for _ in 0..limit {
+        envelope = envelope.unwrap_envelope().unwrap();
+    }
+    assert_eq!(envelope.extract_subject::<i32>().unwrap(), 1);
+    let array = format!("{}1{}", "[".repeat(limit), "]".repeat(limit));
+    assert!(Envelope::from_notation_opt(&array, None).is_ok());
+
+    // Deeper nesting is an error just after the delimiter that opens the
+    // level past the limit, rather than overflowing the stack.
+    assert_eq!(error_offset(&format!("{}1{}", "{".repeat(limit + 1), "}".repeat(limit + 1))), limit + 1);
+    assert_eq!(error_offset(&"{".repeat(1_000_000)), limit + 1);
+    assert_eq!(error_offset(&"[".repeat(1_000_000)), limit + 1);
+    assert_eq!(error_offset(&"Map{".repeat(1_000_000)), (limit + 1) * 4);
+    let assertions = "\"a\" [ \"b\": ";
+    assert_eq!(error_offset(&assertions.repeat(1_000_000)), limit * assertions.len() + 6);
+}
+
+#[cfg(all(feature = "known_value", feature = "expression"))]
+#[test]
+fn test_format_context_snapshot() {
+    use dcbor::prelude::*;
+
+    let my_value = KnownValue::new_with_name(70000u64, "myValue".to_string());
+    let my_func = Function::new_with_static_name(5000, "myFunc");
+    let my_param = Parameter::new_with_static_name(5001, "myParam");
+
+    let mut context = FormatContext::default();
+    bc_envelope::register_tags_in(&mut context);
+    context.insert_tag(Tag::new(60000, "my-tag"));
+    context.insert_known_value(my_value.clone());
+    context.insert_function(my_func.clone());
+    context.insert_parameter(my_param.clone());
+
+    let envelope = Envelope::new(CBOR::to_tagged_value(60000, "Hello."))
+        .add_assertion(my_value.clone(), Envelope::from(Expression::new(my_func.clone()).with_parameter(my_param.clone(), 2)));
+    let expected = indoc! {r#"
+    my-tag("Hello.") [
+        'myValue': «myFunc» [
+            ❰myParam❱: 2
+        ]
+    ]
+    "#}.trim();
+    assert_eq!(envelope.format_opt(Some(&context)), expected);
+
+    // The snapshot round trips, and contexts restored from it format the
+    // same way.
+    let snapshot = context.to_cbor().unwrap();
+    let restored = FormatContext::from_cbor(&snapshot).unwrap();
+    assert_eq!(restored.to_cbor().unwrap(), snapshot);
+    let other = FormatContext::from_cbor(&CBOR::try_from_data(snapshot.to_cbor_data()).unwrap()).unwrap();
+    assert_eq!(envelope.format_opt(Some(&restored)), expected);
+    assert_eq!(envelope.format_opt(Some(&other)), envelope.format_opt(Some(&restored)));
+    assert_eq!(restored.fingerprint().unwrap(), context.fingerprint().unwrap());
+
+    // The order of registration does not change the fingerprint.
+    let mut reordered = FormatContext::default();
+    reordered.insert_parameter(my_param.clone());
+    reordered.insert_function(my_func.clone());
+    reordered.insert_known_value(my_value.clone());
+    reordered.insert_tag(Tag::new(60000, "my-tag"));
+    bc_envelope::register_tags_in(&mut reordered);
+    assert_eq!(reordered.fingerprint().unwrap(), context.fingerprint().unwrap());
+    reordered.insert_known_value(KnownValue::new_with_name(70001u64, "otherValue".to_string()));
+    assert_ne!(reordered.fingerprint().unwrap(), context.fingerprint().unwrap());
+
+    // A snapshot of only the custom entries is merged over the standard ones.
+    let mut custom = FormatContext::default();
+    custom.insert_tag(Tag::new(60000, "my-tag"));
+    custom.insert_known_value(my_value.clone());
+    custom.insert_function(my_func.clone());
+    custom.insert_parameter(my_param.clone());
+    let merged = FormatContext::from_cbor_over_defaults(&custom.to_cbor().unwrap()).unwrap();
+    let envelope = envelope.add_assertion(known_values::NOTE, Envelope::from(Expression::new(functions::ADD).with_parameter(parameters::LHS, 1)));
+    assert_eq!(envelope.format_opt(Some(&merged)), indoc! {r#"
+    my-tag("Hello.") [
+        'myValue': «myFunc» [
+            ❰myParam❱: 2
+        ]
+        'note': «add» [
+            ❰lhs❱: 1
+        ]
+    ]
+    "#}.trim());
+
+    // Summarizers cannot be serialized.
+    context.set_summarizer(60000, std::sync::Arc::new(|_| Ok("summary".to_string())));
+    context.set_summarizer(60001, std::sync::Arc::new(|_| Ok("summary".to_string())));
+    let error = context.to_cbor().unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<EnvelopeError>(),
+        Some(EnvelopeError::UnserializableSummarizers { tags }) if tags == &[60000, 60001]
+    ));
+    assert_eq!(error.to_string(), "the summarizers for tags 60000, 60001 cannot be serialized");
+
+    // Nor can tags changed other than through `insert_tag`, which the
+    // snapshot would silently omit.
+    let mut context = FormatContext::standard();
+    assert!(context.to_cbor().is_ok());
+    context.tags_mut().insert(Tag::new(60002, "untracked"));
+    let error = context.to_cbor().unwrap_err();
+    assert!(matches!(error.downcast_ref::<EnvelopeError>(), Some(EnvelopeError::UntrackedTags)));
+    assert!(context.fingerprint().is_err());
+    assert!(with_format_context!(|context: &FormatContext| context.to_cbor().is_ok()));
+}
+
+#[test]
+fn test_folded_format() {
+    let catalog = (0..200).fold(Envelope::new("catalog"), |envelope, i| {
+        envelope.add_assertion("item", format!("value {}", i))
+    })
+    .add_assertion("name", "Widgets");
+    let context = FormatContext::default();
+
+    // Folding is off by default.
+    let unfolded = catalog.format_with_context(&context);
+    assert_eq!(unfolded.lines().count(), 203);
+    assert_eq!(unfolded.lines().filter(|line| line.contains("\"item\": ")).count(), 200);
+    assert_eq!(Envelope::from_notation_opt(&unfolded, Some(&context)).unwrap().digest(), catalog.digest());
+
+    // More than 10 assertions share the predicate, so they are folded, with
+    // a sample of two objects.
+    let folded_context = context.clone().set_fold(Some(FoldOptions::new(10, 2)));
+    let folded = catalog.format_with_context(&folded_context);
+    assert_eq!(folded, indoc! {r#"
+    "catalog" [
+        "item" (×200): "value 0", "value 1", …(198 more)
+        "name": "Widgets"
+    ]
+    "#}.trim());
+    let flat_context = context.clone().set_fold(Some(FoldOptions::new(10, 0))).set_flat(true);
+    let flat = catalog.format_with_context(&flat_context);
+    assert_eq!(flat, r#""catalog" [ "item" (×200): …(200 more), "name": "Widgets" ]"#);
+    let threshold_context = context.clone().set_fold(Some(FoldOptions::new(200, 2)));
+    assert_eq!(catalog.format_with_context(&threshold_context), unfolded);
+
+    // The tree format shows the sample of assertions and a marker.
+    let tree = catalog.tree_format_with_context(true, &folded_context);
+    assert_eq!(tree, indoc! {r#"
+    "catalog"
+        ASSERTION
+            "item"
+            "value 108"
+        ASSERTION
+            "item"
+            "value 169"
+        …(198 more) "item"
+        ASSERTION
+            "name"
+            "Widgets"
+    "#}.trim());
+    assert_eq!(catalog.tree_format_with_context(false, &folded_context).lines().count(), 12);
+
+    // Folding applies to each node separately, so an assertion omitted from
+    // the catalog is still shown in another node.
+    let sale = Envelope::new("sale").add_assertion("item", "value 0");
+    let tree = catalog.add_assertion("featured", sale).tree_format_with_context(true, &folded_context);
+    assert!(!tree.contains("…(0 more)"));
+    assert_eq!(tree.lines().filter(|line| line.trim() == "\"value 0\"").count(), 1);
+    assert_eq!(tree.lines().filter(|line| line.trim() == "\"sale\"").count(), 1);
+
+    // Folded notation is rejected rather than parsed as something else.
+    for (text, offset) in [(folded.as_str(), 23), (flat.as_str(), 19), (r#""catalog" [ "item": …(3 more) ]"#, 20)] {
+        match Envelope::from_notation_opt(text, Some(&context)).unwrap_err().downcast_ref::<EnvelopeError>() {
+            Some(EnvelopeError::InvalidNotation { message, offset: error_offset }) => {
+                assert!(message.starts_with("folded assertions cannot be reconstructed"), "{}", message);
+                assert_eq!(*error_offset, offset, "{}", text);
+            },
+            error => panic!("unexpected error: {:?}", error),
+        }
+    }
+}
+
+#[test]
+fn test_format_redacting() {
+    let envelope = Envelope::new("Alice")
+        .add_assertion("ssn", "123-45-6789")
+        .add_assertion("age", 42)
+        .add_assertion("card", Envelope::new("Visa").add_assertion("number", 4111111111111111u64).wrap_envelope());
+    let context = FormatContext::default();
+    let is_number = |envelope: &Envelope| envelope.as_leaf().is_some_and(|cbor| matches!(cbor.as_case(), CBORCase::Unsigned(_)));
+
+    // Leaves are redacted wherever they appear, including in wrapped
+    // envelopes.
+    assert_eq!(envelope.format_redacting(is_number, &context), indoc! {r#"
+    "Alice" [
+        "age": <redacted>
+        "card": {
+            "Visa" [
+                "number": <redacted>
+            ]
+        }
+        "ssn": "123-45-6789"
+    ]
+    "#}.trim());
+
+    // Whole assertions can be redacted, and redacted elements are not
+    // visited.
+    let ssn = envelope.assertion_with_predicate("ssn").unwrap();
+    let redact_ssn = |e: &Envelope| {
+        assert!(!e.is_leaf() || e.extract_subject::<String>().ok().as_deref() != Some("123-45-6789"));
+        e.digest() == ssn.digest()
+    };
+    assert_eq!(envelope.format_redacting(redact_ssn, &context.clone().set_flat(true)),
+        r#""Alice" [ <redacted>, "age": 42, "card": { "Visa" [ "number": 4111111111111111 ] } ]"#);
+
+    // Nothing is redacted if the predicate never matches, and the envelope
+    // itself is unchanged.
+    assert_eq!(envelope.format_redacting(|_| false, &context), envelope.format_opt(Some(&context)));
+    assert_eq!(envelope.format_redacting(|_| true, &context), "<redacted>");
+}
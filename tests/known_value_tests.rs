@@ -0,0 +1,253 @@
+#![cfg(feature = "known_value")]
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_envelope::{FormatContext, KnownValue, KnownValuesStore, known_values, register_known_value};
+use indoc::indoc;
+
+mod common;
+
+#[test]
+fn test_known_value_catalog() {
+    // Values from the known value registry (BCR-2023-002).
+    let catalog = [
+        (known_values::IS_A, 1, "isA"),
+        (known_values::ID, 2, "id"),
+        (known_values::SIGNED, 3, "signed"),
+        (known_values::NOTE, 4, "note"),
+        (known_values::HAS_RECIPIENT, 5, "hasRecipient"),
+        (known_values::SSKR_SHARE, 6, "sskrShare"),
+        (known_values::CONTROLLER, 7, "controller"),
+        (known_values::KEY, 8, "key"),
+        (known_values::DEREFERENCE_VIA, 9, "dereferenceVia"),
+        (known_values::ENTITY, 10, "entity"),
+        (known_values::HAS_NAME, 11, "hasName"),
+        (known_values::LANGUAGE, 12, "language"),
+        (known_values::ISSUER, 13, "issuer"),
+        (known_values::HOLDER, 14, "holder"),
+        (known_values::SALT, 15, "salt"),
+        (known_values::DATE, 16, "date"),
+        (known_values::UNKNOWN_VALUE, 17, "Unknown"),
+        (known_values::ATTACHMENT, 50, "attachment"),
+        (known_values::VENDOR, 51, "vendor"),
+        (known_values::CONFORMS_TO, 52, "conformsTo"),
+    ];
+    let store_guard = bc_envelope::KNOWN_VALUES.get();
+    let store = store_guard.as_ref().unwrap();
+    for (known_value, value, name) in catalog {
+        assert_eq!(known_value.value(), value);
+        assert_eq!(known_value.name(), name);
+        assert_eq!(store.known_value_named(name).map(|k| k.value()), Some(value));
+    }
+    assert_eq!(known_values::IS_A_RAW, 1);
+}
+
+#[cfg(feature = "types")]
+#[test]
+fn test_known_value_assertions() {
+    let id = ARID::from_data(hex_literal::hex!("8712dfac3d0ebfa910736b2a9ee39d4b68f64222a77bcc0074f3f5f1c9216d30"));
+    let envelope = Envelope::new("Alice")
+        .add_type("Person")
+        .add_note("A note.")
+        .add_id(&id)
+        .add_name("Alice Adams")
+        .add_date(&dcbor::Date::from_string("2024-01-01").unwrap())
+        .check_encoding().unwrap();
+    assert_eq!(envelope.format(), indoc! {r#"
+    "Alice" [
+        'isA': "Person"
+        'date': 2024-01-01
+        'hasName': "Alice Adams"
+        'id': ARID(8712dfac)
+        'note': "A note."
+    ]
+    "#}.trim());
+
+    let assertion = Envelope::new_assertion_with_known_predicate(known_values::NOTE, "A note.");
+    assert!(envelope.assertions().iter().any(|a| a.is_equivalent_to(&assertion)));
+}
+
+#[test]
+fn test_known_value_code() {
+    let e = Envelope::new(KnownValue::new(1));
+    assert_eq!(e.known_value_code(), Some(1));
+    assert_eq!(e.as_known_value(), Some(&KnownValue::new(1)));
+
+    // Routing on the predicate of an assertion.
+    let e = Envelope::new("Alice").add_type("Person");
+    let predicate = e.assertions()[0].try_predicate().unwrap();
+    assert_eq!(predicate.known_value_code(), Some(known_values::IS_A.value()));
+
+    assert_eq!(Envelope::new(1).known_value_code(), None);
+    assert_eq!(Envelope::new(KnownValue::new(1)).elide().known_value_code(), None);
+}
+
+#[test]
+fn test_known_value_text_round_trip() {
+    // Displaying a known value locks the global store, so it is not held.
+    let store = bc_envelope::KNOWN_VALUES.get().clone().unwrap();
+    let mut named = 0;
+    for value in 0..=1000u64 {
+        let known_value = KnownValuesStore::known_value_for_raw_value(value, Some(&store));
+        if known_value.assigned_name().is_some() {
+            named += 1;
+        }
+        let text = known_value.to_string();
+        let parsed = KnownValue::parse(&text, Some(&store)).unwrap();
+        assert_eq!(parsed, known_value);
+        assert_eq!(parsed.assigned_name(), known_value.assigned_name());
+    }
+    assert!(named > 50);
+}
+
+#[test]
+fn test_known_value_from_str() {
+    let note: KnownValue = "note".parse().unwrap();
+    assert_eq!(note, known_values::NOTE);
+    assert_eq!(note.assigned_name(), Some("note"));
+    assert_eq!("'note'".parse::<KnownValue>().unwrap(), known_values::NOTE);
+
+    // Numeric forms pick up the registered name.
+    let is_a: KnownValue = "knownValue:1".parse().unwrap();
+    assert_eq!(is_a, known_values::IS_A);
+    assert_eq!(is_a.assigned_name(), Some("isA"));
+    assert_eq!("1".parse::<KnownValue>().unwrap(), known_values::IS_A);
+
+    // Unregistered values have no name and display as their value.
+    let unknown: KnownValue = "'knownValue:70000'".parse().unwrap();
+    assert_eq!(unknown.value(), 70000);
+    assert_eq!(unknown.assigned_name(), None);
+    assert_eq!(unknown.to_string(), "70000");
+
+    // Names are resolved in the supplied store.
+    let store = KnownValuesStore::new([KnownValue::new_with_name(70000u64, "my value".to_string())]);
+    let dynamic = KnownValue::parse("'knownValue:70000'", Some(&store)).unwrap();
+    assert_eq!(dynamic.assigned_name(), Some("my value"));
+    // A name with spaces is parsed when flanked, as in formatted envelopes.
+    let flanked = format!("'{}'", dynamic);
+    assert_eq!(KnownValue::parse(&flanked, Some(&store)).unwrap().assigned_name(), Some("my value"));
+    assert!(KnownValue::parse("my value", Some(&store)).is_err());
+    assert!(KnownValue::parse("' my value'", Some(&store)).is_err());
+    let context = FormatContext::new(false, None, Some(&store), None, None);
+    let envelope = Envelope::new(dynamic.clone());
+    let notation = envelope.format_opt(Some(&context));
+    assert_eq!(notation, "'my value'");
+    assert!(Envelope::from_notation_opt(&notation, Some(&context)).unwrap().is_identical_to(&envelope));
+    assert!(KnownValue::parse("note", Some(&store)).is_err());
+    assert!(KnownValue::parse("70000", None).unwrap().assigned_name().is_none());
+
+    for text in ["notAKnownValue", "", "'note", "note'", "function:1", "\"note\"", "is a", "-1", "99999999999999999999"] {
+        let error = text.parse::<KnownValue>().unwrap_err();
+        assert!(matches!(error.downcast_ref::<bc_envelope::EnvelopeError>(), Some(bc_envelope::EnvelopeError::InvalidFormat { .. })), "{}", text);
+    }
+}
+
+#[test]
+fn test_custom_known_values() {
+    let my_predicate = KnownValue::new_with_name(5000u64, "myPredicate".to_string());
+    let e = Envelope::new("Alice").add_assertion(KnownValue::new(5000), "Bob");
+
+    // Without the vocabulary, the value is shown by number.
+    assert_eq!(e.format_opt(Some(&FormatContext::default())), indoc! {r#"
+    "Alice" [
+        '5000': "Bob"
+    ]
+    "#}.trim());
+
+    // A context built with a custom store shows it by name.
+    let mut store = KnownValuesStore::default();
+    store.insert(my_predicate.clone());
+    let context = FormatContext::new(false, None, Some(&store), None, None);
+    assert_eq!(e.format_opt(Some(&context)), indoc! {r#"
+    "Alice" [
+        'myPredicate': "Bob"
+    ]
+    "#}.trim());
+
+    // Renaming a value replaces its old name.
+    store.insert(KnownValue::new_with_name(5000u64, "renamed".to_string()));
+    assert!(store.known_value_named("myPredicate").is_none());
+    assert_eq!(store.known_value_named("renamed").unwrap().value(), 5000);
+
+    // Registering it globally affects `format` and parsing.
+    register_known_value(my_predicate.clone());
+    assert_eq!(e.format(), indoc! {r#"
+    "Alice" [
+        'myPredicate': "Bob"
+    ]
+    "#}.trim());
+    assert_eq!("'myPredicate'".parse::<KnownValue>().unwrap(), my_predicate);
+}
+
+#[test]
+fn test_format_with_context() {
+    let e = Envelope::new("Alice").add_assertion(KnownValue::new(6000), "Bob");
+
+    // Each tenant formats with its own vocabulary, at the same time.
+    let outputs: Vec<(String, String)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = ["employer", "worksFor"].into_iter().map(|name| {
+            scope.spawn(move || {
+                let e = Envelope::new("Alice").add_assertion(KnownValue::new(6000), "Bob");
+                let mut store = KnownValuesStore::default();
+                store.insert(KnownValue::new_with_name(6000u64, name.to_string()));
+                let context = FormatContext::new(false, None, Some(&store), None, None);
+                (e.format_with_context(&context), e.tree_format_with_context(false, &context))
+            })
+        }).collect();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    });
+
+    assert_eq!(outputs[0].0, indoc! {r#"
+    "Alice" [
+        'employer': "Bob"
+    ]
+    "#}.trim());
+    assert_eq!(outputs[1].0, indoc! {r#"
+    "Alice" [
+        'worksFor': "Bob"
+    ]
+    "#}.trim());
+    assert!(outputs[1].1.contains("pred 'worksFor'"), "{}", outputs[1].1);
+
+    // The global context is unaffected.
+    assert_eq!(e.format(), indoc! {r#"
+    "Alice" [
+        '6000': "Bob"
+    ]
+    "#}.trim());
+    assert_eq!(e.format_with_context(&FormatContext::default()), e.format_opt(None));
+}
+
+#[test]
+fn test_known_value_global_names() {
+    // Parsing by name.
+    let is_a: KnownValue = "isA".parse().unwrap();
+    assert_eq!(is_a.value(), 1);
+    assert_eq!("note".parse::<KnownValue>().unwrap(), known_values::NOTE);
+    assert_eq!(KnownValue::named("note"), Some(known_values::NOTE));
+    assert_eq!(KnownValue::named("notAKnownValue"), None);
+
+    // A known value created by number is named by the global store.
+    let is_a = KnownValue::new(1);
+    assert_eq!(is_a.assigned_name(), None);
+    assert_eq!(is_a.name(), "isA");
+    assert_eq!(is_a.to_string(), "isA");
+
+    // Round-tripping through CBOR keeps the value, and the name is found
+    // again.
+    let decoded = KnownValue::try_from(known_values::NOTE.tagged_cbor()).unwrap();
+    assert_eq!(decoded, known_values::NOTE);
+    assert_eq!(decoded.to_string(), "note");
+
+    // An unknown value is shown by number.
+    let unknown = KnownValue::new(424242);
+    assert_eq!(unknown.name(), "424242");
+    assert_eq!(unknown.to_string(), "424242");
+    assert_eq!(unknown.to_string().parse::<KnownValue>().unwrap(), unknown);
+
+    // While holding the global store, the store is passed explicitly.
+    let binding = bc_envelope::KNOWN_VALUES.get();
+    assert_eq!(KnownValue::new(4).name_in(binding.as_ref()), "note");
+    assert_eq!(KnownValue::new(4).name_in(None), "4");
+    drop(binding);
+}
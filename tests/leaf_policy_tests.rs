@@ -0,0 +1,79 @@
+use bc_envelope::prelude::*;
+use dcbor::{ByteString, CBOR};
+use bc_envelope::{set_tagged_leaf_policy, tagged_leaf_policy, EnvelopeError, SuspicionKind, TaggedLeafPolicy};
+use indoc::indoc;
+
+mod common;
+use crate::common::test_data::*;
+
+// The policy is shared by the whole process, so both configurations are
+// tested in a single test.
+#[test]
+fn test_tagged_leaf_policy() {
+    let inner = double_assertion_envelope();
+    let tagged = inner.tagged_cbor();
+
+    // Rejected by default.
+    assert_eq!(tagged_leaf_policy(), TaggedLeafPolicy::Reject);
+    let error = Envelope::try_new_leaf(tagged.clone()).unwrap_err();
+    assert!(matches!(error.downcast_ref::<EnvelopeError>(), Some(EnvelopeError::EnvelopeTaggedLeaf)));
+    assert!(error.to_string().contains("wrap_envelope()"));
+
+    // The infallible conversion creates the leaf, which is flagged.
+    let leaf = Envelope::new(tagged.clone());
+    assert!(leaf.is_leaf());
+    assert_eq!(leaf.is_suspicious_leaf(), Some(SuspicionKind::EnvelopeTagged));
+
+    // Other CBOR is unaffected.
+    assert!(Envelope::try_new_leaf(inner.untagged_cbor()).unwrap().is_leaf());
+    assert!(Envelope::try_new_leaf(CBOR::to_tagged_value(1, "x")).unwrap().is_leaf());
+
+    // Promoted to the envelope it encodes.
+    set_tagged_leaf_policy(TaggedLeafPolicy::Promote);
+    let promoted = Envelope::try_new_leaf(tagged.clone()).unwrap();
+    assert!(promoted.is_identical_to(&inner));
+    let e = Envelope::new("Alice").add_assertion("friend", tagged);
+    assert_eq!(e.format(), indoc! {r#"
+    "Alice" [
+        "friend": "Alice" [
+            "knows": "Bob"
+            "knows": "Carol"
+        ]
+    ]
+    "#}.trim());
+
+    // A tagged value that is not an envelope stays a leaf.
+    let malformed = CBOR::to_tagged_value(bc_components::tags::TAG_ENVELOPE, "x");
+    assert!(Envelope::try_new_leaf(malformed.clone()).is_err());
+    assert!(Envelope::new(malformed).is_leaf());
+
+    set_tagged_leaf_policy(TaggedLeafPolicy::Reject);
+    assert!(Envelope::try_new_leaf(inner.tagged_cbor()).is_err());
+}
+
+#[test]
+fn test_is_suspicious_leaf() {
+    bc_envelope::register_tags();
+    let inner = hello_envelope();
+
+    // A leaf containing a tagged envelope can still be decoded from existing
+    // data, and is flagged.
+    let planted = CBOR::to_tagged_value(bc_components::tags::TAG_LEAF, inner.tagged_cbor());
+    let planted = Envelope::from_untagged_cbor(planted).unwrap();
+    assert!(planted.is_leaf());
+    assert_eq!(planted.is_suspicious_leaf(), Some(SuspicionKind::EnvelopeTagged));
+
+    let e = Envelope::new(ByteString::from(inner.tagged_cbor().to_cbor_data()));
+    assert_eq!(e.is_suspicious_leaf(), Some(SuspicionKind::EncodedEnvelope));
+
+    let e = Envelope::new(inner.ur_string());
+    assert_eq!(e.is_suspicious_leaf(), Some(SuspicionKind::EnvelopeUR));
+    let e = Envelope::new(inner.ur_string().to_uppercase());
+    assert_eq!(e.is_suspicious_leaf(), Some(SuspicionKind::EnvelopeUR));
+
+    assert_eq!(inner.is_suspicious_leaf(), None);
+    assert_eq!(Envelope::new(ByteString::from([1, 2, 3])).is_suspicious_leaf(), None);
+    assert_eq!(Envelope::new("ur:envelope/not-a-ur").is_suspicious_leaf(), None);
+    assert_eq!(inner.wrap_envelope().is_suspicious_leaf(), None);
+    assert_eq!(Envelope::new("Alice").add_assertion("note", inner.ur_string()).is_suspicious_leaf(), None);
+}
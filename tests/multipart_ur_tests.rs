@@ -0,0 +1,108 @@
+use bc_envelope::prelude::full::*;
+use bc_rand::{make_fake_random_number_generator, rng_next_in_closed_range};
+
+mod common;
+
+fn document(name: &str) -> Envelope {
+    (0..100).fold(Envelope::new(name), |envelope, i| {
+        envelope.add_assertion(format!("field {}", i), format!("value {}", i))
+    })
+}
+
+fn error_of(result: anyhow::Result<()>) -> EnvelopeError {
+    result.unwrap_err().downcast::<EnvelopeError>().unwrap()
+}
+
+#[test]
+fn test_multipart_ur() {
+    let envelope = document("Document");
+    let parts = envelope.ur_parts(100);
+    let count = parts.len();
+    assert!(count > 10);
+    for (i, part) in parts.iter().enumerate() {
+        assert!(part.starts_with(&format!("ur:envelope/{}-{}/", i + 1, count)), "{}", part);
+    }
+
+    // The parts are received shuffled, some of them twice, and uppercase.
+    let mut rng = make_fake_random_number_generator();
+    let mut shuffled = parts.clone();
+    for i in (1..shuffled.len()).rev() {
+        let j = rng_next_in_closed_range(&mut rng, &(0..=i));
+        shuffled.swap(i, j);
+    }
+    let mut decoder = EnvelopeURDecoder::new();
+    assert_eq!(decoder.estimated_percent_complete(), 0.0);
+    let mut last_estimate = 0.0;
+    for (i, part) in shuffled.iter().enumerate() {
+        decoder.receive(part).unwrap();
+        if i % 3 == 0 {
+            decoder.receive(&part.to_uppercase()).unwrap();
+        }
+        let estimate = decoder.estimated_percent_complete();
+        assert!(estimate > last_estimate);
+        last_estimate = estimate;
+        assert_eq!(decoder.is_complete(), i == count - 1);
+    }
+    assert_eq!(decoder.estimated_percent_complete(), 1.0);
+    assert!(decoder.envelope().unwrap().is_identical_to(&envelope));
+
+    // Parts received once the envelope is complete are ignored.
+    decoder.receive(&parts[0]).unwrap();
+    assert!(decoder.envelope().unwrap().is_identical_to(&envelope));
+}
+
+#[test]
+fn test_multipart_ur_mixed_messages() {
+    let parts = document("Document").ur_parts(100);
+    let other_parts = document("Other document").ur_parts(100);
+
+    let mut decoder = EnvelopeURDecoder::new();
+    decoder.receive(&parts[0]).unwrap();
+    let estimate = decoder.estimated_percent_complete();
+    for part in &other_parts {
+        assert!(matches!(error_of(decoder.receive(part)), EnvelopeError::InvalidURPart { .. }));
+    }
+    assert!(matches!(error_of(decoder.receive(&other_parts[0].replace("ur:envelope/1-", "ur:envelope/"))), EnvelopeError::NotUR { .. }));
+    assert!(matches!(
+        error_of(decoder.receive(&Envelope::new("Alice").ur_string())),
+        EnvelopeError::InvalidURPart { .. }
+    ));
+    assert!(matches!(
+        error_of(decoder.receive(&parts[1].replace("ur:envelope/", "ur:crypto-seed/"))),
+        EnvelopeError::WrongURType { ur_type } if ur_type == "crypto-seed"
+    ));
+    assert!(matches!(error_of(decoder.receive("envelope/1-2/lpadao")), EnvelopeError::NotUR { .. }));
+
+    // The rejected parts do not disturb the parts already received.
+    assert_eq!(decoder.estimated_percent_complete(), estimate);
+    for part in &parts[1..] {
+        decoder.receive(part).unwrap();
+    }
+    assert!(decoder.envelope().unwrap().is_identical_to(&document("Document")));
+}
+
+#[test]
+fn test_single_part_ur() {
+    let envelope = Envelope::new("Alice").add_assertion("knows", "Bob");
+    let parts = envelope.ur_parts(1000);
+    assert_eq!(parts, vec![envelope.ur_string()]);
+
+    let mut decoder = EnvelopeURDecoder::new();
+    decoder.receive(&parts[0]).unwrap();
+    assert!(decoder.is_complete());
+    assert_eq!(decoder.estimated_percent_complete(), 1.0);
+    assert!(decoder.envelope().unwrap().is_identical_to(&envelope));
+}
+
+#[test]
+fn test_multipart_ur_limits() {
+    let envelope = document("Document");
+    let limits = EnvelopeLimits { max_total_elements: 10, ..EnvelopeLimits::default() };
+    let mut decoder = EnvelopeURDecoder::with_limits(limits);
+    let parts = envelope.ur_parts(100);
+    for part in &parts[..parts.len() - 1] {
+        decoder.receive(part).unwrap();
+    }
+    assert!(matches!(error_of(decoder.receive(parts.last().unwrap())), EnvelopeError::LimitExceeded { .. }));
+    assert!(decoder.envelope().is_none());
+}
@@ -0,0 +1,90 @@
+use bc_envelope::prelude::*;
+use dcbor::{CBOR, CBORCase};
+use bc_envelope::EnvelopeError;
+use indoc::indoc;
+
+mod common;
+
+fn alice_knows_bob() -> Envelope {
+    Envelope::new("Alice").add_assertion("knows", "Bob")
+}
+
+#[test]
+fn test_construction_merges_assertions() {
+    let node = alice_knows_bob();
+    let expected = Envelope::new("Alice")
+        .add_assertion_envelopes(&[
+            Envelope::new_assertion("knows", "Bob"),
+            Envelope::new_assertion("knows", "Carol"),
+        ])
+        .unwrap();
+
+    // Every way of adding assertions to a node merges them into it.
+    let added = [
+        node.add_assertion("knows", "Carol"),
+        node.add_assertion_envelope(Envelope::new_assertion("knows", "Carol")).unwrap(),
+        node.add_assertion_envelopes(&[Envelope::new_assertion("knows", "Carol")]).unwrap(),
+        Envelope::new(node.clone()).add_assertion("knows", "Carol"),
+        node.add_assertion("knows", "Carol").add_assertion("knows", "Bob"),
+    ];
+    for e in added {
+        assert!(e.is_identical_to(&expected));
+        assert!(e.subject().is_leaf());
+        assert_eq!(e.assertions().len(), 2);
+    }
+
+    // Wrapping makes the node the subject of further assertions.
+    let wrapped = node.wrap_envelope().add_assertion("knows", "Carol");
+    assert!(wrapped.subject().is_wrapped());
+    assert_eq!(wrapped.assertions().len(), 1);
+    assert!(wrapped.subject().unwrap_envelope().unwrap().is_identical_to(&node));
+}
+
+#[test]
+fn test_decode_rejects_nested_node() {
+    let node = alice_knows_bob();
+    let CBORCase::Tagged(_, untagged) = node.tagged_cbor().into_case() else { panic!() };
+    let assertion = Envelope::new_assertion("knows", "Carol").untagged_cbor();
+
+    // The untagged node array as the subject of another node.
+    let nested = CBOR::to_tagged_value(200, vec![untagged, assertion.clone()]);
+    for result in [
+        Envelope::from_tagged_cbor(nested.clone()),
+        Envelope::from_tagged_cbor_checked(nested.clone()),
+        Envelope::from_tagged_cbor_data(nested.to_cbor_data()),
+    ] {
+        let error = result.unwrap_err();
+        assert!(matches!(error.downcast_ref::<EnvelopeError>(), Some(EnvelopeError::NestedNode)));
+    }
+
+    // The same node, wrapped, is a valid subject.
+    let wrapped = CBOR::to_tagged_value(200, vec![node.tagged_cbor(), assertion]);
+    let e = Envelope::from_tagged_cbor(wrapped).unwrap();
+    assert!(e.subject().is_wrapped());
+    assert!(e.is_identical_to(&node.wrap_envelope().add_assertion("knows", "Carol")));
+}
+
+#[test]
+fn test_queries_and_format() {
+    let e = alice_knows_bob().add_assertion("age", 30);
+    assert!(e.subject().is_identical_to(&Envelope::new("Alice")));
+    assert_eq!(e.extract_subject::<String>().unwrap(), "Alice");
+    assert_eq!(e.extract_object_for_predicate::<i32>("age").unwrap(), 30);
+    assert_eq!(e.extract_object_for_predicate::<String>("knows").unwrap(), "Bob");
+    assert_eq!(e.elements_count(), 8);
+    assert_eq!(e.format(), indoc! {r#"
+    "Alice" [
+        "age": 30
+        "knows": "Bob"
+    ]
+    "#}.trim());
+    assert_eq!(alice_knows_bob().wrap_envelope().add_assertion("age", 30).format(), indoc! {r#"
+    {
+        "Alice" [
+            "knows": "Bob"
+        ]
+    } [
+        "age": 30
+    ]
+    "#}.trim());
+}
@@ -1,10 +1,11 @@
 #![cfg(feature = "salt")]
 use bc_envelope::prelude::*;
+use bc_envelope::known_values;
+use bc_components::Salt;
 use bc_rand::make_fake_random_number_generator;
 use indoc::indoc;
 
 mod common;
-use crate::common::check_encoding::*;
 
 #[test]
 fn test_envelope_non_correlation() {
@@ -119,3 +120,99 @@ fn test_add_salt() {
     "#}.trim();
     assert_eq!(e1_elided.format(), redacted_expected_format);
 }
+
+#[test]
+fn test_salt_len() {
+    fn salt_len(envelope: &Envelope) -> usize {
+        let salt: Salt = envelope.object_for_predicate(known_values::SALT).unwrap().try_leaf().unwrap().try_into().unwrap();
+        salt.data().len()
+    }
+
+    let e = Envelope::new("Hello.");
+    assert_eq!(salt_len(&e.add_salt_with_len(32).unwrap()), 32);
+    assert_eq!(salt_len(&e.add_salt_with_len_using(8, &mut make_fake_random_number_generator()).unwrap()), 8);
+    assert!(e.add_salt_with_len(7).is_err());
+
+    // The same generator state gives the same salt.
+    let e1 = e.add_salt_using(&mut make_fake_random_number_generator());
+    let e2 = e.add_salt_using(&mut make_fake_random_number_generator());
+    assert!(e1.is_identical_to(&e2));
+    assert!(!e.add_salt().is_identical_to(&e.add_salt()));
+
+    // By default, larger envelopes get more salt.
+    let large = Envelope::new("Hello. ".repeat(1000));
+    assert!(salt_len(&large.add_salt()) > salt_len(&e1));
+}
+
+#[test]
+fn test_salted_assertion_placement() {
+    let assertion = Envelope::new_assertion("knows", "Bob");
+    let salted_assertion = assertion.add_salt().check_encoding().unwrap();
+
+    // The salt is added inside the assertion, so its digest changes.
+    assert!(salted_assertion.is_subject_assertion());
+    assert_ne!(salted_assertion.digest(), assertion.digest());
+    assert_eq!(salted_assertion.format(), indoc! {r#"
+    {
+        "knows": "Bob"
+    } [
+        'salt': Salt
+    ]
+    "#}.trim());
+
+    // Adding the salted assertion to a node keeps the salt nested in the
+    // assertion rather than on the node.
+    let e = Envelope::new("Alice")
+        .add_assertion_envelope(salted_assertion).unwrap()
+        .check_encoding().unwrap();
+    assert_eq!(e.assertions().len(), 1);
+    assert!(e.assertions_with_predicate(known_values::SALT).is_empty());
+    assert_eq!(e.format(), indoc! {r#"
+    "Alice" [
+        {
+            "knows": "Bob"
+        } [
+            'salt': Salt
+        ]
+    ]
+    "#}.trim());
+}
+
+#[test]
+fn test_add_assertions_salted() {
+    let assertions = [
+        Envelope::new_assertion("knows", "Bob"),
+        Envelope::new_assertion("knows", "Carol"),
+    ];
+    let e = Envelope::new("Alice")
+        .add_assertions_salted(&assertions, true).unwrap()
+        .check_encoding().unwrap();
+    assert_eq!(e.format(), indoc! {r#"
+    "Alice" [
+        {
+            "knows": "Bob"
+        } [
+            'salt': Salt
+        ]
+        {
+            "knows": "Carol"
+        } [
+            'salt': Salt
+        ]
+    ]
+    "#}.trim());
+
+    // Each assertion is salted separately, so none of them matches its
+    // unsalted digest, and the node has no salt of its own.
+    for assertion in &assertions {
+        assert!(e.assertions().iter().all(|a| a.digest() != assertion.digest()));
+    }
+    assert!(e.assertions_with_predicate(known_values::SALT).is_empty());
+
+    // Without salt, the assertions are added unchanged.
+    let unsalted = Envelope::new("Alice").add_assertions_salted(&assertions, false).unwrap();
+    assert!(unsalted.is_identical_to(&Envelope::new("Alice").add_assertions(&assertions).unwrap()));
+
+    // Anything that is not an assertion is rejected.
+    assert!(Envelope::new("Alice").add_assertions_salted(&[Envelope::new("Bob")], true).is_err());
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "parity")]
+use bc_envelope::prelude::full::*;
+use bc_envelope::{EnvelopeError, RecoveryReport};
+use indoc::indoc;
+
+mod common;
+use crate::common::test_data::*;
+
+fn protected() -> Envelope {
+    Envelope::new(PLAINTEXT_HELLO)
+        .add_assertion("note", "The quick brown fox jumps over the lazy dog. ".repeat(10))
+}
+
+#[test]
+fn test_add_parity() {
+    let envelope = Envelope::new(PLAINTEXT_HELLO).add_parity(ParityLevel::Low);
+    assert_eq!(envelope.format(), indoc! {r#"
+    {
+        "Hello."
+    } [
+        "parity": [16, 11, Bytes(16)]
+    ]
+    "#}.trim());
+    assert!(envelope.unwrap_envelope().unwrap().is_identical_to(&Envelope::new(PLAINTEXT_HELLO)));
+
+    let protected = protected();
+    for level in [ParityLevel::Low, ParityLevel::Medium, ParityLevel::High] {
+        let envelope = protected.add_parity(level);
+        let (recovered, report) = Envelope::recover_with_parity(envelope.tagged_cbor_data()).unwrap();
+        assert!(recovered.is_identical_to(&envelope));
+        assert!(report.is_intact());
+        assert_eq!(report.blocks(), protected.tagged_cbor_data().len().div_ceil(level.block_size()));
+    }
+}
+
+#[test]
+fn test_recover_with_parity() {
+    let envelope = protected().add_parity(ParityLevel::Low);
+    let mut data = envelope.tagged_cbor_data();
+
+    // Damage the structure of the protected envelope, a byte of text in a
+    // second block, and the parity itself.
+    for position in [3, 4, 10, 300, 301, data.len() - 1] {
+        data[position] ^= 0x40;
+    }
+    assert!(Envelope::from_tagged_cbor_data(&data).is_err());
+
+    let (recovered, report) = Envelope::recover_with_parity(&data).unwrap();
+    assert!(recovered.is_identical_to(&envelope));
+    assert!(recovered.unwrap_envelope().unwrap().is_identical_to(&protected()));
+    assert_eq!(report.corrections(), 6);
+    assert_eq!(report.corrected_blocks(), 2);
+    assert!(!report.is_intact());
+
+    // Damage that leaves the envelope decodable but different is corrected.
+    let mut data = envelope.tagged_cbor_data();
+    let position = data.windows(5).position(|window| window == b"quick").unwrap();
+    data[position] = b'Q';
+    assert!(Envelope::from_tagged_cbor_data(&data).is_ok());
+    let (recovered, report): (Envelope, RecoveryReport) = Envelope::recover_with_parity(&data).unwrap();
+    assert!(recovered.is_identical_to(&envelope));
+    assert_eq!(report.corrections(), 1);
+}
+
+#[test]
+fn test_parity_exceeded() {
+    let envelope = protected().add_parity(ParityLevel::Low);
+    let mut data = envelope.tagged_cbor_data();
+    for position in (0..9).map(|i| 20 + i * 10) {
+        data[position] ^= 0xff;
+    }
+    let error = Envelope::recover_with_parity(&data).unwrap_err();
+    match error.downcast_ref::<EnvelopeError>() {
+        Some(EnvelopeError::ParityExceeded { block, capacity }) => {
+            assert_eq!(*block, 0);
+            assert_eq!(*capacity, 8);
+        },
+        _ => panic!("unexpected error: {}", error),
+    }
+
+    // Data without parity.
+    let error = Envelope::recover_with_parity(protected().tagged_cbor_data()).unwrap_err();
+    assert!(matches!(error.downcast_ref::<EnvelopeError>(), Some(EnvelopeError::ParityNotFound)));
+}
@@ -0,0 +1,247 @@
+use bc_envelope::prelude::*;
+use bc_envelope::{EnvelopeError, FormatContext};
+use bc_components::{Digest, DigestProvider};
+
+mod common;
+use crate::common::test_data::*;
+
+fn address() -> Envelope {
+    Envelope::new("Address")
+        .add_assertion("city", "Edinburgh")
+        .add_assertion("country", "Scotland")
+}
+
+fn holder() -> Envelope {
+    Envelope::new("James Maxwell")
+        .add_assertion("name", "James")
+        .add_assertion("address", address())
+        .add_assertion("knows", "Michael")
+        .add_assertion("knows", "William")
+}
+
+fn credential() -> Envelope {
+    Envelope::new("Certificate")
+        .add_assertion("holder", holder())
+        .add_assertion("issuer", "Example Board")
+}
+
+fn envelope_error(error: &anyhow::Error) -> &EnvelopeError {
+    error.downcast_ref::<EnvelopeError>().unwrap()
+}
+
+#[test]
+fn test_object_at_path() {
+    let credential = credential();
+    let city = credential.object_at_path(&["holder", "address", "city"]).unwrap();
+    assert!(city.is_identical_to(&Envelope::new("Edinburgh")));
+    let name = credential.object_at_path(&["holder", "name"]).unwrap();
+    assert_eq!(name.extract_subject::<String>().unwrap(), "James");
+    let empty: [&str; 0] = [];
+    assert!(credential.object_at_path(&empty).unwrap().is_identical_to(&credential));
+}
+
+#[cfg(feature = "signature")]
+#[test]
+fn test_object_at_path_through_wrapped() {
+    // A signed envelope's assertions are on a wrapped copy of the content.
+    let signed = credential()
+        .wrap_envelope()
+        .add_signature(&alice_private_key())
+        .add_assertion("note", "Signed.");
+    let city = signed.object_at_path(&["holder", "address", "city"]).unwrap();
+    assert!(city.is_identical_to(&Envelope::new("Edinburgh")));
+    // Assertions on the outer envelope are still found first.
+    let note = signed.object_at_path(&["note"]).unwrap();
+    assert!(note.is_identical_to(&Envelope::new("Signed.")));
+    // Wrapped objects are searched too.
+    let wrapped_holder = Envelope::new("Certificate")
+        .add_assertion("holder", holder().wrap_envelope());
+    let city = wrapped_holder.object_at_path(&["holder", "address", "city"]).unwrap();
+    assert!(city.is_identical_to(&Envelope::new("Edinburgh")));
+}
+
+#[test]
+fn test_object_at_path_errors() {
+    let credential = credential();
+
+    let error = credential.object_at_path(&["holder", "knows"]).unwrap_err();
+    match envelope_error(&error) {
+        EnvelopeError::AmbiguousPredicate { predicate, count } => {
+            assert_eq!(predicate, r#""knows""#);
+            assert_eq!(*count, 2);
+        },
+        error => panic!("unexpected error: {}", error),
+    }
+
+    let error = credential.object_at_path(&["holder", "address", "street"]).unwrap_err();
+    match envelope_error(&error) {
+        EnvelopeError::NonexistentPredicate { predicate } => assert_eq!(predicate, r#""street""#),
+        error => panic!("unexpected error: {}", error),
+    }
+
+    // Descending into a leaf finds nothing.
+    let error = credential.object_at_path(&["issuer", "name"]).unwrap_err();
+    assert!(matches!(envelope_error(&error), EnvelopeError::NonexistentPredicate { .. }));
+}
+
+#[test]
+fn test_object_at_path_obscured() {
+    let credential = credential();
+    let address_assertion = Envelope::new_assertion("address", address());
+    let redacted = credential.elide_removing_target(&address_assertion);
+
+    // By default an obscured branch is just missing.
+    let error = redacted.object_at_path(&["holder", "address", "city"]).unwrap_err();
+    assert!(matches!(envelope_error(&error), EnvelopeError::NonexistentPredicate { .. }));
+
+    // On request, the digests of the obscured elements are reported.
+    let error = redacted.object_at_path_opt(&["holder", "address", "city"], true).unwrap_err();
+    match envelope_error(&error) {
+        EnvelopeError::ObscuredPredicate { predicate, digests } => {
+            assert_eq!(predicate, r#""address""#);
+            assert_eq!(digests, &vec![address_assertion.digest().into_owned()]);
+        },
+        error => panic!("unexpected error: {}", error),
+    }
+
+    // Visible paths still resolve.
+    assert!(redacted.object_at_path_opt(&["holder", "name"], true).is_ok());
+
+    // An obscured object reports its own digest.
+    let redacted = credential.elide_removing_target(&holder());
+    let error = redacted.object_at_path_opt(&["holder", "name"], true).unwrap_err();
+    match envelope_error(&error) {
+        EnvelopeError::ObscuredPredicate { digests, .. } => {
+            assert_eq!(digests, &vec![holder().digest().into_owned()]);
+        },
+        error => panic!("unexpected error: {}", error),
+    }
+}
+
+fn digests(envelopes: &[Envelope]) -> Vec<Digest> {
+    envelopes.iter().map(|envelope| envelope.digest().into_owned()).collect()
+}
+
+#[test]
+fn test_resolve_path_expr() {
+    let credential = credential();
+    let context = FormatContext::default();
+
+    let name = credential.resolve_path_expr("assertion[holder]/object/assertion[name]/object", &context).unwrap();
+    assert_eq!(name, digests(&[Envelope::new("James")]));
+    let knows = credential.resolve_path_expr(r#"assertion["holder"] / object / assertion[knows] / object"#, &context).unwrap();
+    assert_eq!(knows.len(), 2);
+    assert!(knows.contains(Envelope::new("Michael").digest().as_ref()));
+    let subject = credential.resolve_path_expr("assertion[issuer]/predicate", &context).unwrap();
+    assert_eq!(subject, digests(&[Envelope::new("issuer")]));
+    let subject = credential.resolve_path_expr("subject", &context).unwrap();
+    assert_eq!(subject, digests(&[Envelope::new("Certificate")]));
+
+    // A wildcard selects every assertion.
+    let all = credential.resolve_path_expr("assertion[*]", &context).unwrap();
+    assert_eq!(all.len(), 2);
+    assert_eq!(all.iter().collect::<std::collections::HashSet<_>>(), digests(credential.assertions()).iter().collect());
+
+    // An assertion can be selected by a prefix of its digest.
+    let issuer = credential.assertion_with_predicate("issuer").unwrap();
+    let prefix = issuer.short_digest_hex(4);
+    let selected = credential.resolve_path_expr(&format!("assertion[Digest({})]/object", prefix), &context).unwrap();
+    assert_eq!(selected, digests(&[Envelope::new("Example Board")]));
+}
+
+#[cfg(feature = "signature")]
+#[test]
+fn test_resolve_path_expr_through_wrapped() {
+    bc_envelope::register_tags();
+    let signed = credential()
+        .wrap_envelope()
+        .add_signature(&alice_private_key());
+
+    let resolve = |expr| with_format_context!(|context| signed.resolve_path_expr(expr, context));
+    assert_eq!(resolve("wrapped/assertion[issuer]/object").unwrap(), digests(&[Envelope::new("Example Board")]));
+    assert_eq!(resolve("wrapped/subject").unwrap(), digests(&[Envelope::new("Certificate")]));
+    assert_eq!(resolve("assertion['signed']").unwrap(), digests(signed.assertions()));
+
+    // A predicate that matches nothing is reported using the given context.
+    let error = resolve("wrapped/assertion[missing]").unwrap_err();
+    match envelope_error(&error) {
+        EnvelopeError::UnmatchedPathStep { step, reason, .. } => {
+            assert_eq!(*step, 2);
+            assert_eq!(reason, r#"no assertion has the predicate "missing""#);
+        },
+        error => panic!("unexpected error: {}", error),
+    }
+
+    // Revealing a path keeps the elements that lead to it, but not the
+    // predicates of the assertions it passes through.
+    let context = FormatContext::default();
+    let revealed = signed.elide_revealing_path("wrapped/assertion[holder]/object/assertion[name]", &context).unwrap();
+    assert!(revealed.is_equivalent_to(&signed));
+    assert_eq!(revealed.format(), indoc::indoc! {r#"
+    {
+        ELIDED [
+            ELIDED: ELIDED [
+                "name": "James"
+                ELIDED (3)
+            ]
+            ELIDED
+        ]
+    } [
+        ELIDED
+    ]
+    "#}.trim());
+    assert!(revealed.verify_signature_from(&alice_public_key()).is_err());
+
+    let removed = signed.elide_removing_path("wrapped/assertion[holder]/object/assertion[knows]", &context).unwrap();
+    assert!(removed.is_equivalent_to(&signed));
+    assert!(removed.verify_signature_from(&alice_public_key()).is_ok());
+    assert_eq!(removed.unwrap_envelope().unwrap().object_at_path(&["holder"]).unwrap().assertions().iter().filter(|a| a.is_elided()).count(), 2);
+}
+
+#[test]
+fn test_resolve_path_expr_errors() {
+    let credential = credential();
+    let context = FormatContext::default();
+
+    // A step that selects nothing is reported with its number and the reason.
+    let error = credential.resolve_path_expr("assertion[holder]/object/wrapped/subject", &context).unwrap_err();
+    match envelope_error(&error) {
+        EnvelopeError::UnmatchedPathStep { step, text, .. } => {
+            assert_eq!(*step, 3);
+            assert_eq!(text, "wrapped");
+        },
+        error => panic!("unexpected error: {}", error),
+    }
+    assert_eq!(error.to_string(), "path step 3 `wrapped` selected nothing: no selected element has a wrapped subject");
+    let error = credential.resolve_path_expr("assertion[holder]/object/assertion[\"street\"]", &context).unwrap_err();
+    assert_eq!(error.to_string(), r#"path step 3 `assertion["street"]` selected nothing: no assertion has the predicate "street""#);
+    let error = credential.resolve_path_expr("subject/object", &context).unwrap_err();
+    assert_eq!(error.to_string(), "path step 2 `object` selected nothing: no selected element is an assertion");
+
+    // A digest prefix that matches more than one assertion is ambiguous. With
+    // 17 assertions, two of their digests must share a first hex digit.
+    let assertions: Vec<Envelope> = (0..17).map(|i| Envelope::new_assertion(i, i)).collect();
+    let envelope = Envelope::new("subject").add_assertion_envelopes(&assertions).unwrap();
+    let prefix = (0..16)
+        .map(|digit| format!("{:x}", digit))
+        .find(|prefix| envelope.assertions().iter().filter(|a| a.digest_hex().starts_with(prefix.as_str())).count() > 1)
+        .unwrap();
+    let error = envelope.resolve_path_expr(&format!("assertion[Digest({})]", prefix), &context).unwrap_err();
+    assert!(matches!(envelope_error(&error), EnvelopeError::AmbiguousDigestPrefix { count, .. } if *count > 1));
+
+    // Syntax errors are reported with their offset.
+    fn offset(error: &anyhow::Error) -> usize {
+        match envelope_error(error) {
+            EnvelopeError::InvalidPathExpression { offset, .. } => *offset,
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+    assert_eq!(offset(&credential.resolve_path_expr("", &context).unwrap_err()), 0);
+    assert_eq!(offset(&credential.resolve_path_expr("subject/", &context).unwrap_err()), 8);
+    assert_eq!(offset(&credential.resolve_path_expr("subject/assertion", &context).unwrap_err()), 17);
+    assert_eq!(offset(&credential.resolve_path_expr("assertion[holder", &context).unwrap_err()), 9);
+    assert_eq!(offset(&credential.resolve_path_expr("object[holder]", &context).unwrap_err()), 0);
+    assert_eq!(offset(&credential.resolve_path_expr("subject/parent", &context).unwrap_err()), 8);
+    assert_eq!(offset(&credential.resolve_path_expr("assertion[Digest(xyz)]", &context).unwrap_err()), 10);
+    assert_eq!(offset(&credential.resolve_path_expr("assertion['noSuchValue']", &context).unwrap_err()), 10);
+}
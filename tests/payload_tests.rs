@@ -0,0 +1,88 @@
+#![cfg(all(feature = "encrypt", feature = "compress"))]
+
+use bc_components::SymmetricKey;
+use bc_envelope::prelude::*;
+use dcbor::CBOR;
+use bc_envelope::{DetachedPayload, EnvelopeError, PayloadKind};
+
+mod common;
+use crate::common::test_data::*;
+
+fn envelope_error(error: &anyhow::Error) -> &EnvelopeError {
+    error.downcast_ref::<EnvelopeError>().unwrap()
+}
+
+fn large_object() -> Envelope {
+    Envelope::new("This is a large photo. ".repeat(100))
+}
+
+#[test]
+fn test_detach_encrypted_payload() {
+    let key = SymmetricKey::new();
+    let photo = large_object().encrypt_subject(&key).unwrap();
+    let envelope = Envelope::new("Alice")
+        .add_assertion("knows", "Bob")
+        .add_assertion("photo", photo.clone());
+
+    let (stub, payload) = envelope.detach_payload(&photo.digest()).unwrap();
+    assert_eq!(payload.kind(), PayloadKind::Encrypted);
+    assert_eq!(payload.digest(), photo.digest().as_ref());
+    assert!(stub.is_equivalent_to(&envelope));
+    assert!(!stub.is_identical_to(&envelope));
+    assert!(stub.object_for_predicate("photo").unwrap().is_elided());
+
+    // The payload survives a round trip through storage.
+    let stored = payload.to_cbor_data();
+    let restored = DetachedPayload::try_from(CBOR::try_from_data(stored).unwrap()).unwrap();
+    assert_eq!(restored, payload);
+
+    let reattached = stub.attach_payload(&restored).unwrap();
+    assert!(reattached.is_identical_to(&envelope));
+    let decrypted = reattached.object_for_predicate("photo").unwrap().decrypt_subject(&key).unwrap();
+    assert!(decrypted.is_identical_to(&large_object()));
+}
+
+#[test]
+fn test_detach_compressed_payload() {
+    let photo = large_object().compress().unwrap();
+    let envelope = Envelope::new("Alice")
+        .add_assertion("photo", photo.clone())
+        .wrap_envelope()
+        .add_assertion("note", "Wrapped.");
+
+    let (stub, payload) = envelope.detach_payload(&photo.digest()).unwrap();
+    assert_eq!(payload.kind(), PayloadKind::Compressed);
+    assert!(stub.is_equivalent_to(&envelope));
+    assert!(stub.tagged_cbor_data().len() < envelope.tagged_cbor_data().len());
+    assert!(stub.attach_payload(&payload).unwrap().is_identical_to(&envelope));
+}
+
+#[test]
+fn test_detach_payload_errors() {
+    let envelope = double_assertion_envelope();
+
+    // Only encrypted and compressed elements can be detached.
+    let error = envelope.detach_payload(&assertion_envelope().digest()).unwrap_err();
+    assert!(matches!(envelope_error(&error), EnvelopeError::NonexistentPayload));
+
+    // A payload with a digest that doesn't match its element is rejected.
+    let photo = large_object().compress().unwrap();
+    let envelope = Envelope::new("Alice").add_assertion("photo", photo.clone());
+    let (stub, payload) = envelope.detach_payload(&photo.digest()).unwrap();
+    let other = Envelope::new("Another photo.").compress().unwrap();
+    let (_, other_payload) = other.detach_payload(&other.digest()).unwrap();
+    let forged: DetachedPayload = {
+        let cbor = CBOR::from(vec![
+            CBOR::from("compressed"),
+            payload.digest().clone().into(),
+            CBOR::to_byte_string(other_payload.data()),
+        ]);
+        cbor.try_into().unwrap()
+    };
+    let error = stub.attach_payload(&forged).unwrap_err();
+    assert!(matches!(envelope_error(&error), EnvelopeError::InvalidDigest));
+
+    // A payload that belongs to a different envelope has nowhere to go.
+    let error = stub.attach_payload(&other_payload).unwrap_err();
+    assert!(matches!(envelope_error(&error), EnvelopeError::NonexistentPayload));
+}
@@ -0,0 +1,74 @@
+#![cfg(all(feature = "signature", feature = "recipient", feature = "salt", feature = "types"))]
+
+use bc_envelope::prelude::*;
+use bc_envelope::EnvelopePipeline;
+use bc_rand::make_fake_random_number_generator;
+
+mod common;
+use crate::common::test_data::*;
+
+fn salted_assertion(predicate: &str, object: &str) -> Envelope {
+    let mut rng = make_fake_random_number_generator();
+    Envelope::new_assertion(predicate, object).add_salt_using(&mut rng)
+}
+
+#[test]
+fn test_pipeline_matches_chained_calls() {
+    let signer = alice_private_key().ed25519_signing_private_key();
+
+    let chained = Envelope::new("Alice")
+        .add_assertion("knows", "Bob")
+        .add_assertion_envelope(salted_assertion("knows", "Carol")).unwrap()
+        .add_assertion("age", 30)
+        .add_type("Person")
+        .wrap_envelope()
+        .add_signature(&signer)
+        .add_assertion("note", "issued");
+
+    let pipelined = EnvelopePipeline::new("Alice")
+        .add_assertion("knows", "Bob")
+        .add_assertion_envelope(salted_assertion("knows", "Carol"))
+        .add_assertion("age", 30)
+        .add_type("Person")
+        .wrap()
+        .sign(&signer)
+        .add_assertion("note", "issued")
+        .build().unwrap()
+        .check_encoding().unwrap();
+
+    assert!(chained.is_identical_to(&pipelined));
+    assert_eq!(chained.tagged_cbor().to_cbor_data(), pipelined.tagged_cbor().to_cbor_data());
+}
+
+#[test]
+fn test_pipeline_encrypt_to_recipients() {
+    let chained = Envelope::new("Alice")
+        .add_assertion("knows", "Bob")
+        .wrap_envelope()
+        .encrypt_subject_to_recipients(&[&bob_public_key(), &carol_public_key()]).unwrap();
+
+    let pipelined = EnvelopePipeline::new("Alice")
+        .add_assertion("knows", "Bob")
+        .wrap()
+        .encrypt_to_recipients(&[&bob_public_key(), &carol_public_key()])
+        .build().unwrap()
+        .check_encoding().unwrap();
+
+    // The content key and sealed messages are ephemeral, so only the digests
+    // of the encrypted subjects can be compared.
+    assert!(chained.subject().is_equivalent_to(&pipelined.subject()));
+    assert_eq!(pipelined.recipients().unwrap().len(), 2);
+
+    let decrypted = pipelined
+        .decrypt_subject_to_recipient(&bob_private_key()).unwrap()
+        .unwrap_envelope().unwrap();
+    assert_eq!(decrypted.format(), Envelope::new("Alice").add_assertion("knows", "Bob").format());
+}
+
+#[test]
+fn test_pipeline_rejects_invalid_assertion() {
+    let result = EnvelopePipeline::new("Alice")
+        .add_assertion_envelope("not an assertion")
+        .build();
+    assert!(result.is_err());
+}
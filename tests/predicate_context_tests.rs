@@ -0,0 +1,73 @@
+use bc_components::URI;
+use bc_envelope::prelude::*;
+use bc_envelope::{PredicateContext, known_values};
+use indoc::indoc;
+
+mod common;
+
+fn context() -> PredicateContext {
+    let context = PredicateContext::new()
+        .add_term("knows", URI::from("https://schema.org/knows"))
+        .add_term("name", URI::from("https://schema.org/name"));
+    #[cfg(feature = "known_value")]
+    let context = context.add_known_value_term("note", URI::from("https://schema.org/comment"), known_values::NOTE);
+    context
+}
+
+fn compact_envelope() -> Envelope {
+    let envelope = Envelope::new("Alice")
+        .add_assertion("knows", Envelope::new("Bob").add_assertion("name", "Robert"))
+        .wrap_envelope()
+        .add_assertion("knows", "Carol");
+    #[cfg(feature = "known_value")]
+    let envelope = envelope.add_assertion(known_values::NOTE, "Compact.");
+    envelope
+}
+
+#[test]
+fn test_expand_and_compact_predicates() {
+    let original = compact_envelope();
+    let expanded = original.expand_predicates(&context()).check_encoding().unwrap();
+    assert!(!expanded.is_equivalent_to(&original));
+    assert_eq!(expanded.objects_for_predicate(URI::from("https://schema.org/knows")).len(), 1);
+    let inner = expanded.unwrap_envelope().unwrap();
+    let bob = inner.object_for_predicate(URI::from("https://schema.org/knows")).unwrap();
+    assert_eq!(bob.extract_object_for_predicate::<String>(URI::from("https://schema.org/name")).unwrap(), "Robert");
+
+    let (compacted, untranslatable) = expanded.compact_predicates(&context());
+    assert!(untranslatable.is_empty());
+    assert!(compacted.is_identical_to(&original));
+}
+
+#[test]
+fn test_untranslatable_predicates() {
+    let envelope = Envelope::new("Alice")
+        .add_assertion(URI::from("https://schema.org/knows"), "Bob")
+        .add_assertion(URI::from("https://example.com/unknown"), "Mystery")
+        .add_assertion("plain", "Ignored");
+    let (compacted, untranslatable) = envelope.compact_predicates(&context());
+    assert_eq!(compacted.extract_object_for_predicate::<String>("knows").unwrap(), "Bob");
+    assert_eq!(compacted.extract_object_for_predicate::<String>(URI::from("https://example.com/unknown")).unwrap(), "Mystery");
+    assert_eq!(untranslatable.len(), 1);
+    assert_eq!(untranslatable[0].extract_subject::<URI>().unwrap(), URI::from("https://example.com/unknown"));
+}
+
+#[test]
+fn test_predicate_context_envelope() {
+    let context = PredicateContext::new()
+        .add_term("knows", URI::from("https://schema.org/knows"));
+    let envelope = Envelope::from(&context).check_encoding().unwrap();
+    assert_eq!(envelope.format(), indoc! {r#"
+    "PredicateContext" [
+        "term": URI(https://schema.org/knows) [
+            "name": "knows"
+        ]
+    ]
+    "#}.trim());
+    assert_eq!(PredicateContext::try_from(envelope).unwrap(), context);
+
+    let context = self::context();
+    let decoded = PredicateContext::try_from(Envelope::from(&context)).unwrap();
+    assert_eq!(decoded.terms().len(), context.terms().len());
+    assert!(compact_envelope().expand_predicates(&decoded).is_identical_to(&compact_envelope().expand_predicates(&context)));
+}
@@ -0,0 +1,30 @@
+#[path = "ui/minimal_prelude.rs"]
+mod minimal_prelude;
+
+#[test]
+fn test_minimal_prelude_has_no_ambiguities() {
+    let (tag, map, ur, date, request, _) = minimal_prelude::run();
+    assert_eq!(tag.0, "app");
+    assert_eq!(map.0.len(), 1);
+    assert!(ur.0.starts_with("ur:envelope/"));
+    assert_eq!(date.0, 2024);
+    assert_eq!((request.0).0, "greet");
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_legacy_prelude() {
+    use bc_envelope::prelude::legacy::*;
+
+    // The names of the 0.18.2 prelude, including the `bc_ur` and `dcbor`
+    // preludes it re-exported.
+    let envelope = Envelope::new("Hello.").add_assertion(known_values::NOTE, "A note.");
+    let cbor: CBOR = envelope.untagged_cbor();
+    assert!(Envelope::from_untagged_cbor(cbor).unwrap().is_identical_to(&envelope));
+    register_tags();
+    let digest: Digest = envelope.digest().into_owned();
+    let ur: UR = envelope.ur();
+    assert_eq!(Envelope::from_ur(ur).unwrap().digest().into_owned(), digest);
+    let context = FormatContext::default();
+    assert!(envelope.format_opt(Some(&context)).contains("'note'"));
+}
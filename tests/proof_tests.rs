@@ -3,9 +3,9 @@
 use std::collections::HashSet;
 
 use bc_envelope::prelude::*;
+use bc_envelope::known_values;
 use indoc::indoc;
 mod common;
-use crate::common::check_encoding::*;
 
 #[cfg(feature = "types")]
 use crate::common::test_seed::Seed;
@@ -77,6 +77,48 @@ fn test_friends_list() {
     assert!(alice_friends_root.confirm_contains_target(&knows_bob_assertion, &alice_knows_bob_proof));
 }
 
+#[test]
+fn test_proof_contains() {
+    let alice_friends = Envelope::new("Alice")
+        .add_assertion_salted("knows", "Bob", true)
+        .add_assertion_salted("knows", "Carol", true)
+        .add_assertion_salted("knows", "Dan", true);
+    let alice_friends_root = alice_friends.elide_revealing_set(&HashSet::new());
+
+    // The proof reveals the target, but elides its siblings.
+    let knows_bob_assertion = Envelope::new_assertion("knows", "Bob");
+    let proof = alice_friends.proof_contains(&knows_bob_assertion.digest()).unwrap().check_encoding().unwrap();
+    assert_eq!(proof.format(), indoc! {r#"
+    ELIDED [
+        {
+            "knows": "Bob"
+        } [
+            ELIDED
+        ]
+        ELIDED (2)
+    ]
+    "#}.trim());
+    assert_eq!(proof.digest(), alice_friends_root.digest());
+    assert!(proof.confirm_contains(&knows_bob_assertion.digest()));
+    assert!(proof.confirm_contains(&Envelope::new("Bob").digest()));
+
+    // The proof does not reveal the other friends.
+    let knows_carol_assertion = Envelope::new_assertion("knows", "Carol");
+    assert!(!proof.confirm_contains(&knows_carol_assertion.digest()));
+    assert!(!alice_friends_root.confirm_contains(&knows_bob_assertion.digest()));
+
+    // A leaf can be the target.
+    let bob_proof = alice_friends.proof_contains(&Envelope::new("Bob").digest()).unwrap();
+    assert_eq!(bob_proof.digest(), alice_friends_root.digest());
+    assert!(bob_proof.confirm_contains(&Envelope::new("Bob").digest()));
+    assert!(!bob_proof.confirm_contains(&Envelope::new("Alice").digest()));
+
+    // There is no proof for an element that is absent, or only present
+    // elided.
+    assert!(alice_friends.proof_contains(&Envelope::new("Eve").digest()).is_none());
+    assert!(proof.proof_contains(&knows_carol_assertion.digest()).is_none());
+}
+
 #[test]
 fn test_multi_position() {
     let alice_friends = Envelope::new("Alice")
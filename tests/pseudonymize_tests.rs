@@ -0,0 +1,130 @@
+use bc_components::SymmetricKey;
+use bc_envelope::prelude::*;
+use bc_envelope::{RedactionPreset, TAG_PSEUDONYM};
+use dcbor::{CBOR, TagsStoreTrait};
+use hex_literal::hex;
+use indoc::indoc;
+
+mod common;
+#[cfg(feature = "signature")]
+use crate::common::test_data::*;
+
+fn key() -> SymmetricKey {
+    SymmetricKey::from_data(hex!("38900719dea655e9a1bc1682aaccf0bfcd79a7239db672d39216e4acdd660dc0"))
+}
+
+fn other_key() -> SymmetricKey {
+    SymmetricKey::from_data(hex!("0e1d3d2cd5d3f6a4c3a7cde5c1b4e0a8e1d3f2b4a6c8e0f2a4c6e8f0a2c4e6f8"))
+}
+
+fn preset() -> RedactionPreset {
+    RedactionPreset::new()
+        .add_predicate("email")
+        .add_predicate("name")
+}
+
+#[test]
+fn test_pseudonyms_are_deterministic() {
+    let e1 = Envelope::new("Alice")
+        .add_assertion("email", "alice@example.com")
+        .add_assertion("age", 30);
+    let e2 = Envelope::new("Order 42")
+        .add_assertion("email", "alice@example.com")
+        .add_assertion("total", 100);
+
+    let (p1, r1) = e1.pseudonymize(&preset(), &key());
+    let (p2, r2) = e2.pseudonymize(&preset(), &key());
+    assert_eq!(r1.replacements.len(), 1);
+    assert_eq!(r2.replacements.len(), 1);
+    assert_eq!(r1.replacements[0].token, r2.replacements[0].token);
+    assert_eq!(r1.replacements[0].original, Envelope::new("alice@example.com").digest().into_owned());
+    assert_eq!(p1.object_for_predicate("email").unwrap(), p2.object_for_predicate("email").unwrap());
+    let token = CBOR::to_tagged_value(TAG_PSEUDONYM, CBOR::to_byte_string(r1.replacements[0].token.data()));
+    assert_eq!(p1.object_for_predicate("email").unwrap().as_leaf().unwrap(), token);
+    assert_eq!(p1.extract_object_for_predicate::<i32>("age").unwrap(), 30);
+    assert!(!p1.is_equivalent_to(&e1));
+
+    // Stable across runs.
+    assert_eq!(
+        Envelope::pseudonym_token(&Envelope::new("alice@example.com"), &key()),
+        e1.pseudonymize(&preset(), &key()).1.replacements[0].token
+    );
+
+    // A different key gives a different token.
+    let (p3, r3) = e1.pseudonymize(&preset(), &other_key());
+    assert_ne!(r3.replacements[0].token, r1.replacements[0].token);
+    assert!(!p3.is_equivalent_to(&p1));
+
+    // Nothing to replace.
+    let (p4, r4) = e1.pseudonymize(&RedactionPreset::new().add_predicate("phone"), &key());
+    assert!(r4.is_empty());
+    assert!(p4.is_identical_to(&e1));
+}
+
+#[test]
+fn test_pseudonymization_report() {
+    let inner = Envelope::new("Bob")
+        .add_assertion("name", "Robert")
+        .add_assertion("knows", Envelope::new("Carol").add_assertion("email", "carol@example.com"));
+    let e = Envelope::new("Alice")
+        .add_assertion("name", Envelope::new("Alice Smith").add_assertion("note", "Legal name"))
+        .add_assertion("friend", inner.wrap_envelope())
+        .add_assertion("email", Envelope::new("Already hidden").elide())
+        .add_assertion("email", "alice@example.com");
+
+    let (pseudonymized, report) = e.pseudonymize(&preset(), &key());
+    let values = [("name", "Alice Smith"), ("name", "Robert"), ("email", "carol@example.com"), ("email", "alice@example.com")];
+    assert_eq!(report.replacements.len(), values.len());
+    for (predicate, value) in values {
+        let value = Envelope::new(value);
+        let replacement = report.replacements.iter()
+            .find(|r| r.original == value.digest().into_owned())
+            .unwrap();
+        assert!(replacement.predicate.is_equivalent_to(&Envelope::new(predicate)));
+        assert_eq!(replacement.token, Envelope::pseudonym_token(&value, &key()));
+    }
+    assert!(report.invalidated_signatures.is_empty());
+
+    let formatted = pseudonymized.format();
+    for value in ["Alice Smith", "Robert", "carol@example.com", "alice@example.com"] {
+        assert!(!formatted.contains(value));
+    }
+    assert!(formatted.contains("Legal name"));
+    assert!(formatted.contains("ELIDED"));
+}
+
+#[cfg(feature = "signature")]
+#[test]
+fn test_pseudonymize_signed() {
+    let e = Envelope::new("Alice")
+        .add_assertion("email", "alice@example.com")
+        .sign(&alice_private_key());
+    let (pseudonymized, report) = e.pseudonymize(&preset(), &key());
+    assert_eq!(report.replacements.len(), 1);
+    assert_eq!(report.invalidated_signatures, vec![e.digest().into_owned()]);
+    assert!(e.verify(&alice_public_key()).is_ok());
+    assert!(pseudonymized.verify(&alice_public_key()).is_err());
+
+    // A signed wrapper whose content is untouched is not reported.
+    let e = Envelope::new("Alice").add_assertion("age", 30).sign(&alice_private_key());
+    let (pseudonymized, report) = e.pseudonymize(&preset(), &key());
+    assert!(report.invalidated_signatures.is_empty());
+    assert!(pseudonymized.verify(&alice_public_key()).is_ok());
+}
+
+#[test]
+fn test_pseudonym_format() {
+    let e = Envelope::new("Alice").add_assertion("email", "alice@example.com");
+    let (pseudonymized, _) = e.pseudonymize(&preset(), &key());
+    assert_eq!(pseudonymized.format(), indoc! {r#"
+    "Alice" [
+        "email": Pseudonym(4bc07710)
+    ]
+    "#}.trim());
+
+    // The tag is registered with the standard tags.
+    let context = bc_envelope::FormatContext::standard();
+    assert_eq!(context.tags().tag_for_value(TAG_PSEUDONYM).unwrap().name().unwrap(), "pseudonym");
+    // And declared in the conformance statement.
+    assert!(bc_envelope::conformance::emitted_tags().contains(&("pseudonym", TAG_PSEUDONYM)));
+}
@@ -0,0 +1,171 @@
+use bc_envelope::prelude::*;
+use bc_components::Digest;
+use bc_envelope::EnvelopeError;
+
+mod common;
+use crate::common::test_data::*;
+
+/// Returns envelopes of every case, along with nodes whose subjects or
+/// assertions are obscured or otherwise unusual.
+fn adversarial_envelopes() -> Vec<Envelope> {
+    let mut envelopes = vec![
+        hello_envelope(),
+        single_assertion_envelope(),
+        double_assertion_envelope(),
+        wrapped_envelope(),
+        assertion_envelope(),
+        hello_envelope().elide(),
+        // A node whose subject is elided.
+        single_assertion_envelope().elide_removing_target(&Envelope::new("Alice")),
+        // A node with an elided assertion.
+        single_assertion_envelope().elide_removing_target(&assertion_envelope()),
+        // A node whose subject is an assertion.
+        assertion_envelope().add_assertion("note", "An assertion with an assertion."),
+        // A node with an assertion that has its own assertions.
+        Envelope::new("Alice")
+            .add_assertion_envelope(assertion_envelope().add_assertion("note", "Nested.")).unwrap(),
+        // A node with an assertion whose subject is elided.
+        Envelope::new("Alice")
+            .add_assertion_envelope(assertion_envelope().elide()).unwrap(),
+    ];
+
+    #[cfg(feature = "known_value")]
+    envelopes.push(known_value_envelope());
+
+    #[cfg(feature = "salt")]
+    envelopes.push(Envelope::new("Alice").add_assertion_salted("knows", "Bob", true));
+
+    #[cfg(feature = "encrypt")]
+    {
+        let key = fake_content_key();
+        envelopes.push(hello_envelope().encrypt_subject(&key).unwrap());
+        envelopes.push(single_assertion_envelope().encrypt_subject(&key).unwrap());
+        envelopes.push(Envelope::new("Alice")
+            .add_assertion_envelope(assertion_envelope().encrypt_subject(&key).unwrap()).unwrap());
+    }
+
+    #[cfg(feature = "compress")]
+    {
+        envelopes.push(hello_envelope().compress().unwrap());
+        envelopes.push(single_assertion_envelope().compress_subject().unwrap());
+        envelopes.push(Envelope::new("Alice")
+            .add_assertion_envelope(assertion_envelope().compress().unwrap()).unwrap());
+    }
+
+    envelopes
+}
+
+#[test]
+fn test_queries_do_not_panic() {
+    for e in adversarial_envelopes() {
+        e.subject();
+        e.assertions();
+        e.has_assertions();
+        e.as_assertion();
+        let _ = e.try_assertion();
+        e.as_predicate();
+        let _ = e.try_predicate();
+        e.as_object();
+        let _ = e.try_object();
+        e.as_leaf();
+        let _ = e.try_leaf();
+        #[cfg(feature = "known_value")]
+        {
+            e.as_known_value();
+            let _ = e.try_known_value();
+            e.is_known_value();
+        }
+        e.is_leaf();
+        e.is_node();
+        e.is_wrapped();
+        e.is_assertion();
+        #[cfg(feature = "encrypt")]
+        {
+            e.is_encrypted();
+            e.is_subject_encrypted();
+        }
+        #[cfg(feature = "compress")]
+        {
+            e.is_compressed();
+            e.is_subject_compressed();
+        }
+        e.is_elided();
+        e.is_subject_assertion();
+        e.is_subject_elided();
+        e.is_subject_obscured();
+        e.is_internal();
+        e.is_obscured();
+        let _ = e.extract_subject::<String>();
+        let _ = e.extract_subject::<Envelope>();
+        let _ = e.extract_subject::<Digest>();
+        for predicate in ["knows", "note", "unknown"] {
+            e.assertions_with_predicate(predicate);
+            let _ = e.assertion_with_predicate(predicate);
+            let _ = e.optional_assertion_with_predicate(predicate);
+            let _ = e.object_for_predicate(predicate);
+            let _ = e.optional_object_for_predicate(predicate);
+            let _ = e.extract_object_for_predicate::<String>(predicate);
+            let _ = e.extract_optional_object_for_predicate::<String>(predicate);
+            let _ = e.extract_object_for_predicate_with_default::<String>(predicate, String::new());
+            e.objects_for_predicate(predicate);
+            let _ = e.extract_objects_for_predicate::<String>(predicate);
+        }
+        let _ = e.extract_object::<String>();
+        let _ = e.extract_predicate::<String>();
+        e.elements_count();
+        let _ = e.unwrap_envelope();
+        let _ = e.add_assertion_envelope(e.clone());
+        let _ = e.add_assertions(std::slice::from_ref(&e));
+        e.replace_subject(hello_envelope());
+        e.remove_assertion(assertion_envelope());
+        e.format();
+        e.tree_format(false);
+    }
+}
+
+#[test]
+fn test_assertion_with_assertions_objects() {
+    // An assertion that has been salted (or otherwise had assertions added to
+    // it) is a node whose subject is the assertion.
+    let e = Envelope::new("Alice")
+        .add_assertion_envelope(Envelope::new_assertion("knows", "Bob").add_assertion("note", "Salt-like")).unwrap();
+    assert_eq!(e.objects_for_predicate("knows").len(), 1);
+    assert_eq!(e.extract_object_for_predicate::<String>("knows").unwrap(), "Bob");
+    assert_eq!(e.extract_optional_object_for_predicate::<String>("knows").unwrap(), Some("Bob".to_string()));
+}
+
+#[test]
+fn test_add_invalid_assertions() {
+    let e = hello_envelope();
+    assert!(e.add_assertions(&[Envelope::new("Not an assertion")]).is_err());
+    assert!(e.add_assertion_envelope(Envelope::new("Not an assertion")).is_err());
+}
+
+#[test]
+fn test_predicate_errors() {
+    let e = double_assertion_envelope();
+
+    let error = e.object_for_predicate("likes").unwrap_err();
+    match error.downcast_ref::<EnvelopeError>() {
+        Some(EnvelopeError::NonexistentPredicate { predicate }) => assert_eq!(predicate, r#""likes""#),
+        _ => panic!("unexpected error: {}", error),
+    }
+
+    let error = e.object_for_predicate("knows").unwrap_err();
+    match error.downcast_ref::<EnvelopeError>() {
+        Some(EnvelopeError::AmbiguousPredicate { predicate, count }) => {
+            assert_eq!(predicate, r#""knows""#);
+            assert_eq!(*count, 2);
+        },
+        _ => panic!("unexpected error: {}", error),
+    }
+    assert_eq!(error.to_string(), r#"more than one assertion (2) matches the predicate "knows""#);
+
+    // Building the errors takes no lock on the global format context, so
+    // predicates can be probed while it is held.
+    let missing = with_format_context!(|_context| e.object_for_predicate("likes").is_err());
+    assert!(missing);
+
+    let error = assertion_envelope().extract_subject::<Digest>().unwrap_err();
+    assert!(matches!(error.downcast_ref::<EnvelopeError>(), Some(EnvelopeError::InvalidFormat { .. })));
+}
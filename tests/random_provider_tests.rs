@@ -0,0 +1,57 @@
+#![cfg(all(feature = "test-support", feature = "recipient", feature = "types"))]
+
+use std::sync::Arc;
+
+use bc_components::{ARID, SSKRGroupSpec, SSKRSpec, SymmetricKey};
+use bc_envelope::prelude::full::*;
+use bc_envelope::{reset_random_provider, set_random_provider, SeededRandomProvider};
+
+mod common;
+use crate::common::test_data::*;
+
+/// Issues a salted, signed credential, encrypts it to Bob, and splits it
+/// with SSKR, returning every encoding produced along the way.
+fn credential_lifecycle() -> Vec<Vec<u8>> {
+    let credential = Envelope::new(ARID::from_data([0x46; 32]))
+        .add_type("Certificate of Completion")
+        .add_assertion("firstName", "James")
+        .add_salt()
+        .add_assertion("lastName", "Maxwell")
+        .add_salt_with_len(16).unwrap();
+    let signed = credential.sign(&alice_private_key());
+    let encrypted = signed.encrypt_to_recipient(&bob_public_key());
+    let content_key = SymmetricKey::from_data([0x11; 32]);
+    let spec = SSKRSpec::new(1, vec![SSKRGroupSpec::new(2, 3).unwrap()]).unwrap();
+    let shares = signed.wrap_envelope().encrypt_subject(&content_key).unwrap()
+        .sskr_split_flattened(&spec, &content_key).unwrap();
+
+    assert!(encrypted.decrypt_to_recipient(&bob_private_key()).unwrap().verify(&alice_public_key()).unwrap().is_identical_to(&credential));
+    let mut encodings = vec![signed.tagged_cbor_data(), encrypted.tagged_cbor_data()];
+    encodings.extend(shares.iter().map(|share| share.tagged_cbor_data()));
+    encodings
+}
+
+#[test]
+fn test_random_provider() {
+    let seed = [1, 2, 3, 4];
+
+    set_random_provider(Arc::new(SeededRandomProvider::new(seed)));
+    let first = credential_lifecycle();
+    set_random_provider(Arc::new(SeededRandomProvider::new(seed)));
+    let second = credential_lifecycle();
+    assert_eq!(first, second);
+
+    // A different seed gives different salt, keys, nonces, and signatures.
+    set_random_provider(Arc::new(SeededRandomProvider::new([5, 6, 7, 8])));
+    let third = credential_lifecycle();
+    for (a, b) in first.iter().zip(&third) {
+        assert_ne!(a, b);
+    }
+
+    reset_random_provider();
+    let first = credential_lifecycle();
+    let second = credential_lifecycle();
+    for (a, b) in first.iter().zip(&second) {
+        assert_ne!(a, b);
+    }
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "serde")]
+use std::collections::BTreeMap;
+
+use bc_envelope::prelude::full::*;
+
+mod common;
+use crate::common::test_data::*;
+
+fn envelopes() -> Vec<Envelope> {
+    vec![
+        hello_envelope(),
+        double_assertion_envelope(),
+        double_assertion_envelope().wrap_envelope().elide_removing_target(&Envelope::new("knows")),
+        known_value_envelope(),
+    ]
+}
+
+#[test]
+fn test_json_round_trip() {
+    bc_envelope::register_tags();
+    for envelope in envelopes() {
+        // Human-readable formats use the UR string.
+        let json = serde_json::to_string(&envelope).unwrap();
+        assert_eq!(json, format!("\"{}\"", envelope.ur_string()));
+        let decoded: Envelope = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.digest(), envelope.digest());
+        assert!(decoded.is_identical_to(&envelope));
+    }
+
+    // Envelopes can be held in application state like any other value.
+    let state: BTreeMap<String, Envelope> = [("greeting".to_string(), hello_envelope())].into();
+    let json = serde_json::to_string(&state).unwrap();
+    let decoded: BTreeMap<String, Envelope> = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded["greeting"].digest(), hello_envelope().digest());
+}
+
+#[test]
+fn test_binary_round_trip() {
+    for envelope in envelopes() {
+        // Binary formats use the tagged CBOR bytes.
+        let mut data = Vec::new();
+        ciborium::into_writer(&envelope, &mut data).unwrap();
+        let bytes: ciborium::Value = ciborium::from_reader(data.as_slice()).unwrap();
+        assert_eq!(bytes.as_bytes(), Some(&envelope.tagged_cbor_data()));
+        let decoded: Envelope = ciborium::from_reader(data.as_slice()).unwrap();
+        assert_eq!(decoded.digest(), envelope.digest());
+        assert!(decoded.is_identical_to(&envelope));
+    }
+}
+
+#[test]
+fn test_invalid_envelopes() {
+    let error = serde_json::from_str::<Envelope>("\"ur:envelope:tpsp\"").unwrap_err();
+    assert!(error.to_string().starts_with("invalid envelope UR"), "{}", error);
+    let error = serde_json::from_str::<Envelope>("\"ur:seed:tpsp\"").unwrap_err();
+    assert!(error.to_string().starts_with("invalid envelope UR"), "{}", error);
+
+    // A leaf whose CBOR is not in canonical form is rejected.
+    let mut data = Vec::new();
+    ciborium::into_writer(&ciborium::Value::Bytes(vec![0xd8, 0xc8, 0x18, 0x01]), &mut data).unwrap();
+    let error = ciborium::from_reader::<Envelope, _>(data.as_slice()).unwrap_err();
+    assert!(error.to_string().contains("invalid envelope CBOR"), "{}", error);
+}
@@ -0,0 +1,116 @@
+use bc_envelope::prelude::full::*;
+use bc_envelope::{annotate_format, ElementSidecar, SidecarConflictPolicy};
+use indoc::indoc;
+
+mod common;
+
+fn document() -> Envelope {
+    Envelope::new("Alice")
+        .add_assertion("knows", "Bob")
+        .add_assertion("email", "alice@example.com")
+}
+
+#[test]
+fn test_sidecar_round_trip() {
+    let document = document();
+    let knows = document.assertion_with_predicate("knows").unwrap();
+    let mut sidecar = ElementSidecar::new();
+    assert!(sidecar.is_empty());
+    sidecar.set(&document, "reviewed");
+    sidecar.set(&knows, true);
+    assert_eq!(sidecar.set(&knows, false), Some(CBOR::from(true)));
+    assert_eq!(sidecar.len(), 2);
+
+    assert_eq!(sidecar.get::<String>(&document).unwrap().as_deref(), Some("reviewed"));
+    assert_eq!(sidecar.get::<bool>(&*knows.digest()).unwrap(), Some(false));
+    assert_eq!(sidecar.get::<bool>(&Envelope::new("Bob")).unwrap(), None);
+    assert!(sidecar.get::<String>(&knows).is_err());
+
+    let envelope = Envelope::from(&sidecar);
+    assert_eq!(envelope.assertions().len(), 2);
+    let decoded = ElementSidecar::try_from(envelope.clone()).unwrap();
+    assert_eq!(decoded, sidecar);
+    let decoded = ElementSidecar::try_from(Envelope::from_tagged_cbor_data(envelope.tagged_cbor_data()).unwrap()).unwrap();
+    assert_eq!(decoded, sidecar);
+
+    assert!(ElementSidecar::try_from(document.clone()).is_err());
+
+    assert_eq!(sidecar.remove(&document), Some(CBOR::from("reviewed")));
+    assert_eq!(sidecar.len(), 1);
+}
+
+#[test]
+fn test_sidecar_prune() {
+    let document = document();
+    let knows = document.assertion_with_predicate("knows").unwrap();
+    let email = document.assertion_with_predicate("email").unwrap();
+    let mut sidecar = ElementSidecar::new();
+    sidecar.set(&document.subject(), "bookmarked");
+    sidecar.set(&knows, "collapsed");
+    sidecar.set(&email, "collapsed");
+    sidecar.set(&document, "reviewed");
+
+    // The document is re-issued without the email assertion, so the values
+    // of the assertion and of the old document are dropped.
+    let reissued = document.remove_assertion(email);
+    assert_eq!(sidecar.prune_to(&reissued), 2);
+    assert_eq!(sidecar.len(), 2);
+    assert_eq!(sidecar.get::<String>(&knows).unwrap().as_deref(), Some("collapsed"));
+    assert_eq!(sidecar.get::<String>(&reissued.subject()).unwrap().as_deref(), Some("bookmarked"));
+    assert_eq!(sidecar.prune_to(&reissued), 0);
+}
+
+#[test]
+fn test_sidecar_merge() {
+    let document = document();
+    let knows = document.assertion_with_predicate("knows").unwrap();
+    let email = document.assertion_with_predicate("email").unwrap();
+    let mut ours = ElementSidecar::new();
+    ours.set(&document, "reviewed");
+    ours.set(&knows, "collapsed");
+    let mut theirs = ElementSidecar::new();
+    theirs.set(&document, "reviewed");
+    theirs.set(&knows, "expanded");
+    theirs.set(&email, "flagged");
+
+    let mut rejected = ours.clone();
+    match rejected.merge(&theirs, SidecarConflictPolicy::Reject).unwrap_err().downcast::<EnvelopeError>() {
+        Ok(EnvelopeError::SidecarConflict(digest)) => assert_eq!(digest, knows.digest().into_owned()),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    assert_eq!(rejected, ours);
+
+    let mut kept = ours.clone();
+    kept.merge(&theirs, SidecarConflictPolicy::KeepExisting).unwrap();
+    assert_eq!(kept.get::<String>(&knows).unwrap().as_deref(), Some("collapsed"));
+    assert_eq!(kept.get::<String>(&email).unwrap().as_deref(), Some("flagged"));
+    assert_eq!(kept.len(), 3);
+
+    let mut replaced = ours.clone();
+    replaced.merge(&theirs, SidecarConflictPolicy::Replace).unwrap();
+    assert_eq!(replaced, theirs);
+
+    // Equal values are not conflicts.
+    let mut agreed = ours.clone();
+    agreed.merge(&ours, SidecarConflictPolicy::Reject).unwrap();
+    assert_eq!(agreed, ours);
+}
+
+#[test]
+fn test_annotate_format() {
+    let document = document();
+    let mut sidecar = ElementSidecar::new();
+    sidecar.set(&document.assertion_with_predicate("knows").unwrap(), "collapsed");
+    sidecar.set(&Envelope::new("Bob"), "bookmarked");
+    let tree = annotate_format(&document, &sidecar, |value| value.to_string());
+    assert_eq!(tree, indoc! {r#"
+    9ed38c0d NODE
+        13941b48 subj "Alice"
+        6f0fa8ed ASSERTION
+            69f1fd05 pred "email"
+            10ca6d56 obj "alice@example.com"
+        78d666eb ASSERTION // "collapsed"
+            db7dd21c pred "knows"
+            13b74194 obj "Bob" // "bookmarked"
+    "#}.trim());
+}
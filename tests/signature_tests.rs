@@ -2,11 +2,13 @@
 
 use indoc::indoc;
 use bc_envelope::prelude::*;
+use bc_envelope::{CeremonyIssue, EnvelopeError, SignatureMetadata, known_values};
+use bc_components::Digest;
+use bc_components::{Signature, Verifier};
 use known_values::NOTE;
 
 mod common;
 use crate::common::test_data::*;
-use crate::common::check_encoding::*;
 
 #[test]
 fn test_signed_plaintext() {
@@ -129,3 +131,99 @@ fn signed_with_metadata() {
         .extract_subject::<String>().unwrap();
     assert_eq!(received_plaintext, PLAINTEXT_HELLO);
 }
+
+#[test]
+fn test_sign_digest() {
+    // Build a large envelope, keeping only the digest needed for signing.
+    let content = (0..1000).fold(Envelope::new("Large"), |envelope, i| {
+        envelope.add_assertion(format!("item-{}", i), i)
+    });
+    let wrapped_digest = Digest::from_digests(&[content.digest().into_owned()]);
+
+    // Alice signs the digest of the wrapped content.
+    let signer = alice_private_key().ed25519_signing_private_key();
+    let signature_assertion = Envelope::signature_assertion_for_digest(&wrapped_digest, &signer).unwrap();
+
+    // The assertion is attached to the wrapped content later.
+    let envelope = content.wrap_envelope()
+        .add_assertion_envelope(signature_assertion).unwrap();
+
+    // Ed25519 signatures are deterministic, so this is the same envelope
+    // `sign` produces.
+    assert!(envelope.is_identical_to(&content.sign(&signer)));
+
+    // Bob receives the envelope and verifies it normally.
+    let received = Envelope::from_tagged_cbor_data(envelope.tagged_cbor().to_cbor_data()).unwrap();
+    let verifier = signer.public_key();
+    let received_content = received.verify(&verifier).unwrap();
+    assert!(received_content.is_identical_to(&content));
+
+    // The signature can also be checked against the digest alone.
+    let signature = received.extract_object_for_predicate::<Signature>(known_values::SIGNED).unwrap();
+    assert!(Envelope::verify_signature_for_digest(&wrapped_digest, &signature, &verifier).is_ok());
+    assert!(Envelope::verify_signature_for_digest(&content.digest(), &signature, &verifier).is_err());
+}
+
+fn ceremony_date(day: u32) -> dcbor::Date {
+    dcbor::Date::from_string(format!("2024-01-{:02}", day)).unwrap()
+}
+
+#[test]
+fn test_signing_ceremony() {
+    let envelope = hello_envelope()
+        .countersign_with_sequence(&alice_private_key(), 1, ceremony_date(1))
+        .countersign_with_sequence(&bob_private_key(), 2, ceremony_date(2))
+        .countersign_with_sequence(&carol_private_key(), 3, ceremony_date(3))
+        .check_encoding().unwrap();
+
+    let verifiers: [&dyn Verifier; 3] = [&alice_public_key(), &bob_public_key(), &carol_public_key()];
+    let steps = envelope.validate_ceremony(&verifiers, true).unwrap();
+    assert_eq!(steps.len(), 3);
+    for (index, step) in steps.iter().enumerate() {
+        assert_eq!(step.depth, 2 - index);
+        assert_eq!(step.sequence, Some(index as u64 + 1));
+        assert_eq!(step.date, Some(ceremony_date(index as u32 + 1)));
+        assert_eq!(step.signer, Some(index));
+        assert!(step.issues.is_empty());
+    }
+
+    // The innermost content is the original envelope.
+    let content = envelope.unwrap_envelope().unwrap().unwrap_envelope().unwrap().unwrap_envelope().unwrap();
+    assert!(content.is_identical_to(&hello_envelope()));
+}
+
+#[test]
+fn test_signing_ceremony_out_of_order() {
+    let envelope = hello_envelope()
+        .countersign_with_sequence(&alice_private_key(), 2, ceremony_date(1))
+        .countersign_with_sequence(&bob_private_key(), 1, ceremony_date(2))
+        .sign(&carol_private_key());
+
+    let steps = envelope.ceremony_report(&[]).unwrap();
+    assert_eq!(steps.len(), 3);
+    assert!(steps[0].issues.is_empty());
+    assert_eq!(steps[1].issues, vec![CeremonyIssue::SequenceOutOfOrder]);
+    assert_eq!(steps[2].issues, vec![CeremonyIssue::MissingMetadata]);
+    assert!(steps.iter().all(|step| step.signer.is_none()));
+    assert!(envelope.validate_ceremony(&[&alice_public_key(), &bob_public_key(), &carol_public_key()], false).is_err());
+
+    let duplicated = hello_envelope()
+        .countersign_with_sequence(&alice_private_key(), 1, ceremony_date(1))
+        .countersign_with_sequence(&bob_private_key(), 1, ceremony_date(2));
+    let steps = duplicated.ceremony_report(&[]).unwrap();
+    assert_eq!(steps[1].issues, vec![CeremonyIssue::DuplicateSequence]);
+}
+
+#[test]
+fn test_signing_ceremony_backdated() {
+    let envelope = hello_envelope()
+        .countersign_with_sequence(&alice_private_key(), 1, ceremony_date(2))
+        .countersign_with_sequence(&bob_private_key(), 2, ceremony_date(1));
+    let verifiers: [&dyn Verifier; 2] = [&alice_public_key(), &bob_public_key()];
+
+    // Without strict dates, the ceremony is consistent.
+    assert!(envelope.validate_ceremony(&verifiers, false).is_ok());
+
+    let error = envelope.validate_ceremony(&verifiers, true).unwrap_err();
+    assert!(matches!(error.downcast_ref::<EnvelopeError>(), Some(EnvelopeError::InvalidCeremony { .. })));
+}
@@ -7,7 +7,6 @@ use ssh_key::HashAlg;
 
 mod common;
 use crate::common::test_data::*;
-use crate::common::check_encoding::*;
 
 #[test]
 fn test_ssh_signed_plaintext() {
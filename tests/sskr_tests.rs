@@ -1,7 +1,8 @@
 #![cfg(all(feature = "sskr", feature = "types"))]
-use bc_components::{SymmetricKey, SSKRGroupSpec, SSKRSpec};
+use bc_components::{SymmetricKey, SSKRGroupSpec, SSKRShare, SSKRSpec};
 use hex_literal::hex;
 use bc_envelope::prelude::*;
+use bc_envelope::known_values;
 use indoc::indoc;
 
 mod common;
@@ -73,3 +74,50 @@ fn test_sskr() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_sskr_metadata() -> anyhow::Result<()> {
+    let secret = Envelope::new("Family photos").add_assertion(known_values::NOTE, "Private");
+    let content_key = SymmetricKey::new();
+    let encrypted = secret.wrap_envelope().encrypt_subject(&content_key)?;
+    let spec = SSKRSpec::new(1, vec![SSKRGroupSpec::new(2, 3)?])?;
+    let metadata = [
+        Envelope::new_assertion("name", "Family photos backup"),
+        Envelope::new_assertion(known_values::DATE, dcbor::Date::from_string("2024-05-01")?),
+    ];
+    let shares: Vec<Envelope> = encrypted
+        .sskr_split_with_metadata(&spec, &content_key, &metadata)?
+        .into_iter()
+        .flatten()
+        .collect();
+    assert_eq!(shares.len(), 3);
+
+    // The metadata is readable without any key material, as is the share's
+    // position in the split.
+    let share = Envelope::from_ur(shares[1].ur())?;
+    assert!(share.subject().is_encrypted());
+    assert_eq!(share.extract_object_for_predicate::<String>("name")?, "Family photos backup");
+    let sskr_share = share.extract_object_for_predicate::<SSKRShare>(known_values::SSKR_SHARE)?;
+    assert_eq!((sskr_share.group_index(), sskr_share.member_index(), sskr_share.member_threshold()), (0, 1, 2));
+    assert!(share.format().contains(r#""name": "Family photos backup""#));
+    assert!(share.format().contains("'date': 2024-05-01"));
+    assert!(share.format().starts_with("ENCRYPTED ["));
+
+    // The metadata does not affect the recovered envelope.
+    let recovered = Envelope::sskr_join(&[&shares[0], &shares[2]])?.unwrap_envelope()?;
+    assert!(recovered.is_identical_to(&secret));
+
+    // Shares whose metadata differs still join.
+    let relabeled = shares[0]
+        .replace_assertion(metadata[0].clone(), Envelope::new_assertion("name", "Old backup"))?
+        .add_assertion(known_values::NOTE, "Kept by Alice");
+    let stripped = shares[2].remove_assertion(metadata[0].clone()).remove_assertion(metadata[1].clone());
+    for quorum in [[&relabeled, &stripped], [&stripped, &relabeled], [&relabeled, &shares[1]]] {
+        let recovered = Envelope::sskr_join(&quorum)?.unwrap_envelope()?;
+        assert!(recovered.is_identical_to(&secret));
+    }
+
+    // Anything that is not an assertion is rejected.
+    assert!(encrypted.sskr_split_with_metadata(&spec, &content_key, &[Envelope::new("label")]).is_err());
+    Ok(())
+}
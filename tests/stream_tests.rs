@@ -0,0 +1,99 @@
+#![cfg(feature = "async")]
+
+use bc_envelope::{EnvelopeError, EnvelopeLimits, EnvelopeStream};
+use futures_util::StreamExt;
+use tokio::io::{duplex, AsyncWriteExt};
+
+mod common;
+use crate::common::test_data::*;
+
+fn envelope_error(error: &anyhow::Error) -> Option<&EnvelopeError> {
+    error.downcast_ref::<EnvelopeError>()
+}
+
+#[tokio::test]
+async fn test_stream_multiple_envelopes() {
+    let (mut client, server) = duplex(64);
+    let envelopes = vec![hello_envelope(), double_assertion_envelope(), wrapped_envelope()];
+    let sent = envelopes.clone();
+    let writer = tokio::spawn(async move {
+        for envelope in sent {
+            // Write each frame in small pieces to exercise partial reads.
+            for piece in envelope.framed_data().chunks(5) {
+                client.write_all(piece).await.unwrap();
+            }
+        }
+    });
+
+    let mut stream = EnvelopeStream::new(server, EnvelopeLimits::default());
+    for envelope in &envelopes {
+        let received = stream.next().await.unwrap().unwrap();
+        assert!(received.is_identical_to(envelope));
+    }
+    writer.await.unwrap();
+    assert!(stream.next().await.is_none());
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn test_stream_mid_frame_disconnect() {
+    let (mut client, server) = duplex(1024);
+    let frame = double_assertion_envelope().framed_data();
+    client.write_all(&hello_envelope().framed_data()).await.unwrap();
+    client.write_all(&frame[..frame.len() / 2]).await.unwrap();
+    drop(client);
+
+    let mut stream = EnvelopeStream::new(server, EnvelopeLimits::default());
+    assert!(stream.next().await.unwrap().unwrap().is_identical_to(&hello_envelope()));
+    let error = stream.next().await.unwrap().unwrap_err();
+    assert!(matches!(envelope_error(&error), Some(EnvelopeError::TruncatedFrame)));
+    assert!(stream.next().await.is_none());
+
+    // A disconnect within the length prefix is also a truncated frame.
+    let (mut client, server) = duplex(1024);
+    client.write_all(&[0, 0]).await.unwrap();
+    drop(client);
+    let mut stream = EnvelopeStream::new(server, EnvelopeLimits::default());
+    let error = stream.next().await.unwrap().unwrap_err();
+    assert!(matches!(envelope_error(&error), Some(EnvelopeError::TruncatedFrame)));
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn test_stream_oversized_frame() {
+    let (mut client, server) = duplex(1024);
+    let limits = EnvelopeLimits::default().with_max_frame_size(100);
+    client.write_all(&hello_envelope().framed_data()).await.unwrap();
+    // Declare a 4 GiB frame; it must be rejected without waiting for it.
+    client.write_all(&u32::MAX.to_be_bytes()).await.unwrap();
+
+    let mut stream = EnvelopeStream::new(server, limits);
+    assert!(stream.next().await.unwrap().unwrap().is_identical_to(&hello_envelope()));
+    let error = stream.next().await.unwrap().unwrap_err();
+    match envelope_error(&error) {
+        Some(EnvelopeError::FrameTooLarge { size, limit }) => {
+            assert_eq!(*size, u32::MAX as usize);
+            assert_eq!(*limit, 100);
+        },
+        _ => panic!("unexpected error: {}", error),
+    }
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn test_stream_corrupt_envelope() {
+    let (mut client, server) = duplex(1024);
+    let mut corrupt = hello_envelope().framed_data();
+    let last = corrupt.len() - 1;
+    corrupt[last] ^= 0xff;
+    client.write_all(&corrupt).await.unwrap();
+    client.write_all(&[0, 0, 0, 0]).await.unwrap();
+    client.write_all(&double_assertion_envelope().framed_data()).await.unwrap();
+    drop(client);
+
+    let mut stream = EnvelopeStream::new(server, EnvelopeLimits::default());
+    assert!(stream.next().await.unwrap().is_err());
+    assert!(stream.next().await.unwrap().is_err());
+    assert!(stream.next().await.unwrap().unwrap().is_identical_to(&double_assertion_envelope()));
+    assert!(stream.next().await.is_none());
+}
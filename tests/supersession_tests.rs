@@ -0,0 +1,125 @@
+#![cfg(feature = "known_value")]
+use bc_envelope::prelude::full::*;
+use bc_envelope::extension::supersession::SUPERSEDES;
+use indoc::indoc;
+
+mod common;
+
+fn date(s: &str) -> Date {
+    Date::from_string(s).unwrap()
+}
+
+fn alice() -> Envelope {
+    Envelope::new("Alice")
+        .add_assertion("knows", "Bob")
+        .add_assertion("address", "1 Main St.")
+}
+
+fn address_assertion(envelope: &Envelope) -> Envelope {
+    envelope.history_for_predicate("address").unwrap().pop().unwrap().0
+}
+
+#[test]
+fn test_supersede_assertion() {
+    let e1 = alice();
+    let original = address_assertion(&e1);
+    let e2 = e1.supersede_assertion(&original.digest(), "2 High St.", date("2023-06-01"), false).unwrap();
+    let second = address_assertion(&e2);
+    let e3 = e2.supersede_assertion(&second.digest(), "3 Park Ln.", date("2024-01-15"), false).unwrap();
+    assert_eq!(e3.format(), indoc! {r#"
+    "Alice" [
+        {
+            "address": "2 High St."
+        } [
+            "supersedes": Digest(d0aed6ec)
+            'date': 2023-06-01
+        ]
+        {
+            "address": "3 Park Ln."
+        } [
+            "supersedes": Digest(0b62226f)
+            'date': 2024-01-15
+        ]
+        "address": "1 Main St."
+        "knows": "Bob"
+    ]
+    "#}.trim());
+
+    assert_eq!(e1.current_object_for_predicate("address").unwrap().extract_subject::<String>().unwrap(), "1 Main St.");
+    assert_eq!(e2.current_object_for_predicate("address").unwrap().extract_subject::<String>().unwrap(), "2 High St.");
+    assert_eq!(e3.current_object_for_predicate("address").unwrap().extract_subject::<String>().unwrap(), "3 Park Ln.");
+    assert_eq!(e3.current_object_for_predicate("knows").unwrap().extract_subject::<String>().unwrap(), "Bob");
+    assert!(e3.current_object_for_predicate("likes").is_err());
+    assert!(e3.history_for_predicate("likes").unwrap().is_empty());
+
+    let history = e3.history_for_predicate("address").unwrap();
+    let objects: Vec<String> = history.iter()
+        .map(|(assertion, _)| assertion.subject().try_object().unwrap().extract_subject().unwrap())
+        .collect();
+    assert_eq!(objects, ["1 Main St.", "2 High St.", "3 Park Ln."]);
+    let dates: Vec<Option<Date>> = history.into_iter().map(|(_, date)| date).collect();
+    assert_eq!(dates, [None, Some(date("2023-06-01")), Some(date("2024-01-15"))]);
+}
+
+#[test]
+fn test_supersede_eliding() {
+    let e1 = alice();
+    let original = address_assertion(&e1);
+    let e2 = e1.supersede_assertion(&original.digest(), "2 High St.", date("2023-06-01"), true).unwrap();
+    assert_eq!(e2.format(), indoc! {r#"
+    "Alice" [
+        {
+            "address": "2 High St."
+        } [
+            "supersedes": Digest(d0aed6ec)
+            'date': 2023-06-01
+        ]
+        "knows": "Bob"
+        ELIDED
+    ]
+    "#}.trim());
+    assert_eq!(e2.current_object_for_predicate("address").unwrap().extract_subject::<String>().unwrap(), "2 High St.");
+
+    // The elided assertion keeps its place in the history.
+    let history = e2.history_for_predicate("address").unwrap();
+    assert_eq!(history.len(), 2);
+    assert!(history[0].0.is_elided());
+    assert_eq!(history[0].0.digest(), original.digest());
+
+    // An elided assertion cannot be superseded, as its predicate is unknown.
+    assert!(e2.supersede_assertion(&original.digest(), "3 Park Ln.", date("2024-01-15"), false).is_err());
+    assert!(matches!(
+        e2.supersede_assertion(&Envelope::new("nothing").digest(), "3 Park Ln.", date("2024-01-15"), false)
+            .unwrap_err()
+            .downcast_ref::<EnvelopeError>(),
+        Some(EnvelopeError::NonexistentAssertion)
+    ));
+}
+
+#[test]
+fn test_supersession_fork() {
+    let e1 = alice();
+    let original = address_assertion(&e1);
+    let e2 = e1.supersede_assertion(&original.digest(), "2 High St.", date("2023-06-01"), false).unwrap();
+
+    // A forged assertion claiming to supersede the same original.
+    let forged = Envelope::new_assertion("address", "4 Fake Rd.")
+        .add_assertion(SUPERSEDES, original.digest().into_owned())
+        .add_assertion(known_values::DATE, date("2023-07-01"));
+    let e3 = e2.add_assertion_envelope(forged).unwrap();
+    let error = e3.current_object_for_predicate("address").unwrap_err();
+    match error.downcast_ref::<EnvelopeError>() {
+        Some(EnvelopeError::SupersessionFork { predicate, digest }) => {
+            assert_eq!(predicate, "\"address\"");
+            assert_eq!(digest, original.digest().as_ref());
+        },
+        _ => panic!("unexpected error: {}", error),
+    }
+
+    // Two unrelated assertions with the predicate do not form a chain.
+    let e4 = e1.add_assertion("address", "5 Other Ave.");
+    assert!(matches!(
+        e4.current_object_for_predicate("address").unwrap_err().downcast_ref::<EnvelopeError>(),
+        Some(EnvelopeError::AmbiguousPredicate { count: 2, .. })
+    ));
+}
@@ -1,10 +1,12 @@
 use bc_envelope::prelude::*;
+use bc_envelope::known_values;
+use bc_ur::UR;
+use dcbor::{CBOR, Map};
 #[cfg(feature = "known_value")]
 use bc_components::DigestProvider;
 use bc_rand::{fake_random_data, make_fake_random_number_generator, rng_next_in_closed_range};
 
 mod common;
-use crate::common::check_encoding::*;
 
 #[cfg(feature = "known_value")]
 #[test]
@@ -16,6 +18,37 @@ fn test_known_value() {
     assert_eq!(format!("{}", envelope.ur_string()), "ur:envelope/axgrbdrnem");
 }
 
+#[test]
+fn test_ur_string() {
+    fn error_of(ur_string: &str) -> EnvelopeError {
+        Envelope::from_ur_string(ur_string).unwrap_err().downcast::<EnvelopeError>().unwrap()
+    }
+
+    // The UR string does not depend on the tags having been registered.
+    let envelope = Envelope::new("Alice").add_assertion("knows", "Bob");
+    let ur_string = envelope.ur_string();
+    assert!(ur_string.starts_with("ur:envelope/"));
+    assert!(Envelope::from_ur_string(ur_string.as_str()).unwrap().is_identical_to(&envelope));
+
+    // Uppercase URs, as used in QR codes, are accepted.
+    assert!(Envelope::from_ur_string(ur_string.to_uppercase()).unwrap().is_identical_to(&envelope));
+
+    assert!(matches!(error_of("envelope/axgrbdrnem"), EnvelopeError::NotUR { .. }));
+    assert!(matches!(error_of("ur:envelope"), EnvelopeError::NotUR { .. }));
+    assert!(matches!(error_of("ur:envelope/axgrbdrnen"), EnvelopeError::NotUR { .. }));
+
+    let seed = UR::new("crypto-seed", CBOR::from(1)).unwrap().string();
+    let error = error_of(&seed);
+    assert!(matches!(&error, EnvelopeError::WrongURType { ur_type } if ur_type == "crypto-seed"));
+    assert_eq!(error.to_string(), "expected a UR of type envelope, but found ur:crypto-seed");
+
+    // Truncated CBOR, and CBOR that is not an envelope.
+    let truncated = format!("ur:envelope/{}", bc_ur::bytewords::encode([0x82, 0x01], bc_ur::bytewords::Style::Minimal));
+    assert!(matches!(error_of(&truncated), EnvelopeError::InvalidURCBOR { .. }));
+    let text = UR::new("envelope", CBOR::from("Alice")).unwrap().string();
+    assert!(matches!(error_of(&text), EnvelopeError::InvalidFormat { .. }));
+}
+
 #[test]
 fn test_date() {
     let date = dcbor::Date::from_string("2018-01-07").unwrap();
@@ -23,6 +56,45 @@ fn test_date() {
     assert_eq!(envelope.format(), "2018-01-07");
 }
 
+#[test]
+fn test_array_leaf() {
+    let envelope = Envelope::new(vec![1u64, 2, 3]).check_encoding().unwrap();
+    assert_eq!(envelope.format(), "[1, 2, 3]");
+    assert!(Envelope::new([1u64, 2, 3].as_slice()).is_identical_to(&envelope));
+    assert_eq!(envelope.extract_subject::<Vec<u64>>().unwrap(), vec![1, 2, 3]);
+    assert_eq!(envelope.extract_subject_array::<u64>().unwrap(), vec![1, 2, 3]);
+
+    let envelope = envelope.add_assertion("note", "counts");
+    assert_eq!(envelope.extract_subject_array::<u64>().unwrap(), vec![1, 2, 3]);
+    assert!(envelope.extract_subject_array::<String>().is_err());
+    assert!(Envelope::new("Alice").extract_subject_array::<u64>().is_err());
+}
+
+#[test]
+fn test_map_leaf() {
+    let mut map = Map::new();
+    map.insert("name", "Alice");
+    map.insert(1, true);
+    map.insert(vec![2u64], 3.5);
+    let envelope = Envelope::new(map.clone()).check_encoding().unwrap();
+    assert_eq!(envelope.format(), r#"Map{1: true, "name": "Alice", [2]: 3.5}"#);
+
+    // The entries are in canonical order, whatever order they were inserted
+    // in, so the digest depends only on the contents.
+    let mut reordered = Map::new();
+    reordered.insert(vec![2u64], 3.5);
+    reordered.insert(1, true);
+    reordered.insert("name", "Alice");
+    assert!(Envelope::new(reordered).is_identical_to(&envelope));
+
+    let extracted = envelope.extract_subject_map().unwrap();
+    assert_eq!(extracted, map);
+    assert_eq!(extracted.extract::<&str, String>("name").unwrap(), "Alice");
+    assert!(extracted.extract::<i32, bool>(1).unwrap());
+    assert_eq!(extracted.extract::<Vec<u64>, f64>(vec![2]).unwrap(), 3.5);
+    assert!(Envelope::new(vec![1u64]).extract_subject_map().is_err());
+}
+
 #[test]
 fn test_fake_random_data() {
     assert_eq!(fake_random_data(100), hex_literal::hex!("7eb559bbbf6cce2632cf9f194aeb50943de7e1cbad54dcfab27a42759f5e2fed518684c556472008a67932f7c682125b50cb72e8216f6906358fdaf28d3545532daee0c5bb5023f50cd8e71ec14901ac746c576c481b893be6656b80622b3a564e59b4e2"));
@@ -34,3 +106,52 @@ fn test_fake_numbers() {
     let array = (0..100).map(|_| rng_next_in_closed_range(&mut rng, &(-50..=50))).collect::<Vec<_>>();
     assert_eq!(format!("{:?}", array), "[-43, -6, 43, -34, -34, 17, -9, 24, 17, -29, -32, -44, 12, -15, -46, 20, 50, -31, -50, 36, -28, -23, 6, -27, -31, -45, -27, 26, 31, -23, 24, 19, -32, 43, -18, -17, 6, -13, -1, -27, 4, -48, -4, -44, -6, 17, -15, 22, 15, 20, -25, -35, -33, -27, -17, -44, -27, 15, -14, -38, -29, -12, 8, 43, 49, -42, -11, -1, -42, -26, -25, 22, -13, 14, 42, -29, -38, 17, 2, 5, 5, -31, 27, -3, 39, -12, 42, 46, -17, -25, -46, -19, 16, 2, -45, 41, 12, -22, 43, -11]");
 }
+
+#[cfg(feature = "types")]
+#[test]
+fn test_check_type() {
+    let e = Envelope::new("Alice")
+        .add_type(known_values::SEED_TYPE)
+        .add_type("Person")
+        .add_type(Envelope::new("Agent").add_assertion("note", "A nested type."));
+    assert_eq!(e.types().len(), 3);
+    assert!(e.get_type().is_err());
+
+    assert!(e.has_type(&known_values::SEED_TYPE));
+    assert!(e.has_type_envelope("Person"));
+    assert!(e.has_type_envelope(Envelope::new("Agent").add_assertion("note", "A nested type.")));
+    assert!(e.check_type(&known_values::SEED_TYPE).is_ok());
+    assert!(e.check_type_envelope("Person").is_ok());
+
+    // Missing types.
+    assert!(!e.has_type(&known_values::PRIVATE_KEY_TYPE));
+    assert!(!e.has_type_envelope("Agent"));
+    let error = e.check_type_envelope("Robot").unwrap_err();
+    assert!(matches!(error.downcast_ref::<bc_envelope::EnvelopeError>(), Some(bc_envelope::EnvelopeError::InvalidType)));
+
+    // Elided types have the same digest as the originals, but never match.
+    let elided = e.elide_removing_target(&Envelope::new("Person"));
+    assert!(elided.is_equivalent_to(&e));
+    assert_eq!(elided.types().len(), 3);
+    assert!(!elided.has_type_envelope("Person"));
+    assert!(elided.check_type_envelope("Person").is_err());
+    assert!(elided.has_type(&known_values::SEED_TYPE));
+
+    // Elided `'isA'` assertions are not types at all.
+    let elided = e.elide_removing_target(&Envelope::new_assertion(known_values::IS_A, "Person"));
+    assert_eq!(elided.types().len(), 2);
+    assert!(!elided.has_type_envelope("Person"));
+}
+
+#[cfg(all(feature = "types", feature = "encrypt"))]
+#[test]
+fn test_check_type_encrypted_subject() {
+    let e = Envelope::new("Alice")
+        .add_type("Person")
+        .encrypt_subject(&bc_components::SymmetricKey::new())
+        .unwrap();
+    assert!(e.is_subject_encrypted());
+    assert!(e.has_type_envelope("Person"));
+    assert!(e.check_type_envelope("Person").is_ok());
+    assert!(e.check_type_envelope("Robot").is_err());
+}
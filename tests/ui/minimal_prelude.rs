@@ -0,0 +1,40 @@
+// A representative downstream program: it glob-imports the minimal prelude
+// alongside `bc_components` and a module of its own whose names overlap with
+// the foreign types and extension types the prelude used to re-export. Any
+// ambiguity would fail to compile.
+
+use bc_components::*;
+use bc_envelope::prelude::*;
+use app::*;
+
+mod app {
+    pub struct Tag(pub &'static str);
+    pub struct Map(pub Vec<(String, String)>);
+    pub struct UR(pub String);
+    pub struct Date(pub u32);
+    pub struct Function(pub &'static str);
+    pub struct Request(pub Function);
+}
+
+pub fn run() -> (Tag, Map, UR, Date, Request, Digest) {
+    bc_envelope::register_tags();
+    let envelope = Envelope::new("Alice")
+        .add_assertion("knows", "Bob")
+        .elide_removing_target(&Envelope::new("Bob"));
+    let ur = envelope.ur_string();
+    let decoded = Envelope::from_ur_string(&ur).unwrap();
+    assert!(decoded.is_identical_to(&envelope));
+    let data = envelope.tagged_cbor_data();
+    assert!(Envelope::from_tagged_cbor_data(data).unwrap().is_identical_to(&envelope));
+    let digest: Digest = envelope.digest().into_owned();
+    let _: Option<&EnvelopeError> = None;
+    let _ = ObscureAction::Elide;
+    (
+        Tag("app"),
+        Map(vec![("name".to_string(), "Alice".to_string())]),
+        UR(ur),
+        Date(2024),
+        Request(Function("greet")),
+        digest,
+    )
+}